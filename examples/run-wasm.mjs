@@ -0,0 +1,33 @@
+// Run a module from `ph build --emit wasm` with Node.js:
+//
+//     ph build examples/math.ph --emit wasm -o math.wasm
+//     node examples/run-wasm.mjs math.wasm
+//
+// In a browser, fetch the module and pass the same imports to
+// `WebAssembly.instantiateStreaming(fetch("math.wasm"), imports)`.
+
+import { readFileSync } from "node:fs";
+
+let line = "";
+
+const imports = {
+    env: {
+        // `kind` is 0 for an int, 1 for a bool and 2 for nothing, `end` is a character code
+        print(value, kind, end) {
+            if (kind === 0) {
+                line += value.toString();
+            } else if (kind === 1) {
+                line += value === 0n ? "false" : "true";
+            }
+            line += String.fromCharCode(end);
+
+            if (end === 10) {
+                process.stdout.write(line);
+                line = "";
+            }
+        },
+    },
+};
+
+const { instance } = await WebAssembly.instantiate(readFileSync(process.argv[2]), imports);
+instance.exports.main();
@@ -4,15 +4,509 @@ pub fn get_arguments() -> clap::ArgMatches {
         .subcommand_required(true)
         .arg_required_else_help(true)
         .author("Adamekka")
+        .arg(
+            clap::Arg::new("verbose")
+                .long("verbose")
+                .short('v')
+                .help("Print each phase and how long it took to stderr, twice to also print what it produced")
+                .action(clap::ArgAction::Count)
+                .global(true),
+        )
+        .arg(
+            clap::Arg::new("quiet")
+                .long("quiet")
+                .short('q')
+                .help("Print nothing but diagnostics and results")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("verbose")
+                .global(true),
+        )
         .subcommand(
             clap::Command::new("build")
                 .about("Builds the project")
                 .visible_alias("b")
+                .arg(
+                    clap::Arg::new("file")
+                        .help("File to build, the entry point in Phoenix.toml by default, `-` for stdin")
+                        .value_hint(clap::ValueHint::FilePath),
+                )
+                .arg(
+                    clap::Arg::new("expr")
+                        .long("expr")
+                        .help("Build this source instead of a file, into expr.phc unless -o says otherwise"),
+                )
+                .group(clap::ArgGroup::new("input").args(["file", "expr"]))
+                .arg(
+                    clap::Arg::new("emit")
+                        .long("emit")
+                        .help("Print artifacts instead of building, separated by commas")
+                        .long_help(
+                            "Print artifacts instead of building, separated by commas: tokens, \
+                             ast, dot, ir, bytecode, c for standalone C source, asm for x86-64 \
+                             assembly, llvm-ir for LLVM IR and wasm for a WebAssembly module of \
+                             int programs, or ast-after=<pass> and bytecode-after=<pass> to see \
+                             the program right after an optimization pass, like \
+                             ast-after=const_fold. One artifact goes to stdout, or the --output \
+                             file. Several are written next to the output, or the source file, \
+                             with their own extension, like prog.ast and prog.ir. Add =<file> \
+                             to choose the file, or =- for stdout, like ast=-,ir=prog.txt",
+                        )
+                        .value_parser(parse_emit)
+                        .value_delimiter(',')
+                        .action(clap::ArgAction::Append),
+                )
+                .arg(
+                    clap::Arg::new("ast")
+                        .long("ast")
+                        .alias("ast-only")
+                        .help("Print the syntax tree, short for `--emit ast`")
+                        .action(clap::ArgAction::SetTrue)
+                        .conflicts_with("emit"),
+                )
+                .arg(
+                    clap::Arg::new("dump-ir")
+                        .long("dump-ir")
+                        .help("Print the intermediate representation the bytecode is compiled from, short for `--emit ir`")
+                        .action(clap::ArgAction::SetTrue)
+                        .conflicts_with_all(["emit", "ast"]),
+                )
+                .arg(
+                    clap::Arg::new("count-tokens")
+                        .long("count-tokens")
+                        .help("Print how many tokens of each type the file has")
+                        .action(clap::ArgAction::SetTrue)
+                        .conflicts_with_all(["emit", "ast", "dump-ir"]),
+                )
+                .arg(
+                    clap::Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .help("Write compiled bytecode here instead of next to the source with a .phc extension, or the artifact `--emit` asks for")
+                        .value_hint(clap::ValueHint::FilePath)
+                        .conflicts_with("count-tokens"),
+                )
+                .arg(
+                    clap::Arg::new("target")
+                        .long("target")
+                        .help("Platform the c, asm, llvm-ir and wasm artifacts are for: x86_64-linux, x86_64-macos or wasm32, defaults to this one, or wasm32 for wasm")
+                        .value_parser(parse_target),
+                )
+                .arg(
+                    clap::Arg::new("link")
+                        .long("link")
+                        .help("Compile the C or asm artifact into an executable with the system C compiler, named by -o or after the source")
+                        .action(clap::ArgAction::SetTrue)
+                        .requires("emit")
+                        .conflicts_with_all(["ast", "dump-ir", "count-tokens"]),
+                )
+                .arg(
+                    clap::Arg::new("keep-intermediates")
+                        .long("keep-intermediates")
+                        .help("Keep the generated .c or .s file next to the executable")
+                        .action(clap::ArgAction::SetTrue)
+                        .requires("link"),
+                )
+                .arg(layout_argument())
+                .arg(lenient_argument())
+                .arg(watch_argument().conflicts_with_all(["expr", "count-tokens"]))
+                .arg(
+                    clap::Arg::new("dump-config")
+                        .long("dump-config")
+                        .help("Print the settings the build would use, after the manifest and defaults, and exit")
+                        .action(clap::ArgAction::SetTrue)
+                        .conflicts_with_all(["watch", "count-tokens"]),
+                )
+                .args(optimization_arguments()),
+        )
+        .subcommand(
+            clap::Command::new("check")
+                .about("Checks a file for errors without running it")
+                .visible_alias("c")
+                .arg(
+                    clap::Arg::new("file")
+                        .help("File to check, `-` for stdin")
+                        .required(true)
+                        .value_hint(clap::ValueHint::FilePath),
+                )
+                .arg(
+                    clap::Arg::new("syntax")
+                        .long("syntax")
+                        .help("Only check that the file parses, skip names and types")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(layout_argument())
+                .arg(lenient_argument())
+                .arg(watch_argument()),
+        )
+        .subcommand(
+            clap::Command::new("fmt")
+                .about("Rewrites files in the canonical style")
+                .visible_alias("f")
                 .arg(
                     clap::Arg::new("file")
                         .required(true)
+                        .num_args(1..)
                         .value_hint(clap::ValueHint::FilePath),
+                )
+                .arg(
+                    clap::Arg::new("stdout")
+                        .long("stdout")
+                        .help("Print the formatted source instead of rewriting the files")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("check")
+                        .long("check")
+                        .help("Write nothing, print a diff for every file that isn't formatted and exit with 1 if there is one")
+                        .action(clap::ArgAction::SetTrue)
+                        .conflicts_with("stdout"),
+                )
+                .arg(
+                    clap::Arg::new("indent-width")
+                        .long("indent-width")
+                        .help("Spaces a block is indented by, overrides .phfmt.toml [default: 4]")
+                        .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..)),
+                )
+                .arg(
+                    clap::Arg::new("max-line-width")
+                        .long("max-line-width")
+                        .help("Wrap expressions after their operators past this width, overrides .phfmt.toml [default: 100]")
+                        .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..)),
+                )
+                .arg(
+                    clap::Arg::new("trailing-newline")
+                        .long("trailing-newline")
+                        .help("End the file with a new line, overrides .phfmt.toml [default: true]")
+                        .value_parser(clap::value_parser!(bool)),
+                )
+                .arg(layout_argument()),
+        )
+        .subcommand(
+            clap::Command::new("new")
+                .about("Creates a project in a new directory, with a Phoenix.toml manifest and src/main.ph")
+                .arg(
+                    clap::Arg::new("name")
+                        .help("Name of the project and its directory")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("init")
+                .about("Creates a project in the current directory, without replacing any file")
+                .arg(
+                    clap::Arg::new("name")
+                        .long("name")
+                        .help("Name of the project, the current directory's by default"),
                 ),
         )
+        .subcommand(
+            clap::Command::new("run")
+                .about("Runs a script")
+                .visible_alias("r")
+                .arg(
+                    // The script's arguments share one argument with the file, so that once the
+                    // file is found nothing after it is taken as an option for ph
+                    clap::Arg::new("file")
+                        .help("Script to run, followed by its arguments, returned by args()")
+                        .long_help(
+                            "Script to run, followed by its arguments, returned by args(). \
+                             Without a file the entry point in Phoenix.toml runs, found in the \
+                             current directory or above. Options for ph go before the file, everything after the file is \
+                             passed to the script, so `ph run --seed 1 f.ph --seed 2` gives \
+                             the script [\"--seed\", \"2\"]. A `--` right after the file is \
+                             dropped, so `ph run f.ph -- --help` gives [\"--help\"].",
+                        )
+                        .value_names(["file", "arguments"])
+                        .num_args(1..)
+                        .trailing_var_arg(true)
+                        .allow_hyphen_values(true)
+                        .value_hint(clap::ValueHint::FilePath),
+                )
+                .arg(
+                    clap::Arg::new("expr")
+                        .long("expr")
+                        .help("Run this source instead of a file, it can span several lines, everything after it is passed to the script")
+                        .long_help(
+                            "Run this source instead of a file. It runs exactly like a file \
+                             would, so it can hold several statements on separate lines, like \
+                             `ph run --expr $'let x = 2\\nprint(x)'`, and everything after it \
+                             is passed to the script.",
+                        ),
+                )
+                .args(evaluation_arguments())
+                .arg(lenient_argument())
+                .args(optimization_arguments()),
+        )
+        .subcommand(
+            clap::Command::new("eval")
+                .about("Evaluates an expression given on the command line")
+                .visible_alias("e")
+                .arg(
+                    clap::Arg::new("expression")
+                        .help("Expression to evaluate, all remaining arguments are joined with spaces")
+                        .long_help(
+                            "Expression to evaluate. All remaining arguments are joined with \
+                             spaces, so `ph eval 1 + 2` and `ph eval \"1 + 2\"` are the same. \
+                             Quote expressions containing characters your shell treats \
+                             specially, like `*` or parentheses.",
+                        )
+                        .required(true)
+                        .num_args(1..)
+                        .trailing_var_arg(true)
+                        .allow_hyphen_values(true),
+                )
+                .args(evaluation_arguments())
+                .arg(lenient_argument())
+                .args(optimization_arguments()),
+        )
+        .subcommand(
+            clap::Command::new("tokens")
+                .about("Prints the tokens of a file")
+                .arg(
+                    clap::Arg::new("file")
+                        .required(true)
+                        .value_hint(clap::ValueHint::FilePath),
+                )
+                .arg(
+                    clap::Arg::new("no-color")
+                        .long("no-color")
+                        .help("Don't color token types, also disabled by setting NO_COLOR")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("raw")
+                        .long("raw")
+                        .help("Print every token the lexer produces, with whitespace and end of file")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(layout_argument()),
+        )
+        .subcommand(
+            clap::Command::new("ast")
+                .about("Prints the syntax tree of a file")
+                .arg(
+                    clap::Arg::new("file")
+                        .required(true)
+                        .value_hint(clap::ValueHint::FilePath),
+                )
+                .arg(
+                    clap::Arg::new("folded")
+                        .long("folded")
+                        .help("Print the tree after operations on literals are folded, like it runs")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(layout_argument()),
+        )
+        .subcommand(
+            clap::Command::new("disasm")
+                .about("Prints the instructions of a file compiled with `ph build -o`")
+                .arg(
+                    clap::Arg::new("file")
+                        .required(true)
+                        .value_hint(clap::ValueHint::FilePath),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("repl").about("Starts an interactive session"),
+        )
         .get_matches()
 }
+
+/// `-O` and `--opt-level`, for `build` and the commands that run a program
+fn optimization_arguments() -> [clap::Arg; 2] {
+    [
+        clap::Arg::new("optimize")
+            .short('O')
+            .help("Run every optimization, short for --opt-level 2")
+            .action(clap::ArgAction::SetTrue),
+        clap::Arg::new("opt-level")
+            .long("opt-level")
+            .help("0 runs no optimization, 1 folds constants and optimizes the bytecode, 2 also removes dead code, programs then run on the vm")
+            .value_parser(clap::value_parser!(u8).range(0..=crate::passes::MAX_OPT_LEVEL as i64))
+            .conflicts_with("optimize"),
+    ]
+}
+
+/// `--layout`, for the commands that read source
+fn layout_argument() -> clap::Arg {
+    clap::Arg::new("layout")
+        .long("layout")
+        .help("Experimental: indentation delimits function bodies instead of braces")
+        .action(clap::ArgAction::SetTrue)
+}
+
+/// `--lenient`, for the commands that parse a program
+fn lenient_argument() -> clap::Arg {
+    clap::Arg::new("lenient")
+        .long("lenient")
+        .help("Read characters that start no token, like `@`, as line breaks instead of failing on them")
+        .action(clap::ArgAction::SetTrue)
+}
+
+fn watch_argument() -> clap::Arg {
+    clap::Arg::new("watch")
+        .long("watch")
+        .help("Build again each time the file or a file it imports changes, until Ctrl-C")
+        .action(clap::ArgAction::SetTrue)
+}
+
+/// Read a `--seed-vars` value, an int or a float for a variable name
+fn parse_seed_var(value: &str) -> Result<(String, crate::eval::Value), String> {
+    let (name, number): (&str, &str) = value
+        .split_once('=')
+        .ok_or_else(|| "expected name=value".to_string())?;
+
+    let mut characters: std::str::Chars = name.chars();
+    let valid: bool = characters
+        .next()
+        .is_some_and(|first: char| first.is_alphabetic() || first == '_')
+        && characters.all(|character: char| character.is_alphanumeric() || character == '_');
+    if !valid {
+        return Err(format!("'{}' is not a variable name", name));
+    }
+
+    if let Ok(number) = number.parse::<isize>() {
+        return Ok((name.to_string(), crate::eval::Value::Int(number)));
+    }
+    match number.parse::<f64>() {
+        Ok(number) if number.is_finite() => {
+            Ok((name.to_string(), crate::eval::Value::Float(number)))
+        }
+        _ => Err(format!("'{}' is not a number", number)),
+    }
+}
+
+/// Split an `--emit` value into the artifact and the file after its `=`, if any
+///
+/// `ast-after=const_fold=out.txt` is the artifact `ast-after=const_fold` and `out.txt`.
+pub fn split_emit(value: &str) -> (&str, Option<&str>) {
+    // The pass name of `ast-after=` is part of the artifact
+    let name_start: usize = match value.split_once('=') {
+        Some((form, _)) if form.ends_with("-after") => form.len() + 1,
+        _ => 0,
+    };
+
+    match value[name_start..].find('=') {
+        Some(index) => (
+            &value[..name_start + index],
+            Some(&value[name_start + index + 1..]),
+        ),
+        None => (value, None),
+    }
+}
+
+/// Find a `--target` by name
+fn parse_target(value: &str) -> Result<crate::target::TargetSpec, String> {
+    crate::target::find_target(value)
+        .ok_or_else(|| format!("expected one of {}", crate::target::target_names()))
+}
+
+/// Check an `--emit` value, `ast-after=` and `bytecode-after=` take the name of a pass that
+/// rewrites that form of the program
+fn parse_emit(value: &str) -> Result<String, String> {
+    const EXPECTED: &str =
+        "expected tokens, ast, dot, ir, bytecode, c, asm, llvm-ir, wasm, ast-after=<pass> or bytecode-after=<pass>";
+
+    let (artifact, path): (&str, Option<&str>) = split_emit(value);
+    if path == Some("") {
+        return Err(format!("expected a file after '{}='", artifact));
+    }
+
+    if matches!(
+        artifact,
+        "tokens" | "ast" | "dot" | "ir" | "bytecode" | "c" | "asm" | "llvm-ir" | "wasm"
+    ) {
+        return Ok(value.to_string());
+    }
+
+    let (form, name): (&str, &str) = artifact
+        .split_once("-after=")
+        .ok_or_else(|| EXPECTED.to_string())?;
+    let Some(pass) = crate::passes::find_pass(name) else {
+        let names: Vec<&str> = crate::passes::PASSES
+            .iter()
+            .map(|pass: &crate::passes::Pass| pass.name)
+            .collect();
+        return Err(format!(
+            "no pass is called '{}', the passes are {}",
+            name,
+            names.join(", ")
+        ));
+    };
+
+    match (form, pass.kind) {
+        ("ast", crate::passes::PassKind::Ast(_))
+        | ("bytecode", crate::passes::PassKind::Bytecode(_)) => Ok(value.to_string()),
+        ("ast", _) => Err(format!(
+            "'{}' rewrites the bytecode, not the syntax tree",
+            name
+        )),
+        ("bytecode", _) => Err(format!(
+            "'{}' rewrites the syntax tree, not the bytecode",
+            name
+        )),
+        _ => Err(EXPECTED.to_string()),
+    }
+}
+
+/// Options shared by the subcommands that evaluate a program
+fn evaluation_arguments() -> [clap::Arg; 13] {
+    [
+        clap::Arg::new("print-each")
+            .long("print-each")
+            .help("Print the value of every top level expression, not only the last")
+            .action(clap::ArgAction::SetTrue),
+        clap::Arg::new("backend")
+            .long("backend")
+            .help("Compile to bytecode and run it on a stack machine, or walk the syntax tree to compare")
+            .value_parser(["vm", "tree"])
+            .default_value("vm"),
+        clap::Arg::new("overflow")
+            .long("overflow")
+            .help("What integer arithmetic does when a result doesn't fit")
+            .value_parser(["checked", "wrap"])
+            .default_value("checked"),
+        layout_argument(),
+        clap::Arg::new("no-std")
+            .long("no-std")
+            .help("Leave out every builtin function and the constants PI and E, so the program can only compute")
+            .action(clap::ArgAction::SetTrue),
+        clap::Arg::new("precision")
+            .long("precision")
+            .help("Print a float result with this many digits after the decimal point")
+            .value_parser(clap::value_parser!(usize)),
+        clap::Arg::new("seed")
+            .long("seed")
+            .help("Seed the random builtins, the same as calling seed(n) first")
+            .value_parser(clap::value_parser!(i64))
+            .allow_negative_numbers(true),
+        clap::Arg::new("seed-vars")
+            .long("seed-vars")
+            .value_name("name=value")
+            .help("Define a variable to a number before the program runs, can be repeated")
+            .value_parser(parse_seed_var)
+            .action(clap::ArgAction::Append),
+        clap::Arg::new("scientific")
+            .long("scientific")
+            .help("Print a float result in exponential notation, like 3.3e-1")
+            .action(clap::ArgAction::SetTrue),
+        clap::Arg::new("trace")
+            .long("trace")
+            .help("Print every instruction and the top of the stack to stderr, runs on the vm")
+            .action(clap::ArgAction::SetTrue),
+        clap::Arg::new("trace-limit")
+            .long("trace-limit")
+            .help("Stop the trace after this many instructions, the program keeps running")
+            .value_parser(clap::value_parser!(usize))
+            .requires("trace"),
+        clap::Arg::new("dump-config")
+            .long("dump-config")
+            .help("Print the settings the program would run with, after defaults, and exit")
+            .action(clap::ArgAction::SetTrue),
+        clap::Arg::new("json-result")
+            .long("json-result")
+            .help("Print the result or the error as one line of JSON on stdout, after any output of the program")
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with("print-each"),
+    ]
+}
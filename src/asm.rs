@@ -0,0 +1,429 @@
+use crate::c_backend::{function_name, global_name};
+use crate::diagnostic::Diagnostic;
+use crate::eval::Value;
+use crate::ir::{Callee, Function, Module, Operation, Target, Temporary, Terminator, Variable};
+use crate::kinds::{Kind, Kinds};
+use crate::parser::OperatorToken;
+use crate::target::{ObjectFormat, TargetSpec};
+
+/// Registers of the first six integer arguments in the System V ABI
+const ARGUMENT_REGISTERS: [&str; 6] = ["%rdi", "%rsi", "%rdx", "%rcx", "%r8", "%r9"];
+
+/// Printing through `printf`, `ph_print_int(value, end)` and `ph_print_bool(value, end)`
+/// write the value followed by the character `end`, and arithmetic that overflows jumps to
+/// `ph_overflow`, which writes out what was printed before stopping. `$printf`, `$putchar`,
+/// `$fflush` and `%rodata` are filled in for the object format, and `%L` with the local label
+/// prefix of the target
+const RUNTIME: &str = "\
+ph_overflow:
+    xorl %edi, %edi
+    call $fflush
+    ud2
+
+ph_print_int:
+    movq %rsi, %rdx
+    movq %rdi, %rsi
+    leaq %Lph_int(%rip), %rdi
+    xorl %eax, %eax
+    jmp $printf
+
+ph_print_bool:
+    movq %rsi, %rdx
+    leaq %Lph_true(%rip), %rsi
+    leaq %Lph_false(%rip), %rax
+    testq %rdi, %rdi
+    cmovzq %rax, %rsi
+    leaq %Lph_bool(%rip), %rdi
+    xorl %eax, %eax
+    jmp $printf
+
+ph_print_newline:
+    movl $10, %edi
+    jmp $putchar
+
+    .section %rodata
+%Lph_int:
+    .asciz \"%ld%c\"
+%Lph_bool:
+    .asciz \"%s%c\"
+%Lph_true:
+    .asciz \"true\"
+%Lph_false:
+    .asciz \"false\"
+";
+
+/// Translate the integer subset of a program to x86-64 assembly in AT&T syntax, for
+/// `ph build --emit asm`
+///
+/// Ints, comparisons, conditionals, user functions and `print` are supported, every value
+/// lives in a stack slot of its function and calls follow the System V ABI. Assemble and
+/// link it with `gcc script.s -o script`.
+///
+/// Where `ph run` fails with an error, the program stops with a signal instead: arithmetic
+/// that overflows runs `ud2`, which is `SIGILL`, and dividing by zero or `/ -1` overflowing is
+/// the hardware's divide error, `SIGFPE`. Unlike `ph run`, shifts use the low 6 bits of the
+/// amount, and deep recursion overflows the stack instead of being an error.
+///
+/// `target` picks the symbol names and directives of Linux or macOS. Fails on anything
+/// `crate::kinds::check` rejects.
+pub fn generate(
+    module: &Module,
+    file_name: &str,
+    target: &TargetSpec,
+) -> Result<String, Diagnostic> {
+    let kinds: Kinds = crate::kinds::check(module, "asm")?;
+    let main: String = format!("{}main", target.symbol_prefix);
+
+    let mut output: String = format!(
+        "# Generated by `ph build --emit asm --target {}` from {}\n\n    .text\n    .globl {}\n",
+        target.name,
+        file_name.replace('\n', " "),
+        main
+    );
+
+    for (function, function_kinds) in module.functions.iter().zip(&kinds.functions) {
+        let mut emitter: Emitter = Emitter::new(module, function, function_kinds, target);
+        emitter.function(&function_name(&function.name), None);
+        output.push('\n');
+        output.push_str(&emitter.output);
+    }
+
+    let mut emitter: Emitter = Emitter::new(module, &module.main, &kinds.main, target);
+    emitter.function(&main, Some(module.has_result));
+    output.push('\n');
+    output.push_str(&emitter.output);
+
+    // ELF calls libc through the PLT, Mach-O resolves the stubs when linking
+    let (printf, putchar, fflush, rodata): (&str, &str, &str, &str) = match target.object_format {
+        ObjectFormat::MachO => (
+            "_printf",
+            "_putchar",
+            "_fflush",
+            "__TEXT,__cstring,cstring_literals",
+        ),
+        _ => ("printf@PLT", "putchar@PLT", "fflush@PLT", ".rodata"),
+    };
+    output.push('\n');
+    output.push_str(
+        &RUNTIME
+            .replace("$printf", printf)
+            .replace("$putchar", putchar)
+            .replace("$fflush", fflush)
+            .replace("%rodata", rodata)
+            .replace("%L", target.local_label_prefix),
+    );
+
+    if !kinds.globals.is_empty() {
+        match target.object_format {
+            ObjectFormat::MachO => {
+                for global in &kinds.globals {
+                    output.push_str(&format!(
+                        "\n    .zerofill __DATA,__bss,{},8,3",
+                        global_name(global)
+                    ));
+                }
+                output.push('\n');
+            }
+            _ => {
+                output.push_str("\n    .bss\n    .align 8\n");
+                for global in &kinds.globals {
+                    output.push_str(&format!("{}:\n    .zero 8\n", global_name(global)));
+                }
+            }
+        }
+    }
+
+    // The stack doesn't need to be executable
+    if target.object_format == ObjectFormat::Elf {
+        output.push_str("\n    .section .note.GNU-stack,\"\",@progbits\n");
+    }
+
+    Ok(output)
+}
+
+struct Emitter<'a> {
+    module: &'a Module,
+    function: &'a Function,
+    kinds: &'a [Kind],
+    target: &'a TargetSpec,
+    output: String,
+}
+
+impl<'a> Emitter<'a> {
+    fn new(
+        module: &'a Module,
+        function: &'a Function,
+        kinds: &'a [Kind],
+        target: &'a TargetSpec,
+    ) -> Emitter<'a> {
+        Emitter {
+            module,
+            function,
+            kinds,
+            target,
+            output: String::new(),
+        }
+    }
+
+    fn line(&mut self, text: &str) {
+        self.output.push_str("    ");
+        self.output.push_str(text);
+        self.output.push('\n');
+    }
+
+    /// Locals come first in the frame, then temporaries
+    fn local(&self, slot: usize) -> String {
+        format!("-{}(%rbp)", 8 * (slot + 1))
+    }
+
+    fn temporary(&self, temporary: Temporary) -> String {
+        self.local(self.function.locals.len() + temporary)
+    }
+
+    fn label(&self, symbol: &str, block: usize) -> String {
+        format!("{}{}_b{}", self.target.local_label_prefix, symbol, block)
+    }
+
+    /// Emit the function as `symbol`, `main` gives whether the top level code has a result
+    /// to print
+    fn function(&mut self, symbol: &str, main: Option<bool>) {
+        let slots: usize = self.function.locals.len() + self.kinds.len();
+        // Keeps the stack 16 byte aligned for calls
+        let frame: usize = (8 * slots).div_ceil(16) * 16;
+
+        self.output.push_str(&format!("{}:\n", symbol));
+        self.line("pushq %rbp");
+        self.line("movq %rsp, %rbp");
+        if frame > 0 {
+            self.line(&format!("subq ${}, %rsp", frame));
+        }
+        for slot in 0..self.function.parameters.len() {
+            let source: String = match ARGUMENT_REGISTERS.get(slot) {
+                Some(register) => register.to_string(),
+                None => {
+                    self.line(&format!("movq {}(%rbp), %rax", 16 + 8 * (slot - 6)));
+                    "%rax".to_string()
+                }
+            };
+            self.line(&format!("movq {}, {}", source, self.local(slot)));
+        }
+
+        for (id, block) in self.function.blocks.iter().enumerate() {
+            if id > 0 {
+                self.output
+                    .push_str(&format!("{}:\n", self.label(symbol, id)));
+            }
+
+            for operation in &block.operations {
+                self.operation(operation);
+            }
+
+            match &block.terminator {
+                Terminator::Jump(target) => self.jump(symbol, id, target, true),
+                Terminator::Branch {
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    self.line(&format!("movq {}, %rax", self.temporary(*condition)));
+                    self.line("testq %rax, %rax");
+                    if otherwise.arguments.is_empty() {
+                        self.line(&format!("jz {}", self.label(symbol, otherwise.block)));
+                        self.jump(symbol, id, then, true);
+                    } else {
+                        // The false path moves values first, so it gets a label of its own
+                        let label: String = format!("{}_f", self.label(symbol, id));
+                        self.line(&format!("jz {}", label));
+                        self.jump(symbol, id, then, false);
+                        self.output.push_str(&format!("{}:\n", label));
+                        self.jump(symbol, id, otherwise, true);
+                    }
+                }
+                Terminator::Return(value) => {
+                    match main {
+                        // Like `ph run`, a nil result isn't printed
+                        Some(has_result) => {
+                            if has_result && self.kinds[*value] != Kind::Nil {
+                                self.print(&[*value]);
+                            }
+                            self.line("xorl %eax, %eax");
+                        }
+                        None => self.line(&format!("movq {}, %rax", self.temporary(*value))),
+                    }
+                    self.line("leave");
+                    self.line("ret");
+                }
+            }
+        }
+    }
+
+    /// Move the arguments into the parameters of `target` and go there, `fall_through` leaves
+    /// out a jump to the next block
+    fn jump(&mut self, symbol: &str, id: usize, target: &Target, fall_through: bool) {
+        let parameters: &[Temporary] = &self.function.blocks[target.block].parameters;
+        for (parameter, argument) in parameters.iter().zip(&target.arguments) {
+            self.line(&format!("movq {}, %rax", self.temporary(*argument)));
+            self.line(&format!("movq %rax, {}", self.temporary(*parameter)));
+        }
+
+        if !(fall_through && target.block == id + 1) {
+            self.line(&format!("jmp {}", self.label(symbol, target.block)));
+        }
+    }
+
+    fn operation(&mut self, operation: &Operation) {
+        match operation {
+            Operation::Constant(destination, value) => {
+                let bits: isize = match value {
+                    Value::Int(value) => *value,
+                    Value::Bool(value) => isize::from(*value),
+                    _ => 0,
+                };
+                if i32::try_from(bits).is_ok() {
+                    self.line(&format!("movq ${}, {}", bits, self.temporary(*destination)));
+                } else {
+                    self.line(&format!("movabsq ${}, %rax", bits));
+                    self.line(&format!("movq %rax, {}", self.temporary(*destination)));
+                }
+            }
+            Operation::Negate(destination, operand) => {
+                self.line(&format!("movq {}, %rax", self.temporary(*operand)));
+                self.line("negq %rax");
+                self.line("jo ph_overflow");
+                self.line(&format!("movq %rax, {}", self.temporary(*destination)));
+            }
+            Operation::Binary {
+                destination,
+                operator_token,
+                left,
+                right,
+            }
+            | Operation::Compare {
+                destination,
+                operator_token,
+                left,
+                right,
+            } => self.binary(*destination, *operator_token, *left, *right),
+            Operation::Load(destination, variable) => {
+                self.line(&format!("movq {}, %rax", self.variable(variable)));
+                self.line(&format!("movq %rax, {}", self.temporary(*destination)));
+            }
+            Operation::Store(variable, value) | Operation::Define(variable, value) => {
+                self.line(&format!("movq {}, %rax", self.temporary(*value)));
+                self.line(&format!("movq %rax, {}", self.variable(variable)));
+            }
+            Operation::Call {
+                destination,
+                callee: Callee::Function(index),
+                arguments,
+                ..
+            } => {
+                // Arguments past the sixth go on the stack, which stays 16 byte aligned
+                let stacked: usize = arguments.len().saturating_sub(ARGUMENT_REGISTERS.len());
+                if stacked % 2 == 1 {
+                    self.line("subq $8, %rsp");
+                }
+                for argument in arguments.iter().skip(ARGUMENT_REGISTERS.len()).rev() {
+                    self.line(&format!("pushq {}", self.temporary(*argument)));
+                }
+                for (argument, register) in arguments.iter().zip(ARGUMENT_REGISTERS) {
+                    self.line(&format!("movq {}, {}", self.temporary(*argument), register));
+                }
+                self.line(&format!(
+                    "call {}",
+                    function_name(&self.module.functions[*index].name)
+                ));
+                if stacked > 0 {
+                    self.line(&format!("addq ${}, %rsp", 8 * (stacked + stacked % 2)));
+                }
+                self.line(&format!("movq %rax, {}", self.temporary(*destination)));
+            }
+            // `print` is the only builtin that passes the check
+            Operation::Call {
+                callee: Callee::Builtin,
+                arguments,
+                ..
+            } => self.print(arguments),
+            _ => {}
+        }
+    }
+
+    fn binary(
+        &mut self,
+        destination: Temporary,
+        operator_token: OperatorToken,
+        left: Temporary,
+        right: Temporary,
+    ) {
+        self.line(&format!("movq {}, %rax", self.temporary(left)));
+        self.line(&format!("movq {}, %rcx", self.temporary(right)));
+
+        let condition: Option<&str> = match operator_token {
+            OperatorToken::Less => Some("l"),
+            OperatorToken::LessEquals => Some("le"),
+            OperatorToken::Greater => Some("g"),
+            OperatorToken::GreaterEquals => Some("ge"),
+            OperatorToken::EqualsEquals => Some("e"),
+            OperatorToken::BangEquals => Some("ne"),
+            _ => None,
+        };
+        match (condition, operator_token) {
+            (Some(condition), _) => {
+                self.line("cmpq %rcx, %rax");
+                self.line(&format!("set{} %al", condition));
+                self.line("movzbq %al, %rax");
+            }
+            (None, OperatorToken::Plus | OperatorToken::Minus | OperatorToken::Star) => {
+                let instruction: &str = match operator_token {
+                    OperatorToken::Plus => "addq",
+                    OperatorToken::Minus => "subq",
+                    _ => "imulq",
+                };
+                self.line(&format!("{} %rcx, %rax", instruction));
+                self.line("jo ph_overflow");
+            }
+            // The quotient is left in `rax` and the remainder in `rdx`
+            (None, OperatorToken::Slash | OperatorToken::Percent) => {
+                self.line("cqo");
+                self.line("idivq %rcx");
+                if operator_token == OperatorToken::Percent {
+                    self.line("movq %rdx, %rax");
+                }
+            }
+            (None, OperatorToken::Ampersand) => self.line("andq %rcx, %rax"),
+            (None, OperatorToken::Pipe) => self.line("orq %rcx, %rax"),
+            (None, OperatorToken::Caret) => self.line("xorq %rcx, %rax"),
+            (None, OperatorToken::LessLess) => self.line("salq %cl, %rax"),
+            (None, _) => self.line("sarq %cl, %rax"),
+        }
+
+        self.line(&format!("movq %rax, {}", self.temporary(destination)));
+    }
+
+    /// Address of a variable
+    fn variable(&self, variable: &Variable) -> String {
+        match variable {
+            Variable::Local(slot) => self.local(*slot),
+            Variable::Global(name) => format!("{}(%rip)", global_name(name)),
+        }
+    }
+
+    /// Print values separated by spaces on one line, like `print`
+    fn print(&mut self, values: &[Temporary]) {
+        if values.is_empty() {
+            self.line("call ph_print_newline");
+        }
+
+        for (index, value) in values.iter().enumerate() {
+            let function: &str = match self.kinds[*value] {
+                Kind::Bool => "ph_print_bool",
+                _ => "ph_print_int",
+            };
+            let end: char = if index + 1 == values.len() { '\n' } else { ' ' };
+
+            self.line(&format!("movq {}, %rdi", self.temporary(*value)));
+            self.line(&format!("movl ${}, %esi", end as u32));
+            self.line(&format!("call {}", function));
+        }
+    }
+}
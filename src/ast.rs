@@ -0,0 +1,159 @@
+use crate::lexer::StringSegment;
+use crate::parser::{ChainLink, ExpressionSyntaxEnum, ParseError, Parser, StatementSyntax};
+
+/// Format statements as an indented tree, one node per line
+pub fn format_statements(statements: &[StatementSyntax]) -> String {
+    let mut output: String = String::new();
+
+    for statement in statements {
+        format_statement(statement, 0, &mut output);
+    }
+
+    output
+}
+
+fn format_statement(statement: &StatementSyntax, depth: usize, output: &mut String) {
+    match statement {
+        StatementSyntax::Let(let_syntax) => {
+            output.push_str(&format!("{}Let {}\n", "  ".repeat(depth), let_syntax.name));
+            format_expression(&let_syntax.value, depth + 1, output);
+        }
+        StatementSyntax::Assign(assign) => {
+            output.push_str(&format!("{}Assign {}\n", "  ".repeat(depth), assign.name));
+            format_expression(&assign.value, depth + 1, output);
+        }
+        StatementSyntax::Function(function) => {
+            output.push_str(&format!(
+                "{}Function {}({})\n",
+                "  ".repeat(depth),
+                function.name,
+                function.parameters.join(", ")
+            ));
+            for statement in &function.body {
+                format_statement(statement, depth + 1, output);
+            }
+        }
+        StatementSyntax::Import(import) => {
+            output.push_str(&format!("{}Import {:?}\n", "  ".repeat(depth), import.path));
+        }
+        StatementSyntax::Expression(expression) => {
+            format_expression(expression, depth, output);
+        }
+    }
+}
+
+fn format_expression(expression: &ExpressionSyntaxEnum, depth: usize, output: &mut String) {
+    output.push_str(&"  ".repeat(depth));
+
+    match expression {
+        ExpressionSyntaxEnum::ExpressionSyntax(binary) => {
+            output.push_str(&format!("Binary {}\n", binary.operator_token.text()));
+            format_expression(&binary.left, depth + 1, output);
+            format_expression(&binary.right, depth + 1, output);
+        }
+        ExpressionSyntaxEnum::Unary(unary) => {
+            output.push_str(&format!("Unary {}\n", unary.operator_token.text()));
+            format_expression(&unary.operand, depth + 1, output);
+        }
+        ExpressionSyntaxEnum::Conditional(conditional) => {
+            output.push_str("Conditional\n");
+            format_expression(&conditional.condition, depth + 1, output);
+            format_expression(&conditional.then, depth + 1, output);
+            format_expression(&conditional.otherwise, depth + 1, output);
+        }
+        ExpressionSyntaxEnum::Chain(chain) => {
+            let operators: Vec<&str> = chain
+                .links
+                .iter()
+                .map(|link: &ChainLink| link.operator_token.text())
+                .collect();
+            output.push_str(&format!("Chain {}\n", operators.join(" ")));
+            format_expression(&chain.first, depth + 1, output);
+            for link in &chain.links {
+                format_expression(&link.operand, depth + 1, output);
+            }
+        }
+        ExpressionSyntaxEnum::Index(index) => {
+            output.push_str("Index\n");
+            format_expression(&index.target, depth + 1, output);
+            format_expression(&index.index, depth + 1, output);
+        }
+        ExpressionSyntaxEnum::Array(elements) => {
+            output.push_str("Array\n");
+            for element in elements {
+                format_expression(element, depth + 1, output);
+            }
+        }
+        ExpressionSyntaxEnum::Number(value) => output.push_str(&format!("Number {}\n", value)),
+        ExpressionSyntaxEnum::Float(value) => output.push_str(&format!("Float {:?}\n", value)),
+        ExpressionSyntaxEnum::String(segments) => {
+            output.push_str("String \"");
+            for segment in segments {
+                match segment {
+                    StringSegment::Text(text) => {
+                        output.push_str(&text.escape_default().to_string().replace('$', "\\$"))
+                    }
+                    StringSegment::EnvironmentVariable { name, .. } => {
+                        output.push_str(&format!("${{{}}}", name))
+                    }
+                }
+            }
+            output.push_str("\"\n");
+        }
+        ExpressionSyntaxEnum::Variable(variable) => {
+            output.push_str(&format!("Variable {}\n", variable.name))
+        }
+        ExpressionSyntaxEnum::Call(call) => {
+            output.push_str(&format!("Call {}\n", call.name));
+            for argument in &call.arguments {
+                format_expression(argument, depth + 1, output);
+            }
+        }
+    }
+}
+
+pub fn ast(args: clap::ArgMatches) {
+    // Get file to parse
+    let file: &String;
+    let folded: bool;
+    let layout: bool;
+    if let Some(arg_match) = args.subcommand_matches("ast") {
+        file = arg_match
+            .get_one::<String>("file")
+            .expect("Failed to get file");
+        folded = arg_match.get_flag("folded");
+        layout = arg_match.get_flag("layout");
+    } else {
+        unreachable!("Subcommand is required");
+    }
+
+    // Get file contents
+    let file_contents: String = crate::source::read_source(file);
+
+    let mut parser: Parser = if layout {
+        Parser::with_layout(file_contents.clone())
+    } else {
+        Parser::new(file_contents.clone())
+    };
+    let statements: Result<Vec<StatementSyntax>, ParseError> = parser.parse_statements();
+
+    let mut statements: Vec<StatementSyntax> = match statements {
+        Ok(statements) => statements,
+        Err(error) => {
+            eprint!(
+                "{}",
+                crate::diagnostic::Diagnostic::from(error).render(file, &file_contents)
+            );
+            std::process::exit(1);
+        }
+    };
+
+    if folded {
+        if let Err(diagnostic) = crate::fold::fold_statements(&mut statements) {
+            eprint!("{}", diagnostic.render(file, &file_contents));
+            std::process::exit(1);
+        }
+    }
+
+    print!("{}", format_statements(&statements));
+}
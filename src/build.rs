@@ -1,296 +1,653 @@
-/// Lexer for the language
-#[derive(Clone, Debug)]
-struct Lexer {
-    text: String,
-    position: usize,
-    syntax_token: SyntaxToken,
+use crate::diagnostic::Diagnostic;
+use crate::import::Loaded;
+use crate::lexer::{Lexer, SyntaxToken};
+use crate::parser::{collect_tokens, StatementSyntax};
+use crate::target::TargetSpec;
+
+/// Artifact asked for with `--emit`, like `ast`, `bytecode-after=peephole` or `ir=prog.txt`
+struct Emit<'a> {
+    /// Artifact as written, without the file
+    name: &'a str,
+    form: &'a str,
+    /// Pass of `ast-after=` and `bytecode-after=`
+    after: Option<&'a str>,
+    /// File after the `=`, `-` is stdout
+    path: Option<&'a str>,
 }
 
-#[derive(Clone, Debug)]
-struct SyntaxToken {
-    text: String,
-    token_type: SyntaxTokenType,
+impl<'a> Emit<'a> {
+    fn new(value: &'a str) -> Emit<'a> {
+        let (name, path): (&str, Option<&str>) = crate::args::split_emit(value);
+        let (form, after): (&str, Option<&str>) = match name.split_once("-after=") {
+            Some((form, pass)) => (form, Some(pass)),
+            None => (name, None),
+        };
+
+        Emit {
+            name,
+            form,
+            after,
+            path,
+        }
+    }
+
+    /// Extension of the file the artifact is written to when there are several
+    fn extension(&self) -> String {
+        let extension: &str = match self.form {
+            "asm" => "s",
+            "llvm-ir" => "ll",
+            form => form,
+        };
+
+        match self.after {
+            Some(pass) => format!("{}.{}", pass, extension),
+            None => extension.to_string(),
+        }
+    }
+}
+
+/// Settings of `ph build` that decide how artifacts are generated and where they go
+struct Options<'a> {
+    opt_level: u8,
+    /// `None` leaves the choice to each backend
+    target: Option<TargetSpec>,
+    output: Option<&'a String>,
+    keep_intermediates: bool,
+    /// Indentation delimits function bodies, the tokens artifact shows `Indent` and `Dedent`
+    layout: bool,
+}
+
+impl Options<'_> {
+    /// Target `backend` generates code for
+    fn target(&self, backend: &str) -> TargetSpec {
+        self.target
+            .unwrap_or_else(|| TargetSpec::default_for(backend))
+    }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-enum SyntaxTokenType {
-    WhiteSpace,
-    Number(std::result::Result<isize, std::num::ParseIntError>),
-    Plus,
-    Minus,
-    Star,
-    Slash,
-    OpenParenthesis,
-    CloseParenthesis,
-    BadToken,
-    EndOfFile,
+/// Step of the pipeline that failed to produce an artifact, and why
+#[derive(Clone, Debug)]
+struct Failure {
+    stage: &'static str,
+    diagnostics: Vec<Diagnostic>,
 }
 
-impl Lexer {
-    /// Get the current character in the text
-    fn current(&self) -> char {
-        if self.position >= self.text.len() {
-            return '\0';
+impl Failure {
+    fn new(stage: &'static str, diagnostic: impl Into<Diagnostic>) -> Failure {
+        Failure {
+            stage,
+            diagnostics: vec![diagnostic.into()],
         }
-        self.text.chars().nth(self.position).unwrap()
     }
+}
+
+/// Settings a build uses after the manifest and defaults are applied, one `key = value` line
+/// each in TOML syntax like `ph run --dump-config`, unset options are commented out
+fn format_config(
+    file: &str,
+    emits: &[&str],
+    link: bool,
+    lenient: bool,
+    options: &Options,
+) -> String {
+    let emits: Vec<String> = emits
+        .iter()
+        .map(|emit: &&str| format!("{:?}", emit))
+        .collect();
+    let optional = |name: &str, value: Option<String>| -> String {
+        match value {
+            Some(value) => format!("{} = {:?}\n", name, value),
+            None => format!("# {} is not set\n", name),
+        }
+    };
 
-    /// Get the next token in the text
-    fn next_token(&mut self) {
-        // Whitespace
-        if self.current().is_whitespace() {
-            let start: usize = self.position;
+    let mut config: String = String::new();
+    config.push_str(&format!("file = {:?}\n", file));
+    config.push_str(&format!("opt_level = {}\n", options.opt_level));
+    config.push_str(&format!("layout = {}\n", options.layout));
+    config.push_str(&format!("lenient = {}\n", lenient));
+    config.push_str(&format!("emit = [{}]\n", emits.join(", ")));
+    config.push_str(&optional("output", options.output.cloned()));
+    config.push_str(&optional(
+        "target",
+        options
+            .target
+            .map(|target: TargetSpec| target.name.to_string()),
+    ));
+    config.push_str(&format!("link = {}\n", link));
+    config.push_str(&format!(
+        "keep_intermediates = {}\n",
+        options.keep_intermediates
+    ));
+
+    config
+}
 
-            while self.current().is_whitespace() {
-                self.position += 1;
+pub fn build(args: clap::ArgMatches) {
+    // Get file or inline source to build, the project's entry point without either
+    let project: Option<crate::project::Project>;
+    let file: &str;
+    let file_contents: String;
+    let emits: Vec<&str>;
+    let count_tokens: bool;
+    let output: Option<&String>;
+    let link: bool;
+    let keep_intermediates: bool;
+    let target: Option<TargetSpec>;
+    let dump_config: bool;
+    let opt_level: u8;
+    let layout: bool;
+    let lenient: bool;
+    let watch: bool;
+    if let Some(arg_match) = args.subcommand_matches("build") {
+        dump_config = arg_match.get_flag("dump-config");
+        if let Some(expression) = arg_match.get_one::<String>("expr") {
+            project = None;
+            file = "<expr>";
+            file_contents = expression.clone();
+        } else {
+            project = match arg_match.contains_id("file") {
+                true => None,
+                false => Some(crate::project::find_project()),
+            };
+            let path: &str = match &project {
+                Some(project) => &project.entry,
+                None => arg_match
+                    .get_one::<String>("file")
+                    .expect("Failed to get file"),
+            };
+            // Stdin is read once, there is nothing to watch
+            if arg_match.get_flag("watch") {
+                crate::watch::reject_stdin(path);
             }
+            // The settings don't depend on the source, which may be stdin
+            (file, file_contents) = match dump_config {
+                true => (path, String::new()),
+                false => crate::source::read_input(path),
+            };
+        }
+        emits = if arg_match.get_flag("ast") {
+            vec!["ast"]
+        } else if arg_match.get_flag("dump-ir") {
+            vec!["ir"]
+        } else {
+            arg_match
+                .get_many::<String>("emit")
+                .map(|emits: clap::parser::ValuesRef<String>| emits.map(String::as_str).collect())
+                .unwrap_or_default()
+        };
+        count_tokens = arg_match.get_flag("count-tokens");
+        output = arg_match.get_one::<String>("output");
+        link = arg_match.get_flag("link");
+        keep_intermediates = arg_match.get_flag("keep-intermediates");
+        layout = arg_match.get_flag("layout");
+        lenient = arg_match.get_flag("lenient");
+        watch = arg_match.get_flag("watch");
+        target = arg_match.get_one::<TargetSpec>("target").copied();
+        // The command line overrides the manifest
+        opt_level = crate::passes::given_opt_level(arg_match)
+            .or_else(|| {
+                project
+                    .as_ref()
+                    .and_then(|project: &crate::project::Project| project.manifest.opt_level)
+            })
+            .unwrap_or(0);
+    } else {
+        unreachable!("Subcommand is required");
+    }
 
-            let length: usize = self.position - start;
-            let text: &str = &self.text[start..start + length];
+    let options: Options = Options {
+        opt_level,
+        target,
+        output,
+        keep_intermediates,
+        layout,
+    };
 
-            self.syntax_token = SyntaxToken {
-                text: text.to_string(),
-                token_type: SyntaxTokenType::WhiteSpace,
-            };
+    if dump_config {
+        print!("{}", format_config(file, &emits, link, lenient, &options));
+        return;
+    }
 
-            return;
-        // Number
-        } else if self.current().is_numeric() {
-            let start: usize = self.position;
+    if watch {
+        crate::watch::watch(file, layout, lenient);
+        return;
+    }
 
-            while self.current().is_numeric() {
-                self.position += 1;
-            }
+    crate::log::verbose(&format!("Building {}", file));
 
-            let length: usize = self.position - start;
-            let text: &str = &self.text[start..start + length];
-            let value: std::result::Result<isize, std::num::ParseIntError> = text.parse::<isize>();
+    if count_tokens {
+        print!(
+            "{}",
+            crate::tokens::format_token_counts(&lex(file_contents, layout))
+        );
+        return;
+    }
 
-            self.syntax_token = SyntaxToken {
-                text: text.to_string(),
-                token_type: SyntaxTokenType::Number(value),
-            };
-            return;
-        }
+    let start: std::time::Instant = std::time::Instant::now();
+    let loaded: Loaded = crate::import::load_program(file, &file_contents, layout, lenient);
 
-        // Operators
-        match self.current() {
-            '+' => {
-                self.position += 1;
-                self.syntax_token = SyntaxToken {
-                    text: "+".to_string(),
-                    token_type: SyntaxTokenType::Plus,
-                };
-            }
-            '-' => {
-                self.position += 1;
-                self.syntax_token = SyntaxToken {
-                    text: "-".to_string(),
-                    token_type: SyntaxTokenType::Minus,
-                };
-            }
-            '*' => {
-                self.position += 1;
-                self.syntax_token = SyntaxToken {
-                    text: "*".to_string(),
-                    token_type: SyntaxTokenType::Star,
-                };
-            }
-            '/' => {
-                self.position += 1;
-                self.syntax_token = SyntaxToken {
-                    text: "/".to_string(),
-                    token_type: SyntaxTokenType::Slash,
-                };
-            }
-            '(' => {
-                self.position += 1;
-                self.syntax_token = SyntaxToken {
-                    text: "(".to_string(),
-                    token_type: SyntaxTokenType::OpenParenthesis,
-                };
-            }
-            ')' => {
-                self.position += 1;
-                self.syntax_token = SyntaxToken {
-                    text: ")".to_string(),
-                    token_type: SyntaxTokenType::CloseParenthesis,
-                };
-            }
-            '\0' => {
-                self.position += 1;
-                self.syntax_token = SyntaxToken {
-                    text: "".to_string(),
-                    token_type: SyntaxTokenType::EndOfFile,
-                };
+    // Same front end as `ph check`, so a program it accepts builds, run in two stages so
+    // `--emit` can tell which one failed
+    let statements: Result<Vec<StatementSyntax>, Failure> =
+        crate::check::front_end(&loaded.source, true, layout, lenient)
+            .map_err(|diagnostics: Vec<Diagnostic>| Failure {
+                stage: "parsing",
+                diagnostics,
+            })
+            .and_then(|parsed: crate::check::Checked| {
+                let warnings: Vec<Diagnostic> = crate::check::check_statements(&parsed.statements)
+                    .map_err(|diagnostics: Vec<Diagnostic>| Failure {
+                        stage: "checking",
+                        diagnostics,
+                    })?;
+                for warning in warnings {
+                    eprint!(
+                        "{}",
+                        warning.render_imported(file, &loaded.imports, &loaded.source)
+                    );
+                }
+                Ok(parsed.statements)
+            });
+    crate::log::phase("Parsed and checked", start);
+
+    if link {
+        let [emit] = emits.as_slice() else {
+            eprintln!("error: --link needs exactly one of --emit c or --emit asm");
+            std::process::exit(2);
+        };
+        build_executable(Emit::new(emit), file, &loaded, statements, &options);
+        return;
+    }
+
+    if !emits.is_empty() {
+        let emits: Vec<Emit> = emits.into_iter().map(Emit::new).collect();
+        emit_artifacts(&emits, file, &loaded, statements, &options);
+        return;
+    }
+
+    let mut statements: Vec<StatementSyntax> = match statements {
+        Ok(statements) => statements,
+        Err(failure) => {
+            for diagnostic in failure.diagnostics {
+                eprint!(
+                    "{}",
+                    diagnostic.render_imported(file, &loaded.imports, &loaded.source)
+                );
             }
-            _ => {
-                self.position += 1;
-                self.syntax_token = SyntaxToken {
-                    text: "".to_string(),
-                    token_type: SyntaxTokenType::BadToken,
-                };
+            std::process::exit(1);
+        }
+    };
+
+    let start: std::time::Instant = std::time::Instant::now();
+    match crate::passes::run_ast_passes(&mut statements, opt_level, None) {
+        Ok(warnings) => {
+            for warning in warnings {
+                eprint!(
+                    "{}",
+                    warning.render_imported(file, &loaded.imports, &loaded.source)
+                );
             }
         }
+        Err(diagnostic) => {
+            eprint!(
+                "{}",
+                diagnostic.render_imported(file, &loaded.imports, &loaded.source)
+            );
+            std::process::exit(1);
+        }
     }
-}
 
-/// Parser for the language
-#[derive(Debug)]
-struct Parser {
-    lexer: Lexer,
-    position: usize,
-    tokens: Vec<SyntaxToken>,
-}
+    crate::log::phase("Ran the syntax tree passes", start);
+    crate::log::debug("statements", &statements);
 
-impl Parser {
-    fn parse(&mut self) {
-        loop {
-            self.lexer.next_token();
-
-            // Whitespace or bad token
-            if self.lexer.syntax_token.token_type == SyntaxTokenType::WhiteSpace
-                || self.lexer.syntax_token.token_type == SyntaxTokenType::BadToken
-            {
-                continue;
-            // End of file
-            } else if self.lexer.syntax_token.token_type == SyntaxTokenType::EndOfFile {
-                break;
-            } else {
-                self.tokens.push(self.lexer.syntax_token.clone());
-            }
+    // The bytecode goes next to the source unless `-o` says otherwise, or into `out.phc` for
+    // source from stdin and `expr.phc` for `--expr`
+    let output: std::path::PathBuf = match (output, file) {
+        (Some(output), _) => std::path::PathBuf::from(output),
+        (None, crate::source::STDIN_NAME) => std::path::PathBuf::from("out.phc"),
+        (None, "<expr>") => std::path::PathBuf::from("expr.phc"),
+        (None, file) => std::path::Path::new(file).with_extension("phc"),
+    };
+    let start: std::time::Instant = std::time::Instant::now();
+    let mut program: crate::bytecode::Program = match crate::bytecode::compile(&statements) {
+        Ok(program) => program,
+        Err(error) => {
+            eprint!(
+                "{}",
+                Diagnostic::from(error).render_imported(file, &loaded.imports, &loaded.source)
+            );
+            std::process::exit(1);
         }
+    };
+
+    crate::passes::run_bytecode_passes(&mut program, opt_level, None);
+    crate::log::phase("Compiled to bytecode", start);
+    crate::log::debug("program", &program);
+
+    crate::log::verbose(&format!("Writing {}", output.display()));
+    if let Err(error) = write_atomic(
+        &output,
+        &crate::phc::encode(&program, file, &loaded.source, &loaded.imports),
+    ) {
+        eprintln!("error: failed to write '{}': {}", output.display(), error);
+        std::process::exit(1);
     }
+}
 
-    fn peek(&self, offset: usize) -> SyntaxToken {
-        let index: usize = self.position + offset;
+/// Generate C or assembly and compile it into an executable with the system C compiler
+fn build_executable(
+    emit: Emit,
+    file: &str,
+    loaded: &Loaded,
+    statements: Result<Vec<StatementSyntax>, Failure>,
+    options: &Options,
+) {
+    let extension: &str = match (emit.form, emit.path) {
+        ("c", None) => "c",
+        ("asm", None) => "s",
+        _ => {
+            eprintln!("error: --link needs exactly one of --emit c or --emit asm");
+            std::process::exit(2);
+        }
+    };
 
-        if index >= self.tokens.len() {
-            return SyntaxToken {
-                text: "".to_string(),
-                token_type: SyntaxTokenType::EndOfFile,
-            };
+    // Fail before generating anything when there is nothing to link with
+    let Some(compiler) = crate::link::find_compiler() else {
+        eprintln!("error: --link needs a C compiler, install gcc or clang, or set CC to one");
+        std::process::exit(1);
+    };
+
+    // The executable is the source without its extension, unless that is the source itself
+    let executable: std::path::PathBuf = match (options.output, file) {
+        (Some(output), _) => std::path::PathBuf::from(output),
+        (None, "<expr>") => std::path::PathBuf::from("expr"),
+        (None, crate::source::STDIN_NAME) => std::path::PathBuf::from("out"),
+        (None, file) if std::path::Path::new(file).extension().is_none() => {
+            std::path::Path::new(file).with_extension("out")
         }
+        (None, file) => std::path::Path::new(file).with_extension(""),
+    };
+    let intermediate: std::path::PathBuf = if options.keep_intermediates {
+        executable.with_extension(extension)
+    } else {
+        let name: String = executable
+            .file_name()
+            .map(|name: &std::ffi::OsStr| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        std::env::temp_dir()
+            .join(format!("ph-{}-{}", std::process::id(), name))
+            .with_extension(extension)
+    };
 
-        self.tokens[index].clone()
+    let intermediate_name: String = intermediate.to_string_lossy().into_owned();
+    let emit: Emit = Emit {
+        path: Some(&intermediate_name),
+        ..emit
+    };
+    emit_artifacts(
+        std::slice::from_ref(&emit),
+        file,
+        loaded,
+        statements,
+        options,
+    );
+
+    crate::log::verbose(&format!("Linking {}", executable.display()));
+    let start: std::time::Instant = std::time::Instant::now();
+    let linked: Result<(), String> = crate::link::link(&compiler, &intermediate, &executable);
+    crate::log::phase("Linked", start);
+    if !options.keep_intermediates {
+        let _ = std::fs::remove_file(&intermediate);
     }
-
-    fn current(&self) -> SyntaxToken {
-        self.peek(0)
+    if let Err(message) = linked {
+        eprintln!("error: {}", message);
+        std::process::exit(1);
     }
 }
 
-#[derive(Clone, Debug)]
-struct ExpressionSyntax {
-    position: usize,
-    left: ExpressionSyntaxEnum,
-    operator_token: OperatorToken,
-    right: ExpressionSyntaxEnum,
-}
-
-#[derive(Clone, Debug)]
-enum ExpressionSyntaxEnum {
-    ExpressionSyntax(Box<ExpressionSyntax>),
-    Number(isize),
-}
+/// Generate every artifact of `--emit`, then write them all, or none when a stage one of them
+/// needs failed
+fn emit_artifacts(
+    emits: &[Emit],
+    file: &str,
+    loaded: &Loaded,
+    parsed: Result<Vec<StatementSyntax>, Failure>,
+    options: &Options,
+) {
+    // The pass must run at this level to stop after it, clap checked the name
+    for emit in emits {
+        if let Some(target) = options.target {
+            let native: bool = matches!(emit.form, "c" | "asm" | "llvm-ir" | "wasm");
+            if native && !target.supports(emit.form) {
+                let targets: Vec<&str> = crate::target::TARGETS
+                    .iter()
+                    .filter(|target: &&TargetSpec| target.supports(emit.form))
+                    .map(|target: &TargetSpec| target.name)
+                    .collect();
+                eprintln!(
+                    "error: the {} backend can't generate code for {}, it supports {}",
+                    emit.form,
+                    target.name,
+                    targets.join(", ")
+                );
+                std::process::exit(2);
+            }
+        }
 
-#[derive(Clone, Debug)]
-enum OperatorToken {
-    Plus,
-    Minus,
-    Star,
-    Slash,
-}
+        if let Some(pass) = emit.after.and_then(crate::passes::find_pass) {
+            if pass.level > options.opt_level {
+                eprintln!(
+                    "error: the '{}' pass only runs with --opt-level {} or higher",
+                    pass.name, pass.level
+                );
+                std::process::exit(1);
+            }
+        }
+    }
 
-impl ExpressionSyntax {
-    fn parse(&mut self, parser: &mut Parser) {
-        // Find open parenthesis
-        self.position = parser
-            .tokens
-            .iter()
-            .position(|x: &SyntaxToken| x.text == "(")
-            .expect("Failed to find open parenthesis, expected '('");
-
-        // Get left expression
-        if let Some(value) = parser
-            .tokens
-            .iter()
-            .filter_map(|token: &SyntaxToken| match &token.token_type {
-                SyntaxTokenType::Number(value) => Some(value),
-                _ => None,
+    // Each stage runs once for all the artifacts that need it
+    let start: std::time::Instant = std::time::Instant::now();
+    let needs_optimized: bool = emits.iter().any(|emit: &Emit| {
+        !(emit.form == "tokens" || emit.form == "dot" || emit.form == "ast" && emit.after.is_some())
+    });
+    let optimized: Result<Vec<StatementSyntax>, Failure> = match &parsed {
+        Ok(statements) if needs_optimized => {
+            let mut statements: Vec<StatementSyntax> = statements.clone();
+            crate::passes::run_ast_passes(&mut statements, options.opt_level, None)
+                .map(|warnings: Vec<Diagnostic>| {
+                    for warning in warnings {
+                        eprint!(
+                            "{}",
+                            warning.render_imported(file, &loaded.imports, &loaded.source)
+                        );
+                    }
+                    statements
+                })
+                .map_err(|diagnostic: Diagnostic| {
+                    Failure::new("the syntax tree passes", diagnostic)
+                })
+        }
+        parsed => parsed.clone(),
+    };
+    let needs_module: bool = emits
+        .iter()
+        .any(|emit: &Emit| matches!(emit.form, "ir" | "c" | "asm" | "llvm-ir" | "wasm"));
+    let module: Option<Result<crate::ir::Module, Failure>> = needs_module.then(|| {
+        optimized
+            .clone()
+            .and_then(|statements: Vec<StatementSyntax>| {
+                crate::ir::lower(&statements).map_err(|error: crate::eval::RuntimeError| {
+                    Failure::new("lowering to IR", error)
+                })
             })
-            .nth(self.position)
-        {
-            self.position += 2;
-            self.left =
-                ExpressionSyntaxEnum::Number(value.clone().expect("Failed to parse number"));
+    });
+    let lowered = || -> Result<crate::ir::Module, Failure> {
+        module.clone().expect("Lowered when an artifact needs it")
+    };
+
+    let artifacts: Vec<Result<Vec<u8>, Failure>> = emits
+        .iter()
+        .map(|emit: &Emit| -> Result<Vec<u8>, Failure> {
+            let text: String = match emit.form {
+                "tokens" => crate::tokens::format_tokens(
+                    &lex(loaded.source.clone(), options.layout),
+                    &loaded.source,
+                    false,
+                ),
+                "ast" => match emit.after {
+                    Some(pass) => {
+                        let mut statements: Vec<StatementSyntax> = parsed.clone()?;
+                        crate::passes::run_ast_passes(
+                            &mut statements,
+                            options.opt_level,
+                            Some(pass),
+                        )
+                        .map_err(|diagnostic: Diagnostic| {
+                            Failure::new("the syntax tree passes", diagnostic)
+                        })?;
+                        crate::ast::format_statements(&statements)
+                    }
+                    None => crate::ast::format_statements(&optimized.clone()?),
+                },
+                // The graph shows the program as written, before any pass folds it
+                "dot" => crate::dot::format_dot(&parsed.clone()?),
+                "bytecode" => {
+                    let mut program: crate::bytecode::Program = crate::bytecode::compile(
+                        &optimized.clone()?,
+                    )
+                    .map_err(|error: crate::eval::RuntimeError| {
+                        Failure::new("compiling to bytecode", error)
+                    })?;
+                    crate::passes::run_bytecode_passes(&mut program, options.opt_level, emit.after);
+                    crate::disasm::disassemble(&program, &loaded.source, &[])
+                }
+                "ir" => crate::ir::format_module(&lowered()?),
+                "c" => crate::c_backend::generate(&lowered()?, file, &loaded.source),
+                "asm" => crate::asm::generate(&lowered()?, file, &options.target("asm")).map_err(
+                    |diagnostic: Diagnostic| Failure::new("the asm backend", diagnostic),
+                )?,
+                "llvm-ir" => crate::llvm::generate(&lowered()?, file, &options.target("llvm-ir"))
+                    .map_err(|diagnostic: Diagnostic| {
+                    Failure::new("the llvm-ir backend", diagnostic)
+                })?,
+                _ => {
+                    return crate::wasm::generate(&lowered()?).map_err(|diagnostic: Diagnostic| {
+                        Failure::new("the wasm backend", diagnostic)
+                    })
+                }
+            };
+
+            Ok(text.into_bytes())
+        })
+        .collect();
+    crate::log::phase("Generated the artifacts", start);
+
+    // A stage several artifacts need is only reported once
+    let mut reported: Vec<&'static str> = Vec::new();
+    for (emit, artifact) in emits.iter().zip(&artifacts) {
+        if let Err(failure) = artifact {
+            if !reported.contains(&failure.stage) {
+                for diagnostic in &failure.diagnostics {
+                    eprint!(
+                        "{}",
+                        diagnostic.render_imported(file, &loaded.imports, &loaded.source)
+                    );
+                }
+                reported.push(failure.stage);
+            }
+            eprintln!(
+                "error: cannot emit '{}' because {} failed",
+                emit.name, failure.stage
+            );
         }
+    }
+    if !reported.is_empty() {
+        std::process::exit(1);
+    }
 
-        // Get operator
-        self.operator_token = match parser.tokens[self.position].text.as_str() {
-            "+" => OperatorToken::Plus,
-            "-" => OperatorToken::Minus,
-            "*" => OperatorToken::Star,
-            "/" => OperatorToken::Slash,
-            _ => panic!(
-                "Invalid operator, expected '+', '-', '*', or '/', found '{}'",
-                parser.tokens[self.position].text
-            ),
+    // Several artifacts are named after the output, or the source file
+    let base: &str = match (options.output, file) {
+        (Some(output), _) => output,
+        (None, "<expr>") => "expr",
+        (None, crate::source::STDIN_NAME) => "out",
+        (None, file) => file,
+    };
+    for (emit, artifact) in emits.iter().zip(artifacts) {
+        let bytes: Vec<u8> = artifact.expect("Failures exit above");
+        let path: Option<std::path::PathBuf> = match (emit.path, options.output) {
+            (Some("-"), _) => None,
+            (Some(path), _) => Some(std::path::PathBuf::from(path)),
+            (None, output) if emits.len() == 1 => output.map(std::path::PathBuf::from),
+            (None, _) => Some(std::path::Path::new(base).with_extension(emit.extension())),
         };
 
-        self.position += 1;
-
-        // Get right expression
-        self.right = match &parser.tokens[self.position].token_type {
-            SyntaxTokenType::Number(value) => {
-                ExpressionSyntaxEnum::Number(value.clone().expect("Failed to parse number"))
+        let written: std::io::Result<()> = match &path {
+            Some(path) => {
+                crate::log::verbose(&format!("Writing {}", path.display()));
+                write_atomic(path, &bytes)
+            }
+            // The module is binary, so it only goes to stdout when that isn't a terminal
+            None if emit.form == "wasm" && std::io::IsTerminal::is_terminal(&std::io::stdout()) => {
+                eprintln!("error: --emit wasm writes a binary module, give a file with --output");
+                std::process::exit(2);
             }
-            _ => panic!(
-                "Invalid number, expected number after operator, found '{}'",
-                parser.tokens[self.position].text
-            ),
+            None => std::io::Write::write_all(&mut std::io::stdout(), &bytes),
+        };
+        if let Err(error) = written {
+            eprintln!(
+                "error: failed to write '{}': {}",
+                path.map(|path: std::path::PathBuf| path.display().to_string())
+                    .unwrap_or("<stdout>".to_string()),
+                error
+            );
+            std::process::exit(1);
         }
     }
 }
 
-pub fn build(args: clap::ArgMatches) {
-    // Get file to build
-    let file: &String;
-    if let Some(arg_match) = args.subcommand_matches("build") {
-        file = arg_match
-            .get_one::<String>("file")
-            .expect("Failed to get file");
-    } else {
-        unreachable!("Subcommand is required");
+/// Write a file through a temporary one in the same directory, renamed over it once complete,
+/// so an interrupted build never leaves a truncated artifact behind
+pub fn write_atomic(path: &std::path::Path, bytes: &[u8]) -> std::io::Result<()> {
+    if path.is_dir() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::IsADirectory,
+            "it is a directory, give a file path",
+        ));
     }
 
-    println!("Building {}", file);
-
-    // Get file contents
-    let file_contents: String = std::fs::read_to_string(file).expect("Failed to read file");
-
-    let mut parser: Parser = Parser {
-        position: 0,
-        tokens: Vec::new(),
-        lexer: Lexer {
-            text: file_contents,
-            position: 0,
-            syntax_token: SyntaxToken {
-                text: "".to_string(),
-                token_type: SyntaxTokenType::BadToken,
-            },
-        },
+    let directory: &std::path::Path = match path.parent() {
+        Some(parent) if parent != std::path::Path::new("") => parent,
+        _ => std::path::Path::new("."),
     };
+    if !directory.is_dir() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("the directory '{}' doesn't exist", directory.display()),
+        ));
+    }
 
-    let mut expression: ExpressionSyntax = ExpressionSyntax {
-        position: 0,
-        left: ExpressionSyntaxEnum::Number(0),
-        operator_token: OperatorToken::Plus,
-        right: ExpressionSyntaxEnum::Number(0),
-    };
+    let name: String = path
+        .file_name()
+        .map(|name: &std::ffi::OsStr| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let temporary: std::path::PathBuf =
+        directory.join(format!(".{}.{}.tmp", name, std::process::id()));
+
+    let result: std::io::Result<()> =
+        std::fs::write(&temporary, bytes).and_then(|()| std::fs::rename(&temporary, path));
+    if result.is_err() {
+        // Nothing to clean up when the temporary file was never created
+        let _ = std::fs::remove_file(&temporary);
+    }
 
-    parser.parse();
-    dbg!(&parser);
-    expression.parse(&mut parser);
-    dbg!(&expression);
+    result
+}
+
+/// Tokens of `source`, with `Indent` and `Dedent` when indentation delimits blocks
+fn lex(source: String, layout: bool) -> Vec<SyntaxToken> {
+    match layout {
+        true => Lexer::with_layout(source).collect(),
+        false => collect_tokens(source),
+    }
 }
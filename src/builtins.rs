@@ -0,0 +1,618 @@
+use crate::diagnostic::Span;
+use crate::eval::{RuntimeError, Value};
+
+/// Function provided by the interpreter
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Builtin {
+    pub name: &'static str,
+    /// Number of arguments, `None` when it takes any number
+    pub parameters: Option<usize>,
+}
+
+/// What a group of builtins needs from the outside world
+///
+/// A sandboxed or deterministic mode can leave out groups by their capability
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Capability {
+    /// Only computes from the arguments, always gives the same result
+    Pure,
+    /// Reads stdin or writes stdout
+    Io,
+    /// Reads the clock or waits
+    Time,
+    /// Gives different results unless seeded
+    Random,
+    /// Reads or changes environment variables
+    Env,
+    /// Reads or writes files
+    File,
+}
+
+/// Related builtins, each group is run by its own function in the interpreter
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BuiltinGroup {
+    pub name: &'static str,
+    pub capability: Capability,
+    pub builtins: &'static [Builtin],
+}
+
+/// Every builtin, programs can't define functions with these names so a builtin always
+/// means the same thing
+pub const BUILTIN_GROUPS: [BuiltinGroup; 9] = [
+    BuiltinGroup {
+        name: "io",
+        capability: Capability::Io,
+        builtins: &[
+            Builtin {
+                name: "print",
+                parameters: None,
+            },
+            Builtin {
+                name: "write",
+                parameters: None,
+            },
+            Builtin {
+                name: "input",
+                parameters: Some(0),
+            },
+            Builtin {
+                name: "input_int",
+                parameters: Some(0),
+            },
+        ],
+    },
+    BuiltinGroup {
+        name: "math",
+        capability: Capability::Pure,
+        builtins: &[
+            Builtin {
+                name: "abs",
+                parameters: Some(1),
+            },
+            Builtin {
+                name: "min",
+                parameters: Some(2),
+            },
+            Builtin {
+                name: "max",
+                parameters: Some(2),
+            },
+            Builtin {
+                name: "pow",
+                parameters: Some(2),
+            },
+            Builtin {
+                name: "sqrt",
+                parameters: Some(1),
+            },
+            Builtin {
+                name: "floor",
+                parameters: Some(1),
+            },
+            Builtin {
+                name: "ceil",
+                parameters: Some(1),
+            },
+            Builtin {
+                name: "round",
+                parameters: Some(1),
+            },
+        ],
+    },
+    BuiltinGroup {
+        name: "string",
+        capability: Capability::Pure,
+        builtins: &[
+            Builtin {
+                name: "len",
+                parameters: Some(1),
+            },
+            Builtin {
+                name: "substr",
+                parameters: Some(3),
+            },
+            Builtin {
+                name: "contains",
+                parameters: Some(2),
+            },
+            Builtin {
+                name: "split",
+                parameters: Some(2),
+            },
+            Builtin {
+                name: "trim",
+                parameters: Some(1),
+            },
+        ],
+    },
+    BuiltinGroup {
+        name: "assert",
+        capability: Capability::Pure,
+        builtins: &[
+            Builtin {
+                name: "assert",
+                parameters: Some(1),
+            },
+            Builtin {
+                name: "assert_eq",
+                parameters: Some(2),
+            },
+        ],
+    },
+    BuiltinGroup {
+        name: "time",
+        capability: Capability::Time,
+        builtins: &[
+            Builtin {
+                name: "now_ms",
+                parameters: Some(0),
+            },
+            Builtin {
+                name: "elapsed_ms",
+                parameters: Some(1),
+            },
+            Builtin {
+                name: "sleep_ms",
+                parameters: Some(1),
+            },
+        ],
+    },
+    BuiltinGroup {
+        name: "random",
+        capability: Capability::Random,
+        builtins: &[
+            Builtin {
+                name: "random",
+                parameters: Some(0),
+            },
+            Builtin {
+                name: "random_int",
+                parameters: Some(2),
+            },
+            Builtin {
+                name: "seed",
+                parameters: Some(1),
+            },
+        ],
+    },
+    BuiltinGroup {
+        name: "env",
+        capability: Capability::Env,
+        builtins: &[
+            Builtin {
+                name: "env",
+                parameters: Some(1),
+            },
+            Builtin {
+                name: "set_env",
+                parameters: Some(2),
+            },
+        ],
+    },
+    BuiltinGroup {
+        name: "file",
+        capability: Capability::File,
+        builtins: &[
+            Builtin {
+                name: "read_file",
+                parameters: Some(1),
+            },
+            Builtin {
+                name: "write_file",
+                parameters: Some(2),
+            },
+            Builtin {
+                name: "append_file",
+                parameters: Some(2),
+            },
+            Builtin {
+                name: "file_exists",
+                parameters: Some(1),
+            },
+        ],
+    },
+    BuiltinGroup {
+        name: "args",
+        capability: Capability::Pure,
+        builtins: &[
+            Builtin {
+                name: "args",
+                parameters: Some(0),
+            },
+            Builtin {
+                name: "argc",
+                parameters: Some(0),
+            },
+        ],
+    },
+];
+
+/// Builtin called `name` and the group it belongs to, if there is one
+pub fn builtin(name: &str) -> Option<(&'static BuiltinGroup, &'static Builtin)> {
+    BUILTIN_GROUPS
+        .iter()
+        .find_map(|group: &'static BuiltinGroup| {
+            group
+                .builtins
+                .iter()
+                .find(|builtin: &&Builtin| builtin.name == name)
+                .map(|builtin: &'static Builtin| (group, builtin))
+        })
+}
+
+/// Run a math builtin with already evaluated and counted arguments
+///
+/// - `abs(x)` keeps the type of `x`
+/// - `min(a, b)` and `max(a, b)` convert an int to a float when mixed, like arithmetic does
+/// - `pow(base, exponent)` is an int for int operands, a negative int exponent is an error
+/// - `sqrt(x)` is always a float, NaN for negative numbers
+/// - `floor(x)`, `ceil(x)` and `round(x)` are always ints, `round` takes halves away from zero
+///   so `round(2.5)` is 3 and `round(-2.5)` is -3, an int is returned as is
+pub fn call_math(name: &str, arguments: &[Value], span: Span) -> Result<Value, RuntimeError> {
+    match (name, arguments) {
+        ("abs", [Value::Int(value)]) => value.checked_abs().map(Value::Int).ok_or_else(|| {
+            RuntimeError::new(
+                format!("attempt to take 'abs' of {} with overflow", value),
+                span,
+            )
+        }),
+        ("abs", [Value::Float(value)]) => Ok(Value::Float(value.abs())),
+        ("min" | "max", [Value::Int(left), Value::Int(right)]) => {
+            Ok(Value::Int(if name == "min" {
+                *left.min(right)
+            } else {
+                *left.max(right)
+            }))
+        }
+        ("min" | "max", [left, right]) => {
+            let (left, right): (f64, f64) = (number(name, left, span)?, number(name, right, span)?);
+
+            Ok(Value::Float(if name == "min" {
+                left.min(right)
+            } else {
+                left.max(right)
+            }))
+        }
+        ("pow", [Value::Int(base), Value::Int(exponent)]) => {
+            if *exponent < 0 {
+                return Err(RuntimeError::new(
+                    format!(
+                        "'pow' can't raise an int to the negative power {}, use a float base",
+                        exponent
+                    ),
+                    span,
+                ));
+            }
+
+            // Powers of 0, 1 and -1 never overflow, whatever the exponent, the others do before
+            // it gets past 32 bits
+            let power: Option<isize> = match base {
+                0 | 1 if *exponent > 0 => Some(*base),
+                -1 => Some(if exponent % 2 == 0 { 1 } else { -1 }),
+                _ => u32::try_from(*exponent)
+                    .ok()
+                    .and_then(|exponent: u32| base.checked_pow(exponent)),
+            };
+
+            power.map(Value::Int).ok_or_else(|| {
+                RuntimeError::new(
+                    format!(
+                        "attempt to raise {} to the power {} with overflow",
+                        base, exponent
+                    ),
+                    span,
+                )
+            })
+        }
+        ("pow", [base, exponent]) => Ok(Value::Float(
+            number(name, base, span)?.powf(number(name, exponent, span)?),
+        )),
+        ("sqrt", [value]) => Ok(Value::Float(number(name, value, span)?.sqrt())),
+        ("floor" | "ceil" | "round", [Value::Int(value)]) => Ok(Value::Int(*value)),
+        ("floor" | "ceil" | "round", [Value::Float(value)]) => {
+            let rounded: f64 = match name {
+                "floor" => value.floor(),
+                "ceil" => value.ceil(),
+                _ => value.round(),
+            };
+
+            // `isize::MAX as f64` rounds up to 2^63, which doesn't fit
+            if rounded.is_nan() || rounded < isize::MIN as f64 || rounded >= isize::MAX as f64 {
+                return Err(RuntimeError::new(
+                    format!(
+                        "'{}' of {} doesn't fit in an int",
+                        name,
+                        Value::Float(*value)
+                    ),
+                    span,
+                ));
+            }
+
+            Ok(Value::Int(rounded as isize))
+        }
+        (name, [value, ..]) => Err(expected_number(name, value, span)),
+        _ => unreachable!("Argument counts are checked before calling"),
+    }
+}
+
+/// Run a string builtin with already evaluated and counted arguments
+///
+/// Positions and lengths count characters, not bytes
+///
+/// - `len(s)` is the length of a string or an array
+/// - `substr(s, start, length)` is the part of `s` starting at `start`, it must fit in `s`
+/// - `contains(s, needle)` is whether `needle` appears in `s`
+/// - `split(s, separator)` is an array of the parts between separators
+/// - `trim(s)` is `s` without leading and trailing whitespace
+pub fn call_string(name: &str, arguments: &[Value], span: Span) -> Result<Value, RuntimeError> {
+    match (name, arguments) {
+        ("len", [Value::String(text)]) => Ok(Value::Int(text.chars().count() as isize)),
+        ("len", [Value::Array(elements)]) => Ok(Value::Int(elements.len() as isize)),
+        ("len", [value]) => Err(RuntimeError::new(
+            format!(
+                "'len' expects a string or an array but got {}",
+                value.type_name()
+            ),
+            span,
+        )),
+        ("substr", [Value::String(text), Value::Int(start), Value::Int(length)]) => {
+            if *start < 0 || *length < 0 {
+                return Err(RuntimeError::new(
+                    format!(
+                        "'substr' expects a start and length of 0 or more but got {} and {}",
+                        start, length
+                    ),
+                    span,
+                ));
+            }
+
+            let text_length: usize = text.chars().count();
+            let (start, length): (usize, usize) = (*start as usize, *length as usize);
+            if start.saturating_add(length) > text_length {
+                return Err(RuntimeError::new(
+                    format!(
+                        "'substr' range {}..{} is out of bounds for a string of length {}",
+                        start,
+                        start.saturating_add(length),
+                        text_length
+                    ),
+                    span,
+                ));
+            }
+
+            Ok(Value::String(
+                text.chars().skip(start).take(length).collect(),
+            ))
+        }
+        ("contains", [Value::String(text), Value::String(needle)]) => {
+            Ok(Value::Bool(text.contains(needle.as_str())))
+        }
+        ("split", [Value::String(_), Value::String(separator)]) if separator.is_empty() => Err(
+            RuntimeError::new("'split' separator can't be empty".to_string(), span),
+        ),
+        ("split", [Value::String(text), Value::String(separator)]) => Ok(Value::Array(
+            text.split(separator.as_str())
+                .map(|part: &str| Value::String(part.to_string()))
+                .collect(),
+        )),
+        ("trim", [Value::String(text)]) => Ok(Value::String(text.trim().to_string())),
+        (name, arguments) => Err(RuntimeError::new(
+            format!(
+                "'{}' expects {} but got {}",
+                name,
+                match name {
+                    "substr" => "a string and two ints",
+                    "contains" | "split" => "two strings",
+                    _ => "a string",
+                },
+                arguments
+                    .iter()
+                    .map(Value::type_name)
+                    .collect::<Vec<&str>>()
+                    .join(" and ")
+            ),
+            span,
+        )),
+    }
+}
+
+/// Run an assert builtin with already evaluated and counted arguments, a failed assertion
+/// is a runtime error so the program stops with a non-zero exit code
+///
+/// - `assert(condition)` fails when `condition` is false, it must be a bool, the message
+///   shows `text`, the source of the call
+/// - `assert_eq(left, right)` fails when the values differ, `1` and `1.0` are different
+pub fn call_assert(
+    name: &str,
+    arguments: &[Value],
+    span: Span,
+    text: &str,
+) -> Result<Value, RuntimeError> {
+    match (name, arguments) {
+        ("assert", [Value::Bool(true)]) => Ok(Value::Nil),
+        ("assert", [Value::Bool(false)]) => Err(RuntimeError::new(
+            format!("assertion failed: {}", text),
+            span,
+        )),
+        ("assert", [value]) => Err(RuntimeError::new(
+            format!("'assert' expects a bool but got {}", value.type_name()),
+            span,
+        )),
+        ("assert_eq", [left, right]) if left == right => Ok(Value::Nil),
+        ("assert_eq", [left, right]) => Err(RuntimeError::new(
+            format!(
+                "assertion failed: left = {}, right = {}",
+                quoted(left),
+                quoted(right)
+            ),
+            span,
+        )),
+        _ => unreachable!("Argument counts are checked before calling"),
+    }
+}
+
+/// Value as shown in an assertion message, strings are quoted so `"1"` and `1` look different
+fn quoted(value: &Value) -> String {
+    match value {
+        Value::String(text) => format!("{:?}", text),
+        value => value.to_string(),
+    }
+}
+
+/// Run a time builtin with already evaluated and counted arguments
+///
+/// - `now_ms()` is the number of milliseconds since the Unix epoch
+/// - `elapsed_ms(start)` is the number of milliseconds since `start`, a value of `now_ms()`
+/// - `sleep_ms(milliseconds)` pauses the program
+pub fn call_time(name: &str, arguments: &[Value], span: Span) -> Result<Value, RuntimeError> {
+    match (name, arguments) {
+        ("now_ms", []) => Ok(Value::Int(now_ms())),
+        ("elapsed_ms", [Value::Int(start)]) => Ok(Value::Int(now_ms().saturating_sub(*start))),
+        ("sleep_ms", [Value::Int(milliseconds)]) if *milliseconds < 0 => Err(RuntimeError::new(
+            format!(
+                "'sleep_ms' can't sleep for a negative time, got {}",
+                milliseconds
+            ),
+            span,
+        )),
+        ("sleep_ms", [Value::Int(milliseconds)]) => {
+            std::thread::sleep(std::time::Duration::from_millis(*milliseconds as u64));
+            Ok(Value::Nil)
+        }
+        (name, [value]) => Err(RuntimeError::new(
+            format!("'{}' expects an int but got {}", name, value.type_name()),
+            span,
+        )),
+        _ => unreachable!("Argument counts are checked before calling"),
+    }
+}
+
+/// Run an environment builtin with already evaluated and counted arguments
+///
+/// - `env(name)` is the variable's value, or nil when it isn't set
+/// - `set_env(name, value)` sets the variable for the rest of the program and the
+///   processes it starts
+///
+/// A value that isn't valid UTF-8 is an error, as are names and values the operating
+/// system can't store
+pub fn call_env(name: &str, arguments: &[Value], span: Span) -> Result<Value, RuntimeError> {
+    match (name, arguments) {
+        ("env", [Value::String(variable)]) => match std::env::var(variable) {
+            Ok(value) => Ok(Value::String(value)),
+            Err(std::env::VarError::NotPresent) => Ok(Value::Nil),
+            Err(std::env::VarError::NotUnicode(_)) => Err(RuntimeError::new(
+                format!("environment variable {:?} is not valid UTF-8", variable),
+                span,
+            )),
+        },
+        ("set_env", [Value::String(variable), Value::String(value)]) => {
+            if variable.is_empty() || variable.contains(['=', '\0']) {
+                return Err(RuntimeError::new(
+                    format!("invalid environment variable name {:?}", variable),
+                    span,
+                ));
+            }
+            if value.contains('\0') {
+                return Err(RuntimeError::new(
+                    format!(
+                        "value of environment variable {:?} can't contain a NUL character",
+                        variable
+                    ),
+                    span,
+                ));
+            }
+
+            std::env::set_var(variable, value);
+            Ok(Value::Nil)
+        }
+        (name, arguments) => Err(RuntimeError::new(
+            format!(
+                "'{}' expects strings but got {}",
+                name,
+                arguments
+                    .iter()
+                    .map(Value::type_name)
+                    .collect::<Vec<&str>>()
+                    .join(" and ")
+            ),
+            span,
+        )),
+    }
+}
+
+/// Run a file builtin with already evaluated and counted arguments
+///
+/// - `read_file(path)` is the whole file as a string
+/// - `write_file(path, contents)` creates the file or replaces what it had
+/// - `append_file(path, contents)` creates the file or adds to its end
+/// - `file_exists(path)` is true for files and directories that can be seen
+///
+/// Relative paths start from the current directory. A failure is an error with the
+/// operating system's reason.
+pub fn call_file(name: &str, arguments: &[Value], span: Span) -> Result<Value, RuntimeError> {
+    let file_error = |action: &str, path: &str, error: std::io::Error| -> RuntimeError {
+        RuntimeError::new(format!("failed to {} '{}': {}", action, path, error), span)
+    };
+
+    match (name, arguments) {
+        ("read_file", [Value::String(path)]) => std::fs::read_to_string(path)
+            .map(Value::String)
+            .map_err(|error: std::io::Error| file_error("read", path, error)),
+        ("write_file", [Value::String(path), Value::String(contents)]) => {
+            std::fs::write(path, contents)
+                .map(|()| Value::Nil)
+                .map_err(|error: std::io::Error| file_error("write", path, error))
+        }
+        ("append_file", [Value::String(path), Value::String(contents)]) => {
+            std::fs::OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(path)
+                .and_then(|mut file: std::fs::File| {
+                    std::io::Write::write_all(&mut file, contents.as_bytes())
+                })
+                .map(|()| Value::Nil)
+                .map_err(|error: std::io::Error| file_error("append to", path, error))
+        }
+        ("file_exists", [Value::String(path)]) => {
+            Ok(Value::Bool(std::path::Path::new(path).exists()))
+        }
+        (name, arguments) => Err(RuntimeError::new(
+            format!(
+                "'{}' expects strings but got {}",
+                name,
+                arguments
+                    .iter()
+                    .map(Value::type_name)
+                    .collect::<Vec<&str>>()
+                    .join(" and ")
+            ),
+            span,
+        )),
+    }
+}
+
+/// Milliseconds since the Unix epoch, a clock set before 1970 counts as the epoch
+fn now_ms() -> isize {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |duration: std::time::Duration| {
+            duration.as_millis() as isize
+        })
+}
+
+/// Numeric argument as a float
+fn number(name: &str, value: &Value, span: Span) -> Result<f64, RuntimeError> {
+    match value {
+        Value::Int(value) => Ok(*value as f64),
+        Value::Float(value) => Ok(*value),
+        value => Err(expected_number(name, value, span)),
+    }
+}
+
+fn expected_number(name: &str, value: &Value, span: Span) -> RuntimeError {
+    RuntimeError::new(
+        format!("'{}' expects numbers but got {}", name, value.type_name()),
+        span,
+    )
+}
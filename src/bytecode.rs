@@ -0,0 +1,309 @@
+use crate::diagnostic::Span;
+use crate::eval::{RuntimeError, Value};
+use crate::interner::{Interner, Symbol};
+use crate::ir::{self, BlockId, Callee, Module, Operation, Terminator, Variable};
+use crate::lexer::StringSegment;
+use crate::parser::{OperatorToken, StatementSyntax};
+
+/// One step of the stack machine, operands are indices into the tables of a `Program`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Instruction {
+    /// Push constant `index`
+    Push(usize),
+    /// Push string literal `index` with its `${NAME}` variables substituted
+    Interpolate(usize),
+    /// Replace the top `count` values with an array holding them
+    Array(usize),
+    /// Pop an index and a target, push the element
+    Index,
+    /// Negate the top value
+    Negate,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    /// Pop two operands and apply any other binary operator
+    Operator(OperatorToken),
+    /// Pop two operands, push the right one back and then the result of comparing them, for
+    /// every comparison of a chain but the last
+    Compare(OperatorToken),
+    /// Push local `slot` of the running function
+    Load(usize),
+    /// Pop into local `slot` of the running function
+    Store(usize),
+    /// Push the global called `names[symbol]`
+    LoadGlobal(Symbol),
+    /// Pop into the global called `names[symbol]`, which must already exist
+    StoreGlobal(Symbol),
+    /// Pop into the global called `names[symbol]`, creating it if needed
+    DefineGlobal(Symbol),
+    Pop,
+    Jump(usize),
+    /// Pop a bool and jump if it is false
+    JumpIfFalse(usize),
+    /// Call a function with the arguments on top of the stack, `site` is for the trace
+    Call {
+        function: usize,
+        site: usize,
+    },
+    /// Call the builtin of call site `index` with the arguments on top of the stack
+    CallBuiltin(usize),
+    /// Show the top value to the observer of the program without popping it, compiled after
+    /// every top level expression statement
+    Yield,
+    /// Pop the result and go back to the caller, or finish the program
+    Return,
+    /// Raise error `index`, for mistakes like a wrong argument count that are known up front
+    /// but only reported when reached, like the tree-walker does
+    Fail(usize),
+}
+
+/// User defined function, its code starts at `address`
+#[derive(Clone, Debug, PartialEq)]
+pub struct FunctionInfo {
+    pub name: String,
+    pub address: usize,
+    pub parameters: usize,
+    /// Slots for parameters and `let` bindings together
+    pub locals: usize,
+}
+
+/// Place a function is called from
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallSite {
+    pub name: String,
+    pub span: Span,
+    /// Source of the call, shown when an `assert` fails
+    pub text: String,
+    pub arguments: usize,
+}
+
+/// Flat instruction list with the tables its operands point into
+///
+/// The top level code starts at instruction 0, function bodies follow it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Program {
+    pub code: Vec<Instruction>,
+    /// Source range of each instruction, for runtime errors
+    pub spans: Vec<Span>,
+    pub constants: Vec<Value>,
+    pub strings: Vec<Vec<StringSegment>>,
+    /// Names of the globals, indexed by their symbol
+    pub names: Vec<String>,
+    pub functions: Vec<FunctionInfo>,
+    pub call_sites: Vec<CallSite>,
+    pub errors: Vec<RuntimeError>,
+    /// Whether the program ends with an expression statement, whose value is the result
+    pub has_result: bool,
+}
+
+/// Lower a program to bytecode, through `crate::ir`
+///
+/// Fails like `Interpreter::execute` does before running anything, when a function takes
+/// the name of a builtin
+pub fn compile(statements: &[StatementSyntax]) -> Result<Program, RuntimeError> {
+    Ok(emit(&crate::ir::lower(statements)?))
+}
+
+/// Lay out the blocks of every function one after another as stack machine code
+///
+/// Temporaries are used in the reverse order they are defined, so each one is on top of the
+/// stack when it is used and no slots are needed for them.
+pub fn emit(module: &Module) -> Program {
+    let mut emitter: Emitter = Emitter::default();
+
+    emitter.program.functions = module
+        .functions
+        .iter()
+        .map(|function: &ir::Function| FunctionInfo {
+            name: function.name.clone(),
+            address: 0,
+            parameters: function.parameters.len(),
+            locals: function.locals.len(),
+        })
+        .collect();
+    emitter.program.has_result = module.has_result;
+
+    emitter.emit_function(&module.main);
+    for (index, function) in module.functions.iter().enumerate() {
+        emitter.program.functions[index].address = emitter.program.code.len();
+        emitter.emit_function(function);
+    }
+
+    emitter.program.names = emitter.names.into_strings();
+    emitter.program
+}
+
+/// What makes two constants the same pool entry, floats compare by their bits so `0.0` and
+/// `-0.0` stay apart
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ConstantKey {
+    Int(isize),
+    Float(u64),
+    String(String),
+    Bool(bool),
+    Nil,
+}
+
+impl ConstantKey {
+    /// Key of a constant, `None` for arrays which are never shared
+    pub fn of(value: &Value) -> Option<ConstantKey> {
+        match value {
+            Value::Int(value) => Some(ConstantKey::Int(*value)),
+            Value::Float(value) => Some(ConstantKey::Float(value.to_bits())),
+            Value::String(text) => Some(ConstantKey::String(text.clone())),
+            Value::Bool(value) => Some(ConstantKey::Bool(*value)),
+            Value::Array(_) => None,
+            Value::Nil => Some(ConstantKey::Nil),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Emitter {
+    program: Program,
+    /// Pool index of every constant, so each value is stored once
+    constant_indices: std::collections::HashMap<ConstantKey, usize>,
+    /// Names of globals, moved into `program.names` when done
+    names: Interner,
+}
+
+impl Emitter {
+    fn emit(&mut self, instruction: Instruction, span: Span) -> usize {
+        self.program.code.push(instruction);
+        self.program.spans.push(span);
+        self.program.code.len() - 1
+    }
+
+    fn emit_function(&mut self, function: &ir::Function) {
+        let mut addresses: Vec<usize> = Vec::with_capacity(function.blocks.len());
+        // Jumps and the blocks they go to, patched once every block has an address
+        let mut jumps: Vec<(usize, BlockId)> = Vec::new();
+
+        for (id, block) in function.blocks.iter().enumerate() {
+            addresses.push(self.program.code.len());
+
+            for (operation, span) in block.operations.iter().zip(&block.spans) {
+                self.emit_operation(operation, *span);
+            }
+
+            // A jump to the block laid out next is left out
+            match &block.terminator {
+                Terminator::Jump(target) => {
+                    if target.block != id + 1 {
+                        jumps.push((
+                            self.emit(Instruction::Jump(0), block.terminator_span),
+                            target.block,
+                        ));
+                    }
+                }
+                Terminator::Branch {
+                    then, otherwise, ..
+                } => {
+                    jumps.push((
+                        self.emit(Instruction::JumpIfFalse(0), block.terminator_span),
+                        otherwise.block,
+                    ));
+                    if then.block != id + 1 {
+                        jumps.push((self.emit(Instruction::Jump(0), Span::new(0, 0)), then.block));
+                    }
+                }
+                Terminator::Return(_) => {
+                    self.emit(Instruction::Return, block.terminator_span);
+                }
+            }
+        }
+
+        for (index, block) in jumps {
+            match &mut self.program.code[index] {
+                Instruction::Jump(address) | Instruction::JumpIfFalse(address) => {
+                    *address = addresses[block]
+                }
+                instruction => unreachable!("Only jumps are patched, found {:?}", instruction),
+            }
+        }
+    }
+
+    fn emit_operation(&mut self, operation: &Operation, span: Span) {
+        let instruction: Instruction = match operation {
+            Operation::Constant(_, value) => Instruction::Push(self.constant(value)),
+            Operation::Interpolate(_, segments) => {
+                let index: usize = match self
+                    .program
+                    .strings
+                    .iter()
+                    .position(|known: &Vec<StringSegment>| known == segments)
+                {
+                    Some(index) => index,
+                    None => {
+                        self.program.strings.push(segments.clone());
+                        self.program.strings.len() - 1
+                    }
+                };
+                Instruction::Interpolate(index)
+            }
+            Operation::Array(_, elements) => Instruction::Array(elements.len()),
+            Operation::Index { .. } => Instruction::Index,
+            Operation::Negate(..) => Instruction::Negate,
+            Operation::Binary { operator_token, .. } => match operator_token {
+                OperatorToken::Plus => Instruction::Add,
+                OperatorToken::Minus => Instruction::Sub,
+                OperatorToken::Star => Instruction::Mul,
+                OperatorToken::Slash => Instruction::Div,
+                operator_token => Instruction::Operator(*operator_token),
+            },
+            Operation::Compare { operator_token, .. } => Instruction::Compare(*operator_token),
+            Operation::Load(_, Variable::Local(slot)) => Instruction::Load(*slot),
+            Operation::Load(_, Variable::Global(name)) => {
+                Instruction::LoadGlobal(self.names.intern(name))
+            }
+            Operation::Store(Variable::Local(slot), _)
+            | Operation::Define(Variable::Local(slot), _) => Instruction::Store(*slot),
+            Operation::Store(Variable::Global(name), _) => {
+                Instruction::StoreGlobal(self.names.intern(name))
+            }
+            Operation::Define(Variable::Global(name), _) => {
+                Instruction::DefineGlobal(self.names.intern(name))
+            }
+            Operation::Call { callee, site, .. } => {
+                self.program.call_sites.push(site.clone());
+                let site: usize = self.program.call_sites.len() - 1;
+
+                match callee {
+                    Callee::Function(function) => Instruction::Call {
+                        function: *function,
+                        site,
+                    },
+                    Callee::Builtin => Instruction::CallBuiltin(site),
+                }
+            }
+            Operation::Yield(_) => Instruction::Yield,
+            Operation::Drop(_) => Instruction::Pop,
+            Operation::Fail(_, error) => {
+                self.program.errors.push(error.clone());
+                Instruction::Fail(self.program.errors.len() - 1)
+            }
+        };
+
+        self.emit(instruction, span);
+    }
+
+    /// Pool index of a constant, adding it if needed
+    fn constant(&mut self, value: &Value) -> usize {
+        let key: Option<ConstantKey> = ConstantKey::of(value);
+        match key
+            .as_ref()
+            .and_then(|key: &ConstantKey| self.constant_indices.get(key))
+        {
+            Some(&index) => index,
+            None => {
+                self.program.constants.push(value.clone());
+                let index: usize = self.program.constants.len() - 1;
+                if let Some(key) = key {
+                    self.constant_indices.insert(key, index);
+                }
+                index
+            }
+        }
+    }
+}
@@ -0,0 +1,490 @@
+use crate::diagnostic::Span;
+use crate::eval::{Value, CONSTANTS};
+use crate::ir::{BlockId, Callee, Function, Module, Operation, Target, Terminator, Variable};
+use crate::lexer::StringSegment;
+use crate::parser::OperatorToken;
+
+/// Values, errors and builtins of the generated programs, copied into each of them
+pub const RUNTIME: &str = include_str!("c_runtime.h");
+
+/// Translate a program to standalone C99, for `ph build --emit c`
+///
+/// Every function becomes a C function and the top level code becomes `main`. The program
+/// prints the same output and reports the same errors as `ph run`, so it embeds `source` to
+/// point at the failing line. Link it with the math library:
+///
+/// ```text
+/// ph build script.ph --emit c -o script.c
+/// cc -std=c99 -Wall script.c -o script -lm
+/// ```
+pub fn generate(module: &Module, file_name: &str, source: &str) -> String {
+    let mut output: String = format!(
+        "/* Generated by `ph build --emit c` from {} */\n\n",
+        file_name.replace("*/", "* /")
+    );
+    output.push_str(RUNTIME);
+
+    output.push_str(&format!(
+        "\nstatic const char ph_file_name[] = {};\n",
+        c_string(file_name)
+    ));
+    output.push_str("static const char ph_source[] =");
+    if source.is_empty() {
+        output.push_str(" \"\"");
+    }
+    for line in source.split_inclusive('\n') {
+        output.push_str(&format!("\n    {}", c_string(line)));
+    }
+    output.push_str(";\n");
+
+    let globals: std::collections::BTreeSet<&str> = std::iter::once(&module.main)
+        .chain(&module.functions)
+        .flat_map(|function: &Function| &function.blocks)
+        .flat_map(|block: &crate::ir::Block| &block.operations)
+        .filter_map(|operation: &Operation| match operation {
+            Operation::Load(_, Variable::Global(name))
+            | Operation::Store(Variable::Global(name), _)
+            | Operation::Define(Variable::Global(name), _) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    if !globals.is_empty() {
+        output.push('\n');
+    }
+    for global in &globals {
+        output.push_str(&format!("static ph_value {};\n", global_name(global)));
+    }
+
+    if !module.functions.is_empty() {
+        output.push('\n');
+    }
+    for function in &module.functions {
+        output.push_str(&format!("{};\n", signature(function)));
+    }
+
+    for function in &module.functions {
+        output.push_str(&format!("\n{} {{\n", signature(function)));
+        output.push_str(&function_body(module, function, None));
+        output.push_str("}\n");
+    }
+
+    output.push_str("\nint main(int argc, char **argv) {\n");
+    output.push_str("    ph_start(argc, argv, ph_file_name, ph_source, sizeof ph_source - 1);\n");
+    for (name, value) in CONSTANTS {
+        if globals.contains(name) {
+            output.push_str(&format!(
+                "    {} = ph_float({});\n",
+                global_name(name),
+                float_literal(value)
+            ));
+        }
+    }
+    output.push_str(&function_body(
+        module,
+        &module.main,
+        Some(module.has_result),
+    ));
+    output.push_str("}\n");
+
+    output
+}
+
+/// `f_` and the name, with the characters C doesn't allow in names escaped
+///
+/// `_` becomes `__` and any other character `_uXXXX`, so different names stay different and
+/// can't be a C keyword or a name of the runtime.
+pub fn function_name(name: &str) -> String {
+    format!("f_{}", mangle(name))
+}
+
+/// `g_` and the name, escaped like `function_name`
+pub fn global_name(name: &str) -> String {
+    format!("g_{}", mangle(name))
+}
+
+fn mangle(name: &str) -> String {
+    let mut mangled: String = String::with_capacity(name.len());
+    for character in name.chars() {
+        match character {
+            '_' => mangled.push_str("__"),
+            character if character.is_ascii_alphanumeric() => mangled.push(character),
+            character => mangled.push_str(&format!("_u{:04x}", character as u32)),
+        }
+    }
+
+    mangled
+}
+
+fn signature(function: &Function) -> String {
+    let parameters: Vec<String> = (0..function.parameters.len())
+        .map(|slot: usize| format!("ph_value l{}", slot))
+        .collect();
+
+    format!(
+        "ph_value {}({})",
+        function_name(&function.name),
+        if parameters.is_empty() {
+            "void".to_string()
+        } else {
+            parameters.join(", ")
+        }
+    )
+}
+
+/// Declarations and statements of a function, `main` gives whether the top level code has a
+/// result to print
+fn function_body(module: &Module, function: &Function, main: Option<bool>) -> String {
+    let mut output: String = String::new();
+
+    for slot in function.parameters.len()..function.locals.len() {
+        output.push_str(&format!("    ph_value l{} = ph_nil();\n", slot));
+    }
+    let temporaries: usize = function
+        .blocks
+        .iter()
+        .flat_map(|block: &crate::ir::Block| {
+            block.parameters.iter().copied().chain(
+                block
+                    .operations
+                    .iter()
+                    .filter_map(|operation: &Operation| destination(operation)),
+            )
+        })
+        .map(|temporary: usize| temporary + 1)
+        .max()
+        .unwrap_or(0);
+    for temporary in 0..temporaries {
+        output.push_str(&format!("    ph_value t{} = ph_nil();\n", temporary));
+    }
+
+    // Blocks are laid out in order, a jump to the next one falls through without a label
+    let mut labels: std::collections::HashSet<BlockId> = std::collections::HashSet::new();
+    let mut blocks: Vec<String> = Vec::new();
+    for (id, block) in function.blocks.iter().enumerate() {
+        let mut text: String = String::new();
+
+        for (operation, span) in block.operations.iter().zip(&block.spans) {
+            text.push_str(&format!(
+                "    {}\n",
+                operation_text(module, operation, *span)
+            ));
+        }
+
+        // Inside an `if` the jump always needs a `goto`
+        let mut jump = |target: &Target, nested: bool, text: &mut String| {
+            let indent: &str = if nested { "        " } else { "    " };
+            let parameters: &[usize] = &function.blocks[target.block].parameters;
+            for (parameter, argument) in parameters.iter().zip(&target.arguments) {
+                text.push_str(&format!("{}t{} = t{};\n", indent, parameter, argument));
+            }
+            if nested || target.block != id + 1 {
+                labels.insert(target.block);
+                text.push_str(&format!("{}goto b{};\n", indent, target.block));
+            }
+        };
+
+        match &block.terminator {
+            Terminator::Jump(target) => jump(target, false, &mut text),
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let truth: String = format!(
+                    "ph_truth(t{}, {})",
+                    condition,
+                    span_literal(block.terminator_span)
+                );
+                if then.block == id + 1 && otherwise.block != id + 1 {
+                    text.push_str(&format!("    if (!{}) {{\n", truth));
+                    jump(otherwise, true, &mut text);
+                    text.push_str("    }\n");
+                    jump(then, false, &mut text);
+                } else {
+                    text.push_str(&format!("    if ({}) {{\n", truth));
+                    jump(then, true, &mut text);
+                    text.push_str("    }\n");
+                    jump(otherwise, false, &mut text);
+                }
+            }
+            Terminator::Return(value) => match main {
+                Some(has_result) => text.push_str(&format!(
+                    "    ph_finish(t{}, {});\n    return 0;\n",
+                    value,
+                    u8::from(has_result)
+                )),
+                None => text.push_str(&format!("    return t{};\n", value)),
+            },
+        }
+
+        blocks.push(text);
+    }
+
+    for (id, text) in blocks.iter().enumerate() {
+        if labels.contains(&id) {
+            output.push_str(&format!("b{}:\n", id));
+            // A label must be followed by a statement
+            if text.is_empty() {
+                output.push_str("    ;\n");
+            }
+        }
+        output.push_str(text);
+    }
+
+    output
+}
+
+/// Temporary an operation gives a value to
+pub fn destination(operation: &Operation) -> Option<usize> {
+    match operation {
+        Operation::Constant(destination, _)
+        | Operation::Interpolate(destination, _)
+        | Operation::Array(destination, _)
+        | Operation::Index { destination, .. }
+        | Operation::Negate(destination, _)
+        | Operation::Binary { destination, .. }
+        | Operation::Compare { destination, .. }
+        | Operation::Load(destination, _)
+        | Operation::Call { destination, .. }
+        | Operation::Fail(destination, _) => Some(*destination),
+        Operation::Store(..) | Operation::Define(..) | Operation::Yield(_) | Operation::Drop(_) => {
+            None
+        }
+    }
+}
+
+fn operation_text(module: &Module, operation: &Operation, span: Span) -> String {
+    match operation {
+        Operation::Constant(destination, value) => {
+            format!("t{} = {};", destination, value_literal(value))
+        }
+        Operation::Interpolate(destination, segments) => {
+            let segments: Vec<String> = segments
+                .iter()
+                .map(|segment: &StringSegment| match segment {
+                    StringSegment::Text(text) => {
+                        format!("{{{}, {}, 0, {{0, 0}}}}", c_string(text), text.len())
+                    }
+                    // The error points at `${NAME}`
+                    StringSegment::EnvironmentVariable { name, position } => format!(
+                        "{{{}, {}, 1, {{{}, {}}}}}",
+                        c_string(name),
+                        name.len(),
+                        position,
+                        position + name.len() + 3
+                    ),
+                })
+                .collect();
+            format!(
+                "t{} = ph_interpolate({}, (ph_segment[]){{{}}});",
+                destination,
+                segments.len(),
+                segments.join(", ")
+            )
+        }
+        Operation::Array(destination, elements) => format!(
+            "t{} = {};",
+            destination,
+            array_literal(
+                &elements
+                    .iter()
+                    .map(|element: &usize| format!("t{}", element))
+                    .collect::<Vec<String>>()
+            )
+        ),
+        Operation::Index {
+            destination,
+            target,
+            index,
+        } => format!(
+            "t{} = ph_index(t{}, t{}, {});",
+            destination,
+            target,
+            index,
+            span_literal(span)
+        ),
+        Operation::Negate(destination, operand) => format!(
+            "t{} = ph_negate(t{}, {});",
+            destination,
+            operand,
+            span_literal(span)
+        ),
+        Operation::Binary {
+            destination,
+            operator_token,
+            left,
+            right,
+        }
+        | Operation::Compare {
+            destination,
+            operator_token,
+            left,
+            right,
+        } => format!(
+            "t{} = ph_binary({}, t{}, t{}, {});",
+            destination,
+            operator_name(*operator_token),
+            left,
+            right,
+            span_literal(span)
+        ),
+        Operation::Load(destination, Variable::Local(slot)) => {
+            format!("t{} = l{};", destination, slot)
+        }
+        Operation::Load(destination, Variable::Global(name)) => format!(
+            "t{} = ph_load_global({}, {}, {});",
+            destination,
+            global_name(name),
+            c_string(name),
+            span_literal(span)
+        ),
+        Operation::Store(Variable::Local(slot), value)
+        | Operation::Define(Variable::Local(slot), value) => format!("l{} = t{};", slot, value),
+        Operation::Store(Variable::Global(name), value) => format!(
+            "ph_store_global(&{}, t{}, {}, {});",
+            global_name(name),
+            value,
+            c_string(name),
+            span_literal(span)
+        ),
+        Operation::Define(Variable::Global(name), value) => {
+            format!("{} = t{};", global_name(name), value)
+        }
+        Operation::Call {
+            destination,
+            callee: Callee::Function(index),
+            site,
+            arguments,
+        } => format!(
+            "ph_enter({}, {});\n    t{} = {}({});\n    ph_leave();",
+            c_string(&site.name),
+            span_literal(site.span),
+            destination,
+            function_name(&module.functions[*index].name),
+            arguments
+                .iter()
+                .map(|argument: &usize| format!("t{}", argument))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        Operation::Call {
+            destination,
+            callee: Callee::Builtin,
+            site,
+            arguments,
+        } => format!(
+            "t{} = ph_b_{}({}, {}, {}, {});",
+            destination,
+            site.name,
+            if arguments.is_empty() {
+                "NULL".to_string()
+            } else {
+                format!(
+                    "(ph_value[]){{{}}}",
+                    arguments
+                        .iter()
+                        .map(|argument: &usize| format!("t{}", argument))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )
+            },
+            arguments.len(),
+            span_literal(site.span),
+            c_string(&site.text)
+        ),
+        Operation::Yield(value) | Operation::Drop(value) => format!("(void)t{};", value),
+        Operation::Fail(destination, error) => format!(
+            "t{} = ph_raise({}, {});",
+            destination,
+            span_literal(error.span),
+            c_string(&error.message)
+        ),
+    }
+}
+
+fn operator_name(operator_token: OperatorToken) -> &'static str {
+    match operator_token {
+        OperatorToken::Plus => "PH_ADD",
+        OperatorToken::Minus => "PH_SUB",
+        OperatorToken::Star => "PH_MUL",
+        OperatorToken::Slash => "PH_DIV",
+        OperatorToken::Percent => "PH_REM",
+        OperatorToken::Ampersand => "PH_AND",
+        OperatorToken::Pipe => "PH_OR",
+        OperatorToken::Caret => "PH_XOR",
+        OperatorToken::LessLess => "PH_SHL",
+        OperatorToken::GreaterGreater => "PH_SHR",
+        OperatorToken::Less => "PH_LT",
+        OperatorToken::LessEquals => "PH_LE",
+        OperatorToken::Greater => "PH_GT",
+        OperatorToken::GreaterEquals => "PH_GE",
+        OperatorToken::EqualsEquals => "PH_EQ",
+        OperatorToken::BangEquals => "PH_NE",
+    }
+}
+
+/// Expression building `value`
+fn value_literal(value: &Value) -> String {
+    match value {
+        Value::Int(value) if *value == isize::MIN => "ph_int(INTPTR_MIN)".to_string(),
+        Value::Int(value) if i32::try_from(*value).is_ok() => format!("ph_int({})", value),
+        Value::Int(value) => format!("ph_int(INT64_C({}))", value),
+        Value::Float(value) => format!("ph_float({})", float_literal(*value)),
+        Value::String(text) => format!("ph_new_string({}, {})", c_string(text), text.len()),
+        Value::Bool(value) => format!("ph_bool({})", u8::from(*value)),
+        Value::Array(elements) => {
+            array_literal(&elements.iter().map(value_literal).collect::<Vec<String>>())
+        }
+        Value::Nil => "ph_nil()".to_string(),
+    }
+}
+
+/// `{:e}` is the shortest text that reads back as the same float, and valid C
+fn float_literal(value: f64) -> String {
+    if value.is_nan() {
+        "NAN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "HUGE_VAL" } else { "-HUGE_VAL" }.to_string()
+    } else {
+        format!("{:e}", value)
+    }
+}
+
+fn array_literal(elements: &[String]) -> String {
+    if elements.is_empty() {
+        "ph_array_of(0, NULL)".to_string()
+    } else {
+        format!(
+            "ph_array_of({}, (ph_value[]){{{}}})",
+            elements.len(),
+            elements.join(", ")
+        )
+    }
+}
+
+fn span_literal(span: Span) -> String {
+    format!("(ph_span){{{}, {}}}", span.start, span.end)
+}
+
+/// C string literal of `text`, bytes outside printable ASCII are octal escapes
+///
+/// `?` is escaped too, C99 would read `??=` as a trigraph.
+pub fn c_string(text: &str) -> String {
+    let mut literal: String = String::with_capacity(text.len() + 2);
+    literal.push('"');
+
+    for byte in text.bytes() {
+        match byte {
+            b'"' => literal.push_str("\\\""),
+            b'\\' => literal.push_str("\\\\"),
+            b'?' => literal.push_str("\\?"),
+            b'\n' => literal.push_str("\\n"),
+            b'\t' => literal.push_str("\\t"),
+            b' '..=b'~' => literal.push(byte as char),
+            byte => literal.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+
+    literal.push('"');
+    literal
+}
@@ -0,0 +1,1548 @@
+/* Runtime of a program translated to C by `ph build --emit c`
+ *
+ * Values behave like they do in `ph run`, and runtime errors are reported the same way.
+ * Memory is never freed, a translated program lives as long as a script does.
+ *
+ * Everything here starts with `ph_` or `PH_`, the names of the program start with `f_`
+ * for functions, `g_` for globals, `l` for locals and `t` for temporaries, so they can't
+ * collide with the runtime or with C keywords.
+ */
+#define _POSIX_C_SOURCE 200809L
+
+#include <errno.h>
+#include <inttypes.h>
+#include <limits.h>
+#include <math.h>
+#include <stdarg.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <time.h>
+
+typedef enum {
+    /* A global that no `let` has defined yet */
+    PH_UNSET = 0,
+    PH_NIL,
+    PH_INT,
+    PH_FLOAT,
+    PH_STRING,
+    PH_BOOL,
+    PH_ARRAY
+} ph_type;
+
+typedef struct ph_string {
+    size_t length;
+    char text[];
+} ph_string;
+
+typedef struct ph_array ph_array;
+
+typedef struct {
+    ph_type type;
+    union {
+        intptr_t i;
+        double f;
+        int b;
+        ph_string *s;
+        ph_array *a;
+    } as;
+} ph_value;
+
+struct ph_array {
+    size_t length;
+    ph_value elements[];
+};
+
+/* Byte range in the source, like `Span` */
+typedef struct {
+    size_t start;
+    size_t end;
+} ph_span;
+
+typedef enum {
+    PH_ADD,
+    PH_SUB,
+    PH_MUL,
+    PH_DIV,
+    PH_REM,
+    PH_AND,
+    PH_OR,
+    PH_XOR,
+    PH_SHL,
+    PH_SHR,
+    PH_LT,
+    PH_LE,
+    PH_GT,
+    PH_GE,
+    PH_EQ,
+    PH_NE
+} ph_operator;
+
+static const char *const ph_operator_text[] = {
+    "+", "-", "*", "/", "%", "&", "|", "^", "<<", ">>", "<", "<=", ">", ">=", "==", "!=",
+};
+
+/* Part of an interpolated string, the name of an environment variable when `variable` */
+typedef struct {
+    const char *text;
+    size_t length;
+    int variable;
+    ph_span span;
+} ph_segment;
+
+#define PH_MAX_CALL_DEPTH 10000
+#define PH_MAX_TRACE_FRAMES 10
+
+/* Lets the compiler see that reporting an error ends the program */
+#if defined(__GNUC__)
+#define PH_NORETURN __attribute__((noreturn))
+#else
+#define PH_NORETURN
+#endif
+
+static const char *ph_file_name_text;
+static const char *ph_source_text;
+static size_t ph_source_length;
+static int ph_argument_count;
+static char **ph_argument_values;
+
+/* User function calls in progress, for the trace of an error */
+static struct {
+    const char *name;
+    ph_span span;
+} ph_frames[PH_MAX_CALL_DEPTH];
+static size_t ph_depth;
+
+/* xoshiro256** like `Random`, seeded on first use unless `seed` is called */
+static uint64_t ph_random_state[4];
+static int ph_random_seeded;
+
+/* Growing text */
+typedef struct {
+    char *data;
+    size_t length;
+    size_t capacity;
+} ph_buffer;
+
+static inline void *ph_allocate(size_t size) {
+    void *memory = malloc(size ? size : 1);
+    if (memory == NULL) {
+        fputs("error: out of memory\n", stderr);
+        exit(1);
+    }
+    return memory;
+}
+
+static inline void ph_append(ph_buffer *buffer, const char *text, size_t length) {
+    if (buffer->length + length + 1 > buffer->capacity) {
+        size_t capacity = buffer->capacity ? buffer->capacity * 2 : 64;
+        while (capacity < buffer->length + length + 1) {
+            capacity *= 2;
+        }
+        char *data = ph_allocate(capacity);
+        if (buffer->length) {
+            memcpy(data, buffer->data, buffer->length);
+        }
+        free(buffer->data);
+        buffer->data = data;
+        buffer->capacity = capacity;
+    }
+    memcpy(buffer->data + buffer->length, text, length);
+    buffer->length += length;
+    buffer->data[buffer->length] = '\0';
+}
+
+static inline void ph_append_text(ph_buffer *buffer, const char *text) {
+    ph_append(buffer, text, strlen(text));
+}
+
+static inline void ph_append_format(ph_buffer *buffer, const char *format, ...) {
+    va_list arguments;
+    va_start(arguments, format);
+    int length = vsnprintf(NULL, 0, format, arguments);
+    va_end(arguments);
+
+    char *text = ph_allocate((size_t)length + 1);
+    va_start(arguments, format);
+    vsnprintf(text, (size_t)length + 1, format, arguments);
+    va_end(arguments);
+
+    ph_append(buffer, text, (size_t)length);
+    free(text);
+}
+
+/* Length of the UTF-8 sequence starting with `byte` */
+static inline size_t ph_utf8_width(unsigned char byte) {
+    if (byte < 0x80) {
+        return 1;
+    } else if (byte >= 0xf0) {
+        return 4;
+    } else if (byte >= 0xe0) {
+        return 3;
+    } else if (byte >= 0xc0) {
+        return 2;
+    }
+    return 1;
+}
+
+/* Number of characters in UTF-8 text */
+static inline size_t ph_utf8_count(const char *text, size_t length) {
+    size_t count = 0;
+    for (size_t index = 0; index < length; index++) {
+        if (((unsigned char)text[index] & 0xc0) != 0x80) {
+            count++;
+        }
+    }
+    return count;
+}
+
+/* Byte offset of character `position` */
+static inline size_t ph_utf8_offset(const char *text, size_t length, size_t position) {
+    size_t offset = 0;
+    while (position > 0 && offset < length) {
+        offset += ph_utf8_width((unsigned char)text[offset]);
+        position--;
+    }
+    return offset < length ? offset : length;
+}
+
+/* Character at `offset`, `width` is set to its length in bytes */
+static inline uint32_t ph_utf8_decode(const char *text, size_t length, size_t offset, size_t *width) {
+    const unsigned char *bytes = (const unsigned char *)text + offset;
+    size_t size = ph_utf8_width(bytes[0]);
+    if (offset + size > length) {
+        size = 1;
+    }
+    *width = size;
+
+    switch (size) {
+    case 2:
+        return ((uint32_t)(bytes[0] & 0x1f) << 6) | (bytes[1] & 0x3f);
+    case 3:
+        return ((uint32_t)(bytes[0] & 0x0f) << 12) | ((uint32_t)(bytes[1] & 0x3f) << 6) |
+               (bytes[2] & 0x3f);
+    case 4:
+        return ((uint32_t)(bytes[0] & 0x07) << 18) | ((uint32_t)(bytes[1] & 0x3f) << 12) |
+               ((uint32_t)(bytes[2] & 0x3f) << 6) | (bytes[3] & 0x3f);
+    default:
+        return bytes[0];
+    }
+}
+
+static inline int ph_utf8_valid(const char *text, size_t length) {
+    size_t offset = 0;
+    while (offset < length) {
+        unsigned char byte = (unsigned char)text[offset];
+        size_t width = byte < 0x80 ? 1 : byte >= 0xc2 && byte < 0xe0 ? 2 : byte >= 0xe0 && byte < 0xf0 ? 3 : byte >= 0xf0 && byte < 0xf5 ? 4 : 0;
+        if (width == 0 || offset + width > length) {
+            return 0;
+        }
+        for (size_t index = 1; index < width; index++) {
+            if (((unsigned char)text[offset + index] & 0xc0) != 0x80) {
+                return 0;
+            }
+        }
+        offset += width;
+    }
+    return 1;
+}
+
+/* Whether a character is whitespace to Unicode, like Rust's `char::is_whitespace` */
+static inline int ph_is_whitespace(uint32_t character) {
+    return (character >= 0x09 && character <= 0x0d) || character == 0x20 || character == 0x85 ||
+           character == 0xa0 || character == 0x1680 || (character >= 0x2000 && character <= 0x200a) ||
+           character == 0x2028 || character == 0x2029 || character == 0x202f || character == 0x205f ||
+           character == 0x3000;
+}
+
+/* 1-based line and column of a byte offset, like `line_column` */
+static inline void ph_line_column(size_t offset, size_t *line, size_t *column) {
+    if (offset > ph_source_length) {
+        offset = ph_source_length;
+    }
+    size_t line_start = 0;
+    *line = 1;
+    for (size_t index = 0; index < offset; index++) {
+        if (ph_source_text[index] == '\n') {
+            (*line)++;
+            line_start = index + 1;
+        }
+    }
+    *column = ph_utf8_count(ph_source_text + line_start, offset - line_start) + 1;
+}
+
+/* Print an error with the source line it points at and the calls in progress, then exit */
+PH_NORETURN static inline void ph_report(const char *message, ph_span span) {
+    fflush(stdout);
+
+    size_t line, column;
+    ph_line_column(span.start, &line, &column);
+
+    size_t line_start = 0;
+    size_t current = 1;
+    for (size_t index = 0; index < ph_source_length && current < line; index++) {
+        if (ph_source_text[index] == '\n') {
+            current++;
+            line_start = index + 1;
+        }
+    }
+    size_t line_end = line_start;
+    while (line_end < ph_source_length && ph_source_text[line_end] != '\n') {
+        line_end++;
+    }
+    if (line_end > line_start && ph_source_text[line_end - 1] == '\r') {
+        line_end--;
+    }
+
+    char gutter[32];
+    int gutter_width = snprintf(gutter, sizeof gutter, "%zu", line);
+    memset(gutter, ' ', (size_t)gutter_width);
+
+    size_t underline_end = span.end < line_end ? span.end : line_end;
+    size_t underline = 0;
+    if (span.start <= underline_end && underline_end <= ph_source_length) {
+        underline = ph_utf8_count(ph_source_text + span.start, underline_end - span.start);
+    }
+    if (underline == 0) {
+        underline = 1;
+    }
+
+    fprintf(stderr, "runtime error: %s\n%s--> %s:%zu:%zu\n%s |\n%zu | %.*s\n%s | ", message, gutter,
+            ph_file_name_text, line, column, gutter, line, (int)(line_end - line_start),
+            ph_source_text + line_start, gutter);
+    for (size_t index = 1; index < column; index++) {
+        fputc(' ', stderr);
+    }
+    for (size_t index = 0; index < underline; index++) {
+        fputc('^', stderr);
+    }
+    fputc('\n', stderr);
+
+    for (size_t shown = 0; shown < ph_depth && shown < PH_MAX_TRACE_FRAMES; shown++) {
+        size_t frame = ph_depth - 1 - shown;
+        size_t call_line, call_column;
+        ph_line_column(ph_frames[frame].span.start, &call_line, &call_column);
+        fprintf(stderr, "%s = in '%s', called at %s:%zu:%zu\n", gutter, ph_frames[frame].name,
+                ph_file_name_text, call_line, call_column);
+    }
+    if (ph_depth > PH_MAX_TRACE_FRAMES) {
+        fprintf(stderr, "%s = ... and %zu more frames\n", gutter, ph_depth - PH_MAX_TRACE_FRAMES);
+    }
+
+    exit(1);
+}
+
+PH_NORETURN static inline void ph_fail(ph_span span, const char *format, ...) {
+    va_list arguments;
+    va_start(arguments, format);
+    int length = vsnprintf(NULL, 0, format, arguments);
+    va_end(arguments);
+
+    char *message = ph_allocate((size_t)length + 1);
+    va_start(arguments, format);
+    vsnprintf(message, (size_t)length + 1, format, arguments);
+    va_end(arguments);
+
+    ph_report(message, span);
+}
+
+static inline ph_value ph_int(intptr_t value) {
+    ph_value result;
+    result.type = PH_INT;
+    result.as.i = value;
+    return result;
+}
+
+static inline ph_value ph_float(double value) {
+    ph_value result;
+    result.type = PH_FLOAT;
+    result.as.f = value;
+    return result;
+}
+
+static inline ph_value ph_bool(int value) {
+    ph_value result;
+    result.type = PH_BOOL;
+    result.as.b = value != 0;
+    return result;
+}
+
+static inline ph_value ph_nil(void) {
+    ph_value result;
+    result.type = PH_NIL;
+    result.as.i = 0;
+    return result;
+}
+
+static inline ph_value ph_new_string(const char *text, size_t length) {
+    ph_string *string = ph_allocate(sizeof(ph_string) + length + 1);
+    string->length = length;
+    memcpy(string->text, text, length);
+    string->text[length] = '\0';
+
+    ph_value result;
+    result.type = PH_STRING;
+    result.as.s = string;
+    return result;
+}
+
+static inline ph_value ph_array_of(size_t length, const ph_value *elements) {
+    ph_array *array = ph_allocate(sizeof(ph_array) + length * sizeof(ph_value));
+    array->length = length;
+    for (size_t index = 0; index < length; index++) {
+        array->elements[index] = elements[index];
+    }
+
+    ph_value result;
+    result.type = PH_ARRAY;
+    result.as.a = array;
+    return result;
+}
+
+/* Error for a failure known before running, like a wrong argument count */
+static inline ph_value ph_raise(ph_span span, const char *message) {
+    ph_report(message, span);
+    return ph_nil();
+}
+
+static inline const char *ph_type_name(ph_value value) {
+    switch (value.type) {
+    case PH_INT:
+        return "int";
+    case PH_FLOAT:
+        return "float";
+    case PH_STRING:
+        return "string";
+    case PH_BOOL:
+        return "bool";
+    case PH_ARRAY:
+        return "array";
+    default:
+        return "nil";
+    }
+}
+
+/* Shortest text that reads back as the same float, like Rust's `{:?}` */
+static inline void ph_format_float(ph_buffer *buffer, double value) {
+    if (isnan(value)) {
+        ph_append_text(buffer, "NaN");
+        return;
+    }
+    if (isinf(value)) {
+        ph_append_text(buffer, value < 0 ? "-inf" : "inf");
+        return;
+    }
+    if (value == 0) {
+        ph_append_text(buffer, signbit(value) ? "-0.0" : "0.0");
+        return;
+    }
+
+    char scientific[40];
+    for (int precision = 0; precision < 17; precision++) {
+        snprintf(scientific, sizeof scientific, "%.*e", precision, value);
+        if (strtod(scientific, NULL) == value) {
+            break;
+        }
+    }
+
+    /* Split `-d.ddde+xx` into sign, digits and exponent */
+    const char *cursor = scientific;
+    int negative = *cursor == '-';
+    if (negative) {
+        cursor++;
+    }
+    char digits[40];
+    size_t digit_count = 0;
+    while (*cursor != 'e') {
+        if (*cursor != '.') {
+            digits[digit_count++] = *cursor;
+        }
+        cursor++;
+    }
+    while (digit_count > 1 && digits[digit_count - 1] == '0') {
+        digit_count--;
+    }
+    int exponent = atoi(cursor + 1);
+
+    if (negative) {
+        ph_append_text(buffer, "-");
+    }
+    if (exponent < -4 || exponent >= 16) {
+        ph_append(buffer, digits, 1);
+        if (digit_count > 1) {
+            ph_append_text(buffer, ".");
+            ph_append(buffer, digits + 1, digit_count - 1);
+        }
+        ph_append_format(buffer, "e%d", exponent);
+    } else if (exponent < 0) {
+        ph_append_text(buffer, "0.");
+        for (int index = -1; index > exponent; index--) {
+            ph_append_text(buffer, "0");
+        }
+        ph_append(buffer, digits, digit_count);
+    } else {
+        size_t whole = (size_t)exponent + 1;
+        for (size_t index = 0; index < whole; index++) {
+            ph_append(buffer, index < digit_count ? digits + index : "0", 1);
+        }
+        ph_append_text(buffer, ".");
+        if (digit_count > whole) {
+            ph_append(buffer, digits + whole, digit_count - whole);
+        } else {
+            ph_append_text(buffer, "0");
+        }
+    }
+}
+
+/* String in double quotes with escapes, like Rust's `{:?}` */
+static inline void ph_quote(ph_buffer *buffer, const ph_string *string) {
+    ph_append_text(buffer, "\"");
+    size_t offset = 0;
+    while (offset < string->length) {
+        size_t width;
+        uint32_t character = ph_utf8_decode(string->text, string->length, offset, &width);
+        switch (character) {
+        case '"':
+            ph_append_text(buffer, "\\\"");
+            break;
+        case '\\':
+            ph_append_text(buffer, "\\\\");
+            break;
+        case '\n':
+            ph_append_text(buffer, "\\n");
+            break;
+        case '\r':
+            ph_append_text(buffer, "\\r");
+            break;
+        case '\t':
+            ph_append_text(buffer, "\\t");
+            break;
+        case '\0':
+            ph_append_text(buffer, "\\0");
+            break;
+        default:
+            if (character < 0x20 || (character >= 0x7f && character < 0xa0)) {
+                ph_append_format(buffer, "\\u{%" PRIx32 "}", character);
+            } else {
+                ph_append(buffer, string->text + offset, width);
+            }
+        }
+        offset += width;
+    }
+    ph_append_text(buffer, "\"");
+}
+
+/* Value as `print` shows it, strings in arrays are quoted */
+static inline void ph_display(ph_buffer *buffer, ph_value value) {
+    switch (value.type) {
+    case PH_INT:
+        ph_append_format(buffer, "%" PRIdPTR, value.as.i);
+        break;
+    case PH_FLOAT:
+        ph_format_float(buffer, value.as.f);
+        break;
+    case PH_STRING:
+        ph_append(buffer, value.as.s->text, value.as.s->length);
+        break;
+    case PH_BOOL:
+        ph_append_text(buffer, value.as.b ? "true" : "false");
+        break;
+    case PH_ARRAY:
+        ph_append_text(buffer, "[");
+        for (size_t index = 0; index < value.as.a->length; index++) {
+            if (index > 0) {
+                ph_append_text(buffer, ", ");
+            }
+            ph_value element = value.as.a->elements[index];
+            if (element.type == PH_STRING) {
+                ph_quote(buffer, element.as.s);
+            } else {
+                ph_display(buffer, element);
+            }
+        }
+        ph_append_text(buffer, "]");
+        break;
+    default:
+        ph_append_text(buffer, "nil");
+    }
+}
+
+static inline int ph_equal(ph_value left, ph_value right) {
+    if (left.type != right.type) {
+        return 0;
+    }
+    switch (left.type) {
+    case PH_INT:
+        return left.as.i == right.as.i;
+    case PH_FLOAT:
+        return left.as.f == right.as.f;
+    case PH_STRING:
+        return left.as.s->length == right.as.s->length &&
+               memcmp(left.as.s->text, right.as.s->text, left.as.s->length) == 0;
+    case PH_BOOL:
+        return left.as.b == right.as.b;
+    case PH_ARRAY:
+        if (left.as.a->length != right.as.a->length) {
+            return 0;
+        }
+        for (size_t index = 0; index < left.as.a->length; index++) {
+            if (!ph_equal(left.as.a->elements[index], right.as.a->elements[index])) {
+                return 0;
+            }
+        }
+        return 1;
+    default:
+        return 1;
+    }
+}
+
+static inline void ph_enter(const char *name, ph_span span) {
+    if (ph_depth >= PH_MAX_CALL_DEPTH) {
+        ph_fail(span, "calling '%s' exceeds the maximum call depth of %d", name, PH_MAX_CALL_DEPTH);
+    }
+    ph_frames[ph_depth].name = name;
+    ph_frames[ph_depth].span = span;
+    ph_depth++;
+}
+
+static inline void ph_leave(void) {
+    ph_depth--;
+}
+
+static inline int ph_truth(ph_value condition, ph_span span) {
+    if (condition.type != PH_BOOL) {
+        ph_fail(span, "condition must be a bool but found %s", ph_type_name(condition));
+    }
+    return condition.as.b;
+}
+
+static inline ph_value ph_load_global(ph_value global, const char *name, ph_span span) {
+    if (global.type == PH_UNSET) {
+        ph_fail(span, "undefined variable '%s'", name);
+    }
+    return global;
+}
+
+static inline void ph_store_global(ph_value *global, ph_value value, const char *name, ph_span span) {
+    if (global->type == PH_UNSET) {
+        ph_fail(span, "cannot assign to undefined variable '%s', use 'let' to define it", name);
+    }
+    *global = value;
+}
+
+static inline ph_value ph_interpolate(size_t count, const ph_segment *segments) {
+    ph_buffer buffer = {NULL, 0, 0};
+    ph_append(&buffer, "", 0);
+
+    for (size_t index = 0; index < count; index++) {
+        if (!segments[index].variable) {
+            ph_append(&buffer, segments[index].text, segments[index].length);
+            continue;
+        }
+
+        const char *value = getenv(segments[index].text);
+        if (value == NULL) {
+            ph_fail(segments[index].span, "environment variable '%s' is not set", segments[index].text);
+        }
+        if (!ph_utf8_valid(value, strlen(value))) {
+            ph_fail(segments[index].span, "environment variable '%s' is not valid unicode",
+                    segments[index].text);
+        }
+        ph_append_text(&buffer, value);
+    }
+
+    return ph_new_string(buffer.data, buffer.length);
+}
+
+static inline ph_value ph_index(ph_value target, ph_value position, ph_span span) {
+    if (position.type != PH_INT) {
+        ph_fail(span, "index must be an int but found %s", ph_type_name(position));
+    }
+
+    size_t length;
+    if (target.type == PH_ARRAY) {
+        length = target.as.a->length;
+        if (position.as.i >= 0 && (size_t)position.as.i < length) {
+            return target.as.a->elements[position.as.i];
+        }
+    } else if (target.type == PH_STRING) {
+        length = ph_utf8_count(target.as.s->text, target.as.s->length);
+        if (position.as.i >= 0 && (size_t)position.as.i < length) {
+            size_t offset = ph_utf8_offset(target.as.s->text, target.as.s->length, (size_t)position.as.i);
+            return ph_new_string(target.as.s->text + offset, ph_utf8_width((unsigned char)target.as.s->text[offset]));
+        }
+    } else {
+        ph_fail(span, "cannot index %s", ph_type_name(target));
+        return ph_nil();
+    }
+
+    ph_fail(span, "index %" PRIdPTR " is out of bounds for %s of length %zu", position.as.i,
+            target.type == PH_ARRAY ? "an array" : "a string", length);
+    return ph_nil();
+}
+
+static inline ph_value ph_negate(ph_value operand, ph_span span) {
+    if (operand.type == PH_INT) {
+        if (operand.as.i == INTPTR_MIN) {
+            ph_fail(span, "attempt to negate with overflow: -(%" PRIdPTR ")", operand.as.i);
+        }
+        return ph_int(-operand.as.i);
+    }
+    if (operand.type == PH_FLOAT) {
+        return ph_float(-operand.as.f);
+    }
+    ph_fail(span, "cannot apply unary '-' to %s", ph_type_name(operand));
+    return ph_nil();
+}
+
+static inline ph_value ph_type_error(ph_operator operator, ph_value left, ph_value right, ph_span span) {
+    ph_fail(span, "cannot apply '%s' to %s and %s", ph_operator_text[operator], ph_type_name(left),
+            ph_type_name(right));
+    return ph_nil();
+}
+
+static inline ph_value ph_compare(ph_operator operator, ph_value left, ph_value right, ph_span span) {
+    int equality = operator == PH_EQ || operator == PH_NE;
+    /* -1, 0 or 1, 2 when unordered */
+    int ordering;
+
+    if (left.type == PH_INT && right.type == PH_INT) {
+        ordering = (left.as.i > right.as.i) - (left.as.i < right.as.i);
+    } else if (left.type == PH_FLOAT && right.type == PH_FLOAT) {
+        ordering = isnan(left.as.f) || isnan(right.as.f) ? 2 : (left.as.f > right.as.f) - (left.as.f < right.as.f);
+    } else if (left.type == PH_STRING && right.type == PH_STRING) {
+        size_t shorter = left.as.s->length < right.as.s->length ? left.as.s->length : right.as.s->length;
+        int bytes = memcmp(left.as.s->text, right.as.s->text, shorter);
+        ordering = bytes != 0 ? (bytes > 0) - (bytes < 0)
+                              : (left.as.s->length > right.as.s->length) - (left.as.s->length < right.as.s->length);
+    } else if (left.type == PH_BOOL && right.type == PH_BOOL && equality) {
+        ordering = left.as.b - right.as.b;
+    } else {
+        return ph_type_error(operator, left, right, span);
+    }
+
+    switch (operator) {
+    case PH_LT:
+        return ph_bool(ordering == -1);
+    case PH_LE:
+        return ph_bool(ordering == -1 || ordering == 0);
+    case PH_GT:
+        return ph_bool(ordering == 1);
+    case PH_GE:
+        return ph_bool(ordering == 1 || ordering == 0);
+    case PH_EQ:
+        return ph_bool(ordering == 0);
+    default:
+        return ph_bool(ordering != 0);
+    }
+}
+
+/* Product of two ints, 0 when it overflows */
+static inline int ph_multiply(intptr_t left, intptr_t right, intptr_t *result) {
+    if (left != 0 && right != 0 &&
+        ((left > 0 && right > 0 && left > INTPTR_MAX / right) || (left > 0 && right < 0 && right < INTPTR_MIN / left) ||
+         (left < 0 && right > 0 && left < INTPTR_MIN / right) || (left < 0 && right < 0 && right < INTPTR_MAX / left))) {
+        return 0;
+    }
+    *result = left * right;
+    return 1;
+}
+
+static inline ph_value ph_overflow(const char *verb, ph_operator operator, intptr_t left, intptr_t right, ph_span span) {
+    ph_fail(span, "attempt to %s with overflow: %" PRIdPTR " %s %" PRIdPTR, verb, left, ph_operator_text[operator],
+            right);
+    return ph_nil();
+}
+
+static inline ph_value ph_binary(ph_operator operator, ph_value left, ph_value right, ph_span span) {
+    int bitwise = operator >= PH_AND && operator <= PH_SHR;
+
+    if (!bitwise && left.type == PH_INT && right.type == PH_FLOAT) {
+        left = ph_float((double)left.as.i);
+    } else if (!bitwise && left.type == PH_FLOAT && right.type == PH_INT) {
+        right = ph_float((double)right.as.i);
+    }
+
+    if (operator >= PH_LT) {
+        return ph_compare(operator, left, right, span);
+    }
+
+    if (left.type == PH_INT && right.type == PH_INT) {
+        intptr_t l = left.as.i;
+        intptr_t r = right.as.i;
+        intptr_t bits = (intptr_t)(sizeof(intptr_t) * CHAR_BIT);
+
+        if (r == 0 && (operator == PH_DIV || operator == PH_REM)) {
+            ph_fail(span, "division by zero");
+        }
+        if ((operator == PH_SHL || operator == PH_SHR) && (r < 0 || r >= bits)) {
+            ph_fail(span, "shift amount %" PRIdPTR " is out of range, expected 0 to %" PRIdPTR, r, bits - 1);
+        }
+
+        switch (operator) {
+        case PH_ADD:
+            if ((r > 0 && l > INTPTR_MAX - r) || (r < 0 && l < INTPTR_MIN - r)) {
+                return ph_overflow("add", operator, l, r, span);
+            }
+            return ph_int(l + r);
+        case PH_SUB:
+            if ((r < 0 && l > INTPTR_MAX + r) || (r > 0 && l < INTPTR_MIN + r)) {
+                return ph_overflow("subtract", operator, l, r, span);
+            }
+            return ph_int(l - r);
+        case PH_MUL: {
+            intptr_t product;
+            if (!ph_multiply(l, r, &product)) {
+                return ph_overflow("multiply", operator, l, r, span);
+            }
+            return ph_int(product);
+        }
+        case PH_DIV:
+            if (l == INTPTR_MIN && r == -1) {
+                return ph_overflow("divide", operator, l, r, span);
+            }
+            return ph_int(l / r);
+        case PH_REM:
+            if (l == INTPTR_MIN && r == -1) {
+                return ph_overflow("calculate the remainder", operator, l, r, span);
+            }
+            return ph_int(l % r);
+        case PH_AND:
+            return ph_int(l & r);
+        case PH_OR:
+            return ph_int(l | r);
+        case PH_XOR:
+            return ph_int(l ^ r);
+        case PH_SHL:
+            return ph_int((intptr_t)((uintptr_t)l << r));
+        default:
+            return ph_int(l < 0 ? ~(~l >> r) : l >> r);
+        }
+    }
+
+    if (left.type == PH_FLOAT && right.type == PH_FLOAT && !bitwise) {
+        switch (operator) {
+        case PH_ADD:
+            return ph_float(left.as.f + right.as.f);
+        case PH_SUB:
+            return ph_float(left.as.f - right.as.f);
+        case PH_MUL:
+            return ph_float(left.as.f * right.as.f);
+        case PH_DIV:
+            return ph_float(left.as.f / right.as.f);
+        default:
+            return ph_float(fmod(left.as.f, right.as.f));
+        }
+    }
+
+    return ph_type_error(operator, left, right, span);
+}
+
+/* Names of the argument types joined with " and ", for builtin errors */
+static inline const char *ph_type_names(const ph_value *arguments, size_t count) {
+    ph_buffer buffer = {NULL, 0, 0};
+    ph_append(&buffer, "", 0);
+    for (size_t index = 0; index < count; index++) {
+        if (index > 0) {
+            ph_append_text(&buffer, " and ");
+        }
+        ph_append_text(&buffer, ph_type_name(arguments[index]));
+    }
+    return buffer.data;
+}
+
+static inline void ph_print(const ph_value *arguments, size_t count, int line) {
+    ph_buffer buffer = {NULL, 0, 0};
+    ph_append(&buffer, "", 0);
+    for (size_t index = 0; index < count; index++) {
+        if (index > 0) {
+            ph_append_text(&buffer, " ");
+        }
+        ph_display(&buffer, arguments[index]);
+    }
+    if (line) {
+        ph_append_text(&buffer, "\n");
+    }
+    fwrite(buffer.data, 1, buffer.length, stdout);
+    if (!line) {
+        fflush(stdout);
+    }
+    free(buffer.data);
+}
+
+static inline ph_value ph_b_print(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)span;
+    (void)text;
+    ph_print(arguments, count, 1);
+    return ph_nil();
+}
+
+static inline ph_value ph_b_write(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)span;
+    (void)text;
+    ph_print(arguments, count, 0);
+    return ph_nil();
+}
+
+/* One line of stdin without its line ending, `NULL` at the end of the input */
+static inline ph_buffer *ph_read_line(ph_span span) {
+    fflush(stdout);
+
+    static ph_buffer line;
+    line.length = 0;
+    ph_append(&line, "", 0);
+
+    int character;
+    int read = 0;
+    while ((character = fgetc(stdin)) != EOF) {
+        read = 1;
+        char byte = (char)character;
+        ph_append(&line, &byte, 1);
+        if (byte == '\n') {
+            break;
+        }
+    }
+    if (ferror(stdin)) {
+        ph_fail(span, "failed to read input: %s (os error %d)", strerror(errno), errno);
+    }
+    if (!read) {
+        return NULL;
+    }
+
+    while (line.length > 0 && (line.data[line.length - 1] == '\n' || line.data[line.length - 1] == '\r')) {
+        line.data[--line.length] = '\0';
+    }
+    return &line;
+}
+
+static inline ph_value ph_b_input(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)arguments;
+    (void)count;
+    (void)text;
+    ph_buffer *line = ph_read_line(span);
+    return line == NULL ? ph_nil() : ph_new_string(line->data, line->length);
+}
+
+/* Text without leading and trailing whitespace, `start` and `end` are set to its bytes */
+static inline void ph_trim(const char *text, size_t length, size_t *start, size_t *end) {
+    size_t width;
+    *start = 0;
+    while (*start < length && ph_is_whitespace(ph_utf8_decode(text, length, *start, &width))) {
+        *start += width;
+    }
+    *end = length;
+    while (*end > *start) {
+        size_t previous = *end - 1;
+        while (previous > *start && ((unsigned char)text[previous] & 0xc0) == 0x80) {
+            previous--;
+        }
+        if (!ph_is_whitespace(ph_utf8_decode(text, length, previous, &width))) {
+            break;
+        }
+        *end = previous;
+    }
+}
+
+static inline ph_value ph_b_input_int(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)arguments;
+    (void)count;
+    (void)text;
+    ph_buffer *line = ph_read_line(span);
+    if (line == NULL) {
+        ph_fail(span, "expected an integer but reached the end of the input");
+    }
+
+    size_t start, end;
+    ph_trim(line->data, line->length, &start, &end);
+    int negative = start < end && line->data[start] == '-';
+    if (start < end && (line->data[start] == '-' || line->data[start] == '+')) {
+        start++;
+    }
+
+    intptr_t value = 0;
+    int valid = start < end;
+    for (size_t index = start; index < end && valid; index++) {
+        int digit = line->data[index] - '0';
+        if (digit < 0 || digit > 9) {
+            valid = 0;
+        } else if (negative ? value < (INTPTR_MIN + digit) / 10 : value > (INTPTR_MAX - digit) / 10) {
+            valid = 0;
+        } else {
+            value = value * 10 + (negative ? -digit : digit);
+        }
+    }
+    if (!valid) {
+        ph_fail(span, "expected an integer but read '%s'", line->data);
+    }
+    return ph_int(value);
+}
+
+/* Numeric argument as a float */
+static inline double ph_number(const char *name, ph_value value, ph_span span) {
+    if (value.type == PH_INT) {
+        return (double)value.as.i;
+    }
+    if (value.type != PH_FLOAT) {
+        ph_fail(span, "'%s' expects numbers but got %s", name, ph_type_name(value));
+    }
+    return value.as.f;
+}
+
+static inline ph_value ph_b_abs(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)count;
+    (void)text;
+    if (arguments[0].type == PH_INT) {
+        if (arguments[0].as.i == INTPTR_MIN) {
+            ph_fail(span, "attempt to take 'abs' of %" PRIdPTR " with overflow", arguments[0].as.i);
+        }
+        return ph_int(arguments[0].as.i < 0 ? -arguments[0].as.i : arguments[0].as.i);
+    }
+    return ph_float(fabs(ph_number("abs", arguments[0], span)));
+}
+
+static inline ph_value ph_min_max(const char *name, const ph_value *arguments, ph_span span, int maximum) {
+    if (arguments[0].type == PH_INT && arguments[1].type == PH_INT) {
+        intptr_t left = arguments[0].as.i;
+        intptr_t right = arguments[1].as.i;
+        return ph_int(maximum ? (left > right ? left : right) : (left < right ? left : right));
+    }
+    double left = ph_number(name, arguments[0], span);
+    double right = ph_number(name, arguments[1], span);
+    return ph_float(maximum ? fmax(left, right) : fmin(left, right));
+}
+
+static inline ph_value ph_b_min(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)count;
+    (void)text;
+    return ph_min_max("min", arguments, span, 0);
+}
+
+static inline ph_value ph_b_max(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)count;
+    (void)text;
+    return ph_min_max("max", arguments, span, 1);
+}
+
+static inline ph_value ph_b_pow(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)count;
+    (void)text;
+    if (arguments[0].type == PH_INT && arguments[1].type == PH_INT) {
+        intptr_t base = arguments[0].as.i;
+        intptr_t exponent = arguments[1].as.i;
+        if (exponent < 0) {
+            ph_fail(span, "'pow' can't raise an int to the negative power %" PRIdPTR ", use a float base",
+                    exponent);
+        }
+
+        /* Powers of 0, 1 and -1 never overflow, whatever the exponent */
+        if ((base == 0 || base == 1) && exponent > 0) {
+            return ph_int(base);
+        }
+        if (base == -1) {
+            return ph_int(exponent % 2 == 0 ? 1 : -1);
+        }
+
+        /* Squaring like Rust's `checked_pow`, which also takes at most a 32-bit power */
+        intptr_t result = 1;
+        intptr_t factor = base;
+        uint64_t power = (uint64_t)exponent;
+        int fits = power <= UINT32_MAX;
+        while (fits && power > 1) {
+            if (power & 1) {
+                fits = ph_multiply(result, factor, &result);
+            }
+            power /= 2;
+            fits = fits && ph_multiply(factor, factor, &factor);
+        }
+        if (fits && power == 1) {
+            fits = ph_multiply(result, factor, &result);
+        }
+        if (!fits) {
+            ph_fail(span, "attempt to raise %" PRIdPTR " to the power %" PRIdPTR " with overflow", base, exponent);
+        }
+        return ph_int(result);
+    }
+
+    double base = ph_number("pow", arguments[0], span);
+    double exponent = ph_number("pow", arguments[1], span);
+    return ph_float(pow(base, exponent));
+}
+
+static inline ph_value ph_b_sqrt(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)count;
+    (void)text;
+    return ph_float(sqrt(ph_number("sqrt", arguments[0], span)));
+}
+
+/* `floor`, `ceil` and `round` give an int, `round` takes halves away from zero */
+static inline ph_value ph_to_int(const char *name, const ph_value *arguments, ph_span span) {
+    if (arguments[0].type == PH_INT) {
+        return arguments[0];
+    }
+
+    double value = ph_number(name, arguments[0], span);
+    double rounded = round(value);
+    if (strcmp(name, "floor") == 0) {
+        rounded = floor(value);
+    } else if (strcmp(name, "ceil") == 0) {
+        rounded = ceil(value);
+    }
+    /* `INTPTR_MAX` as a double rounds up to 2^63, which doesn't fit */
+    if (isnan(rounded) || rounded < (double)INTPTR_MIN || rounded >= (double)INTPTR_MAX) {
+        ph_buffer buffer = {NULL, 0, 0};
+        ph_format_float(&buffer, value);
+        ph_fail(span, "'%s' of %s doesn't fit in an int", name, buffer.data);
+    }
+    return ph_int((intptr_t)rounded);
+}
+
+static inline ph_value ph_b_floor(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)count;
+    (void)text;
+    return ph_to_int("floor", arguments, span);
+}
+
+static inline ph_value ph_b_ceil(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)count;
+    (void)text;
+    return ph_to_int("ceil", arguments, span);
+}
+
+static inline ph_value ph_b_round(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)count;
+    (void)text;
+    return ph_to_int("round", arguments, span);
+}
+
+/* Error for a builtin given the wrong types, `expected` describes what it takes */
+static inline void ph_expects(const char *name, const char *expected, const ph_value *arguments, size_t count,
+                              ph_span span) {
+    ph_fail(span, "'%s' expects %s but got %s", name, expected, ph_type_names(arguments, count));
+}
+
+static inline ph_value ph_b_len(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)count;
+    (void)text;
+    if (arguments[0].type == PH_STRING) {
+        return ph_int((intptr_t)ph_utf8_count(arguments[0].as.s->text, arguments[0].as.s->length));
+    }
+    if (arguments[0].type == PH_ARRAY) {
+        return ph_int((intptr_t)arguments[0].as.a->length);
+    }
+    ph_expects("len", "a string or an array", arguments, 1, span);
+    return ph_nil();
+}
+
+static inline ph_value ph_b_substr(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)text;
+    if (arguments[0].type != PH_STRING || arguments[1].type != PH_INT || arguments[2].type != PH_INT) {
+        ph_expects("substr", "a string and two ints", arguments, count, span);
+    }
+
+    intptr_t start = arguments[1].as.i;
+    intptr_t length = arguments[2].as.i;
+    if (start < 0 || length < 0) {
+        ph_fail(span, "'substr' expects a start and length of 0 or more but got %" PRIdPTR " and %" PRIdPTR, start,
+                length);
+    }
+
+    const ph_string *string = arguments[0].as.s;
+    size_t text_length = ph_utf8_count(string->text, string->length);
+    size_t end = (size_t)start + (size_t)length;
+    if (end < (size_t)start) {
+        end = SIZE_MAX;
+    }
+    if (end > text_length) {
+        ph_fail(span, "'substr' range %" PRIdPTR "..%zu is out of bounds for a string of length %zu", start, end,
+                text_length);
+    }
+
+    size_t from = ph_utf8_offset(string->text, string->length, (size_t)start);
+    size_t to = ph_utf8_offset(string->text, string->length, end);
+    return ph_new_string(string->text + from, to - from);
+}
+
+/* Byte offset of `needle` in `text` from `start`, `SIZE_MAX` when it isn't there */
+static inline size_t ph_find(const ph_string *text, size_t start, const ph_string *needle) {
+    for (size_t offset = start; offset + needle->length <= text->length; offset++) {
+        if (memcmp(text->text + offset, needle->text, needle->length) == 0) {
+            return offset;
+        }
+    }
+    return SIZE_MAX;
+}
+
+static inline ph_value ph_b_contains(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)text;
+    if (arguments[0].type != PH_STRING || arguments[1].type != PH_STRING) {
+        ph_expects("contains", "two strings", arguments, count, span);
+    }
+    return ph_bool(ph_find(arguments[0].as.s, 0, arguments[1].as.s) != SIZE_MAX);
+}
+
+static inline ph_value ph_b_split(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)text;
+    if (arguments[0].type != PH_STRING || arguments[1].type != PH_STRING) {
+        ph_expects("split", "two strings", arguments, count, span);
+    }
+    const ph_string *string = arguments[0].as.s;
+    const ph_string *separator = arguments[1].as.s;
+    if (separator->length == 0) {
+        ph_fail(span, "'split' separator can't be empty");
+    }
+
+    size_t parts = 1;
+    for (size_t offset = ph_find(string, 0, separator); offset != SIZE_MAX;
+         offset = ph_find(string, offset + separator->length, separator)) {
+        parts++;
+    }
+
+    ph_value *elements = ph_allocate(parts * sizeof(ph_value));
+    size_t start = 0;
+    for (size_t part = 0; part < parts; part++) {
+        size_t end = ph_find(string, start, separator);
+        if (end == SIZE_MAX) {
+            end = string->length;
+        }
+        elements[part] = ph_new_string(string->text + start, end - start);
+        start = end + separator->length;
+    }
+    ph_value result = ph_array_of(parts, elements);
+    free(elements);
+    return result;
+}
+
+static inline ph_value ph_b_trim(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)text;
+    if (arguments[0].type != PH_STRING) {
+        ph_expects("trim", "a string", arguments, count, span);
+    }
+    size_t start, end;
+    ph_trim(arguments[0].as.s->text, arguments[0].as.s->length, &start, &end);
+    return ph_new_string(arguments[0].as.s->text + start, end - start);
+}
+
+static inline ph_value ph_b_assert(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)count;
+    if (arguments[0].type != PH_BOOL) {
+        ph_fail(span, "'assert' expects a bool but got %s", ph_type_name(arguments[0]));
+    }
+    if (!arguments[0].as.b) {
+        ph_fail(span, "assertion failed: %s", text);
+    }
+    return ph_nil();
+}
+
+/* Value as shown in an assertion message, strings are quoted */
+static inline void ph_display_quoted(ph_buffer *buffer, ph_value value) {
+    if (value.type == PH_STRING) {
+        ph_quote(buffer, value.as.s);
+    } else {
+        ph_display(buffer, value);
+    }
+}
+
+static inline ph_value ph_b_assert_eq(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)count;
+    (void)text;
+    if (!ph_equal(arguments[0], arguments[1])) {
+        ph_buffer buffer = {NULL, 0, 0};
+        ph_append_text(&buffer, "assertion failed: left = ");
+        ph_display_quoted(&buffer, arguments[0]);
+        ph_append_text(&buffer, ", right = ");
+        ph_display_quoted(&buffer, arguments[1]);
+        ph_report(buffer.data, span);
+    }
+    return ph_nil();
+}
+
+static inline intptr_t ph_now_ms(void) {
+    struct timespec now;
+    if (clock_gettime(CLOCK_REALTIME, &now) != 0 || now.tv_sec < 0) {
+        return 0;
+    }
+    return (intptr_t)now.tv_sec * 1000 + now.tv_nsec / 1000000;
+}
+
+static inline void ph_expects_int(const char *name, const ph_value *arguments, ph_span span) {
+    if (arguments[0].type != PH_INT) {
+        ph_fail(span, "'%s' expects an int but got %s", name, ph_type_name(arguments[0]));
+    }
+}
+
+static inline ph_value ph_b_now_ms(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)arguments;
+    (void)count;
+    (void)span;
+    (void)text;
+    return ph_int(ph_now_ms());
+}
+
+static inline ph_value ph_b_elapsed_ms(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)count;
+    (void)text;
+    ph_expects_int("elapsed_ms", arguments, span);
+    intptr_t now = ph_now_ms();
+    intptr_t start = arguments[0].as.i;
+    if (start < 0 && now > INTPTR_MAX + start) {
+        return ph_int(INTPTR_MAX);
+    }
+    if (start > 0 && now < INTPTR_MIN + start) {
+        return ph_int(INTPTR_MIN);
+    }
+    return ph_int(now - start);
+}
+
+static inline ph_value ph_b_sleep_ms(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)count;
+    (void)text;
+    ph_expects_int("sleep_ms", arguments, span);
+    if (arguments[0].as.i < 0) {
+        ph_fail(span, "'sleep_ms' can't sleep for a negative time, got %" PRIdPTR, arguments[0].as.i);
+    }
+
+    struct timespec duration;
+    duration.tv_sec = (time_t)(arguments[0].as.i / 1000);
+    duration.tv_nsec = (long)(arguments[0].as.i % 1000) * 1000000;
+    while (nanosleep(&duration, &duration) != 0 && errno == EINTR) {
+    }
+    return ph_nil();
+}
+
+static inline uint64_t ph_rotate_left(uint64_t value, int amount) {
+    return (value << amount) | (value >> (64 - amount));
+}
+
+static inline void ph_seed(uint64_t seed) {
+    for (int index = 0; index < 4; index++) {
+        seed += UINT64_C(0x9E3779B97F4A7C15);
+        uint64_t z = seed;
+        z = (z ^ (z >> 30)) * UINT64_C(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)) * UINT64_C(0x94D049BB133111EB);
+        ph_random_state[index] = z ^ (z >> 31);
+    }
+    ph_random_seeded = 1;
+}
+
+static inline uint64_t ph_next_u64(void) {
+    if (!ph_random_seeded) {
+        uint64_t seed = (uint64_t)time(NULL) ^ ((uint64_t)clock() << 32);
+        FILE *entropy = fopen("/dev/urandom", "rb");
+        if (entropy != NULL) {
+            if (fread(&seed, sizeof seed, 1, entropy) != 1) {
+                seed ^= (uint64_t)(uintptr_t)&seed;
+            }
+            fclose(entropy);
+        }
+        ph_seed(seed);
+    }
+
+    uint64_t *state = ph_random_state;
+    uint64_t result = ph_rotate_left(state[1] * 5, 7) * 9;
+    uint64_t t = state[1] << 17;
+
+    state[2] ^= state[0];
+    state[3] ^= state[1];
+    state[1] ^= state[2];
+    state[0] ^= state[3];
+    state[2] ^= t;
+    state[3] = ph_rotate_left(state[3], 45);
+
+    return result;
+}
+
+static inline ph_value ph_b_random(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)arguments;
+    (void)count;
+    (void)span;
+    (void)text;
+    return ph_float((double)(ph_next_u64() >> 11) / (double)(UINT64_C(1) << 53));
+}
+
+static inline ph_value ph_b_random_int(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)text;
+    if (arguments[0].type != PH_INT || arguments[1].type != PH_INT) {
+        ph_expects("random_int", "ints", arguments, count, span);
+    }
+    intptr_t low = arguments[0].as.i;
+    intptr_t high = arguments[1].as.i;
+    if (low > high) {
+        ph_fail(span, "'random_int' range is empty, %" PRIdPTR " is above %" PRIdPTR, low, high);
+    }
+
+    uint64_t range = (uint64_t)high - (uint64_t)low;
+    if (range == UINT64_MAX) {
+        return ph_int((intptr_t)ph_next_u64());
+    }
+
+    uint64_t values = range + 1;
+    uint64_t limit = UINT64_MAX - (UINT64_MAX % values);
+    for (;;) {
+        uint64_t value = ph_next_u64();
+        if (value < limit) {
+            return ph_int((intptr_t)((uint64_t)low + value % values));
+        }
+    }
+}
+
+static inline ph_value ph_b_seed(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)text;
+    if (arguments[0].type != PH_INT) {
+        ph_expects("seed", "ints", arguments, count, span);
+    }
+    ph_seed((uint64_t)arguments[0].as.i);
+    return ph_nil();
+}
+
+/* Debug text of a string, for messages that quote a name */
+static inline const char *ph_quoted(const ph_string *string) {
+    ph_buffer buffer = {NULL, 0, 0};
+    ph_quote(&buffer, string);
+    return buffer.data;
+}
+
+static inline ph_value ph_b_env(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)text;
+    if (arguments[0].type != PH_STRING) {
+        ph_expects("env", "strings", arguments, count, span);
+    }
+    /* Names that can't be set are never set */
+    const ph_string *name = arguments[0].as.s;
+    const char *value = getenv(name->text);
+    if (value == NULL || name->length == 0 || strchr(name->text, '=') != NULL || strlen(name->text) != name->length) {
+        return ph_nil();
+    }
+    if (!ph_utf8_valid(value, strlen(value))) {
+        ph_fail(span, "environment variable %s is not valid UTF-8", ph_quoted(name));
+    }
+    return ph_new_string(value, strlen(value));
+}
+
+static inline ph_value ph_b_set_env(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)text;
+    if (arguments[0].type != PH_STRING || arguments[1].type != PH_STRING) {
+        ph_expects("set_env", "strings", arguments, count, span);
+    }
+    const ph_string *name = arguments[0].as.s;
+    const ph_string *value = arguments[1].as.s;
+    if (name->length == 0 || strchr(name->text, '=') != NULL || strlen(name->text) != name->length) {
+        ph_fail(span, "invalid environment variable name %s", ph_quoted(name));
+    }
+    if (strlen(value->text) != value->length) {
+        ph_fail(span, "value of environment variable %s can't contain a NUL character", ph_quoted(name));
+    }
+    setenv(name->text, value->text, 1);
+    return ph_nil();
+}
+
+static inline void ph_file_error(const char *action, const ph_string *path, ph_span span) {
+    int error = errno;
+    ph_fail(span, "failed to %s '%s': %s (os error %d)", action, path->text, strerror(error), error);
+}
+
+static inline ph_value ph_b_read_file(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)text;
+    if (arguments[0].type != PH_STRING) {
+        ph_expects("read_file", "strings", arguments, count, span);
+    }
+    FILE *file = fopen(arguments[0].as.s->text, "rb");
+    if (file == NULL) {
+        ph_file_error("read", arguments[0].as.s, span);
+    }
+
+    ph_buffer buffer = {NULL, 0, 0};
+    ph_append(&buffer, "", 0);
+    char chunk[4096];
+    size_t read;
+    while ((read = fread(chunk, 1, sizeof chunk, file)) > 0) {
+        ph_append(&buffer, chunk, read);
+    }
+    if (ferror(file)) {
+        ph_file_error("read", arguments[0].as.s, span);
+    }
+    fclose(file);
+
+    if (!ph_utf8_valid(buffer.data, buffer.length)) {
+        ph_fail(span, "failed to read '%s': stream did not contain valid UTF-8", arguments[0].as.s->text);
+    }
+    return ph_new_string(buffer.data, buffer.length);
+}
+
+static inline ph_value ph_write(const char *name, const char *action, const char *mode, const ph_value *arguments,
+                                size_t count, ph_span span) {
+    if (arguments[0].type != PH_STRING || arguments[1].type != PH_STRING) {
+        ph_expects(name, "strings", arguments, count, span);
+    }
+    FILE *file = fopen(arguments[0].as.s->text, mode);
+    if (file == NULL) {
+        ph_file_error(action, arguments[0].as.s, span);
+    }
+    if (fwrite(arguments[1].as.s->text, 1, arguments[1].as.s->length, file) != arguments[1].as.s->length ||
+        fclose(file) != 0) {
+        ph_file_error(action, arguments[0].as.s, span);
+    }
+    return ph_nil();
+}
+
+static inline ph_value ph_b_write_file(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)text;
+    return ph_write("write_file", "write", "wb", arguments, count, span);
+}
+
+static inline ph_value ph_b_append_file(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)text;
+    return ph_write("append_file", "append to", "ab", arguments, count, span);
+}
+
+static inline ph_value ph_b_file_exists(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)text;
+    if (arguments[0].type != PH_STRING) {
+        ph_expects("file_exists", "strings", arguments, count, span);
+    }
+    struct stat status;
+    return ph_bool(stat(arguments[0].as.s->text, &status) == 0);
+}
+
+static inline ph_value ph_b_args(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)arguments;
+    (void)count;
+    (void)span;
+    (void)text;
+    size_t length = ph_argument_count > 1 ? (size_t)ph_argument_count - 1 : 0;
+    ph_value *elements = ph_allocate(length * sizeof(ph_value) + 1);
+    for (size_t index = 0; index < length; index++) {
+        elements[index] = ph_new_string(ph_argument_values[index + 1], strlen(ph_argument_values[index + 1]));
+    }
+    ph_value result = ph_array_of(length, elements);
+    free(elements);
+    return result;
+}
+
+static inline ph_value ph_b_argc(const ph_value *arguments, size_t count, ph_span span, const char *text) {
+    (void)arguments;
+    (void)count;
+    (void)span;
+    (void)text;
+    return ph_int(ph_argument_count > 1 ? ph_argument_count - 1 : 0);
+}
+
+static inline void ph_start(int argc, char **argv, const char *file_name, const char *source, size_t length) {
+    ph_argument_count = argc;
+    ph_argument_values = argv;
+    ph_file_name_text = file_name;
+    ph_source_text = source;
+    ph_source_length = length;
+
+    /* Like Rust's stdout, so output and errors interleave the same way */
+    setvbuf(stdout, NULL, _IOLBF, 0);
+}
+
+/* Print the result of the program like `ph run` */
+static inline void ph_finish(ph_value result, int has_result) {
+    if (has_result && result.type != PH_NIL) {
+        ph_print(&result, 1, 1);
+    }
+    fflush(stdout);
+}
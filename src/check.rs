@@ -0,0 +1,690 @@
+use crate::builtins::builtin;
+use crate::diagnostic::{Diagnostic, Severity, Span};
+use crate::eval::{argument_count_error, RuntimeError, CONSTANTS};
+use crate::interner::{Interner, Symbol};
+use crate::parser::{
+    CallSyntax, ExpressionSyntaxEnum, OperatorToken, Parser, StatementSyntax, VariableSyntax,
+};
+
+/// Error for an import the checker or the interpreter sees, `crate::import::load` merges the
+/// imported files of a program read from a file before either runs
+pub const IMPORT_OUTSIDE_FILE: &str = "imports can only be used in a file";
+
+/// Type of a value when it can be known without running the program
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Type {
+    Int,
+    Float,
+    String,
+    Bool,
+    Array,
+}
+
+impl Type {
+    /// Same names as `Value::type_name`, so messages match the runtime errors
+    pub fn name(&self) -> &'static str {
+        match self {
+            Type::Int => "int",
+            Type::Float => "float",
+            Type::String => "string",
+            Type::Bool => "bool",
+            Type::Array => "array",
+        }
+    }
+
+    fn is_number(&self) -> bool {
+        matches!(self, Type::Int | Type::Float)
+    }
+}
+
+/// Variable in scope, `binding` is the index of its `let` in `Checker::bindings`, parameters
+/// and constants have none
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Variable {
+    variable_type: Option<Type>,
+    binding: Option<usize>,
+}
+
+/// Variable defined with `let`, to warn when it is never read
+#[derive(Clone, Debug)]
+struct Binding {
+    name: Symbol,
+    span: Span,
+    global: bool,
+    used: bool,
+}
+
+/// Finds undefined names, wrong argument counts, and operators applied to the wrong types,
+/// and warns about variables that are never read
+///
+/// A variable's type is only tracked while it is certain, `None` means it could be anything
+#[derive(Debug, Default)]
+pub struct Checker {
+    /// Every name the program uses, scopes are looked up by their symbols
+    names: Interner,
+    /// Innermost scope last, a function body gets its own scope on top of the globals
+    scopes: Vec<std::collections::HashMap<Symbol, Variable>>,
+    /// Every `let` in the order they were checked
+    bindings: Vec<Binding>,
+    /// Parameter count of every function
+    functions: std::collections::HashMap<Symbol, usize>,
+    /// Globals assigned inside a function, their type depends on when the function is called
+    assigned_in_functions: std::collections::HashSet<Symbol>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker {
+    pub fn new() -> Checker {
+        Checker::default()
+    }
+
+    /// Check a whole program, returning every problem found, warnings last
+    pub fn check(mut self, statements: &[StatementSyntax]) -> Vec<Diagnostic> {
+        let mut globals: std::collections::HashMap<Symbol, Variable> =
+            std::collections::HashMap::new();
+        for (name, _) in CONSTANTS {
+            globals.insert(
+                self.names.intern(name),
+                Variable {
+                    variable_type: Some(Type::Float),
+                    binding: None,
+                },
+            );
+        }
+        self.scopes.push(globals);
+
+        for statement in statements {
+            if let StatementSyntax::Function(function) = statement {
+                if builtin(&function.name).is_some() {
+                    self.diagnostics.push(Diagnostic::error(
+                        format!(
+                            "cannot define function '{}', it is a builtin",
+                            function.name
+                        ),
+                        Span::new(function.position, function.position + function.name.len()),
+                    ));
+                    continue;
+                }
+
+                self.functions
+                    .insert(self.names.intern(&function.name), function.parameters.len());
+                collect_assignments(
+                    &function.body,
+                    &mut self.names,
+                    &mut self.assigned_in_functions,
+                );
+            }
+        }
+
+        self.check_statements(statements);
+
+        // Bodies run after the top level has defined its globals, so check them last
+        for statement in statements {
+            if let StatementSyntax::Function(function) = statement {
+                let parameters: std::collections::HashMap<Symbol, Variable> = function
+                    .parameters
+                    .iter()
+                    .map(|parameter: &String| {
+                        (
+                            self.names.intern(parameter),
+                            Variable {
+                                variable_type: None,
+                                binding: None,
+                            },
+                        )
+                    })
+                    .collect();
+                self.scopes.push(parameters);
+                self.check_statements(&function.body);
+                self.scopes.pop();
+            }
+        }
+
+        // A name starting with `_` is unused on purpose
+        for binding in &self.bindings {
+            let name: &str = self.names.resolve(binding.name);
+            if !binding.used && !name.starts_with('_') {
+                self.diagnostics.push(Diagnostic::warning(
+                    format!("variable '{}' is never used", name),
+                    binding.span,
+                ));
+            }
+        }
+
+        self.diagnostics
+    }
+
+    fn check_statements(&mut self, statements: &[StatementSyntax]) {
+        for statement in statements {
+            match statement {
+                StatementSyntax::Let(let_syntax) => {
+                    let value_type: Option<Type> = self.check_expression(&let_syntax.value);
+                    let name: Symbol = self.names.intern(&let_syntax.name);
+                    let value_type: Option<Type> = self.trusted_type(name, value_type);
+
+                    // Shadowing a variable hides it, so it is reported if it wasn't read before
+                    self.bindings.push(Binding {
+                        name,
+                        span: Span::new(
+                            let_syntax.position,
+                            let_syntax.position + let_syntax.name.len(),
+                        ),
+                        global: self.scopes.len() == 1,
+                        used: false,
+                    });
+                    self.scopes
+                        .last_mut()
+                        .expect("There is always a global scope")
+                        .insert(
+                            name,
+                            Variable {
+                                variable_type: value_type,
+                                binding: Some(self.bindings.len() - 1),
+                            },
+                        );
+                }
+                StatementSyntax::Assign(assign) => {
+                    let value_type: Option<Type> = self.check_expression(&assign.value);
+                    let name: Symbol = self.names.intern(&assign.name);
+                    let value_type: Option<Type> = self.trusted_type(name, value_type);
+
+                    match self.lookup_mut(name) {
+                        Some(variable) => variable.variable_type = value_type,
+                        None => self.diagnostics.push(Diagnostic::error(
+                            format!(
+                                "cannot assign to undefined variable '{}', use 'let' to define it",
+                                assign.name
+                            ),
+                            Span::new(assign.position, assign.position + assign.name.len()),
+                        )),
+                    }
+                }
+                // Checked by `check` once the top level is done
+                StatementSyntax::Function(_) => {}
+                StatementSyntax::Import(import) => self.diagnostics.push(Diagnostic::error(
+                    IMPORT_OUTSIDE_FILE.to_string(),
+                    import.span,
+                )),
+                StatementSyntax::Expression(expression) => {
+                    self.check_expression(expression);
+                }
+            }
+        }
+    }
+
+    /// Type of an expression, or `None` when it isn't known
+    fn check_expression(&mut self, expression: &ExpressionSyntaxEnum) -> Option<Type> {
+        match expression {
+            ExpressionSyntaxEnum::Number(_) => Some(Type::Int),
+            ExpressionSyntaxEnum::Float(_) => Some(Type::Float),
+            ExpressionSyntaxEnum::String(_) => Some(Type::String),
+            ExpressionSyntaxEnum::Variable(variable) => self.check_variable(variable),
+            ExpressionSyntaxEnum::Call(call) => {
+                self.check_call(call);
+                None
+            }
+            ExpressionSyntaxEnum::Conditional(conditional) => {
+                let condition: Option<Type> = self.check_expression(&conditional.condition);
+                if let Some(condition) =
+                    condition.filter(|condition: &Type| *condition != Type::Bool)
+                {
+                    self.diagnostics.push(Diagnostic::error(
+                        format!("condition must be a bool but found {}", condition.name()),
+                        Span::new(conditional.position, conditional.position + 1),
+                    ));
+                }
+
+                let then: Option<Type> = self.check_expression(&conditional.then);
+                let otherwise: Option<Type> = self.check_expression(&conditional.otherwise);
+
+                if then == otherwise {
+                    then
+                } else {
+                    None
+                }
+            }
+            ExpressionSyntaxEnum::Array(elements) => {
+                for element in elements {
+                    self.check_expression(element);
+                }
+                Some(Type::Array)
+            }
+            ExpressionSyntaxEnum::Index(index) => {
+                let target: Option<Type> = self.check_expression(&index.target);
+                let position: Option<Type> = self.check_expression(&index.index);
+                let span: Span = Span::new(index.position, index.position + 1);
+
+                if let Some(target) =
+                    target.filter(|target: &Type| !matches!(target, Type::Array | Type::String))
+                {
+                    self.diagnostics.push(Diagnostic::error(
+                        format!("cannot index {}", target.name()),
+                        span,
+                    ));
+                }
+                if let Some(position) = position.filter(|position: &Type| *position != Type::Int) {
+                    self.diagnostics.push(Diagnostic::error(
+                        format!("index must be an int but found {}", position.name()),
+                        span,
+                    ));
+                }
+
+                // Characters of a string are strings, array elements could be anything
+                target.filter(|target: &Type| *target == Type::String)
+            }
+            ExpressionSyntaxEnum::Chain(chain) => {
+                let mut left: Option<Type> = self.check_expression(&chain.first);
+
+                for link in &chain.links {
+                    let right: Option<Type> = self.check_expression(&link.operand);
+                    if let (Some(left), Some(right)) = (left, right) {
+                        if binary_type(link.operator_token, left, right).is_none() {
+                            self.diagnostics.push(Diagnostic::error(
+                                format!(
+                                    "cannot apply '{}' to {} and {}",
+                                    link.operator_token.text(),
+                                    left.name(),
+                                    right.name()
+                                ),
+                                link.operator_token.span(link.position),
+                            ));
+                        }
+                    }
+                    left = right;
+                }
+
+                Some(Type::Bool)
+            }
+            ExpressionSyntaxEnum::Unary(unary) => {
+                let operand: Option<Type> = self.check_expression(&unary.operand);
+
+                match operand {
+                    Some(operand)
+                        if unary.operator_token == OperatorToken::Minus && !operand.is_number() =>
+                    {
+                        self.diagnostics.push(Diagnostic::error(
+                            format!(
+                                "cannot apply unary '{}' to {}",
+                                unary.operator_token.text(),
+                                operand.name()
+                            ),
+                            unary.operator_token.span(unary.position),
+                        ));
+                        None
+                    }
+                    operand => operand,
+                }
+            }
+            ExpressionSyntaxEnum::ExpressionSyntax(binary) => {
+                let left: Option<Type> = self.check_expression(&binary.left);
+                let right: Option<Type> = self.check_expression(&binary.right);
+                let (Some(left), Some(right)) = (left, right) else {
+                    return if binary.operator_token.is_comparison() {
+                        Some(Type::Bool)
+                    } else {
+                        None
+                    };
+                };
+
+                let result: Option<Type> = binary_type(binary.operator_token, left, right);
+                if result.is_none() {
+                    self.diagnostics.push(Diagnostic::error(
+                        format!(
+                            "cannot apply '{}' to {} and {}",
+                            binary.operator_token.text(),
+                            left.name(),
+                            right.name()
+                        ),
+                        binary.span,
+                    ));
+                }
+
+                result
+            }
+        }
+    }
+
+    fn check_variable(&mut self, variable: &VariableSyntax) -> Option<Type> {
+        let name: Symbol = self.names.intern(&variable.name);
+        match self.lookup(name) {
+            Some(found) => {
+                if let Some(binding) = found.binding {
+                    self.bindings[binding].used = true;
+                }
+
+                // A function can run between any two definitions of a global it reads
+                let global: bool = self.scopes.len() > 1
+                    && self.scopes.last().is_some_and(
+                        |scope: &std::collections::HashMap<Symbol, Variable>| {
+                            !scope.contains_key(&name)
+                        },
+                    );
+                if global {
+                    for binding in &mut self.bindings {
+                        if binding.global && binding.name == name {
+                            binding.used = true;
+                        }
+                    }
+                }
+
+                found.variable_type
+            }
+            None => {
+                self.diagnostics.push(Diagnostic::error(
+                    format!("undefined variable '{}'", variable.name),
+                    Span::new(variable.position, variable.position + variable.name.len()),
+                ));
+                None
+            }
+        }
+    }
+
+    fn check_call(&mut self, call: &CallSyntax) {
+        let argument_types: Vec<Option<Type>> = call
+            .arguments
+            .iter()
+            .map(|argument: &ExpressionSyntaxEnum| self.check_expression(argument))
+            .collect();
+
+        let parameter_count: Option<usize> = match builtin(&call.name) {
+            Some((group, builtin)) => {
+                if builtin.parameters == Some(call.arguments.len()) {
+                    if let Some(message) =
+                        argument_type_error(group.name, &call.name, &argument_types)
+                    {
+                        self.diagnostics.push(Diagnostic::error(message, call.span));
+                    }
+                }
+                builtin.parameters
+            }
+            None => match self.functions.get(&self.names.intern(&call.name)) {
+                Some(&parameter_count) => Some(parameter_count),
+                None => {
+                    self.diagnostics.push(Diagnostic::error(
+                        format!("undefined function '{}'", call.name),
+                        call.span,
+                    ));
+                    return;
+                }
+            },
+        };
+
+        if let Some(parameter_count) = parameter_count
+            .filter(|parameter_count: &usize| *parameter_count != call.arguments.len())
+        {
+            let error: RuntimeError =
+                argument_count_error(&call.name, parameter_count, call.arguments.len(), call.span);
+            self.diagnostics
+                .push(Diagnostic::error(error.message, error.span));
+        }
+    }
+
+    /// Forget the type of globals that a function may change at any time
+    fn trusted_type(&self, name: Symbol, value_type: Option<Type>) -> Option<Type> {
+        if self.scopes.len() == 1 && self.assigned_in_functions.contains(&name) {
+            None
+        } else {
+            value_type
+        }
+    }
+
+    /// Variable in the innermost scope that has it
+    fn lookup(&self, name: Symbol) -> Option<Variable> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope: &std::collections::HashMap<Symbol, Variable>| {
+                scope.get(&name).copied()
+            })
+    }
+
+    fn lookup_mut(&mut self, name: Symbol) -> Option<&mut Variable> {
+        self.scopes.iter_mut().rev().find_map(
+            |scope: &mut std::collections::HashMap<Symbol, Variable>| scope.get_mut(&name),
+        )
+    }
+}
+
+/// Error the runtime would give for a builtin called with arguments of these types, like
+/// `'sqrt' expects numbers but got string`, `None` when the known types are accepted
+fn argument_type_error(group: &str, name: &str, types: &[Option<Type>]) -> Option<String> {
+    let known: Option<Vec<Type>> = types.iter().copied().collect();
+    let listed = |types: &[Type]| -> String {
+        types
+            .iter()
+            .map(Type::name)
+            .collect::<Vec<&str>>()
+            .join(" and ")
+    };
+
+    match (group, name) {
+        ("math", _) => types
+            .iter()
+            .flatten()
+            .find(|value_type: &&Type| !value_type.is_number())
+            .map(|value_type: &Type| {
+                format!("'{}' expects numbers but got {}", name, value_type.name())
+            }),
+        ("string", "len") => match types {
+            [Some(value_type @ (Type::Int | Type::Float | Type::Bool))] => Some(format!(
+                "'len' expects a string or an array but got {}",
+                value_type.name()
+            )),
+            _ => None,
+        },
+        ("string", _) => {
+            let known: Vec<Type> = known?;
+            let expected: &[Type] = match name {
+                "substr" => &[Type::String, Type::Int, Type::Int],
+                "contains" | "split" => &[Type::String, Type::String],
+                _ => &[Type::String],
+            };
+
+            (known != expected).then(|| {
+                format!(
+                    "'{}' expects {} but got {}",
+                    name,
+                    match name {
+                        "substr" => "a string and two ints",
+                        "contains" | "split" => "two strings",
+                        _ => "a string",
+                    },
+                    listed(&known)
+                )
+            })
+        }
+        ("assert", "assert") => match types {
+            [Some(value_type)] if *value_type != Type::Bool => Some(format!(
+                "'assert' expects a bool but got {}",
+                value_type.name()
+            )),
+            _ => None,
+        },
+        ("time", "elapsed_ms" | "sleep_ms") => match types {
+            [Some(value_type)] if *value_type != Type::Int => Some(format!(
+                "'{}' expects an int but got {}",
+                name,
+                value_type.name()
+            )),
+            _ => None,
+        },
+        ("env" | "file", _) => {
+            let known: Vec<Type> = known?;
+
+            known
+                .iter()
+                .any(|value_type: &Type| *value_type != Type::String)
+                .then(|| format!("'{}' expects strings but got {}", name, listed(&known)))
+        }
+        _ => None,
+    }
+}
+
+/// Result type of a binary operator, `None` when the runtime would reject the operands
+fn binary_type(operator_token: OperatorToken, left: Type, right: Type) -> Option<Type> {
+    if operator_token.is_comparison() {
+        let is_equality: bool = matches!(
+            operator_token,
+            OperatorToken::EqualsEquals | OperatorToken::BangEquals
+        );
+
+        return match (left, right) {
+            (left, right) if left.is_number() && right.is_number() => Some(Type::Bool),
+            (Type::String, Type::String) => Some(Type::Bool),
+            (Type::Bool, Type::Bool) if is_equality => Some(Type::Bool),
+            _ => None,
+        };
+    }
+
+    match (left, right) {
+        (Type::Int, Type::Int) => Some(Type::Int),
+        (left, right) if left.is_number() && right.is_number() && !operator_token.is_bitwise() => {
+            Some(Type::Float)
+        }
+        _ => None,
+    }
+}
+
+/// Symbols of the names assigned with `name = value` anywhere in `statements`
+fn collect_assignments(
+    statements: &[StatementSyntax],
+    interner: &mut Interner,
+    names: &mut std::collections::HashSet<Symbol>,
+) {
+    for statement in statements {
+        match statement {
+            StatementSyntax::Assign(assign) => {
+                names.insert(interner.intern(&assign.name));
+            }
+            StatementSyntax::Function(function) => {
+                collect_assignments(&function.body, interner, names)
+            }
+            StatementSyntax::Let(_)
+            | StatementSyntax::Import(_)
+            | StatementSyntax::Expression(_) => {}
+        }
+    }
+}
+
+/// Program that made it through the front end, with the warnings found on the way
+#[derive(Clone, Debug)]
+pub struct Checked {
+    /// The program as parsed, before any pass rewrote it
+    pub statements: Vec<StatementSyntax>,
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// Parse and check `source`, the front end `ph check` and `ph build` share so they agree on
+/// which programs are valid
+///
+/// Returns every diagnostic, warnings last, when any of them is an error. With `layout`,
+/// indentation delimits function bodies, and with `lenient`, characters that start no token
+/// are new lines.
+pub fn front_end(
+    source: &str,
+    syntax_only: bool,
+    layout: bool,
+    lenient: bool,
+) -> Result<Checked, Vec<Diagnostic>> {
+    let mut parser: Parser = if layout {
+        Parser::with_layout(source.to_string())
+    } else {
+        Parser::new(source.to_string())
+    };
+    parser.set_lenient(lenient);
+    let statements: Vec<StatementSyntax> = parser
+        .parse_statements()
+        .map_err(|error: crate::parser::ParseError| vec![Diagnostic::from(error)])?;
+    if syntax_only {
+        return Ok(Checked {
+            statements,
+            warnings: Vec::new(),
+        });
+    }
+
+    let warnings: Vec<Diagnostic> = check_statements(&statements)?;
+    Ok(Checked {
+        statements,
+        warnings,
+    })
+}
+
+/// Find a literal divided by zero, like `1 / 0` or `1 % (2 - 2)`, which fails whenever it
+/// runs, returning `statements` folded
+///
+/// It doesn't depend on the optimization level, `ph run` reports it before running even when
+/// the program isn't folded.
+pub fn check_division(statements: &[StatementSyntax]) -> Result<Vec<StatementSyntax>, Diagnostic> {
+    let mut folded: Vec<StatementSyntax> = statements.to_vec();
+    crate::fold::fold_statements(&mut folded)?;
+    Ok(folded)
+}
+
+/// Check parsed `statements` without changing them, returning the warnings, or every
+/// diagnostic, warnings last, when any of them is an error
+pub fn check_statements(
+    statements: &[StatementSyntax],
+) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+    // The checker sees the folded types
+    let folded: Vec<StatementSyntax> =
+        check_division(statements).map_err(|diagnostic: Diagnostic| vec![diagnostic])?;
+    let diagnostics: Vec<Diagnostic> = Checker::new().check(&folded);
+
+    if diagnostics
+        .iter()
+        .any(|diagnostic: &Diagnostic| diagnostic.severity != Severity::Warning)
+    {
+        return Err(diagnostics);
+    }
+
+    Ok(diagnostics)
+}
+
+pub fn check(args: clap::ArgMatches) {
+    // Get file to check
+    let file: &String;
+    let syntax_only: bool;
+    let layout: bool;
+    let lenient: bool;
+    let watch: bool;
+    if let Some(arg_match) = args.subcommand_matches("check") {
+        file = arg_match
+            .get_one::<String>("file")
+            .expect("Failed to get file");
+        syntax_only = arg_match.get_flag("syntax");
+        layout = arg_match.get_flag("layout");
+        lenient = arg_match.get_flag("lenient");
+        watch = arg_match.get_flag("watch");
+    } else {
+        unreachable!("Subcommand is required");
+    }
+
+    if watch {
+        crate::watch::reject_stdin(file);
+        crate::watch::watch(file, layout, lenient);
+        return;
+    }
+
+    // Get file contents, with the files it imports
+    let (file, file_contents): (&str, String) = crate::source::read_input(file);
+    let loaded: crate::import::Loaded =
+        crate::import::load_program(file, &file_contents, layout, lenient);
+
+    // Nothing is lowered, run or written, warnings alone don't fail the check
+    let (diagnostics, failed): (Vec<Diagnostic>, bool) =
+        match front_end(&loaded.source, syntax_only, layout, lenient) {
+            Ok(checked) => (checked.warnings, false),
+            Err(diagnostics) => (diagnostics, true),
+        };
+
+    for diagnostic in &diagnostics {
+        eprint!(
+            "{}",
+            diagnostic.render_imported(file, &loaded.imports, &loaded.source)
+        );
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,40 @@
+/// Terminal colors for output meant to be read by people
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+}
+
+impl Color {
+    /// ANSI escape sequence that switches to the color
+    fn code(&self) -> &'static str {
+        match self {
+            Color::Red => "\x1b[31m",
+            Color::Green => "\x1b[32m",
+            Color::Yellow => "\x1b[33m",
+            Color::Blue => "\x1b[34m",
+            Color::Magenta => "\x1b[35m",
+            Color::Cyan => "\x1b[36m",
+        }
+    }
+
+    /// `text` in this color, unchanged when `enabled` is false
+    pub fn paint(&self, text: &str, enabled: bool) -> String {
+        if enabled {
+            format!("{}{}\x1b[0m", self.code(), text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Whether to color stdout, only for a terminal and never with `--no-color` or `NO_COLOR` set
+pub fn stdout_color(no_color: bool) -> bool {
+    !no_color
+        && std::env::var_os("NO_COLOR").is_none_or(|value: std::ffi::OsString| value.is_empty())
+        && std::io::IsTerminal::is_terminal(&std::io::stdout())
+}
@@ -0,0 +1,165 @@
+use crate::diagnostic::{Diagnostic, Span};
+use crate::eval::{binary_operation, Overflow, Value};
+use crate::lexer::StringSegment;
+use crate::parser::{ExpressionSyntaxEnum, StatementSyntax};
+
+/// Remove code that never runs and values that are never used, after `fold_statements`
+///
+/// A conditional whose condition compares literals is replaced with the branch it takes, and
+/// a literal on its own line in a function body, other than the last, is dropped. Nothing
+/// that could fail or call a function is removed, so the program behaves the same. Literals
+/// on their own line at the top level stay, `--print-each` prints them.
+///
+/// Returns a warning for every branch that can never run. The checker runs before this, so
+/// mistakes in removed code are still reported.
+pub fn eliminate_dead_code(statements: &mut [StatementSyntax]) -> Vec<Diagnostic> {
+    let mut warnings: Vec<Diagnostic> = Vec::new();
+
+    for statement in statements {
+        match statement {
+            StatementSyntax::Let(let_syntax) => {
+                eliminate_in_expression(&mut let_syntax.value, &mut warnings)
+            }
+            StatementSyntax::Assign(assign) => {
+                eliminate_in_expression(&mut assign.value, &mut warnings)
+            }
+            StatementSyntax::Function(function) => {
+                warnings.extend(eliminate_dead_code(&mut function.body));
+
+                // The value of the last statement is returned, the others are thrown away
+                let last: usize = function.body.len().saturating_sub(1);
+                let mut index: usize = 0;
+                function.body.retain(|statement: &StatementSyntax| {
+                    let is_last: bool = index == last;
+                    index += 1;
+                    is_last || !is_unused_literal(statement)
+                });
+            }
+            StatementSyntax::Import(_) => {}
+            // Only its inside, the passes don't know whether `--print-each` shows its value
+            StatementSyntax::Expression(expression) => {
+                eliminate_in_expression(expression, &mut warnings)
+            }
+        }
+    }
+
+    warnings
+}
+
+fn eliminate_in_expression(expression: &mut ExpressionSyntaxEnum, warnings: &mut Vec<Diagnostic>) {
+    let taken: Option<ExpressionSyntaxEnum> = match expression {
+        ExpressionSyntaxEnum::ExpressionSyntax(binary) => {
+            eliminate_in_expression(&mut binary.left, warnings);
+            eliminate_in_expression(&mut binary.right, warnings);
+            None
+        }
+        ExpressionSyntaxEnum::Unary(unary) => {
+            eliminate_in_expression(&mut unary.operand, warnings);
+            None
+        }
+        ExpressionSyntaxEnum::Conditional(conditional) => {
+            eliminate_in_expression(&mut conditional.condition, warnings);
+            eliminate_in_expression(&mut conditional.then, warnings);
+            eliminate_in_expression(&mut conditional.otherwise, warnings);
+
+            constant_condition(&conditional.condition).map(|condition: bool| {
+                warnings.push(Diagnostic::warning(
+                    format!(
+                        "the condition is always {}, the {} branch never runs",
+                        condition,
+                        if condition { "':'" } else { "'?'" }
+                    ),
+                    Span::new(conditional.position, conditional.position + 1),
+                ));
+
+                // The conditional is replaced as a whole by the branch that runs
+                if condition {
+                    conditional.then.clone()
+                } else {
+                    conditional.otherwise.clone()
+                }
+            })
+        }
+        ExpressionSyntaxEnum::Chain(chain) => {
+            eliminate_in_expression(&mut chain.first, warnings);
+            for link in &mut chain.links {
+                eliminate_in_expression(&mut link.operand, warnings);
+            }
+            None
+        }
+        ExpressionSyntaxEnum::Index(index) => {
+            eliminate_in_expression(&mut index.target, warnings);
+            eliminate_in_expression(&mut index.index, warnings);
+            None
+        }
+        ExpressionSyntaxEnum::Array(elements) => {
+            for element in elements {
+                eliminate_in_expression(element, warnings);
+            }
+            None
+        }
+        ExpressionSyntaxEnum::Call(call) => {
+            for argument in &mut call.arguments {
+                eliminate_in_expression(argument, warnings);
+            }
+            None
+        }
+        ExpressionSyntaxEnum::Number(_)
+        | ExpressionSyntaxEnum::Float(_)
+        | ExpressionSyntaxEnum::String(_)
+        | ExpressionSyntaxEnum::Variable(_) => None,
+    };
+
+    if let Some(taken) = taken {
+        *expression = taken;
+    }
+}
+
+/// Value of a condition comparing two literals, `None` when it depends on the program or the
+/// comparison would fail
+fn constant_condition(condition: &ExpressionSyntaxEnum) -> Option<bool> {
+    let ExpressionSyntaxEnum::ExpressionSyntax(binary) = condition else {
+        return None;
+    };
+
+    match binary_operation(
+        binary.operator_token,
+        literal_value(&binary.left)?,
+        literal_value(&binary.right)?,
+        Span::new(0, 0),
+        Overflow::Checked,
+    ) {
+        Ok(Value::Bool(value)) => Some(value),
+        _ => None,
+    }
+}
+
+/// Value of a number or of a string without `${NAME}` variables
+fn literal_value(expression: &ExpressionSyntaxEnum) -> Option<Value> {
+    match expression {
+        ExpressionSyntaxEnum::Number(value) => Some(Value::Int(*value)),
+        ExpressionSyntaxEnum::Float(value) => Some(Value::Float(*value)),
+        ExpressionSyntaxEnum::String(segments) => segments
+            .iter()
+            .map(|segment: &StringSegment| match segment {
+                StringSegment::Text(text) => Some(text.as_str()),
+                StringSegment::EnvironmentVariable { .. } => None,
+            })
+            .collect::<Option<String>>()
+            .map(Value::String),
+        _ => None,
+    }
+}
+
+/// Whether evaluating the expression can't fail or have an effect, environment variables in
+/// strings can be missing
+fn is_literal(expression: &ExpressionSyntaxEnum) -> bool {
+    match expression {
+        ExpressionSyntaxEnum::Array(elements) => elements.iter().all(is_literal),
+        expression => literal_value(expression).is_some(),
+    }
+}
+
+fn is_unused_literal(statement: &StatementSyntax) -> bool {
+    matches!(statement, StatementSyntax::Expression(expression) if is_literal(expression))
+}
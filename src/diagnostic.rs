@@ -0,0 +1,176 @@
+/// Range of bytes in the source text
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Error,
+    RuntimeError,
+    Warning,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::RuntimeError => write!(f, "runtime error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// File merged into a program by an import, as the range of the program's source it takes
+///
+/// `crate::import::load` puts imported files before the file that was given, so everything
+/// after the last of them belongs to that file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceFile {
+    /// Path of the file relative to the current directory
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// File `offset` of a program with `imports` is in, as its name, its text and the offset the
+/// text starts at
+pub fn locate<'a>(
+    file_name: &'a str,
+    imports: &'a [SourceFile],
+    source: &'a str,
+    offset: usize,
+) -> (&'a str, &'a str, usize) {
+    match imports.iter().find(|file: &&SourceFile| offset < file.end) {
+        Some(file) => (&file.name, &source[file.start..file.end], file.start),
+        None => {
+            let start: usize = imports.last().map_or(0, |file: &SourceFile| file.end);
+            (file_name, &source[start..], start)
+        }
+    }
+}
+
+/// Message about a problem in the source, pointing at the offending code
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    /// Function calls leading to the problem as the callee name and call site, innermost first
+    pub trace: Vec<(String, Span)>,
+}
+
+/// Number of call frames shown before the rest are summarized
+const MAX_TRACE_FRAMES: usize = 10;
+
+impl Diagnostic {
+    pub fn error(message: String, span: Span) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            message,
+            span,
+            trace: Vec::new(),
+        }
+    }
+
+    pub fn warning(message: String, span: Span) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            message,
+            span,
+            trace: Vec::new(),
+        }
+    }
+
+    /// Render the diagnostic with the source line it points at
+    ///
+    /// ```text
+    /// runtime error: division by zero
+    ///  --> examples/math.ph:1:4
+    ///   |
+    /// 1 | (1 / 0)
+    ///   |    ^
+    ///   = in 'half', called at examples/math.ph:4:1
+    /// ```
+    pub fn render(&self, file_name: &str, source: &str) -> String {
+        self.render_imported(file_name, &[], source)
+    }
+
+    /// Render the diagnostic of a program with `imports`, naming the file each location is in
+    pub fn render_imported(&self, file_name: &str, imports: &[SourceFile], source: &str) -> String {
+        let (call_file_name, call_source): (&str, &str) = (file_name, source);
+        let (file_name, source, start): (&str, &str, usize) =
+            locate(file_name, imports, source, self.span.start);
+        let span: Span = Span::new(self.span.start - start, self.span.end.saturating_sub(start));
+
+        let (line, column): (usize, usize) = line_column(source, span.start);
+        let line_text: &str = source.lines().nth(line - 1).unwrap_or("");
+        let gutter: String = " ".repeat(line.to_string().len());
+
+        // Underline at least one character, but never past the end of the line
+        let line_start: usize = source[..span.start.min(source.len())]
+            .rfind('\n')
+            .map_or(0, |index: usize| index + 1);
+        let underline_end: usize = span.end.min(line_start + line_text.len());
+        let underline_length: usize = source
+            .get(span.start..underline_end)
+            .map_or(0, |text: &str| text.chars().count())
+            .max(1);
+
+        let mut output: String = format!(
+            "{}: {}\n{}--> {}:{}:{}\n{} |\n{} | {}\n{} | {}{}\n",
+            self.severity,
+            self.message,
+            gutter,
+            file_name,
+            line,
+            column,
+            gutter,
+            line,
+            line_text,
+            gutter,
+            " ".repeat(column - 1),
+            "^".repeat(underline_length),
+        );
+
+        for (name, call_span) in self.trace.iter().take(MAX_TRACE_FRAMES) {
+            let (file_name, source, start): (&str, &str, usize) =
+                locate(call_file_name, imports, call_source, call_span.start);
+            let (line, column): (usize, usize) = line_column(source, call_span.start - start);
+            output.push_str(&format!(
+                "{} = in '{}', called at {}:{}:{}\n",
+                gutter, name, file_name, line, column
+            ));
+        }
+
+        if self.trace.len() > MAX_TRACE_FRAMES {
+            output.push_str(&format!(
+                "{} = ... and {} more frames\n",
+                gutter,
+                self.trace.len() - MAX_TRACE_FRAMES
+            ));
+        }
+
+        output
+    }
+}
+
+/// Get the 1-based line and column of a byte offset
+pub fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before: &str = &source[..offset.min(source.len())];
+    let line: usize = before.matches('\n').count() + 1;
+    let column: usize = before
+        .rsplit('\n')
+        .next()
+        .map_or(0, |text: &str| text.chars().count())
+        + 1;
+
+    (line, column)
+}
@@ -0,0 +1,177 @@
+/// Unchanged lines shown around every change
+const CONTEXT: usize = 3;
+
+/// Step from the old text to the new one, with line indices into each
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Edit {
+    Keep(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Unified diff from `old` to `new`, both shown as `name`, empty when they are the same
+///
+/// Hunks have three lines of context and a missing new line at the end of the file is
+/// marked like `diff -u` does, so the output can be applied with `patch`.
+pub fn unified_diff(old: &str, new: &str, name: &str) -> String {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let edits: Vec<Edit> = edits(&old_lines, &new_lines);
+
+    let changes: Vec<usize> = (0..edits.len())
+        .filter(|index: &usize| !matches!(edits[*index], Edit::Keep(..)))
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    let mut output: String = format!("--- {}\n+++ {}\n", name, name);
+
+    // Changes closer than twice the context share a hunk
+    let mut first: usize = 0;
+    while first < changes.len() {
+        let mut last: usize = first;
+        while last + 1 < changes.len() && changes[last + 1] - changes[last] <= 2 * CONTEXT {
+            last += 1;
+        }
+
+        let start: usize = changes[first].saturating_sub(CONTEXT);
+        let end: usize = (changes[last] + CONTEXT + 1).min(edits.len());
+        push_hunk(&edits[start..end], &old_lines, &new_lines, &mut output);
+
+        first = last + 1;
+    }
+
+    output
+}
+
+/// Append a hunk header and its lines
+fn push_hunk(edits: &[Edit], old_lines: &[&str], new_lines: &[&str], output: &mut String) {
+    // Context lines are in both texts, so only an empty text has no lines in a hunk
+    let old_start: usize = edits
+        .iter()
+        .find_map(|edit: &Edit| match edit {
+            Edit::Keep(old, _) | Edit::Delete(old) => Some(*old),
+            Edit::Insert(_) => None,
+        })
+        .unwrap_or(0);
+    let new_start: usize = edits
+        .iter()
+        .find_map(|edit: &Edit| match edit {
+            Edit::Keep(_, new) | Edit::Insert(new) => Some(*new),
+            Edit::Delete(_) => None,
+        })
+        .unwrap_or(0);
+    let old_count: usize = edits
+        .iter()
+        .filter(|edit: &&Edit| !matches!(edit, Edit::Insert(_)))
+        .count();
+    let new_count: usize = edits
+        .iter()
+        .filter(|edit: &&Edit| !matches!(edit, Edit::Delete(_)))
+        .count();
+
+    output.push_str(&format!(
+        "@@ -{} +{} @@\n",
+        range(old_start, old_count),
+        range(new_start, new_count)
+    ));
+
+    for edit in edits {
+        let (prefix, line): (char, &str) = match edit {
+            Edit::Keep(old, _) => (' ', old_lines[*old]),
+            Edit::Delete(old) => ('-', old_lines[*old]),
+            Edit::Insert(new) => ('+', new_lines[*new]),
+        };
+        output.push(prefix);
+        output.push_str(line);
+        if !line.ends_with('\n') {
+            output.push_str("\n\\ No newline at end of file\n");
+        }
+    }
+}
+
+/// Hunk range as `diff -u` writes it, 1-based with the count left out when it is 1
+fn range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        count => format!("{},{}", start + 1, count),
+    }
+}
+
+/// Shortest edit script from `old` to `new`, found with Myers' algorithm
+fn edits(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let old_length: isize = old.len() as isize;
+    let new_length: isize = new.len() as isize;
+    let offset: isize = old_length + new_length;
+
+    // `frontier[k + offset]` is the furthest old line reached on diagonal `k`, one copy is
+    // kept for every edit count to walk the path back
+    let mut frontier: Vec<isize> = vec![0; 2 * offset as usize + 2];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'search: for distance in 0..=offset {
+        trace.push(frontier.clone());
+
+        for diagonal in (-distance..=distance).step_by(2) {
+            let index: usize = (diagonal + offset) as usize;
+            let mut x: isize = if diagonal == -distance
+                || diagonal != distance && frontier[index - 1] < frontier[index + 1]
+            {
+                frontier[index + 1]
+            } else {
+                frontier[index - 1] + 1
+            };
+            let mut y: isize = x - diagonal;
+
+            while x < old_length && y < new_length && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            frontier[index] = x;
+
+            if x >= old_length && y >= new_length {
+                break 'search;
+            }
+        }
+    }
+
+    // Walk back from the end, each step is a diagonal run of kept lines and one edit
+    let mut edits: Vec<Edit> = Vec::new();
+    let (mut x, mut y): (isize, isize) = (old_length, new_length);
+    for (distance, frontier) in trace.iter().enumerate().rev() {
+        let distance: isize = distance as isize;
+        let diagonal: isize = x - y;
+        let index: usize = (diagonal + offset) as usize;
+
+        let previous_diagonal: isize = if diagonal == -distance
+            || diagonal != distance && frontier[index - 1] < frontier[index + 1]
+        {
+            diagonal + 1
+        } else {
+            diagonal - 1
+        };
+        let previous_x: isize = frontier[(previous_diagonal + offset) as usize];
+        let previous_y: isize = previous_x - previous_diagonal;
+
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Edit::Keep(x as usize, y as usize));
+        }
+        if distance > 0 {
+            if x == previous_x {
+                edits.push(Edit::Insert((y - 1) as usize));
+            } else {
+                edits.push(Edit::Delete((x - 1) as usize));
+            }
+        }
+
+        x = previous_x;
+        y = previous_y;
+    }
+
+    edits.reverse();
+    edits
+}
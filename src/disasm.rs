@@ -0,0 +1,233 @@
+use crate::bytecode::{CallSite, FunctionInfo, Instruction, Program};
+use crate::diagnostic::{line_column, Span};
+use crate::eval::{RuntimeError, Value};
+use crate::lexer::StringSegment;
+
+/// Listing of a program, one instruction per line with its index, mnemonic and operands
+///
+/// Operands are shown resolved, jump targets get `L<n>` labels and function bodies start with
+/// their name. Each source line is shown above the first instruction compiled from it. Bytes
+/// in `bad_bytes` are listed before the instruction they come before, and operands pointing
+/// outside of the tables are marked instead of resolved.
+pub fn disassemble(program: &Program, source: &str, bad_bytes: &[(usize, u8)]) -> String {
+    // Sorted so labels are numbered in the order they appear
+    let mut targets: Vec<usize> = program
+        .code
+        .iter()
+        .filter_map(|instruction: &Instruction| match instruction {
+            Instruction::Jump(target) | Instruction::JumpIfFalse(target) => Some(*target),
+            _ => None,
+        })
+        .collect();
+    targets.sort_unstable();
+    targets.dedup();
+
+    let label = |target: usize| -> String {
+        match targets.binary_search(&target) {
+            Ok(position) => format!("L{}", position),
+            Err(_) => format!("<invalid target {}>", target),
+        }
+    };
+
+    let mut listing: String = format!(
+        "; instructions: {}, constants: {}, functions: {}\n",
+        program.code.len(),
+        program.constants.len(),
+        program.functions.len()
+    );
+    let mut bad_bytes: std::iter::Peekable<std::slice::Iter<(usize, u8)>> =
+        bad_bytes.iter().peekable();
+    let mut last_line: Option<usize> = None;
+
+    for (index, instruction) in program.code.iter().enumerate() {
+        while let Some((_, byte)) = bad_bytes.next_if(|(before, _): &&(usize, u8)| *before <= index)
+        {
+            listing.push_str(&format!("       <bad byte 0x{:02x}>\n", byte));
+        }
+
+        if index == 0 {
+            listing.push_str("main:\n");
+        }
+        for function in &program.functions {
+            if function.address == index {
+                listing.push_str(&format!("{}/{}:\n", function.name, function.parameters));
+                last_line = None;
+            }
+        }
+        if targets.binary_search(&index).is_ok() {
+            listing.push_str(&format!("{}:\n", label(index)));
+        }
+
+        if let Some(span) = program.spans.get(index).copied().filter(has_source(source)) {
+            let (line, _): (usize, usize) = line_column(source, span.start);
+            if last_line != Some(line) {
+                let text: &str = source.lines().nth(line - 1).unwrap_or_default();
+                listing.push_str(&format!("       ; {} | {}\n", line, text.trim()));
+                last_line = Some(line);
+            }
+        }
+
+        let operands: String = format_operands(program, instruction, &label);
+
+        listing.push_str(
+            format!("  {:04}  {:<14} {}", index, mnemonic(instruction), operands).trim_end(),
+        );
+        listing.push('\n');
+    }
+
+    for (_, byte) in bad_bytes {
+        listing.push_str(&format!("       <bad byte 0x{:02x}>\n", byte));
+    }
+
+    listing
+}
+
+/// Operands of an instruction resolved through the tables of `program`, `label` names jump
+/// targets
+pub fn format_operands(
+    program: &Program,
+    instruction: &Instruction,
+    label: &dyn Fn(usize) -> String,
+) -> String {
+    match *instruction {
+        Instruction::Push(constant) => program
+            .constants
+            .get(constant)
+            .map_or_else(|| invalid("constant", constant), constant_text),
+        Instruction::Interpolate(string) => program.strings.get(string).map_or_else(
+            || invalid("string", string),
+            |segments: &Vec<StringSegment>| string_text(segments),
+        ),
+        Instruction::Array(count) => count.to_string(),
+        Instruction::Operator(operator_token) | Instruction::Compare(operator_token) => {
+            operator_token.text().to_string()
+        }
+        Instruction::Load(slot) | Instruction::Store(slot) => slot.to_string(),
+        Instruction::LoadGlobal(name)
+        | Instruction::StoreGlobal(name)
+        | Instruction::DefineGlobal(name) => program
+            .names
+            .get(name as usize)
+            .map_or_else(|| invalid("name", name as usize), String::clone),
+        Instruction::Jump(target) | Instruction::JumpIfFalse(target) => label(target),
+        Instruction::Call { function, .. } => program.functions.get(function).map_or_else(
+            || invalid("function", function),
+            |function: &FunctionInfo| format!("{}/{}", function.name, function.parameters),
+        ),
+        Instruction::CallBuiltin(site) => program.call_sites.get(site).map_or_else(
+            || invalid("call site", site),
+            |site: &CallSite| format!("{}/{}", site.name, site.arguments),
+        ),
+        Instruction::Fail(error) => program.errors.get(error).map_or_else(
+            || invalid("error", error),
+            |error: &RuntimeError| format!("{:?}", error.message),
+        ),
+        Instruction::Index
+        | Instruction::Negate
+        | Instruction::Add
+        | Instruction::Sub
+        | Instruction::Mul
+        | Instruction::Div
+        | Instruction::Pop
+        | Instruction::Yield
+        | Instruction::Return => String::new(),
+    }
+}
+
+/// Name of an instruction in listings
+pub fn mnemonic(instruction: &Instruction) -> &'static str {
+    match instruction {
+        Instruction::Push(_) => "push",
+        Instruction::Interpolate(_) => "interpolate",
+        Instruction::Array(_) => "array",
+        Instruction::Index => "index",
+        Instruction::Negate => "negate",
+        Instruction::Add => "add",
+        Instruction::Sub => "sub",
+        Instruction::Mul => "mul",
+        Instruction::Div => "div",
+        Instruction::Operator(_) => "operator",
+        Instruction::Compare(_) => "compare",
+        Instruction::Load(_) => "load",
+        Instruction::Store(_) => "store",
+        Instruction::LoadGlobal(_) => "load_global",
+        Instruction::StoreGlobal(_) => "store_global",
+        Instruction::DefineGlobal(_) => "define_global",
+        Instruction::Pop => "pop",
+        Instruction::Jump(_) => "jump",
+        Instruction::JumpIfFalse(_) => "jump_if_false",
+        Instruction::Call { .. } => "call",
+        Instruction::CallBuiltin(_) => "call_builtin",
+        Instruction::Yield => "yield",
+        Instruction::Return => "return",
+        Instruction::Fail(_) => "fail",
+    }
+}
+
+/// Whether a span points at source text, a salvaged file may have spans that don't
+fn has_source(source: &str) -> impl Fn(&Span) -> bool + '_ {
+    |span: &Span| {
+        span.start < span.end && span.end <= source.len() && source.is_char_boundary(span.start)
+    }
+}
+
+fn invalid(table: &str, index: usize) -> String {
+    format!("<invalid {} {}>", table, index)
+}
+
+/// Constant as it would be written in source, strings are quoted
+pub fn constant_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => format!("{:?}", text),
+        value => value.to_string(),
+    }
+}
+
+/// String literal with its `${NAME}` variables, quoted
+pub fn string_text(segments: &[StringSegment]) -> String {
+    let text: String = segments
+        .iter()
+        .map(|segment: &StringSegment| match segment {
+            StringSegment::Text(text) => text.clone(),
+            StringSegment::EnvironmentVariable { name, .. } => format!("${{{}}}", name),
+        })
+        .collect();
+
+    format!("{:?}", text)
+}
+
+pub fn disasm(args: clap::ArgMatches) {
+    // Get file to disassemble
+    let file: &String;
+    if let Some(arg_match) = args.subcommand_matches("disasm") {
+        file = arg_match
+            .get_one::<String>("file")
+            .expect("Failed to get file");
+    } else {
+        unreachable!("Subcommand is required");
+    }
+
+    let bytes: Vec<u8> = crate::source::read_bytes(file);
+    if !crate::phc::is_phc(&bytes) {
+        eprintln!(
+            "error: '{}' is not compiled bytecode, use `ph build --emit bytecode` for source files",
+            file
+        );
+        std::process::exit(1);
+    }
+
+    // List what can be read even from a damaged file, that's when a listing helps the most
+    let salvaged: crate::phc::Salvaged = crate::phc::salvage(&bytes);
+    for problem in &salvaged.problems {
+        eprintln!("warning: {}", problem);
+    }
+
+    print!(
+        "{}",
+        disassemble(&salvaged.program, &salvaged.source, &salvaged.bad_bytes)
+    );
+
+    if !salvaged.problems.is_empty() || !salvaged.bad_bytes.is_empty() {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::lexer::StringSegment;
+use crate::parser::{ChainLink, ExpressionSyntaxEnum, StatementSyntax};
+
+/// Format statements as a Graphviz DOT graph, render with `dot -Tpng`
+pub fn format_dot(statements: &[StatementSyntax]) -> String {
+    let mut graph: DotGraph = DotGraph {
+        output: "digraph ast {\n    node [shape=box];\n".to_string(),
+        node_count: 0,
+    };
+
+    for statement in statements {
+        graph.statement(statement);
+    }
+
+    graph.output.push_str("}\n");
+    graph.output
+}
+
+struct DotGraph {
+    output: String,
+    node_count: usize,
+}
+
+impl DotGraph {
+    /// Add a node and return its id
+    fn node(&mut self, label: &str) -> usize {
+        let id: usize = self.node_count;
+        self.node_count += 1;
+
+        self.output.push_str(&format!(
+            "    n{} [label=\"{}\"];\n",
+            id,
+            label.replace('\\', "\\\\").replace('"', "\\\"")
+        ));
+
+        id
+    }
+
+    fn edge(&mut self, from: usize, to: usize) {
+        self.output
+            .push_str(&format!("    n{} -> n{};\n", from, to));
+    }
+
+    /// Add the nodes of a statement and return the id of its root
+    fn statement(&mut self, statement: &StatementSyntax) -> usize {
+        match statement {
+            StatementSyntax::Let(let_syntax) => {
+                let node: usize = self.node(&format!("let {}", let_syntax.name));
+                let value: usize = self.expression(&let_syntax.value);
+                self.edge(node, value);
+                node
+            }
+            StatementSyntax::Assign(assign) => {
+                let node: usize = self.node(&format!("{} =", assign.name));
+                let value: usize = self.expression(&assign.value);
+                self.edge(node, value);
+                node
+            }
+            StatementSyntax::Function(function) => {
+                let node: usize = self.node(&format!(
+                    "fn {}({})",
+                    function.name,
+                    function.parameters.join(", ")
+                ));
+                for statement in &function.body {
+                    let child: usize = self.statement(statement);
+                    self.edge(node, child);
+                }
+                node
+            }
+            StatementSyntax::Import(import) => self.node(&format!("import {:?}", import.path)),
+            StatementSyntax::Expression(expression) => self.expression(expression),
+        }
+    }
+
+    /// Add the nodes of an expression and return the id of its root
+    fn expression(&mut self, expression: &ExpressionSyntaxEnum) -> usize {
+        match expression {
+            ExpressionSyntaxEnum::ExpressionSyntax(binary) => {
+                let node: usize = self.node(binary.operator_token.text());
+                let left: usize = self.expression(&binary.left);
+                let right: usize = self.expression(&binary.right);
+                self.edge(node, left);
+                self.edge(node, right);
+                node
+            }
+            ExpressionSyntaxEnum::Unary(unary) => {
+                let node: usize = self.node(unary.operator_token.text());
+                let operand: usize = self.expression(&unary.operand);
+                self.edge(node, operand);
+                node
+            }
+            ExpressionSyntaxEnum::Conditional(conditional) => {
+                let node: usize = self.node("?:");
+                for child in [
+                    &conditional.condition,
+                    &conditional.then,
+                    &conditional.otherwise,
+                ] {
+                    let child: usize = self.expression(child);
+                    self.edge(node, child);
+                }
+                node
+            }
+            ExpressionSyntaxEnum::Chain(chain) => {
+                let operators: Vec<&str> = chain
+                    .links
+                    .iter()
+                    .map(|link: &ChainLink| link.operator_token.text())
+                    .collect();
+                let node: usize = self.node(&operators.join(" "));
+                let first: usize = self.expression(&chain.first);
+                self.edge(node, first);
+                for link in &chain.links {
+                    let child: usize = self.expression(&link.operand);
+                    self.edge(node, child);
+                }
+                node
+            }
+            ExpressionSyntaxEnum::Index(index) => {
+                let node: usize = self.node("[]");
+                for child in [&index.target, &index.index] {
+                    let child: usize = self.expression(child);
+                    self.edge(node, child);
+                }
+                node
+            }
+            ExpressionSyntaxEnum::Array(elements) => {
+                let node: usize = self.node("array");
+                for element in elements {
+                    let child: usize = self.expression(element);
+                    self.edge(node, child);
+                }
+                node
+            }
+            ExpressionSyntaxEnum::Number(value) => self.node(&value.to_string()),
+            ExpressionSyntaxEnum::Float(value) => self.node(&format!("{:?}", value)),
+            ExpressionSyntaxEnum::String(segments) => {
+                let mut label: String = String::new();
+                for segment in segments {
+                    match segment {
+                        StringSegment::Text(text) => label.push_str(text),
+                        StringSegment::EnvironmentVariable { name, .. } => {
+                            label.push_str(&format!("${{{}}}", name))
+                        }
+                    }
+                }
+                self.node(&format!("\"{}\"", label))
+            }
+            ExpressionSyntaxEnum::Variable(variable) => self.node(&variable.name),
+            ExpressionSyntaxEnum::Call(call) => {
+                let node: usize = self.node(&format!("{}()", call.name));
+                for argument in &call.arguments {
+                    let child: usize = self.expression(argument);
+                    self.edge(node, child);
+                }
+                node
+            }
+        }
+    }
+}
@@ -0,0 +1,1041 @@
+use crate::builtins::{builtin, Builtin, BuiltinGroup};
+use crate::diagnostic::{Diagnostic, Severity, Span};
+use crate::lexer::StringSegment;
+use crate::parser::{
+    CallSyntax, ExpressionSyntaxEnum, FunctionSyntax, OperatorToken, StatementSyntax,
+};
+use crate::random::Random;
+
+/// Runtime value produced by evaluating an expression
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Int(isize),
+    Float(f64),
+    String(String),
+    Bool(bool),
+    Array(Vec<Value>),
+    /// Result of statements that produce no value
+    Nil,
+}
+
+impl Value {
+    /// Name of the value's type, as shown in error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::String(_) => "string",
+            Value::Bool(_) => "bool",
+            Value::Array(_) => "array",
+            Value::Nil => "nil",
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{:?}", value),
+            Value::String(value) => write!(f, "{}", value),
+            Value::Bool(value) => write!(f, "{}", value),
+            // Strings are quoted so `["a, b"]` and `["a", "b"]` look different
+            Value::Array(elements) => {
+                write!(f, "[")?;
+                for (index, element) in elements.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    match element {
+                        Value::String(value) => write!(f, "{:?}", value)?,
+                        element => write!(f, "{}", element)?,
+                    }
+                }
+                write!(f, "]")
+            }
+            Value::Nil => write!(f, "nil"),
+        }
+    }
+}
+
+/// Function call that was in progress when an error happened
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallFrame {
+    pub name: String,
+    pub call_span: Span,
+}
+
+/// Error raised while evaluating, pointing at the expression that failed
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuntimeError {
+    pub message: String,
+    pub span: Span,
+    /// Calls the error propagated through, innermost first
+    pub trace: Vec<CallFrame>,
+}
+
+impl RuntimeError {
+    pub fn new(message: String, span: Span) -> RuntimeError {
+        RuntimeError {
+            message,
+            span,
+            trace: Vec::new(),
+        }
+    }
+}
+
+impl From<RuntimeError> for Diagnostic {
+    fn from(error: RuntimeError) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::RuntimeError,
+            message: error.message,
+            span: error.span,
+            trace: error
+                .trace
+                .into_iter()
+                .map(|frame: CallFrame| (frame.name, frame.call_span))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScopeKind {
+    Global,
+    Function,
+    Block,
+}
+
+#[derive(Clone, Debug)]
+struct Scope {
+    kind: ScopeKind,
+    variables: std::collections::HashMap<String, Value>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EnvironmentError {
+    /// Assignment to a name that was never defined
+    Undefined(String),
+}
+
+/// Variable bindings as a chain of scopes, the global scope is always the outermost
+///
+/// Lookups walk outward from the innermost scope, but stop at a function scope and continue
+/// in the global scope, so a function body can't see the variables of its caller.
+#[derive(Clone, Debug)]
+pub struct Environment {
+    scopes: Vec<Scope>,
+}
+
+impl Default for Environment {
+    fn default() -> Environment {
+        Environment {
+            scopes: vec![Scope {
+                kind: ScopeKind::Global,
+                variables: std::collections::HashMap::new(),
+            }],
+        }
+    }
+}
+
+impl Environment {
+    pub fn new() -> Environment {
+        Environment::default()
+    }
+
+    /// Enter a new innermost scope, a function scope's parent is the global scope
+    pub fn push_scope(&mut self, kind: ScopeKind) {
+        self.scopes.push(Scope {
+            kind,
+            variables: std::collections::HashMap::new(),
+        });
+    }
+
+    /// Leave the innermost scope, the global scope is never removed
+    pub fn pop_scope(&mut self) {
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
+
+    /// Indices of the scopes visible from the innermost one, innermost first
+    fn visible_scopes(&self) -> impl Iterator<Item = usize> + '_ {
+        let function_scope: usize = self
+            .scopes
+            .iter()
+            .rposition(|scope: &Scope| scope.kind == ScopeKind::Function)
+            .unwrap_or(0);
+
+        (function_scope..self.scopes.len())
+            .rev()
+            .chain(std::iter::once(0).filter(move |_| function_scope != 0))
+    }
+
+    /// Create a binding in the innermost scope, shadowing any outer binding of the same name
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.scopes
+            .last_mut()
+            .expect("The global scope always exists")
+            .variables
+            .insert(name.to_string(), value);
+    }
+
+    /// Look up the innermost visible binding of `name`
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.visible_scopes()
+            .find_map(|index: usize| self.scopes[index].variables.get(name))
+    }
+
+    /// Change the innermost visible binding of `name`, which must already exist
+    pub fn assign(&mut self, name: &str, value: Value) -> Result<(), EnvironmentError> {
+        let index: Option<usize> = self
+            .visible_scopes()
+            .find(|index: &usize| self.scopes[*index].variables.contains_key(name));
+
+        match index {
+            Some(index) => {
+                self.scopes[index].variables.insert(name.to_string(), value);
+                Ok(())
+            }
+            None => Err(EnvironmentError::Undefined(name.to_string())),
+        }
+    }
+
+    /// Bindings of the global scope, in no particular order
+    pub fn globals(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.scopes[0].variables.iter()
+    }
+}
+
+/// Constants defined in the global scope before a program starts, programs may shadow them
+pub const CONSTANTS: [(&str, f64); 2] = [("PI", std::f64::consts::PI), ("E", std::f64::consts::E)];
+
+/// Most user function calls that can be in progress at once, deeper recursion is an error
+/// instead of overflowing the stack
+pub const MAX_CALL_DEPTH: usize = 10_000;
+
+/// Error for calling `name` when `MAX_CALL_DEPTH` calls are already in progress
+pub fn call_depth_error(name: &str, span: Span) -> RuntimeError {
+    RuntimeError::new(
+        format!(
+            "calling '{}' exceeds the maximum call depth of {}",
+            name, MAX_CALL_DEPTH
+        ),
+        span,
+    )
+}
+
+/// Error for calling `name` with the wrong number of arguments
+pub fn argument_count_error(name: &str, expected: usize, found: usize, span: Span) -> RuntimeError {
+    RuntimeError::new(
+        format!(
+            "function '{}' expects {} argument{} but got {}",
+            name,
+            expected,
+            if expected == 1 { "" } else { "s" },
+            found
+        ),
+        span,
+    )
+}
+
+/// What integer arithmetic does when the result doesn't fit in an int
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Overflow {
+    /// Stop with a runtime error
+    #[default]
+    Checked,
+    /// Wrap around in two's complement
+    Wrap,
+}
+
+/// Tree-walking interpreter, keeps variables and functions between calls to `execute`
+pub struct Interpreter {
+    environment: Environment,
+    functions: std::collections::HashMap<String, std::rc::Rc<FunctionSyntax>>,
+    overflow: Overflow,
+    /// Where `input()` reads lines from, `None` for stdin
+    input: Option<Box<dyn std::io::BufRead>>,
+    /// Source of `random()`, seeded from the operating system unless `seed` is called
+    random: Random,
+    /// Command line arguments given to the program, returned by `args()`
+    arguments: Vec<String>,
+    /// User function calls in progress
+    call_depth: usize,
+    /// Builtins and constants are left out, from `--no-std`
+    no_std: bool,
+}
+
+impl std::fmt::Debug for Interpreter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Interpreter")
+            .field("environment", &self.environment)
+            .field("functions", &self.functions)
+            .field("overflow", &self.overflow)
+            .field("no_std", &self.no_std)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Interpreter {
+        let mut environment: Environment = Environment::new();
+
+        for (name, value) in CONSTANTS {
+            environment.define(name, Value::Float(value));
+        }
+
+        Interpreter {
+            environment,
+            functions: std::collections::HashMap::new(),
+            overflow: Overflow::default(),
+            input: None,
+            random: Random::from_entropy(),
+            arguments: Vec::new(),
+            call_depth: 0,
+            no_std: false,
+        }
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter::default()
+    }
+
+    pub fn with_overflow(overflow: Overflow) -> Interpreter {
+        Interpreter {
+            overflow,
+            ..Interpreter::default()
+        }
+    }
+
+    /// What integer arithmetic does on overflow
+    pub fn overflow(&self) -> Overflow {
+        self.overflow
+    }
+
+    /// Read lines for `input()` from `input` instead of stdin
+    pub fn set_input(&mut self, input: Box<dyn std::io::BufRead>) {
+        self.input = Some(input);
+    }
+
+    /// Make `random()` and `random_int()` repeat the same sequence for the same seed,
+    /// like calling `seed(seed)` at the start of the program
+    pub fn set_seed(&mut self, seed: u64) {
+        self.random = Random::from_seed(seed);
+    }
+
+    /// Command line arguments for `args()` and `argc()`, without the interpreter or file name
+    pub fn set_arguments(&mut self, arguments: Vec<String>) {
+        self.arguments = arguments;
+    }
+
+    /// Leave out every builtin and the constants, so a program can only compute with what it
+    /// defines itself, like `--no-std` does
+    ///
+    /// Variables defined before are forgotten too.
+    pub fn set_no_std(&mut self) {
+        self.environment = Environment::new();
+        self.no_std = true;
+    }
+
+    /// Builtin called `name`, unless builtins are left out
+    pub fn builtin(&self, name: &str) -> Option<(&'static BuiltinGroup, &'static Builtin)> {
+        builtin(name).filter(|_| !self.no_std)
+    }
+
+    /// Define a global variable before the program runs, like `--seed-vars` does
+    pub fn define_variable(&mut self, name: &str, value: Value) {
+        self.environment.define(name, value);
+    }
+
+    /// Value of a global variable
+    pub fn variable(&self, name: &str) -> Option<&Value> {
+        self.environment.get(name)
+    }
+
+    /// Global variables bound so far, in no particular order
+    pub fn variables(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.environment.globals()
+    }
+
+    /// Execute statements in order, returning the value of a trailing expression statement
+    ///
+    /// Functions are defined before anything runs, so they can be called above their definition
+    pub fn execute(
+        &mut self,
+        statements: &[StatementSyntax],
+    ) -> Result<Option<Value>, RuntimeError> {
+        self.execute_each(statements, &mut |_: &Value| {})
+    }
+
+    /// Like `execute`, also calling `each` with the value of every top level expression statement
+    pub fn execute_each(
+        &mut self,
+        statements: &[StatementSyntax],
+        each: &mut dyn FnMut(&Value),
+    ) -> Result<Option<Value>, RuntimeError> {
+        for statement in statements {
+            if let StatementSyntax::Function(function) = statement {
+                if builtin(&function.name).is_some() {
+                    return Err(RuntimeError::new(
+                        format!(
+                            "cannot define function '{}', it is a builtin",
+                            function.name
+                        ),
+                        Span::new(function.position, function.position + function.name.len()),
+                    ));
+                }
+
+                self.functions
+                    .insert(function.name.clone(), std::rc::Rc::new(function.clone()));
+            }
+        }
+
+        let mut result: Option<Value> = None;
+        for statement in statements {
+            result = self.execute_statements(std::slice::from_ref(statement))?;
+            if let Some(value) = &result {
+                each(value);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Execute statements in order, returning the value of a trailing expression statement
+    fn execute_statements(
+        &mut self,
+        statements: &[StatementSyntax],
+    ) -> Result<Option<Value>, RuntimeError> {
+        let mut result: Option<Value> = None;
+
+        for statement in statements {
+            result = match statement {
+                StatementSyntax::Let(let_syntax) => {
+                    let value: Value = self.eval_expr(&let_syntax.value)?;
+                    self.environment.define(&let_syntax.name, value);
+                    None
+                }
+                StatementSyntax::Assign(assign) => {
+                    let value: Value = self.eval_expr(&assign.value)?;
+                    self.environment.assign(&assign.name, value).map_err(|_| {
+                        RuntimeError::new(
+                            format!(
+                                "cannot assign to undefined variable '{}', use 'let' to define it",
+                                assign.name
+                            ),
+                            Span::new(assign.position, assign.position + assign.name.len()),
+                        )
+                    })?;
+                    None
+                }
+                // Already defined by `execute`
+                StatementSyntax::Function(_) => None,
+                StatementSyntax::Import(import) => {
+                    return Err(RuntimeError::new(
+                        crate::check::IMPORT_OUTSIDE_FILE.to_string(),
+                        import.span,
+                    ))
+                }
+                StatementSyntax::Expression(expression) => Some(self.eval_expr(expression)?),
+            };
+        }
+
+        Ok(result)
+    }
+
+    pub fn eval_expr(&mut self, expression: &ExpressionSyntaxEnum) -> Result<Value, RuntimeError> {
+        match expression {
+            ExpressionSyntaxEnum::Number(value) => Ok(Value::Int(*value)),
+            ExpressionSyntaxEnum::Float(value) => Ok(Value::Float(*value)),
+            ExpressionSyntaxEnum::String(segments) => interpolate(segments).map(Value::String),
+            ExpressionSyntaxEnum::Variable(variable) => self
+                .environment
+                .get(&variable.name)
+                .cloned()
+                .ok_or_else(|| {
+                    RuntimeError::new(
+                        format!("undefined variable '{}'", variable.name),
+                        Span::new(variable.position, variable.position + variable.name.len()),
+                    )
+                }),
+            ExpressionSyntaxEnum::Call(call) => self.call(call),
+            ExpressionSyntaxEnum::Array(elements) => {
+                let mut values: Vec<Value> = Vec::new();
+                for element in elements {
+                    values.push(self.eval_expr(element)?);
+                }
+                Ok(Value::Array(values))
+            }
+            ExpressionSyntaxEnum::Index(index) => {
+                let target: Value = self.eval_expr(&index.target)?;
+                let position: Value = self.eval_expr(&index.index)?;
+
+                index_value(
+                    target,
+                    position,
+                    Span::new(index.position, index.position + 1),
+                )
+            }
+            ExpressionSyntaxEnum::Conditional(conditional) => {
+                match self.eval_expr(&conditional.condition)? {
+                    Value::Bool(true) => self.eval_expr(&conditional.then),
+                    Value::Bool(false) => self.eval_expr(&conditional.otherwise),
+                    value => Err(RuntimeError::new(
+                        format!("condition must be a bool but found {}", value.type_name()),
+                        Span::new(conditional.position, conditional.position + 1),
+                    )),
+                }
+            }
+            ExpressionSyntaxEnum::Chain(chain) => {
+                let mut left: Value = self.eval_expr(&chain.first)?;
+
+                for link in &chain.links {
+                    let right: Value = self.eval_expr(&link.operand)?;
+                    let span: Span = link.operator_token.span(link.position);
+
+                    match binary_operation(
+                        link.operator_token,
+                        left,
+                        right.clone(),
+                        span,
+                        self.overflow,
+                    )? {
+                        Value::Bool(true) => left = right,
+                        result => return Ok(result),
+                    }
+                }
+
+                Ok(Value::Bool(true))
+            }
+            ExpressionSyntaxEnum::Unary(unary) => {
+                let operand: Value = self.eval_expr(&unary.operand)?;
+
+                unary_operation(
+                    unary.operator_token,
+                    operand,
+                    unary.operator_token.span(unary.position),
+                    self.overflow,
+                )
+            }
+            ExpressionSyntaxEnum::ExpressionSyntax(binary) => {
+                let left: Value = self.eval_expr(&binary.left)?;
+                let right: Value = self.eval_expr(&binary.right)?;
+                let span: Span = binary.operator_span;
+
+                binary_operation(binary.operator_token, left, right, span, self.overflow)
+            }
+        }
+    }
+}
+
+impl Interpreter {
+    /// Call a builtin, or a user defined function in a fresh scope whose parent is the global scope
+    fn call(&mut self, call: &CallSyntax) -> Result<Value, RuntimeError> {
+        if let Some((group, builtin)) = self.builtin(&call.name) {
+            if let Some(parameters) = builtin
+                .parameters
+                .filter(|parameters: &usize| *parameters != call.arguments.len())
+            {
+                return Err(argument_count_error(
+                    &call.name,
+                    parameters,
+                    call.arguments.len(),
+                    call.span,
+                ));
+            }
+
+            let mut arguments: Vec<Value> = Vec::new();
+            for argument in &call.arguments {
+                arguments.push(self.eval_expr(argument)?);
+            }
+
+            return self.call_builtin(group, &call.name, &arguments, call.span, &call.text);
+        }
+
+        let function: std::rc::Rc<FunctionSyntax> = match self.functions.get(&call.name) {
+            Some(function) => function.clone(),
+            None => {
+                return Err(RuntimeError::new(
+                    format!("undefined function '{}'", call.name),
+                    call.span,
+                ))
+            }
+        };
+
+        if call.arguments.len() != function.parameters.len() {
+            return Err(argument_count_error(
+                &call.name,
+                function.parameters.len(),
+                call.arguments.len(),
+                call.span,
+            ));
+        }
+
+        let mut arguments: Vec<Value> = Vec::new();
+        for argument in &call.arguments {
+            arguments.push(self.eval_expr(argument)?);
+        }
+
+        if self.call_depth >= MAX_CALL_DEPTH {
+            return Err(call_depth_error(&call.name, call.span));
+        }
+
+        self.environment.push_scope(ScopeKind::Function);
+        for (parameter, argument) in function.parameters.iter().zip(arguments) {
+            self.environment.define(parameter, argument);
+        }
+
+        self.call_depth += 1;
+        let result: Result<Option<Value>, RuntimeError> = self.execute_statements(&function.body);
+        self.call_depth -= 1;
+
+        self.environment.pop_scope();
+
+        match result {
+            Ok(value) => Ok(value.unwrap_or(Value::Nil)),
+            Err(mut error) => {
+                error.trace.push(CallFrame {
+                    name: call.name.clone(),
+                    call_span: call.span,
+                });
+                Err(error)
+            }
+        }
+    }
+}
+
+impl Interpreter {
+    /// Run a builtin of `group` with already evaluated and counted arguments, `text` is the
+    /// source of the call
+    pub fn call_builtin(
+        &mut self,
+        group: &BuiltinGroup,
+        name: &str,
+        arguments: &[Value],
+        span: Span,
+        text: &str,
+    ) -> Result<Value, RuntimeError> {
+        match group.name {
+            "math" => crate::builtins::call_math(name, arguments, span),
+            "string" => crate::builtins::call_string(name, arguments, span),
+            "time" => crate::builtins::call_time(name, arguments, span),
+            "random" => self.call_random(name, arguments, span),
+            "env" => crate::builtins::call_env(name, arguments, span),
+            "file" => crate::builtins::call_file(name, arguments, span),
+            "args" => Ok(self.call_args(name)),
+            "assert" => crate::builtins::call_assert(name, arguments, span, text),
+            _ => self.call_io(name, arguments, span),
+        }
+    }
+
+    /// Run an io builtin with already evaluated arguments
+    ///
+    /// - `print` writes its arguments separated by spaces and ends the line
+    /// - `write` does the same without the new line
+    /// - `input` reads a line without its line ending, `nil` at the end of input
+    /// - `input_int` reads a line holding an integer, the end of input is an error
+    fn call_io(
+        &mut self,
+        name: &str,
+        arguments: &[Value],
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match name {
+            "print" | "write" => {
+                let text: String = arguments
+                    .iter()
+                    .map(|argument: &Value| argument.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ");
+
+                if name == "print" {
+                    println!("{}", text);
+                } else {
+                    print!("{}", text);
+                    // Failing to flush only delays the output
+                    let _ = std::io::Write::flush(&mut std::io::stdout());
+                }
+
+                Ok(Value::Nil)
+            }
+            "input" => Ok(self
+                .read_input_line(span)?
+                .map_or(Value::Nil, Value::String)),
+            "input_int" => {
+                let Some(line) = self.read_input_line(span)? else {
+                    return Err(RuntimeError::new(
+                        "expected an integer but reached the end of the input".to_string(),
+                        span,
+                    ));
+                };
+
+                line.trim().parse::<isize>().map(Value::Int).map_err(|_| {
+                    RuntimeError::new(format!("expected an integer but read '{}'", line), span)
+                })
+            }
+            _ => unreachable!("Only io builtins are called here"),
+        }
+    }
+
+    /// Run `args()`, the command line arguments as strings, or `argc()`, how many there are
+    fn call_args(&self, name: &str) -> Value {
+        match name {
+            "argc" => Value::Int(self.arguments.len() as isize),
+            _ => Value::Array(
+                self.arguments
+                    .iter()
+                    .map(|argument: &String| Value::String(argument.clone()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Run a random builtin with already evaluated and counted arguments
+    ///
+    /// - `random()` is a float in `[0, 1)`
+    /// - `random_int(low, high)` is an int from `low` to `high`, both included
+    /// - `seed(n)` restarts the sequence so it repeats for the same `n`
+    fn call_random(
+        &mut self,
+        name: &str,
+        arguments: &[Value],
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match (name, arguments) {
+            ("random", []) => Ok(Value::Float(self.random.next_float())),
+            ("random_int", [Value::Int(low), Value::Int(high)]) if low > high => {
+                Err(RuntimeError::new(
+                    format!("'random_int' range is empty, {} is above {}", low, high),
+                    span,
+                ))
+            }
+            ("random_int", [Value::Int(low), Value::Int(high)]) => {
+                Ok(Value::Int(self.random.next_in_range(*low, *high)))
+            }
+            ("seed", [Value::Int(seed)]) => {
+                self.set_seed(*seed as u64);
+                Ok(Value::Nil)
+            }
+            (name, arguments) => Err(RuntimeError::new(
+                format!(
+                    "'{}' expects ints but got {}",
+                    name,
+                    arguments
+                        .iter()
+                        .map(Value::type_name)
+                        .collect::<Vec<&str>>()
+                        .join(" and ")
+                ),
+                span,
+            )),
+        }
+    }
+
+    /// Read one line for `input()` without its line ending, `None` at the end of input
+    fn read_input_line(&mut self, span: Span) -> Result<Option<String>, RuntimeError> {
+        // Flush first so a prompt written with `write` shows before waiting
+        let _ = std::io::Write::flush(&mut std::io::stdout());
+
+        let mut line: String = String::new();
+        let read: std::io::Result<usize> = match &mut self.input {
+            Some(input) => input.read_line(&mut line),
+            // Locks stdin only for this line, so the REPL can keep reading from it too
+            None => std::io::stdin().read_line(&mut line),
+        };
+
+        match read {
+            Ok(0) => Ok(None),
+            Ok(_) => Ok(Some(line.trim_end_matches(['\n', '\r']).to_string())),
+            Err(error) => Err(RuntimeError::new(
+                format!("failed to read input: {}", error),
+                span,
+            )),
+        }
+    }
+}
+
+/// Build a string literal's value, substituting `${NAME}` with the environment variable `NAME`
+///
+/// A variable that isn't set, or isn't valid unicode, is an error rather than an empty string,
+/// so a typo in the name can't silently produce a wrong value
+pub fn interpolate(segments: &[StringSegment]) -> Result<String, RuntimeError> {
+    let mut text: String = String::new();
+
+    for segment in segments {
+        match segment {
+            StringSegment::Text(segment_text) => text.push_str(segment_text),
+            StringSegment::EnvironmentVariable { name, position } => match std::env::var(name) {
+                Ok(value) => text.push_str(&value),
+                Err(error) => {
+                    return Err(RuntimeError::new(
+                        match error {
+                            std::env::VarError::NotPresent => {
+                                format!("environment variable '{}' is not set", name)
+                            }
+                            std::env::VarError::NotUnicode(_) => {
+                                format!("environment variable '{}' is not valid unicode", name)
+                            }
+                        },
+                        // `${` + name + `}`
+                        Span::new(*position, position + name.len() + 3),
+                    ));
+                }
+            },
+        }
+    }
+
+    Ok(text)
+}
+
+/// Element `position` of an array, or character `position` of a string, `span` points at the `[`
+pub fn index_value(target: Value, position: Value, span: Span) -> Result<Value, RuntimeError> {
+    let Value::Int(position) = position else {
+        return Err(RuntimeError::new(
+            format!("index must be an int but found {}", position.type_name()),
+            span,
+        ));
+    };
+
+    let (element, length): (Option<Value>, usize) = match &target {
+        Value::Array(elements) => (
+            usize::try_from(position)
+                .ok()
+                .and_then(|position: usize| elements.get(position))
+                .cloned(),
+            elements.len(),
+        ),
+        // Strings are indexed by character like `len` counts them
+        Value::String(text) => (
+            usize::try_from(position)
+                .ok()
+                .and_then(|position: usize| text.chars().nth(position))
+                .map(|character: char| Value::String(character.to_string())),
+            text.chars().count(),
+        ),
+        target => {
+            return Err(RuntimeError::new(
+                format!("cannot index {}", target.type_name()),
+                span,
+            ))
+        }
+    };
+
+    element.ok_or_else(|| {
+        RuntimeError::new(
+            format!(
+                "index {} is out of bounds for {} of length {}",
+                position,
+                if matches!(target, Value::Array(_)) {
+                    "an array"
+                } else {
+                    "a string"
+                },
+                length
+            ),
+            span,
+        )
+    })
+}
+
+/// Apply a prefix operator to an already evaluated operand
+pub fn unary_operation(
+    operator_token: OperatorToken,
+    operand: Value,
+    span: Span,
+    overflow: Overflow,
+) -> Result<Value, RuntimeError> {
+    match (operator_token, operand) {
+        (OperatorToken::Plus, value) => Ok(value),
+        (OperatorToken::Minus, Value::Int(value)) if overflow == Overflow::Wrap => {
+            Ok(Value::Int(value.wrapping_neg()))
+        }
+        (OperatorToken::Minus, Value::Int(value)) => {
+            value.checked_neg().map(Value::Int).ok_or_else(|| {
+                RuntimeError::new(
+                    format!("attempt to negate with overflow: -({})", value),
+                    span,
+                )
+            })
+        }
+        (OperatorToken::Minus, Value::Float(value)) => Ok(Value::Float(-value)),
+        (operator_token, value) => Err(RuntimeError::new(
+            format!(
+                "cannot apply unary '{}' to {}",
+                operator_token.text(),
+                value.type_name()
+            ),
+            span,
+        )),
+    }
+}
+
+/// Apply a binary operator to two already evaluated operands
+///
+/// When an int meets a float the int is converted to a float first. Float arithmetic
+/// follows IEEE 754, so dividing a float by zero gives infinity or NaN instead of an error.
+pub fn binary_operation(
+    operator_token: OperatorToken,
+    left: Value,
+    right: Value,
+    span: Span,
+    overflow: Overflow,
+) -> Result<Value, RuntimeError> {
+    let (left, right): (Value, Value) = match (left, right) {
+        (Value::Int(left), Value::Float(right)) if !operator_token.is_bitwise() => {
+            (Value::Float(left as f64), Value::Float(right))
+        }
+        (Value::Float(left), Value::Int(right)) if !operator_token.is_bitwise() => {
+            (Value::Float(left), Value::Float(right as f64))
+        }
+        operands => operands,
+    };
+
+    if operator_token.is_comparison() {
+        return compare(operator_token, left, right, span);
+    }
+
+    match (left, right) {
+        (Value::Int(left), Value::Int(right)) => {
+            if right == 0
+                && matches!(
+                    operator_token,
+                    OperatorToken::Slash | OperatorToken::Percent
+                )
+            {
+                return Err(RuntimeError::new("division by zero".to_string(), span));
+            }
+
+            if matches!(
+                operator_token,
+                OperatorToken::LessLess | OperatorToken::GreaterGreater
+            ) && !(0..isize::BITS as isize).contains(&right)
+            {
+                return Err(RuntimeError::new(
+                    format!(
+                        "shift amount {} is out of range, expected 0 to {}",
+                        right,
+                        isize::BITS - 1
+                    ),
+                    span,
+                ));
+            }
+
+            if overflow == Overflow::Wrap {
+                return Ok(Value::Int(match operator_token {
+                    OperatorToken::Plus => left.wrapping_add(right),
+                    OperatorToken::Minus => left.wrapping_sub(right),
+                    OperatorToken::Star => left.wrapping_mul(right),
+                    OperatorToken::Slash => left.wrapping_div(right),
+                    OperatorToken::Percent => left.wrapping_rem(right),
+                    OperatorToken::Ampersand => left & right,
+                    OperatorToken::Pipe => left | right,
+                    OperatorToken::Caret => left ^ right,
+                    OperatorToken::LessLess => left << right,
+                    OperatorToken::GreaterGreater => left >> right,
+                    _ => unreachable!("Comparisons are handled before arithmetic"),
+                }));
+            }
+
+            let (result, verb): (Option<isize>, &str) = match operator_token {
+                OperatorToken::Plus => (left.checked_add(right), "add"),
+                OperatorToken::Minus => (left.checked_sub(right), "subtract"),
+                OperatorToken::Star => (left.checked_mul(right), "multiply"),
+                OperatorToken::Slash => (left.checked_div(right), "divide"),
+                OperatorToken::Percent => (left.checked_rem(right), "calculate the remainder"),
+                OperatorToken::Ampersand => (Some(left & right), ""),
+                OperatorToken::Pipe => (Some(left | right), ""),
+                OperatorToken::Caret => (Some(left ^ right), ""),
+                OperatorToken::LessLess => (Some(left << right), ""),
+                OperatorToken::GreaterGreater => (Some(left >> right), ""),
+                _ => unreachable!("Comparisons are handled before arithmetic"),
+            };
+
+            result.map(Value::Int).ok_or_else(|| {
+                RuntimeError::new(
+                    format!(
+                        "attempt to {} with overflow: {} {} {}",
+                        verb,
+                        left,
+                        operator_token.text(),
+                        right
+                    ),
+                    span,
+                )
+            })
+        }
+        (Value::Float(left), Value::Float(right)) if !operator_token.is_bitwise() => {
+            Ok(Value::Float(match operator_token {
+                OperatorToken::Plus => left + right,
+                OperatorToken::Minus => left - right,
+                OperatorToken::Star => left * right,
+                OperatorToken::Slash => left / right,
+                OperatorToken::Percent => left % right,
+                _ => unreachable!(
+                    "Bitwise operators are rejected by the guard and comparisons are handled above"
+                ),
+            }))
+        }
+        (left, right) => Err(RuntimeError::new(
+            format!(
+                "cannot apply '{}' to {} and {}",
+                operator_token.text(),
+                left.type_name(),
+                right.type_name()
+            ),
+            span,
+        )),
+    }
+}
+
+/// Compare two operands of the same type
+///
+/// Numbers and strings can be ordered, bools can only be checked for equality.
+/// Comparisons with NaN follow IEEE 754: everything but `!=` is false.
+fn compare(
+    operator_token: OperatorToken,
+    left: Value,
+    right: Value,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    let is_equality: bool = matches!(
+        operator_token,
+        OperatorToken::EqualsEquals | OperatorToken::BangEquals
+    );
+
+    let ordering: Option<std::cmp::Ordering> = match (&left, &right) {
+        (Value::Int(left), Value::Int(right)) => left.partial_cmp(right),
+        (Value::Float(left), Value::Float(right)) => left.partial_cmp(right),
+        (Value::String(left), Value::String(right)) => left.partial_cmp(right),
+        (Value::Bool(left), Value::Bool(right)) if is_equality => left.partial_cmp(right),
+        _ => {
+            return Err(RuntimeError::new(
+                format!(
+                    "cannot apply '{}' to {} and {}",
+                    operator_token.text(),
+                    left.type_name(),
+                    right.type_name()
+                ),
+                span,
+            ))
+        }
+    };
+
+    Ok(Value::Bool(match operator_token {
+        OperatorToken::Less => ordering == Some(std::cmp::Ordering::Less),
+        OperatorToken::LessEquals => matches!(
+            ordering,
+            Some(std::cmp::Ordering::Less | std::cmp::Ordering::Equal)
+        ),
+        OperatorToken::Greater => ordering == Some(std::cmp::Ordering::Greater),
+        OperatorToken::GreaterEquals => matches!(
+            ordering,
+            Some(std::cmp::Ordering::Greater | std::cmp::Ordering::Equal)
+        ),
+        OperatorToken::EqualsEquals => ordering == Some(std::cmp::Ordering::Equal),
+        OperatorToken::BangEquals => ordering != Some(std::cmp::Ordering::Equal),
+        _ => unreachable!("Only comparisons are passed to compare"),
+    }))
+}
@@ -0,0 +1,821 @@
+use crate::diagnostic::Diagnostic;
+use crate::lexer::{SyntaxToken, SyntaxTokenType};
+use crate::parser::{
+    CallSyntax, ChainLink, Comments, ConditionalSyntax, ExpressionSyntax, ExpressionSyntaxEnum,
+    OperatorToken, ParseError, Parser, StatementComments, StatementSyntax,
+};
+
+/// How tightly an operand binds, `binding` gives every expression one
+const CONDITIONAL: u8 = 0;
+const UNARY: u8 = 9;
+const POSTFIX: u8 = 10;
+
+/// Name of the formatter configuration, looked for next to a file and in every directory above
+pub const CONFIG_FILE: &str = ".phfmt.toml";
+
+/// Style `format_source` prints in, from `.phfmt.toml` and the command line
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FormatOptions {
+    /// Spaces a block is indented by
+    pub indent_width: usize,
+    /// Longest line before an expression is wrapped after its operators
+    pub max_line_width: usize,
+    /// End the file with a new line
+    pub trailing_newline: bool,
+    /// Indentation delimits function bodies, they are read and printed without braces
+    pub layout: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> FormatOptions {
+        FormatOptions {
+            indent_width: 4,
+            max_line_width: 100,
+            trailing_newline: true,
+            layout: false,
+        }
+    }
+}
+
+/// Reprint `source` canonically: one statement per line, indented blocks, single spaces
+/// around binary operators and none inside brackets
+///
+/// The syntax tree decides the layout, but literals are copied as written, so `0xff`, `5u8`
+/// and `10%` keep their spelling. Parentheses are only kept where they are needed. A line
+/// longer than `max_line_width` is wrapped after operators or commas, starting with the
+/// outermost expression, and each wrapped line is indented past the line it continues.
+/// Comments stay on their own lines before the statement they precede, or after the one they
+/// follow on its line, a comment inside a statement moves before it.
+pub fn format_source(source: &str, options: &FormatOptions) -> Result<String, ParseError> {
+    let mut parser: Parser = parser(source, options.layout);
+    let statements: Vec<StatementSyntax> = parser.parse_statements()?;
+
+    let mut formatter: Formatter = Formatter {
+        source,
+        options,
+        tokens: crate::parser::collect_tokens(source.to_string()),
+        next_literal: 0,
+        comments: parser.comments(),
+        next_comments: 0,
+        pieces: Vec::new(),
+        output: String::new(),
+    };
+    for (index, statement) in statements.iter().enumerate() {
+        // Functions are set apart from what is around them by an empty line, and the imports
+        // from what follows them
+        let previous: Option<&StatementSyntax> =
+            index.checked_sub(1).map(|index: usize| &statements[index]);
+        if previous.is_some_and(|previous: &StatementSyntax| {
+            matches!(previous, StatementSyntax::Function(_))
+                || matches!(statement, StatementSyntax::Function(_))
+                || (matches!(previous, StatementSyntax::Import(_))
+                    && !matches!(statement, StatementSyntax::Import(_)))
+        }) {
+            formatter.output.push('\n');
+        }
+
+        formatter.statement(statement, 0);
+    }
+
+    // Comments at the end are set apart from a function like a statement would be
+    let end: &[String] = &formatter.comments.end;
+    if !end.is_empty() && matches!(statements.last(), Some(StatementSyntax::Function(_))) {
+        formatter.output.push('\n');
+    }
+    formatter.comment_lines(end, 0);
+
+    if !options.trailing_newline && formatter.output.ends_with('\n') {
+        formatter.output.pop();
+    }
+
+    Ok(formatter.output)
+}
+
+/// How the breaks of a group are wrapped
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Group {
+    /// Wrapped only at the breaks whose next part wouldn't fit, like operands and arguments
+    Fill,
+    /// Wrapped at every break once it doesn't fit, like the branches of a conditional
+    Consistent,
+}
+
+/// Part of a statement as `flush` lays it out
+#[derive(Clone, Debug)]
+enum Piece {
+    Text(String),
+    /// Space after an operator or a comma, where the line can be wrapped
+    Break,
+    /// Start of the breaks that wrap together, lines wrapped in it are indented past the
+    /// line it starts on
+    Open(Group),
+    Close,
+}
+
+/// Group of breaks while `flush` lays out a statement
+struct Frame {
+    group: Group,
+    /// Whether it didn't fit on the rest of the line
+    wrapped: bool,
+    /// Indentation of the lines it wraps
+    indent: usize,
+}
+
+/// Prints a syntax tree back as source
+struct Formatter<'a> {
+    source: &'a str,
+    options: &'a FormatOptions,
+    /// Every token of the source, the literals come in the order the tree prints them
+    tokens: Vec<SyntaxToken>,
+    /// Index in `tokens` to look for the next literal from
+    next_literal: usize,
+    /// Comments of every statement, in the order `statement` prints them
+    comments: &'a Comments,
+    /// Index in `comments.statements` of the next statement printed
+    next_comments: usize,
+    /// Statement being printed, written out by `flush` once it is complete
+    pieces: Vec<Piece>,
+    output: String,
+}
+
+impl<'a> Formatter<'a> {
+    fn statement(&mut self, statement: &StatementSyntax, depth: usize) {
+        let comments: &'a StatementComments = &self.comments.statements[self.next_comments];
+        self.next_comments += 1;
+        self.comment_lines(&comments.leading, depth);
+
+        match statement {
+            StatementSyntax::Let(let_syntax) => {
+                self.text(&format!("let {} = ", let_syntax.name));
+                self.expression(&let_syntax.value);
+            }
+            StatementSyntax::Assign(assign) => {
+                self.text(&format!("{} = ", assign.name));
+                self.expression(&assign.value);
+            }
+            StatementSyntax::Function(function) => {
+                self.text(&format!(
+                    "fn {}({})",
+                    function.name,
+                    function.parameters.join(", ")
+                ));
+                // An empty body has no lines to indent, it keeps its braces in layout mode
+                if function.body.is_empty() && comments.closing.is_empty() {
+                    self.text(" {}");
+                } else if self.options.layout && !function.body.is_empty() {
+                    self.flush(depth);
+                    for statement in &function.body {
+                        self.statement(statement, depth + 1);
+                    }
+                    self.comment_lines(&comments.closing, depth + 1);
+                    return;
+                } else {
+                    self.text(" {");
+                    self.flush(depth);
+                    for statement in &function.body {
+                        self.statement(statement, depth + 1);
+                    }
+                    self.comment_lines(&comments.closing, depth + 1);
+                    self.text("}");
+                }
+            }
+            StatementSyntax::Import(import) => {
+                // The path is kept as written, a string path is a literal `literal` must skip
+                let index: usize = self
+                    .tokens
+                    .iter()
+                    .position(|token: &SyntaxToken| {
+                        token.position > import.span.start && token.span().end == import.span.end
+                    })
+                    .expect("Every import in the tree has a path token");
+                if import.quoted {
+                    self.next_literal = self.next_literal.max(index + 1);
+                }
+
+                let path: String = self.tokens[index].text.clone();
+                self.text(&format!("import {}", path));
+            }
+            StatementSyntax::Expression(expression) => self.expression(expression),
+        }
+
+        self.flush(depth);
+        if let Some(trailing) = &comments.trailing {
+            self.output.pop();
+            self.output.push_str(&format!(" {}\n", trailing.trim_end()));
+        }
+    }
+
+    /// Write every comment of `comments` on a line of its own indented `depth` blocks deep
+    fn comment_lines(&mut self, comments: &[String], depth: usize) {
+        for comment in comments {
+            self.text(comment.trim_end());
+            self.flush(depth);
+        }
+    }
+
+    /// Write out the statement in `pieces` on a line indented `depth` blocks deep
+    ///
+    /// A group that doesn't fit on the rest of its line is wrapped, and the groups in it get
+    /// the same chance on the lines that gives. The layout only depends on the tree, so
+    /// formatting the output again gives the same lines.
+    fn flush(&mut self, depth: usize) {
+        let pieces: Vec<Piece> = std::mem::take(&mut self.pieces);
+        let indent: usize = depth * self.options.indent_width;
+
+        // Width of every piece on one line, with a group as wide as everything in it
+        let mut widths: Vec<usize> = pieces
+            .iter()
+            .map(|piece: &Piece| match piece {
+                Piece::Text(text) => text.chars().count(),
+                Piece::Break => 1,
+                Piece::Open(_) | Piece::Close => 0,
+            })
+            .collect();
+        let mut closes: Vec<usize> = vec![0; pieces.len()];
+        let mut opened: Vec<usize> = Vec::new();
+        for (index, piece) in pieces.iter().enumerate() {
+            match piece {
+                Piece::Open(_) => opened.push(index),
+                Piece::Close => {
+                    let open: usize = opened.pop().expect("Every group is closed once");
+                    closes[open] = index;
+                    widths[open] = widths[open + 1..index].iter().sum();
+                }
+                _ => {}
+            }
+        }
+
+        // What follows a group on its line, up to the next break of any group around it
+        let after = |close: usize| -> usize {
+            pieces[close + 1..]
+                .iter()
+                .take_while(|piece: &&Piece| !matches!(piece, Piece::Break))
+                .map(|piece: &Piece| match piece {
+                    Piece::Text(text) => text.chars().count(),
+                    _ => 0,
+                })
+                .sum()
+        };
+
+        // Width from `start` to the next place the line could be wrapped, past the end of
+        // the group it is in when that comes first
+        let until_break = |start: usize| -> usize {
+            let mut width: usize = 0;
+            let mut index: usize = start;
+            while index < pieces.len() {
+                match pieces[index] {
+                    Piece::Break => break,
+                    Piece::Close => return width + after(index),
+                    Piece::Open(_) => {
+                        width += widths[index];
+                        index = closes[index];
+                    }
+                    Piece::Text(_) => width += widths[index],
+                }
+                index += 1;
+            }
+            width
+        };
+        let mut line: String = " ".repeat(indent);
+        let mut column: usize = indent;
+        // Indentation of the line being written, a group wrapped on it is indented past it
+        let mut line_indent: usize = indent;
+        let mut frames: Vec<Frame> = vec![Frame {
+            group: Group::Fill,
+            wrapped: false,
+            indent,
+        }];
+        for (index, piece) in pieces.iter().enumerate() {
+            let frame: &Frame = frames.last().expect("The statement is a frame");
+            match piece {
+                Piece::Text(text) => {
+                    line.push_str(text);
+                    column += widths[index];
+                }
+                Piece::Open(group) => {
+                    let wrapped: bool =
+                        column + widths[index] + after(closes[index]) > self.options.max_line_width;
+                    let indent: usize = if wrapped {
+                        line_indent + self.options.indent_width
+                    } else {
+                        frame.indent
+                    };
+                    frames.push(Frame {
+                        group: *group,
+                        wrapped,
+                        indent,
+                    });
+                }
+                Piece::Close => {
+                    frames.pop();
+                }
+                Piece::Break => {
+                    let next: usize = until_break(index + 1);
+                    let wrap: bool = frame.wrapped
+                        && (frame.group == Group::Consistent
+                            || column + 1 + next > self.options.max_line_width);
+                    if wrap {
+                        line.push('\n');
+                        line.push_str(&" ".repeat(frame.indent));
+                        column = frame.indent;
+                        line_indent = frame.indent;
+                    } else {
+                        line.push(' ');
+                        column += 1;
+                    }
+                }
+            }
+        }
+
+        self.output.push_str(&line);
+        self.output.push('\n');
+    }
+
+    fn text(&mut self, text: &str) {
+        match self.pieces.last_mut() {
+            Some(Piece::Text(last)) => last.push_str(text),
+            _ => self.pieces.push(Piece::Text(text.to_string())),
+        }
+    }
+
+    fn expression(&mut self, expression: &ExpressionSyntaxEnum) {
+        match expression {
+            ExpressionSyntaxEnum::ExpressionSyntax(binary) => {
+                // `10% of x` is the only binary expression whose operator isn't its own text
+                let operator: &str =
+                    &self.source[binary.operator_span.start..binary.operator_span.end];
+                if operator == "of" {
+                    self.literal(&binary.left, true);
+                    self.text(" of ");
+                    self.operand(&binary.right, binding(&binary.right) < UNARY);
+                    return;
+                }
+
+                self.pieces.push(Piece::Open(Group::Fill));
+                self.binary(binary);
+                self.pieces.push(Piece::Close);
+            }
+            ExpressionSyntaxEnum::Unary(unary) => {
+                // `- -128i8` would otherwise lose the space between the minuses
+                let negative: bool =
+                    matches!(unary.operand, ExpressionSyntaxEnum::Number(value) if value < 0);
+                self.text(unary.operator_token.text());
+                self.operand(&unary.operand, binding(&unary.operand) < UNARY || negative);
+            }
+            ExpressionSyntaxEnum::Conditional(conditional) => {
+                self.pieces.push(Piece::Open(Group::Consistent));
+                self.conditional(conditional);
+                self.pieces.push(Piece::Close);
+            }
+            ExpressionSyntaxEnum::Chain(chain) => {
+                let precedence: u8 = OperatorToken::Less.precedence();
+                self.pieces.push(Piece::Open(Group::Fill));
+                self.operand(&chain.first, binding(&chain.first) <= precedence);
+                for link in &chain.links {
+                    self.text(&format!(" {}", link.operator_token.text()));
+                    self.pieces.push(Piece::Break);
+                    self.operand(&link.operand, binding(&link.operand) <= precedence);
+                }
+                self.pieces.push(Piece::Close);
+            }
+            ExpressionSyntaxEnum::Index(index) => {
+                self.operand(&index.target, binding(&index.target) < POSTFIX);
+                self.text("[");
+                self.expression(&index.index);
+                self.text("]");
+            }
+            ExpressionSyntaxEnum::Number(_)
+            | ExpressionSyntaxEnum::Float(_)
+            | ExpressionSyntaxEnum::String(_) => self.literal(expression, false),
+            ExpressionSyntaxEnum::Array(elements) => {
+                self.text("[");
+                self.list(elements);
+                self.text("]");
+            }
+            ExpressionSyntaxEnum::Variable(variable) => self.text(&variable.name),
+            ExpressionSyntaxEnum::Call(call) => self.call(call),
+        }
+    }
+
+    /// Print a binary expression in the group of its operator, `a + b - c` wraps after
+    /// either operator
+    fn binary(&mut self, binary: &ExpressionSyntax) {
+        let precedence: u8 = binary.operator_token.precedence();
+        // `(a < b) < c` would read back as a chain
+        let chained: bool = binary.operator_token.chains() && is_chaining(&binary.left);
+        match &binary.left {
+            ExpressionSyntaxEnum::ExpressionSyntax(left)
+                if !chained
+                    && left.operator_token.precedence() == precedence
+                    && &self.source[left.operator_span.start..left.operator_span.end] != "of" =>
+            {
+                self.binary(left)
+            }
+            left => self.operand(left, binding(left) < precedence || chained),
+        }
+
+        self.text(&format!(" {}", binary.operator_token.text()));
+        self.pieces.push(Piece::Break);
+        // `10 % -3` and `10 % of + 1` would read back as percentages
+        let percentage: bool = binary.operator_token == OperatorToken::Percent
+            && ends_with_number(&binary.left)
+            && (matches!(binary.right, ExpressionSyntaxEnum::Unary(_))
+                || starts_with_of(&binary.right));
+        self.operand(
+            &binary.right,
+            binding(&binary.right) <= precedence || percentage,
+        );
+    }
+
+    /// Print a conditional in the group of the conditionals it ends with, `a ? b : c ? d : e`
+    /// wraps before every branch
+    fn conditional(&mut self, conditional: &ConditionalSyntax) {
+        self.operand(
+            &conditional.condition,
+            binding(&conditional.condition) == CONDITIONAL,
+        );
+        self.text(" ?");
+        self.pieces.push(Piece::Break);
+        self.expression(&conditional.then);
+        self.text(" :");
+        self.pieces.push(Piece::Break);
+        match &conditional.otherwise {
+            ExpressionSyntaxEnum::Conditional(otherwise) => self.conditional(otherwise),
+            otherwise => self.expression(otherwise),
+        }
+    }
+
+    /// Print `expression` where it binds too loosely without `parentheses`
+    fn operand(&mut self, expression: &ExpressionSyntaxEnum, parentheses: bool) {
+        if parentheses {
+            self.text("(");
+        }
+        self.expression(expression);
+        if parentheses {
+            self.text(")");
+        }
+    }
+
+    /// Print a call the way it was written, `x.len()` or `len(x)`
+    fn call(&mut self, call: &CallSyntax) {
+        match call.arguments.split_first() {
+            Some((receiver, arguments)) if is_method(call) => {
+                self.operand(receiver, binding(receiver) < POSTFIX);
+                self.text(&format!(".{}(", call.name));
+                self.list(arguments);
+            }
+            _ => {
+                self.text(&format!("{}(", call.name));
+                self.list(&call.arguments);
+            }
+        }
+        self.text(")");
+    }
+
+    /// Print comma separated `expressions`, wrapped after the commas
+    fn list(&mut self, expressions: &[ExpressionSyntaxEnum]) {
+        self.pieces.push(Piece::Open(Group::Fill));
+        for (index, expression) in expressions.iter().enumerate() {
+            if index > 0 {
+                self.text(",");
+                self.pieces.push(Piece::Break);
+            }
+            self.expression(expression);
+        }
+        self.pieces.push(Piece::Close);
+    }
+
+    /// Copy the next number or string literal from the source, with the `%` after it when
+    /// `literal` is a percentage
+    fn literal(&mut self, literal: &ExpressionSyntaxEnum, percentage: bool) {
+        let index: usize = self.tokens[self.next_literal..]
+            .iter()
+            .position(|token: &SyntaxToken| {
+                matches!(
+                    token.token_type,
+                    SyntaxTokenType::Number(_)
+                        | SyntaxTokenType::Float(_)
+                        | SyntaxTokenType::String(_)
+                )
+            })
+            .map(|offset: usize| self.next_literal + offset)
+            .expect("Every literal in the tree has a token");
+        self.next_literal = index + 1;
+
+        let token: SyntaxToken = self.tokens[index].clone();
+        // The smallest value of a type is parsed with the minus before it, like `-128i8`
+        if matches!(literal, ExpressionSyntaxEnum::Number(value) if *value < 0) {
+            self.text("-");
+        }
+        self.text(&token.text);
+
+        // A percentage is parsed as a float a hundred times smaller than the number written
+        let percentage: bool = percentage
+            || match (&token.token_type, literal) {
+                (SyntaxTokenType::Number(_), ExpressionSyntaxEnum::Float(_)) => true,
+                (SyntaxTokenType::Float(Ok(written)), ExpressionSyntaxEnum::Float(value)) => {
+                    written != value
+                }
+                _ => false,
+            };
+        if percentage {
+            self.text("%");
+        }
+    }
+}
+
+/// How tightly `expression` holds together, an operand that binds looser than its place
+/// needs parentheses
+fn binding(expression: &ExpressionSyntaxEnum) -> u8 {
+    match expression {
+        ExpressionSyntaxEnum::Conditional(_) => CONDITIONAL,
+        ExpressionSyntaxEnum::ExpressionSyntax(binary) => binary.operator_token.precedence(),
+        ExpressionSyntaxEnum::Chain(_) => OperatorToken::Less.precedence(),
+        ExpressionSyntaxEnum::Unary(_) => UNARY,
+        // The smallest value of a type, like `-128i8`, is written with a minus
+        ExpressionSyntaxEnum::Number(value) if *value < 0 => UNARY,
+        _ => POSTFIX,
+    }
+}
+
+/// Whether `call` was written as a method, `x.len()` and not `len(x)`
+fn is_method(call: &CallSyntax) -> bool {
+    !call
+        .text
+        .strip_prefix(&call.name)
+        .is_some_and(|rest: &str| rest.trim_start().starts_with('('))
+}
+
+/// Whether the last token of `expression` may be a number literal, which a `%` after it could
+/// be the percent sign of
+fn ends_with_number(expression: &ExpressionSyntaxEnum) -> bool {
+    match expression {
+        ExpressionSyntaxEnum::Number(_) | ExpressionSyntaxEnum::Float(_) => true,
+        ExpressionSyntaxEnum::ExpressionSyntax(binary) => ends_with_number(&binary.right),
+        ExpressionSyntaxEnum::Unary(unary) => ends_with_number(&unary.operand),
+        ExpressionSyntaxEnum::Conditional(conditional) => ends_with_number(&conditional.otherwise),
+        ExpressionSyntaxEnum::Chain(chain) => chain
+            .links
+            .last()
+            .is_some_and(|link: &ChainLink| ends_with_number(&link.operand)),
+        _ => false,
+    }
+}
+
+/// Whether the first token of `expression` is a name `of`, which after a `%` could take a
+/// percentage of what follows it
+fn starts_with_of(expression: &ExpressionSyntaxEnum) -> bool {
+    match expression {
+        ExpressionSyntaxEnum::Variable(variable) => variable.name == "of",
+        ExpressionSyntaxEnum::Index(index) => starts_with_of(&index.target),
+        ExpressionSyntaxEnum::Call(call) => match call.arguments.first() {
+            Some(receiver) if is_method(call) => starts_with_of(receiver),
+            _ => call.name == "of",
+        },
+        _ => false,
+    }
+}
+
+/// Whether `expression` is a comparison that would join a chain after it, like `a < b`
+fn is_chaining(expression: &ExpressionSyntaxEnum) -> bool {
+    match expression {
+        ExpressionSyntaxEnum::ExpressionSyntax(binary) => binary.operator_token.chains(),
+        ExpressionSyntaxEnum::Chain(_) => true,
+        _ => false,
+    }
+}
+
+/// What `ph fmt` does with a formatted file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    /// Rewrite the file when it changed
+    Write,
+    /// Print the formatted source
+    Stdout,
+    /// Print a diff when it changed, writing nothing
+    Check,
+}
+
+/// Parser for `source`, where indentation delimits function bodies with `layout`
+fn parser(source: &str, layout: bool) -> Parser {
+    match layout {
+        true => Parser::with_layout(source.to_string()),
+        false => Parser::new(source.to_string()),
+    }
+}
+
+pub fn fmt(args: clap::ArgMatches) {
+    // Get files to format
+    let files: Vec<&String>;
+    let mode: Mode;
+    let indent_width: Option<usize>;
+    let max_line_width: Option<usize>;
+    let trailing_newline: Option<bool>;
+    let layout: bool;
+    if let Some(arg_match) = args.subcommand_matches("fmt") {
+        files = arg_match
+            .get_many::<String>("file")
+            .expect("Failed to get file")
+            .collect();
+        mode = if arg_match.get_flag("check") {
+            Mode::Check
+        } else if arg_match.get_flag("stdout") {
+            Mode::Stdout
+        } else {
+            Mode::Write
+        };
+        indent_width = arg_match.get_one::<usize>("indent-width").copied();
+        max_line_width = arg_match.get_one::<usize>("max-line-width").copied();
+        trailing_newline = arg_match.get_one::<bool>("trailing-newline").copied();
+        layout = arg_match.get_flag("layout");
+    } else {
+        unreachable!("Subcommand is required");
+    }
+
+    // Files in one directory share a configuration, it is read and warned about once
+    let mut configs: std::collections::HashMap<std::path::PathBuf, Option<FormatOptions>> =
+        std::collections::HashMap::new();
+
+    // Every file is handled even after one fails, so all of them are reported
+    let mut failed: bool = false;
+    for file in files {
+        let mut options: FormatOptions = match find_config(std::path::Path::new(file)) {
+            Some(path) => {
+                let options: &Option<FormatOptions> =
+                    configs.entry(path.clone()).or_insert_with(|| {
+                        match read_config(&path, FormatOptions::default()) {
+                            Ok(options) => Some(options),
+                            Err(error) => {
+                                eprintln!("error: {}", error);
+                                None
+                            }
+                        }
+                    });
+                match options {
+                    Some(options) => *options,
+                    None => {
+                        failed = true;
+                        continue;
+                    }
+                }
+            }
+            None => FormatOptions::default(),
+        };
+
+        // The command line overrides the configuration
+        options.indent_width = indent_width.unwrap_or(options.indent_width);
+        options.max_line_width = max_line_width.unwrap_or(options.max_line_width);
+        options.trailing_newline = trailing_newline.unwrap_or(options.trailing_newline);
+        options.layout = layout;
+
+        if !format_file(file, mode, &options) {
+            failed = true;
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// `.phfmt.toml` in the directory of `file` or the closest one above it
+pub fn find_config(file: &std::path::Path) -> Option<std::path::PathBuf> {
+    let file: std::path::PathBuf = std::fs::canonicalize(file).ok()?;
+    file.parent()?
+        .ancestors()
+        .map(|directory: &std::path::Path| directory.join(CONFIG_FILE))
+        .find(|path: &std::path::PathBuf| path.is_file())
+}
+
+/// Read the configuration at `path` over `options`, printing a warning for every key it
+/// doesn't know
+pub fn read_config(
+    path: &std::path::Path,
+    options: FormatOptions,
+) -> Result<FormatOptions, String> {
+    let text: String = std::fs::read_to_string(path).map_err(|error: std::io::Error| {
+        format!("failed to read '{}': {}", path.display(), error)
+    })?;
+
+    let (options, warnings): (FormatOptions, Vec<String>) = parse_config(&text, options)
+        .map_err(|error: String| format!("{}:{}", path.display(), error))?;
+    for warning in warnings {
+        eprintln!("warning: {}:{}", path.display(), warning);
+    }
+
+    Ok(options)
+}
+
+/// Parse the `key = value` lines of a configuration over `options`
+///
+/// Keys and tables this version doesn't know are returned as warnings, so a configuration
+/// written for a newer `ph` still works. Errors and warnings start with their line number.
+pub fn parse_config(
+    text: &str,
+    mut options: FormatOptions,
+) -> Result<(FormatOptions, Vec<String>), String> {
+    let document: crate::toml::Document =
+        crate::toml::parse(text).map_err(|error: crate::toml::TomlError| error.to_string())?;
+
+    // Keys under a table aren't ours, the table is warned about instead
+    let mut warnings: Vec<(usize, String)> = document
+        .tables
+        .iter()
+        .map(|(table, line): &(String, usize)| {
+            (*line, format!("unknown table [{}], it is ignored", table))
+        })
+        .collect();
+
+    for entry in &document.entries {
+        if !entry.table.is_empty() {
+            continue;
+        }
+
+        let width = || -> Result<usize, String> {
+            match entry.value {
+                crate::toml::Value::Integer(width) if width > 0 => Ok(width as usize),
+                _ => Err(format!(
+                    "{}: '{}' must be a positive integer",
+                    entry.line, entry.key
+                )),
+            }
+        };
+        match entry.key.as_str() {
+            "indent_width" => options.indent_width = width()?,
+            "max_line_width" => options.max_line_width = width()?,
+            "trailing_newline" => {
+                options.trailing_newline = match entry.value {
+                    crate::toml::Value::Boolean(trailing_newline) => trailing_newline,
+                    _ => {
+                        return Err(format!(
+                            "{}: 'trailing_newline' must be true or false",
+                            entry.line
+                        ))
+                    }
+                }
+            }
+            key => warnings.push((entry.line, format!("unknown key '{}', it is ignored", key))),
+        }
+    }
+
+    // Warnings come in the order of the file
+    warnings.sort_by_key(|(line, _): &(usize, String)| *line);
+    let warnings: Vec<String> = warnings
+        .into_iter()
+        .map(|(line, warning): (usize, String)| format!("{}: {}", line, warning))
+        .collect();
+
+    Ok((options, warnings))
+}
+
+/// Format one file the way `mode` says, false when it doesn't parse, can't be written, or
+/// with `Mode::Check` isn't formatted
+fn format_file(file: &str, mode: Mode, options: &FormatOptions) -> bool {
+    // Get file contents
+    let file_contents: String = crate::source::read_source(file);
+
+    let formatted: String = match format_source(&file_contents, options) {
+        Ok(formatted) => formatted,
+        Err(error) => {
+            eprint!("{}", Diagnostic::from(error).render(file, &file_contents));
+            return false;
+        }
+    };
+
+    // Formatting must never change what the program means, check before touching the file
+    let tree = |source: &str| -> Option<String> {
+        parser(source, options.layout)
+            .parse_statements()
+            .ok()
+            .map(|statements: Vec<StatementSyntax>| crate::ast::format_statements(&statements))
+    };
+    if tree(&formatted) != tree(&file_contents) {
+        eprintln!(
+            "error: formatting '{}' would change what it means, it was left as is",
+            file
+        );
+        return false;
+    }
+
+    match mode {
+        Mode::Stdout => print!("{}", formatted),
+        Mode::Check => {
+            print!(
+                "{}",
+                crate::diff::unified_diff(&file_contents, &formatted, file)
+            );
+            return formatted == file_contents;
+        }
+        Mode::Write if formatted != file_contents => {
+            if let Err(error) =
+                crate::build::write_atomic(std::path::Path::new(file), formatted.as_bytes())
+            {
+                eprintln!("error: failed to write '{}': {}", file, error);
+                return false;
+            }
+        }
+        Mode::Write => {}
+    }
+
+    true
+}
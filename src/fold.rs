@@ -0,0 +1,137 @@
+use crate::diagnostic::{Diagnostic, Span};
+use crate::eval::{binary_operation, Overflow, Value};
+use crate::parser::{ExpressionSyntaxEnum, OperatorToken, StatementSyntax};
+
+/// Replace operations on number literals with their result before running
+///
+/// Dividing a literal by a literal zero is reported here instead of at runtime.
+/// Operations that would fail for another reason, like overflow, are left for the
+/// interpreter so `--overflow` still decides what happens.
+pub fn fold_statements(statements: &mut [StatementSyntax]) -> Result<(), Diagnostic> {
+    for statement in statements {
+        match statement {
+            StatementSyntax::Let(let_syntax) => fold_expression(&mut let_syntax.value)?,
+            StatementSyntax::Assign(assign) => fold_expression(&mut assign.value)?,
+            StatementSyntax::Function(function) => fold_statements(&mut function.body)?,
+            StatementSyntax::Import(_) => {}
+            StatementSyntax::Expression(expression) => fold_expression(expression)?,
+        }
+    }
+
+    Ok(())
+}
+
+fn fold_expression(expression: &mut ExpressionSyntaxEnum) -> Result<(), Diagnostic> {
+    let folded: Option<ExpressionSyntaxEnum> = match expression {
+        ExpressionSyntaxEnum::ExpressionSyntax(binary) => {
+            fold_expression(&mut binary.left)?;
+            fold_expression(&mut binary.right)?;
+
+            if matches!(
+                binary.operator_token,
+                OperatorToken::Slash | OperatorToken::Percent
+            ) && matches!(
+                (&binary.left, &binary.right),
+                (
+                    ExpressionSyntaxEnum::Number(_),
+                    ExpressionSyntaxEnum::Number(0)
+                )
+            ) {
+                return Err(Diagnostic::error(
+                    "division by zero".to_string(),
+                    binary.operator_span,
+                ));
+            }
+
+            match (constant(&binary.left), constant(&binary.right)) {
+                (Some(left), Some(right)) => binary_operation(
+                    binary.operator_token,
+                    left,
+                    right,
+                    Span::new(0, 0),
+                    Overflow::Checked,
+                )
+                .ok()
+                .and_then(literal),
+                _ => None,
+            }
+        }
+        ExpressionSyntaxEnum::Unary(unary) => {
+            fold_expression(&mut unary.operand)?;
+
+            match (unary.operator_token, &unary.operand) {
+                (OperatorToken::Plus, ExpressionSyntaxEnum::Number(value)) => {
+                    Some(ExpressionSyntaxEnum::Number(*value))
+                }
+                (OperatorToken::Plus, ExpressionSyntaxEnum::Float(value)) => {
+                    Some(ExpressionSyntaxEnum::Float(*value))
+                }
+                (OperatorToken::Minus, ExpressionSyntaxEnum::Number(value)) => {
+                    value.checked_neg().map(ExpressionSyntaxEnum::Number)
+                }
+                (OperatorToken::Minus, ExpressionSyntaxEnum::Float(value)) => {
+                    Some(ExpressionSyntaxEnum::Float(-value))
+                }
+                _ => None,
+            }
+        }
+        ExpressionSyntaxEnum::Conditional(conditional) => {
+            fold_expression(&mut conditional.condition)?;
+            fold_expression(&mut conditional.then)?;
+            fold_expression(&mut conditional.otherwise)?;
+            None
+        }
+        ExpressionSyntaxEnum::Chain(chain) => {
+            fold_expression(&mut chain.first)?;
+            for link in &mut chain.links {
+                fold_expression(&mut link.operand)?;
+            }
+            None
+        }
+        ExpressionSyntaxEnum::Index(index) => {
+            fold_expression(&mut index.target)?;
+            fold_expression(&mut index.index)?;
+            None
+        }
+        ExpressionSyntaxEnum::Array(elements) => {
+            for element in elements {
+                fold_expression(element)?;
+            }
+            None
+        }
+        ExpressionSyntaxEnum::Call(call) => {
+            for argument in &mut call.arguments {
+                fold_expression(argument)?;
+            }
+            None
+        }
+        ExpressionSyntaxEnum::Number(_)
+        | ExpressionSyntaxEnum::Float(_)
+        | ExpressionSyntaxEnum::String(_)
+        | ExpressionSyntaxEnum::Variable(_) => None,
+    };
+
+    if let Some(folded) = folded {
+        *expression = folded;
+    }
+
+    Ok(())
+}
+
+/// Value of a number literal
+fn constant(expression: &ExpressionSyntaxEnum) -> Option<Value> {
+    match expression {
+        ExpressionSyntaxEnum::Number(value) => Some(Value::Int(*value)),
+        ExpressionSyntaxEnum::Float(value) => Some(Value::Float(*value)),
+        _ => None,
+    }
+}
+
+/// Number literal holding a value
+fn literal(value: Value) -> Option<ExpressionSyntaxEnum> {
+    match value {
+        Value::Int(value) => Some(ExpressionSyntaxEnum::Number(value)),
+        Value::Float(value) => Some(ExpressionSyntaxEnum::Float(value)),
+        _ => None,
+    }
+}
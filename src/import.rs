@@ -0,0 +1,303 @@
+use crate::diagnostic::{Diagnostic, SourceFile, Span};
+use crate::parser::{ImportSyntax, Parser, StatementSyntax};
+
+/// Extension a module name gets to become a file name, `import utils` reads `utils.ph`
+pub const EXTENSION: &str = "ph";
+
+/// A program with the files it imports put together into one source text
+#[derive(Clone, Debug, PartialEq)]
+pub struct Loaded {
+    /// Every imported file once, each before the first file importing it, then the file that
+    /// was given, with the import statements blanked out
+    pub source: String,
+    /// Where each imported file is in `source`, in order
+    pub imports: Vec<SourceFile>,
+}
+
+/// Why the files of a program couldn't be put together
+#[derive(Clone, Debug)]
+pub struct ImportError {
+    /// File the diagnostic points into, with its text
+    pub file_name: String,
+    pub source: String,
+    pub diagnostic: Diagnostic,
+}
+
+impl ImportError {
+    pub fn render(&self) -> String {
+        self.diagnostic.render(&self.file_name, &self.source)
+    }
+}
+
+/// Put `source`, read from `file_name`, together with every file it imports
+///
+/// A quoted path is relative to the importing file. A module name is looked for next to the
+/// importing file, then in the directory of the project's entry point. Importing a file again,
+/// from anywhere, does nothing, so functions and variables are defined once. The statements
+/// of an imported file run before those of the file importing it.
+///
+/// Every file is parsed like `layout` and `lenient` say. A syntax error in the given file
+/// isn't reported here, its source comes back as it is for the caller to report the error like
+/// in a program without imports.
+pub fn load(
+    file_name: &str,
+    source: &str,
+    layout: bool,
+    lenient: bool,
+) -> Result<Loaded, ImportError> {
+    // A program without imports is the common case, it isn't parsed twice
+    if !source.contains("import") {
+        return Ok(Loaded {
+            source: source.to_string(),
+            imports: Vec::new(),
+        });
+    }
+
+    let Ok(statements) = parse(source, layout, lenient) else {
+        return Ok(Loaded {
+            source: source.to_string(),
+            imports: Vec::new(),
+        });
+    };
+
+    let mut loader: Loader = Loader {
+        layout,
+        lenient,
+        loaded: std::collections::HashSet::new(),
+        stack: Vec::new(),
+        output: Loaded {
+            source: String::new(),
+            imports: Vec::new(),
+        },
+    };
+    let key: Option<std::path::PathBuf> = std::fs::canonicalize(file_name).ok();
+    let source: String = loader.visit(file_name, source, &statements, key)?;
+
+    let mut loaded: Loaded = loader.output;
+    loaded.source.push_str(&source);
+    Ok(loaded)
+}
+
+/// Put `source` together with every file it imports, or print why they can't be and exit
+pub fn load_program(file_name: &str, source: &str, layout: bool, lenient: bool) -> Loaded {
+    match load(file_name, source, layout, lenient) {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            eprint!("{}", error.render());
+            std::process::exit(1);
+        }
+    }
+}
+
+fn parse(source: &str, layout: bool, lenient: bool) -> Result<Vec<StatementSyntax>, Diagnostic> {
+    let mut parser: Parser = match layout {
+        true => Parser::with_layout(source.to_string()),
+        false => Parser::new(source.to_string()),
+    };
+    parser.set_lenient(lenient);
+
+    parser.parse_statements().map_err(Diagnostic::from)
+}
+
+struct Loader {
+    layout: bool,
+    lenient: bool,
+    /// Canonical paths of the files already in `output`
+    loaded: std::collections::HashSet<std::path::PathBuf>,
+    /// Canonical path and name of every file being loaded, the importing file first
+    stack: Vec<(Option<std::path::PathBuf>, String)>,
+    output: Loaded,
+}
+
+impl Loader {
+    /// Load what the file `name` imports and give back its source with the imports blanked out
+    fn visit(
+        &mut self,
+        name: &str,
+        source: &str,
+        statements: &[StatementSyntax],
+        key: Option<std::path::PathBuf>,
+    ) -> Result<String, ImportError> {
+        let error = |message: String, span: Span| -> ImportError {
+            ImportError {
+                file_name: name.to_string(),
+                source: source.to_string(),
+                diagnostic: Diagnostic::error(message, span),
+            }
+        };
+
+        self.stack.push((key, name.to_string()));
+        let mut blanked: String = source.to_string();
+
+        for statement in statements {
+            let StatementSyntax::Import(import) = statement else {
+                continue;
+            };
+            blank(&mut blanked, import.span);
+
+            let path: std::path::PathBuf =
+                resolve(name, import).map_err(|message: String| error(message, import.span))?;
+            let key: std::path::PathBuf =
+                std::fs::canonicalize(&path).map_err(|io_error: std::io::Error| {
+                    error(
+                        format!(
+                            "cannot import '{}': failed to read '{}': {}",
+                            import.path,
+                            path.display(),
+                            io_error
+                        ),
+                        import.span,
+                    )
+                })?;
+            let imported_name: String = path.to_string_lossy().into_owned();
+
+            if let Some(first) =
+                self.stack
+                    .iter()
+                    .position(|(file, _): &(Option<std::path::PathBuf>, String)| {
+                        file.as_ref() == Some(&key)
+                    })
+            {
+                let chain: Vec<&str> = self.stack[first..]
+                    .iter()
+                    .map(|(_, name): &(Option<std::path::PathBuf>, String)| name.as_str())
+                    .chain(std::iter::once(imported_name.as_str()))
+                    .collect();
+                return Err(error(
+                    format!("import cycle: {}", chain.join(" -> ")),
+                    import.span,
+                ));
+            }
+            if self.loaded.contains(&key) {
+                continue;
+            }
+
+            let imported: String = read(&path).map_err(|message: String| {
+                error(
+                    format!("cannot import '{}': {}", import.path, message),
+                    import.span,
+                )
+            })?;
+            let imported_statements: Vec<StatementSyntax> =
+                parse(&imported, self.layout, self.lenient).map_err(|diagnostic: Diagnostic| {
+                    ImportError {
+                        file_name: imported_name.clone(),
+                        source: imported.clone(),
+                        diagnostic,
+                    }
+                })?;
+            let imported: String = self.visit(
+                &imported_name,
+                &imported,
+                &imported_statements,
+                Some(key.clone()),
+            )?;
+
+            // Each file starts on a line of its own, so its first statement doesn't continue
+            // the last one of the file before it
+            let start: usize = self.output.source.len();
+            self.output.source.push_str(&imported);
+            if !imported.ends_with('\n') {
+                self.output.source.push('\n');
+            }
+            self.output.imports.push(SourceFile {
+                name: imported_name,
+                start,
+                end: self.output.source.len(),
+            });
+            self.loaded.insert(key);
+        }
+
+        self.stack.pop();
+        Ok(blanked)
+    }
+}
+
+/// Replace the text of an import statement with spaces, offsets after it stay the same
+fn blank(source: &mut String, span: Span) {
+    let spaces: String = source[span.start..span.end]
+        .chars()
+        .map(|character: char| match character {
+            '\n' => "\n".to_string(),
+            character => " ".repeat(character.len_utf8()),
+        })
+        .collect();
+
+    source.replace_range(span.start..span.end, &spaces);
+}
+
+/// Path of the file `import` in the file `importing` names
+fn resolve(importing: &str, import: &ImportSyntax) -> Result<std::path::PathBuf, String> {
+    let directory: &std::path::Path = std::path::Path::new(importing)
+        .parent()
+        .unwrap_or(std::path::Path::new(""));
+
+    if import.quoted {
+        return Ok(normalize(&directory.join(&import.path)));
+    }
+
+    let file_name: String = format!("{}.{}", import.path, EXTENSION);
+    let next_to: std::path::PathBuf = normalize(&directory.join(&file_name));
+    if next_to.exists() {
+        return Ok(next_to);
+    }
+
+    match crate::project::source_directory(directory) {
+        Some(source_directory) => {
+            let in_project: std::path::PathBuf = normalize(&source_directory.join(&file_name));
+            match in_project.exists() {
+                true => Ok(in_project),
+                false => Err(format!(
+                    "cannot import '{}': there is no '{}' or '{}'",
+                    import.path,
+                    next_to.display(),
+                    in_project.display()
+                )),
+            }
+        }
+        None => Err(format!(
+            "cannot import '{}': there is no '{}'",
+            import.path,
+            next_to.display()
+        )),
+    }
+}
+
+/// Text of an imported file, like `crate::source::source_text` but returning the problem
+fn read(path: &std::path::Path) -> Result<String, String> {
+    let bytes: Vec<u8> = std::fs::read(path).map_err(|error: std::io::Error| {
+        format!("failed to read '{}': {}", path.display(), error)
+    })?;
+    let text: String = String::from_utf8(bytes).map_err(|_| {
+        format!(
+            "failed to read '{}': stream did not contain valid UTF-8",
+            path.display()
+        )
+    })?;
+
+    Ok(match text.strip_prefix('\u{FEFF}') {
+        Some(rest) => rest.to_string(),
+        None => text,
+    })
+}
+
+/// `path` without `.` and with `directory/..` dropped, so names in messages stay short
+pub fn normalize(path: &std::path::Path) -> std::path::PathBuf {
+    let mut normalized: std::path::PathBuf = std::path::PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(std::path::Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
@@ -0,0 +1,53 @@
+/// Id of an interned string
+pub type Symbol = u32;
+
+/// Strings numbered in the order they are first seen, equal strings get the same id
+///
+/// Lets names be compared and looked up by id instead of by their text. The checker resolves
+/// names with one and the bytecode compiler numbers the globals with one.
+#[derive(Clone, Debug, Default)]
+pub struct Interner {
+    ids: std::collections::HashMap<String, Symbol>,
+    strings: Vec<String>,
+}
+
+impl Interner {
+    pub fn new() -> Interner {
+        Interner::default()
+    }
+
+    /// Id of `text`, adding it if it wasn't seen before
+    pub fn intern(&mut self, text: &str) -> Symbol {
+        if let Some(&id) = self.ids.get(text) {
+            return id;
+        }
+
+        let id: Symbol = Symbol::try_from(self.strings.len()).expect("Programs have fewer names");
+        self.strings.push(text.to_string());
+        self.ids.insert(text.to_string(), id);
+        id
+    }
+
+    /// Id of `text` if it was interned
+    pub fn get(&self, text: &str) -> Option<Symbol> {
+        self.ids.get(text).copied()
+    }
+
+    /// Text of an id returned by `intern`
+    pub fn resolve(&self, id: Symbol) -> &str {
+        &self.strings[id as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Every interned string, indexed by its id
+    pub fn into_strings(self) -> Vec<String> {
+        self.strings
+    }
+}
@@ -0,0 +1,799 @@
+use crate::builtins::builtin;
+use crate::bytecode::CallSite;
+use crate::diagnostic::Span;
+use crate::eval::{argument_count_error, RuntimeError, Value};
+use crate::lexer::StringSegment;
+use crate::parser::{
+    CallSyntax, ExpressionSyntaxEnum, FunctionSyntax, OperatorToken, StatementSyntax,
+};
+
+/// Value computed by an operation, numbered from 0 in each function
+///
+/// Every temporary is defined once, values that meet after a conditional are passed as block
+/// parameters. Temporaries are used in the reverse order they are defined, like values on a
+/// stack, which lets the bytecode emitter keep them on the VM stack.
+pub type Temporary = usize;
+
+/// Index of a block in `Function::blocks`
+pub type BlockId = usize;
+
+/// Program lowered to basic blocks, between the syntax tree and the backends
+#[derive(Clone, Debug, PartialEq)]
+pub struct Module {
+    /// Top level code
+    pub main: Function,
+    pub functions: Vec<Function>,
+    /// Whether the program ends with an expression statement, whose value is the result
+    pub has_result: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub parameters: Vec<String>,
+    /// Name of each local slot, parameters first, empty at the top level
+    pub locals: Vec<String>,
+    /// The entry block comes first, the others follow in the order they are laid out
+    pub blocks: Vec<Block>,
+}
+
+/// Operations that always run one after another, ending in a jump or a return
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    /// Temporaries given by the jumps to the block
+    pub parameters: Vec<Temporary>,
+    pub operations: Vec<Operation>,
+    /// Source range of each operation, for runtime errors
+    pub spans: Vec<Span>,
+    pub terminator: Terminator,
+    pub terminator_span: Span,
+}
+
+/// Storage of a variable
+#[derive(Clone, Debug, PartialEq)]
+pub enum Variable {
+    /// Slot of the running function's parameters and `let` bindings
+    Local(usize),
+    Global(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Callee {
+    /// Index in `Module::functions`
+    Function(usize),
+    Builtin,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operation {
+    Constant(Temporary, Value),
+    /// String literal with its `${NAME}` variables substituted
+    Interpolate(Temporary, Vec<StringSegment>),
+    Array(Temporary, Vec<Temporary>),
+    Index {
+        destination: Temporary,
+        target: Temporary,
+        index: Temporary,
+    },
+    Negate(Temporary, Temporary),
+    Binary {
+        destination: Temporary,
+        operator_token: OperatorToken,
+        left: Temporary,
+        right: Temporary,
+    },
+    /// Comparison of a chain other than the last, `right` is compared again by the next one
+    Compare {
+        destination: Temporary,
+        operator_token: OperatorToken,
+        left: Temporary,
+        right: Temporary,
+    },
+    Load(Temporary, Variable),
+    /// Assign a variable that must already exist
+    Store(Variable, Temporary),
+    /// Assign a variable, creating it if needed
+    Define(Variable, Temporary),
+    Call {
+        destination: Temporary,
+        callee: Callee,
+        site: CallSite,
+        arguments: Vec<Temporary>,
+    },
+    /// Show a top level value to the observer of the program
+    Yield(Temporary),
+    /// The value is not used anymore
+    Drop(Temporary),
+    /// Raise an error known up front, like a wrong argument count, the temporary never gets
+    /// a value
+    Fail(Temporary, RuntimeError),
+}
+
+/// Block to jump to with the values for its parameters
+#[derive(Clone, Debug, PartialEq)]
+pub struct Target {
+    pub block: BlockId,
+    pub arguments: Vec<Temporary>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Terminator {
+    Jump(Target),
+    /// Go to `then` if `condition` is true and `otherwise` if it is false, it must be a bool
+    Branch {
+        condition: Temporary,
+        then: Target,
+        otherwise: Target,
+    },
+    /// Go back to the caller, or finish the program, with the value
+    Return(Temporary),
+}
+
+/// Lower a program to IR
+///
+/// Fails like `Interpreter::execute` does before running anything, when a function takes
+/// the name of a builtin. Calls that can't work, like to an undefined function, lower to a
+/// `Fail` so the error is only raised if they run.
+pub fn lower(statements: &[StatementSyntax]) -> Result<Module, RuntimeError> {
+    let mut lowering: Lowering = Lowering::default();
+
+    // Functions can be called above their definition, a later definition replaces an earlier one
+    let mut bodies: Vec<&FunctionSyntax> = Vec::new();
+    for statement in statements {
+        if let StatementSyntax::Function(function) = statement {
+            if builtin(&function.name).is_some() {
+                return Err(RuntimeError::new(
+                    format!(
+                        "cannot define function '{}', it is a builtin",
+                        function.name
+                    ),
+                    Span::new(function.position, function.position + function.name.len()),
+                ));
+            }
+
+            match lowering.function_indices.get(&function.name) {
+                Some(&index) => bodies[index] = function,
+                None => {
+                    lowering
+                        .function_indices
+                        .insert(function.name.clone(), bodies.len());
+                    bodies.push(function);
+                }
+            }
+        }
+    }
+    lowering.parameter_counts = bodies
+        .iter()
+        .map(|function: &&FunctionSyntax| function.parameters.len())
+        .collect();
+
+    let (main, has_result): (Function, bool) =
+        lowering.lower_function("main", &[], None, statements);
+    let functions: Vec<Function> = bodies
+        .iter()
+        .map(|function: &&FunctionSyntax| {
+            lowering
+                .lower_function(
+                    &function.name,
+                    &function.parameters,
+                    Some(function.parameters.clone()),
+                    &function.body,
+                )
+                .0
+        })
+        .collect();
+
+    Ok(Module {
+        main,
+        functions,
+        has_result,
+    })
+}
+
+#[derive(Debug, Default)]
+struct Lowering {
+    function_indices: std::collections::HashMap<String, usize>,
+    /// Parameter count of each function
+    parameter_counts: Vec<usize>,
+    /// Blocks of the function being lowered, `None` until they are terminated
+    blocks: Vec<Option<Block>>,
+    /// Block ids in the order the blocks were started, the order they are laid out in
+    started: Vec<BlockId>,
+    current: BlockId,
+    parameters: Vec<Temporary>,
+    operations: Vec<Operation>,
+    spans: Vec<Span>,
+    temporaries: usize,
+    /// Names of the running function's local slots, `None` at the top level
+    locals: Option<Vec<String>>,
+}
+
+impl Lowering {
+    /// Lower a body to a function returning the value of its trailing expression statement,
+    /// or nil, and whether there was one
+    fn lower_function(
+        &mut self,
+        name: &str,
+        parameters: &[String],
+        locals: Option<Vec<String>>,
+        statements: &[StatementSyntax],
+    ) -> (Function, bool) {
+        self.blocks.clear();
+        self.started.clear();
+        self.temporaries = 0;
+        self.locals = locals;
+
+        let entry: BlockId = self.reserve_block();
+        self.start_block(entry, Vec::new());
+
+        let mut result: Option<Temporary> = None;
+        for statement in statements {
+            if let Some(value) = result.take() {
+                self.push(Operation::Drop(value), Span::new(0, 0));
+            }
+
+            match statement {
+                StatementSyntax::Let(let_syntax) => {
+                    let value: Temporary = self.lower_expression(&let_syntax.value);
+
+                    let variable: Variable = match &mut self.locals {
+                        Some(locals) => Variable::Local(
+                            match locals
+                                .iter()
+                                .position(|local: &String| *local == let_syntax.name)
+                            {
+                                Some(slot) => slot,
+                                None => {
+                                    locals.push(let_syntax.name.clone());
+                                    locals.len() - 1
+                                }
+                            },
+                        ),
+                        None => Variable::Global(let_syntax.name.clone()),
+                    };
+                    self.push(Operation::Define(variable, value), Span::new(0, 0));
+                }
+                StatementSyntax::Assign(assign) => {
+                    let value: Temporary = self.lower_expression(&assign.value);
+                    self.push(
+                        Operation::Store(self.variable(&assign.name), value),
+                        Span::new(assign.position, assign.position + assign.name.len()),
+                    );
+                }
+                // Lowered after the top level by `lower`
+                StatementSyntax::Function(_) => {}
+                StatementSyntax::Import(_) => unreachable!("Imports are merged before lowering"),
+                StatementSyntax::Expression(expression) => {
+                    let value: Temporary = self.lower_expression(expression);
+                    if self.locals.is_none() {
+                        self.push(Operation::Yield(value), Span::new(0, 0));
+                    }
+                    result = Some(value);
+                }
+            }
+        }
+
+        let value: Temporary = match result {
+            Some(value) => value,
+            None => self.constant(Value::Nil),
+        };
+        self.finish_block(Terminator::Return(value), Span::new(0, 0));
+
+        let function: Function = Function {
+            name: name.to_string(),
+            parameters: parameters.to_vec(),
+            locals: self.locals.take().unwrap_or_default(),
+            blocks: self.layout(),
+        };
+        (function, result.is_some())
+    }
+
+    /// Blocks in the order they were started, renumbered to match
+    fn layout(&mut self) -> Vec<Block> {
+        let mut ids: Vec<BlockId> = vec![0; self.blocks.len()];
+        for (new, old) in self.started.iter().enumerate() {
+            ids[*old] = new;
+        }
+
+        let mut blocks: Vec<Option<Block>> = std::mem::take(&mut self.blocks);
+        self.started
+            .iter()
+            .map(|old: &BlockId| {
+                let mut block: Block = blocks[*old].take().expect("Every block is terminated");
+                match &mut block.terminator {
+                    Terminator::Jump(target) => target.block = ids[target.block],
+                    Terminator::Branch {
+                        then, otherwise, ..
+                    } => {
+                        then.block = ids[then.block];
+                        otherwise.block = ids[otherwise.block];
+                    }
+                    Terminator::Return(_) => {}
+                }
+                block
+            })
+            .collect()
+    }
+
+    /// Id for a block that is started later
+    fn reserve_block(&mut self) -> BlockId {
+        self.blocks.push(None);
+        self.blocks.len() - 1
+    }
+
+    fn start_block(&mut self, block: BlockId, parameters: Vec<Temporary>) {
+        self.started.push(block);
+        self.current = block;
+        self.parameters = parameters;
+    }
+
+    fn finish_block(&mut self, terminator: Terminator, span: Span) {
+        self.blocks[self.current] = Some(Block {
+            parameters: std::mem::take(&mut self.parameters),
+            operations: std::mem::take(&mut self.operations),
+            spans: std::mem::take(&mut self.spans),
+            terminator,
+            terminator_span: span,
+        });
+    }
+
+    fn temporary(&mut self) -> Temporary {
+        self.temporaries += 1;
+        self.temporaries - 1
+    }
+
+    fn push(&mut self, operation: Operation, span: Span) {
+        self.operations.push(operation);
+        self.spans.push(span);
+    }
+
+    fn constant(&mut self, value: Value) -> Temporary {
+        let destination: Temporary = self.temporary();
+        self.push(Operation::Constant(destination, value), Span::new(0, 0));
+        destination
+    }
+
+    fn variable(&self, name: &str) -> Variable {
+        match self
+            .locals
+            .as_ref()
+            .and_then(|locals: &Vec<String>| locals.iter().position(|local: &String| local == name))
+        {
+            Some(slot) => Variable::Local(slot),
+            None => Variable::Global(name.to_string()),
+        }
+    }
+
+    fn lower_expression(&mut self, expression: &ExpressionSyntaxEnum) -> Temporary {
+        match expression {
+            ExpressionSyntaxEnum::Number(value) => self.constant(Value::Int(*value)),
+            ExpressionSyntaxEnum::Float(value) => self.constant(Value::Float(*value)),
+            // Only strings with `${NAME}` need interpolating, the rest are plain constants
+            ExpressionSyntaxEnum::String(segments) => match plain_text(segments) {
+                Some(text) => self.constant(Value::String(text)),
+                None => {
+                    let destination: Temporary = self.temporary();
+                    self.push(
+                        Operation::Interpolate(destination, segments.clone()),
+                        Span::new(0, 0),
+                    );
+                    destination
+                }
+            },
+            ExpressionSyntaxEnum::Variable(variable) => {
+                let destination: Temporary = self.temporary();
+                self.push(
+                    Operation::Load(destination, self.variable(&variable.name)),
+                    Span::new(variable.position, variable.position + variable.name.len()),
+                );
+                destination
+            }
+            ExpressionSyntaxEnum::Call(call) => self.lower_call(call),
+            ExpressionSyntaxEnum::Array(elements) => {
+                let elements: Vec<Temporary> = elements
+                    .iter()
+                    .map(|element: &ExpressionSyntaxEnum| self.lower_expression(element))
+                    .collect();
+                let destination: Temporary = self.temporary();
+                self.push(Operation::Array(destination, elements), Span::new(0, 0));
+                destination
+            }
+            ExpressionSyntaxEnum::Index(index) => {
+                let target: Temporary = self.lower_expression(&index.target);
+                let position: Temporary = self.lower_expression(&index.index);
+                let destination: Temporary = self.temporary();
+                self.push(
+                    Operation::Index {
+                        destination,
+                        target,
+                        index: position,
+                    },
+                    Span::new(index.position, index.position + 1),
+                );
+                destination
+            }
+            ExpressionSyntaxEnum::Conditional(conditional) => {
+                let condition: Temporary = self.lower_expression(&conditional.condition);
+                let then: BlockId = self.reserve_block();
+                let otherwise: BlockId = self.reserve_block();
+                let join: BlockId = self.reserve_block();
+                self.finish_block(
+                    Terminator::Branch {
+                        condition,
+                        then: Target {
+                            block: then,
+                            arguments: Vec::new(),
+                        },
+                        otherwise: Target {
+                            block: otherwise,
+                            arguments: Vec::new(),
+                        },
+                    },
+                    Span::new(conditional.position, conditional.position + 1),
+                );
+
+                for (block, branch) in [
+                    (then, &conditional.then),
+                    (otherwise, &conditional.otherwise),
+                ] {
+                    self.start_block(block, Vec::new());
+                    let value: Temporary = self.lower_expression(branch);
+                    self.finish_block(
+                        Terminator::Jump(Target {
+                            block: join,
+                            arguments: vec![value],
+                        }),
+                        Span::new(0, 0),
+                    );
+                }
+
+                let destination: Temporary = self.temporary();
+                self.start_block(join, vec![destination]);
+                destination
+            }
+            // Each comparison but the last keeps its right operand for the next one, a false
+            // result passes it to a block that drops it and gives false
+            ExpressionSyntaxEnum::Chain(chain) => {
+                let mut left: Temporary = self.lower_expression(&chain.first);
+                let on_false: BlockId = self.reserve_block();
+
+                let mut result: Temporary = left;
+                for (index, link) in chain.links.iter().enumerate() {
+                    let right: Temporary = self.lower_expression(&link.operand);
+                    let span: Span = link.operator_token.span(link.position);
+                    let destination: Temporary = self.temporary();
+
+                    if index + 1 == chain.links.len() {
+                        self.push(
+                            Operation::Binary {
+                                destination,
+                                operator_token: link.operator_token,
+                                left,
+                                right,
+                            },
+                            span,
+                        );
+                        result = destination;
+                    } else {
+                        self.push(
+                            Operation::Compare {
+                                destination,
+                                operator_token: link.operator_token,
+                                left,
+                                right,
+                            },
+                            span,
+                        );
+                        let next: BlockId = self.reserve_block();
+                        self.finish_block(
+                            Terminator::Branch {
+                                condition: destination,
+                                then: Target {
+                                    block: next,
+                                    arguments: Vec::new(),
+                                },
+                                otherwise: Target {
+                                    block: on_false,
+                                    arguments: vec![right],
+                                },
+                            },
+                            span,
+                        );
+                        self.start_block(next, Vec::new());
+                    }
+                    left = right;
+                }
+
+                let join: BlockId = self.reserve_block();
+                self.finish_block(
+                    Terminator::Jump(Target {
+                        block: join,
+                        arguments: vec![result],
+                    }),
+                    Span::new(0, 0),
+                );
+
+                let kept: Temporary = self.temporary();
+                self.start_block(on_false, vec![kept]);
+                self.push(Operation::Drop(kept), Span::new(0, 0));
+                let value: Temporary = self.constant(Value::Bool(false));
+                self.finish_block(
+                    Terminator::Jump(Target {
+                        block: join,
+                        arguments: vec![value],
+                    }),
+                    Span::new(0, 0),
+                );
+
+                let destination: Temporary = self.temporary();
+                self.start_block(join, vec![destination]);
+                destination
+            }
+            ExpressionSyntaxEnum::Unary(unary) => {
+                let operand: Temporary = self.lower_expression(&unary.operand);
+
+                // Unary `+` gives back any value unchanged
+                if unary.operator_token != OperatorToken::Minus {
+                    return operand;
+                }
+
+                let destination: Temporary = self.temporary();
+                self.push(
+                    Operation::Negate(destination, operand),
+                    unary.operator_token.span(unary.position),
+                );
+                destination
+            }
+            ExpressionSyntaxEnum::ExpressionSyntax(binary) => {
+                let left: Temporary = self.lower_expression(&binary.left);
+                let right: Temporary = self.lower_expression(&binary.right);
+                let destination: Temporary = self.temporary();
+                self.push(
+                    Operation::Binary {
+                        destination,
+                        operator_token: binary.operator_token,
+                        left,
+                        right,
+                    },
+                    binary.operator_span,
+                );
+                destination
+            }
+        }
+    }
+
+    /// Lower a call, a wrong argument count or an unknown function fails before the arguments
+    /// are evaluated
+    fn lower_call(&mut self, call: &CallSyntax) -> Temporary {
+        let (parameters, callee): (Option<usize>, Callee) = match builtin(&call.name) {
+            Some((_, builtin)) => (builtin.parameters, Callee::Builtin),
+            None => match self.function_indices.get(&call.name) {
+                Some(&index) => (Some(self.parameter_counts[index]), Callee::Function(index)),
+                None => {
+                    let destination: Temporary = self.temporary();
+                    self.push(
+                        Operation::Fail(
+                            destination,
+                            RuntimeError::new(
+                                format!("undefined function '{}'", call.name),
+                                call.span,
+                            ),
+                        ),
+                        call.span,
+                    );
+                    return destination;
+                }
+            },
+        };
+
+        if let Some(parameters) =
+            parameters.filter(|parameters: &usize| *parameters != call.arguments.len())
+        {
+            let destination: Temporary = self.temporary();
+            self.push(
+                Operation::Fail(
+                    destination,
+                    argument_count_error(&call.name, parameters, call.arguments.len(), call.span),
+                ),
+                call.span,
+            );
+            return destination;
+        }
+
+        let arguments: Vec<Temporary> = call
+            .arguments
+            .iter()
+            .map(|argument: &ExpressionSyntaxEnum| self.lower_expression(argument))
+            .collect();
+        let destination: Temporary = self.temporary();
+        self.push(
+            Operation::Call {
+                destination,
+                callee,
+                site: CallSite {
+                    name: call.name.clone(),
+                    span: call.span,
+                    text: call.text.clone(),
+                    arguments: call.arguments.len(),
+                },
+                arguments,
+            },
+            call.span,
+        );
+        destination
+    }
+}
+
+/// Text of a string literal without `${NAME}` variables
+pub fn plain_text(segments: &[StringSegment]) -> Option<String> {
+    segments
+        .iter()
+        .map(|segment: &StringSegment| match segment {
+            StringSegment::Text(text) => Some(text.as_str()),
+            StringSegment::EnvironmentVariable { .. } => None,
+        })
+        .collect()
+}
+
+/// Text form of a module, for `ph build --emit ir`
+///
+/// ```text
+/// fn half(x):
+///   b0:
+///     t0 = load local 0
+///     t1 = const 2
+///     t2 = t0 / t1
+///     return t2
+/// ```
+pub fn format_module(module: &Module) -> String {
+    let mut output: String = String::new();
+
+    output.push_str("main:\n");
+    format_function(module, &module.main, &mut output);
+
+    for function in &module.functions {
+        output.push_str(&format!(
+            "fn {}({}):\n",
+            function.name,
+            function.parameters.join(", ")
+        ));
+        format_function(module, function, &mut output);
+    }
+
+    output
+}
+
+fn format_function(module: &Module, function: &Function, output: &mut String) {
+    for (id, block) in function.blocks.iter().enumerate() {
+        output.push_str(&format!("  b{}{}:\n", id, temporaries(&block.parameters)));
+
+        for operation in &block.operations {
+            output.push_str(&format!(
+                "    {}\n",
+                format_operation(module, function, operation)
+            ));
+        }
+
+        let terminator: String = match &block.terminator {
+            Terminator::Jump(target) => format!("jump {}", format_target(target)),
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => format!(
+                "branch t{}, {}, {}",
+                condition,
+                format_target(then),
+                format_target(otherwise)
+            ),
+            Terminator::Return(value) => format!("return t{}", value),
+        };
+        output.push_str(&format!("    {}\n", terminator));
+    }
+}
+
+fn format_operation(module: &Module, function: &Function, operation: &Operation) -> String {
+    let variable = |variable: &Variable| -> String {
+        match variable {
+            Variable::Local(slot) => match function.locals.get(*slot) {
+                Some(name) => format!("local {} ({})", slot, name),
+                None => format!("local {}", slot),
+            },
+            Variable::Global(name) => format!("global {}", name),
+        }
+    };
+
+    match operation {
+        Operation::Constant(destination, value) => format!(
+            "t{} = const {}",
+            destination,
+            crate::disasm::constant_text(value)
+        ),
+        Operation::Interpolate(destination, segments) => format!(
+            "t{} = interpolate {}",
+            destination,
+            crate::disasm::string_text(segments)
+        ),
+        Operation::Array(destination, elements) => {
+            format!("t{} = array [{}]", destination, join(elements))
+        }
+        Operation::Index {
+            destination,
+            target,
+            index,
+        } => format!("t{} = t{}[t{}]", destination, target, index),
+        Operation::Negate(destination, operand) => format!("t{} = -t{}", destination, operand),
+        Operation::Binary {
+            destination,
+            operator_token,
+            left,
+            right,
+        } => format!(
+            "t{} = t{} {} t{}",
+            destination,
+            left,
+            operator_token.text(),
+            right
+        ),
+        Operation::Compare {
+            destination,
+            operator_token,
+            left,
+            right,
+        } => format!(
+            "t{} = compare t{} {} t{}",
+            destination,
+            left,
+            operator_token.text(),
+            right
+        ),
+        Operation::Load(destination, source) => {
+            format!("t{} = load {}", destination, variable(source))
+        }
+        Operation::Store(destination, value) => {
+            format!("store {}, t{}", variable(destination), value)
+        }
+        Operation::Define(destination, value) => {
+            format!("define {}, t{}", variable(destination), value)
+        }
+        Operation::Call {
+            destination,
+            callee,
+            site,
+            arguments,
+        } => {
+            let name: &str = match callee {
+                Callee::Function(index) => &module.functions[*index].name,
+                Callee::Builtin => &site.name,
+            };
+            format!("t{} = call {}({})", destination, name, join(arguments))
+        }
+        Operation::Yield(value) => format!("yield t{}", value),
+        Operation::Drop(value) => format!("drop t{}", value),
+        Operation::Fail(destination, error) => {
+            format!("t{} = fail {:?}", destination, error.message)
+        }
+    }
+}
+
+fn format_target(target: &Target) -> String {
+    format!("b{}{}", target.block, temporaries(&target.arguments))
+}
+
+/// `(t1, t2)`, or nothing when there are none
+fn temporaries(temporaries: &[Temporary]) -> String {
+    if temporaries.is_empty() {
+        String::new()
+    } else {
+        format!("({})", join(temporaries))
+    }
+}
+
+/// `t1, t2`
+fn join(temporaries: &[Temporary]) -> String {
+    let temporaries: Vec<String> = temporaries
+        .iter()
+        .map(|temporary: &Temporary| format!("t{}", temporary))
+        .collect();
+    temporaries.join(", ")
+}
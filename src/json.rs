@@ -0,0 +1,115 @@
+use crate::diagnostic::{line_column, locate, Diagnostic, SourceFile, Span};
+use crate::eval::Value;
+use crate::run::FloatFormat;
+
+/// Outcome of a run as one line of JSON, for programs calling `ph`
+///
+/// ```text
+/// {"ok": true, "value": 7}
+/// {"ok": false, "error": {"severity": "runtime error", "message": "division by zero", ...}}
+/// ```
+///
+/// A program without a result gives `"value": null`. `float_format` applies to a float
+/// result like it does without JSON.
+pub fn format_result(
+    result: &Result<Option<Value>, Diagnostic>,
+    float_format: FloatFormat,
+    file_name: &str,
+    imports: &[SourceFile],
+    source: &str,
+) -> String {
+    match result {
+        Ok(value) => {
+            let value: String = match value {
+                Some(Value::Float(value)) if value.is_finite() => float_format.format(*value),
+                Some(value) => format_value(value),
+                None => "null".to_string(),
+            };
+            format!("{{\"ok\": true, \"value\": {}}}", value)
+        }
+        Err(diagnostic) => format!(
+            "{{\"ok\": false, \"error\": {}}}",
+            format_diagnostic(diagnostic, file_name, imports, source)
+        ),
+    }
+}
+
+/// Value as JSON, nil is `null` and floats JSON can't hold, like `inf`, are strings
+pub fn format_value(value: &Value) -> String {
+    match value {
+        Value::Int(value) => value.to_string(),
+        Value::Float(value) if value.is_finite() => format!("{:?}", value),
+        Value::Float(value) => format_string(&format!("{:?}", value)),
+        Value::String(value) => format_string(value),
+        Value::Bool(value) => value.to_string(),
+        Value::Array(elements) => {
+            let elements: Vec<String> = elements.iter().map(format_value).collect();
+            format!("[{}]", elements.join(", "))
+        }
+        Value::Nil => "null".to_string(),
+    }
+}
+
+/// Diagnostic as a JSON object, lines and columns are 1-based like in rendered diagnostics
+/// and `start` and `end` are byte offsets into the file the diagnostic is in
+pub fn format_diagnostic(
+    diagnostic: &Diagnostic,
+    file_name: &str,
+    imports: &[SourceFile],
+    source: &str,
+) -> String {
+    let trace: Vec<String> = diagnostic
+        .trace
+        .iter()
+        .map(|(name, call_span): &(String, Span)| {
+            let (file_name, source, start): (&str, &str, usize) =
+                locate(file_name, imports, source, call_span.start);
+            let (line, column): (usize, usize) = line_column(source, call_span.start - start);
+            format!(
+                "{{\"function\": {}, \"file\": {}, \"line\": {}, \"column\": {}}}",
+                format_string(name),
+                format_string(file_name),
+                line,
+                column
+            )
+        })
+        .collect();
+    let (file_name, source, start): (&str, &str, usize) =
+        locate(file_name, imports, source, diagnostic.span.start);
+    let (line, column): (usize, usize) = line_column(source, diagnostic.span.start - start);
+
+    format!(
+        "{{\"severity\": {}, \"message\": {}, \"file\": {}, \"line\": {}, \"column\": {}, \"start\": {}, \"end\": {}, \"trace\": [{}]}}",
+        format_string(&diagnostic.severity.to_string()),
+        format_string(&diagnostic.message),
+        format_string(file_name),
+        line,
+        column,
+        diagnostic.span.start - start,
+        diagnostic.span.end.saturating_sub(start),
+        trace.join(", ")
+    )
+}
+
+/// Quoted JSON string
+pub fn format_string(text: &str) -> String {
+    let mut output: String = String::with_capacity(text.len() + 2);
+    output.push('"');
+
+    for character in text.chars() {
+        match character {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            character if character.is_control() => {
+                output.push_str(&format!("\\u{:04x}", character as u32))
+            }
+            character => output.push(character),
+        }
+    }
+
+    output.push('"');
+    output
+}
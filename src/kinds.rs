@@ -0,0 +1,357 @@
+use crate::diagnostic::{Diagnostic, Span};
+use crate::eval::Value;
+use crate::ir::{Callee, Function, Module, Operation, Target, Temporary, Terminator, Variable};
+use crate::parser::OperatorToken;
+
+/// What a temporary holds, the native backends only compile programs where it is known up
+/// front
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Int,
+    /// Result of a comparison
+    Bool,
+    /// Result of `print`
+    Nil,
+}
+
+impl Kind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::Int => "int",
+            Kind::Bool => "bool",
+            Kind::Nil => "nil",
+        }
+    }
+}
+
+/// Kinds of the temporaries of every function of a module, indexed by temporary
+#[derive(Clone, Debug, PartialEq)]
+pub struct Kinds {
+    pub main: Vec<Kind>,
+    pub functions: Vec<Vec<Kind>>,
+    /// Globals some `let` defines, sorted
+    pub globals: Vec<String>,
+}
+
+/// Check that a module only uses ints, bools and `print`, and find the kind of every
+/// temporary, for the backend called `backend` in errors
+///
+/// Variables, arguments and function results are ints, conditions are bools and both sides
+/// of a conditional give the same kind. Anything else fails, pointing at where it is used.
+pub fn check(module: &Module, backend: &str) -> Result<Kinds, Diagnostic> {
+    let mut globals: Vec<String> = std::iter::once(&module.main)
+        .chain(&module.functions)
+        .flat_map(|function: &Function| &function.blocks)
+        .flat_map(|block: &crate::ir::Block| &block.operations)
+        .filter_map(|operation: &Operation| match operation {
+            Operation::Define(Variable::Global(name), _) => Some(name.clone()),
+            _ => None,
+        })
+        .collect();
+    globals.sort();
+    globals.dedup();
+
+    let mut functions: Vec<Vec<Kind>> = Vec::new();
+    for function in &module.functions {
+        functions.push(Checker::new(function, backend, &globals).function(false)?);
+    }
+    let main: Vec<Kind> = Checker::new(&module.main, backend, &globals).function(true)?;
+
+    Ok(Kinds {
+        main,
+        functions,
+        globals,
+    })
+}
+
+/// Number of temporaries a function uses
+pub fn temporaries(function: &Function) -> usize {
+    function
+        .blocks
+        .iter()
+        .flat_map(|block: &crate::ir::Block| {
+            block.parameters.iter().copied().chain(
+                block
+                    .operations
+                    .iter()
+                    .filter_map(crate::c_backend::destination),
+            )
+        })
+        .map(|temporary: Temporary| temporary + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+struct Checker<'a> {
+    function: &'a Function,
+    backend: &'a str,
+    globals: &'a [String],
+    kinds: Vec<Option<Kind>>,
+    /// Source of the last operation with one, for errors about values that have none
+    span: Span,
+}
+
+impl<'a> Checker<'a> {
+    fn new(function: &'a Function, backend: &'a str, globals: &'a [String]) -> Checker<'a> {
+        Checker {
+            function,
+            backend,
+            globals,
+            kinds: vec![None; temporaries(function)],
+            span: Span::new(0, 0),
+        }
+    }
+
+    fn error(&self, message: String, span: Span) -> Diagnostic {
+        let span: Span = if span == Span::new(0, 0) {
+            self.span
+        } else {
+            span
+        };
+        Diagnostic::error(message, span)
+    }
+
+    /// Kind a temporary was given, a temporary without one never gets a value
+    fn kind(&self, temporary: Temporary) -> Kind {
+        self.kinds[temporary].unwrap_or(Kind::Int)
+    }
+
+    fn expect(&self, temporary: Temporary, expected: Kind, span: Span) -> Result<(), Diagnostic> {
+        match self.kinds[temporary] {
+            Some(kind) if kind != expected => Err(self.error(
+                format!(
+                    "the {} backend needs an {} here but found {}",
+                    self.backend,
+                    expected.name(),
+                    kind.name()
+                ),
+                span,
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn function(mut self, main: bool) -> Result<Vec<Kind>, Diagnostic> {
+        for block in &self.function.blocks {
+            for (operation, span) in block.operations.iter().zip(&block.spans) {
+                if *span != Span::new(0, 0) {
+                    self.span = *span;
+                }
+                self.operation(operation, *span)?;
+            }
+
+            match &block.terminator {
+                Terminator::Jump(target) => self.jump(target)?,
+                Terminator::Branch {
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    self.expect(*condition, Kind::Bool, block.terminator_span)?;
+                    self.jump(then)?;
+                    self.jump(otherwise)?;
+                }
+                Terminator::Return(value) => {
+                    if !main && self.kind(*value) != Kind::Int {
+                        return Err(self.error(
+                            format!(
+                                "the {} backend needs '{}' to return an int but it can return {}",
+                                self.backend,
+                                self.function.name,
+                                self.kind(*value).name()
+                            ),
+                            Span::new(0, 0),
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(self
+            .kinds
+            .iter()
+            .map(|kind: &Option<Kind>| kind.unwrap_or(Kind::Int))
+            .collect())
+    }
+
+    /// Values meeting after a conditional must be of one kind
+    fn jump(&mut self, target: &Target) -> Result<(), Diagnostic> {
+        let parameters: &[Temporary] = &self.function.blocks[target.block].parameters;
+        for (parameter, argument) in parameters.iter().zip(&target.arguments) {
+            match (self.kinds[*parameter], self.kinds[*argument]) {
+                (Some(kind), Some(argument_kind)) if kind != argument_kind => {
+                    return Err(self.error(
+                        format!(
+                            "the {} backend needs both sides of a conditional to give the same type, found {} and {}",
+                            self.backend,
+                            kind.name(),
+                            argument_kind.name()
+                        ),
+                        Span::new(0, 0),
+                    ));
+                }
+                _ => self.kinds[*parameter] = self.kinds[*argument],
+            }
+        }
+
+        Ok(())
+    }
+
+    fn operation(&mut self, operation: &Operation, span: Span) -> Result<(), Diagnostic> {
+        match operation {
+            Operation::Constant(destination, value) => {
+                let kind: Kind = match value {
+                    Value::Int(_) => Kind::Int,
+                    Value::Bool(_) => Kind::Bool,
+                    Value::Nil => Kind::Nil,
+                    value => {
+                        return Err(self.error(
+                            format!(
+                                "the {} backend only supports ints and bools, found a {}",
+                                self.backend,
+                                value.type_name()
+                            ),
+                            span,
+                        ))
+                    }
+                };
+                self.kinds[*destination] = Some(kind);
+            }
+            Operation::Interpolate(..) => {
+                return Err(self.error(
+                    format!(
+                        "the {} backend only supports ints and bools, found a string",
+                        self.backend
+                    ),
+                    span,
+                ))
+            }
+            Operation::Array(..) | Operation::Index { .. } => {
+                return Err(self.error(
+                    format!(
+                        "the {} backend only supports ints and bools, found an array",
+                        self.backend
+                    ),
+                    span,
+                ))
+            }
+            Operation::Negate(destination, operand) => {
+                self.expect(*operand, Kind::Int, span)?;
+                self.kinds[*destination] = Some(Kind::Int);
+            }
+            Operation::Binary {
+                destination,
+                operator_token,
+                left,
+                right,
+            }
+            | Operation::Compare {
+                destination,
+                operator_token,
+                left,
+                right,
+            } => {
+                let (left_kind, right_kind): (Kind, Kind) = (self.kind(*left), self.kind(*right));
+                let comparison: bool = matches!(
+                    operator_token,
+                    OperatorToken::Less
+                        | OperatorToken::LessEquals
+                        | OperatorToken::Greater
+                        | OperatorToken::GreaterEquals
+                        | OperatorToken::EqualsEquals
+                        | OperatorToken::BangEquals
+                );
+                let equality: bool = matches!(
+                    operator_token,
+                    OperatorToken::EqualsEquals | OperatorToken::BangEquals
+                );
+                let allowed: bool = match (left_kind, right_kind) {
+                    (Kind::Int, Kind::Int) => true,
+                    (Kind::Bool, Kind::Bool) => equality,
+                    _ => false,
+                };
+                if !allowed {
+                    return Err(self.error(
+                        format!(
+                            "cannot apply '{}' to {} and {}",
+                            operator_token.text(),
+                            left_kind.name(),
+                            right_kind.name()
+                        ),
+                        span,
+                    ));
+                }
+                self.kinds[*destination] = Some(if comparison { Kind::Bool } else { Kind::Int });
+            }
+            Operation::Load(destination, variable) => {
+                self.variable(variable, span)?;
+                self.kinds[*destination] = Some(Kind::Int);
+            }
+            Operation::Store(variable, value) | Operation::Define(variable, value) => {
+                if self.kind(*value) != Kind::Int {
+                    return Err(self.error(
+                        format!(
+                            "the {} backend only stores ints in variables, found {}",
+                            self.backend,
+                            self.kind(*value).name()
+                        ),
+                        span,
+                    ));
+                }
+                self.variable(variable, span)?;
+            }
+            Operation::Call {
+                destination,
+                callee: Callee::Function(_),
+                site,
+                arguments,
+            } => {
+                for argument in arguments {
+                    self.expect(*argument, Kind::Int, site.span)?;
+                }
+                self.kinds[*destination] = Some(Kind::Int);
+            }
+            Operation::Call {
+                destination,
+                callee: Callee::Builtin,
+                site,
+                arguments,
+            } => {
+                if site.name != "print" {
+                    return Err(self.error(
+                        format!(
+                            "the {} backend only supports the 'print' builtin, found '{}'",
+                            self.backend, site.name
+                        ),
+                        site.span,
+                    ));
+                }
+                for argument in arguments {
+                    if self.kind(*argument) == Kind::Nil {
+                        return Err(self.error(
+                            format!("the {} backend can't print nil", self.backend),
+                            Span::new(0, 0),
+                        ));
+                    }
+                }
+                self.kinds[*destination] = Some(Kind::Nil);
+            }
+            Operation::Yield(_) | Operation::Drop(_) => {}
+            Operation::Fail(_, error) => {
+                return Err(self.error(error.message.clone(), error.span));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// A global must be defined somewhere
+    fn variable(&self, variable: &Variable, span: Span) -> Result<(), Diagnostic> {
+        match variable {
+            Variable::Global(name) if !self.globals.contains(name) => {
+                Err(self.error(format!("undefined variable '{}'", name), span))
+            }
+            _ => Ok(()),
+        }
+    }
+}
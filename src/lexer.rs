@@ -0,0 +1,1092 @@
+use crate::diagnostic::Span;
+
+/// Lexer for the language
+#[derive(Clone, Debug)]
+pub struct Lexer {
+    text: String,
+    position: usize,
+    pub syntax_token: SyntaxToken,
+    /// Indentation of the open blocks when indentation delimits blocks, from `--layout`
+    layout: Option<Layout>,
+}
+
+/// Indentation a lexer in layout mode keeps track of to produce `Indent` and `Dedent`
+#[derive(Clone, Debug)]
+struct Layout {
+    /// Width of every open block, innermost last, the top level is 0
+    indents: Vec<usize>,
+    /// `Dedent` tokens still to produce
+    dedents: usize,
+    /// Start of a line between two outer levels, which gets an `Indent` after the dedents
+    misaligned: Option<usize>,
+    /// The next token starts a line, so its indentation is measured first
+    line_start: bool,
+    /// Brackets open, lines inside them continue the line they started on
+    brackets: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct SyntaxToken {
+    pub text: String,
+    pub token_type: SyntaxTokenType,
+    /// Byte offset of the first character of the token
+    pub position: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SyntaxTokenType {
+    WhiteSpace,
+    /// `//` up to the end of the line, trivia like white space that `ph fmt` keeps
+    Comment,
+    NewLine,
+    /// Start of a line indented deeper than the one before, only in layout mode
+    Indent,
+    /// Empty token where a line goes back out of an indented block, only in layout mode
+    Dedent,
+    Number(std::result::Result<IntegerLiteral, IntegerLiteralError>),
+    Float(std::result::Result<f64, HexFloatError>),
+    String(std::result::Result<Vec<StringSegment>, StringLiteralError>),
+    Identifier,
+    LetKeyword,
+    FnKeyword,
+    ImportKeyword,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Ampersand,
+    AmpersandAmpersand,
+    Pipe,
+    PipePipe,
+    Caret,
+    LessLess,
+    GreaterGreater,
+    Less,
+    LessEquals,
+    Greater,
+    GreaterEquals,
+    EqualsEquals,
+    BangEquals,
+    /// `=<`, never valid, lexed as one token so the error can suggest `<=`
+    EqualsLess,
+    /// `=!`, never valid, lexed as one token so the error can suggest `!=`
+    EqualsBang,
+    Question,
+    Colon,
+    Equals,
+    Semicolon,
+    Comma,
+    Dot,
+    OpenParenthesis,
+    CloseParenthesis,
+    OpenBrace,
+    CloseBrace,
+    OpenBracket,
+    CloseBracket,
+    BadToken,
+    EndOfFile,
+}
+
+/// Value of an integer literal and the type its suffix asks for, like `255u8`
+#[derive(Clone, Debug, PartialEq)]
+pub struct IntegerLiteral {
+    pub value: isize,
+    /// `None` without a suffix
+    pub integer_type: Option<IntegerType>,
+}
+
+/// Integer type an integer literal suffix names, every one is an int for now
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IntegerType {
+    I8,
+    I16,
+    I32,
+    I64,
+    Isize,
+    U8,
+    U16,
+    U32,
+    U64,
+    Usize,
+}
+
+impl IntegerType {
+    pub const ALL: [IntegerType; 10] = [
+        IntegerType::I8,
+        IntegerType::I16,
+        IntegerType::I32,
+        IntegerType::I64,
+        IntegerType::Isize,
+        IntegerType::U8,
+        IntegerType::U16,
+        IntegerType::U32,
+        IntegerType::U64,
+        IntegerType::Usize,
+    ];
+
+    /// Suffix naming the type, like `u8`
+    pub fn name(&self) -> &'static str {
+        match self {
+            IntegerType::I8 => "i8",
+            IntegerType::I16 => "i16",
+            IntegerType::I32 => "i32",
+            IntegerType::I64 => "i64",
+            IntegerType::Isize => "isize",
+            IntegerType::U8 => "u8",
+            IntegerType::U16 => "u16",
+            IntegerType::U32 => "u32",
+            IntegerType::U64 => "u64",
+            IntegerType::Usize => "usize",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<IntegerType> {
+        IntegerType::ALL
+            .into_iter()
+            .find(|integer_type: &IntegerType| integer_type.name() == name)
+    }
+
+    /// Smallest and largest value of the type
+    pub fn range(&self) -> (i128, i128) {
+        match self {
+            IntegerType::I8 => (i8::MIN as i128, i8::MAX as i128),
+            IntegerType::I16 => (i16::MIN as i128, i16::MAX as i128),
+            IntegerType::I32 => (i32::MIN as i128, i32::MAX as i128),
+            IntegerType::I64 => (i64::MIN as i128, i64::MAX as i128),
+            IntegerType::Isize => (isize::MIN as i128, isize::MAX as i128),
+            IntegerType::U8 => (0, u8::MAX as i128),
+            IntegerType::U16 => (0, u16::MAX as i128),
+            IntegerType::U32 => (0, u32::MAX as i128),
+            IntegerType::U64 => (0, u64::MAX as i128),
+            IntegerType::Usize => (0, usize::MAX as i128),
+        }
+    }
+}
+
+/// Error produced when an integer literal is malformed
+#[derive(Clone, Debug, PartialEq)]
+pub enum IntegerLiteralError {
+    /// The digits don't make an int
+    Invalid(std::num::ParseIntError),
+    UnknownSuffix(String),
+    /// The value doesn't fit in the type the suffix names
+    OutOfRange {
+        value: isize,
+        integer_type: IntegerType,
+    },
+    /// The value is the smallest of a signed type without its minus, like the `128` of
+    /// `-128i8`, it only fits right after a unary minus, `None` for an int without a suffix
+    Minimum {
+        integer_type: Option<IntegerType>,
+    },
+}
+
+impl std::fmt::Display for IntegerLiteralError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntegerLiteralError::Invalid(error) => write!(f, "{}", error),
+            IntegerLiteralError::UnknownSuffix(suffix) => {
+                let names: Vec<&str> = IntegerType::ALL.iter().map(IntegerType::name).collect();
+                write!(
+                    f,
+                    "unknown integer suffix '{}', expected one of {}",
+                    suffix,
+                    names.join(", ")
+                )
+            }
+            IntegerLiteralError::OutOfRange {
+                value,
+                integer_type,
+            } => {
+                let (min, max): (i128, i128) = integer_type.range();
+                write!(
+                    f,
+                    "{} doesn't fit in {}, which goes from {} to {}",
+                    value,
+                    integer_type.name(),
+                    min,
+                    max
+                )
+            }
+            IntegerLiteralError::Minimum { integer_type } => {
+                let (min, max): (i128, i128) = integer_type.map_or(
+                    (isize::MIN as i128, isize::MAX as i128),
+                    |integer_type: IntegerType| integer_type.range(),
+                );
+                write!(
+                    f,
+                    "{} doesn't fit in {}, which goes from {} to {}",
+                    -min,
+                    integer_type.map_or("int", |integer_type: IntegerType| integer_type.name()),
+                    min,
+                    max
+                )
+            }
+        }
+    }
+}
+
+/// Error produced when a hexadecimal float literal is malformed
+#[derive(Clone, Debug, PartialEq)]
+pub enum HexFloatError {
+    MissingDigits,
+    MissingExponent,
+    MissingExponentDigits,
+    ExponentOutOfRange,
+}
+
+impl std::fmt::Display for HexFloatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HexFloatError::MissingDigits => {
+                write!(f, "hexadecimal float has no digits")
+            }
+            HexFloatError::MissingExponent => {
+                write!(
+                    f,
+                    "hexadecimal float requires a 'p' exponent, e.g. '0x1.8p3'"
+                )
+            }
+            HexFloatError::MissingExponentDigits => {
+                write!(f, "hexadecimal float exponent has no digits")
+            }
+            HexFloatError::ExponentOutOfRange => {
+                write!(f, "hexadecimal float exponent is out of range")
+            }
+        }
+    }
+}
+
+/// Piece of a string literal, either plain text or a `${NAME}` interpolation
+#[derive(Clone, Debug, PartialEq)]
+pub enum StringSegment {
+    Text(String),
+    /// Environment variable substituted at evaluation time, `position` is the byte offset of `${`
+    EnvironmentVariable {
+        name: String,
+        position: usize,
+    },
+}
+
+/// Error produced when a string literal is malformed
+#[derive(Clone, Debug, PartialEq)]
+pub enum StringLiteralError {
+    Unterminated,
+    UnterminatedInterpolation,
+    InvalidVariableName(String),
+    UnknownEscape(char),
+    /// `\u` not followed by one to six hex digits in braces
+    MalformedUnicodeEscape,
+    /// `\u{...}` of a surrogate or a number above `0x10FFFF`
+    InvalidCodePoint(u32),
+}
+
+impl std::fmt::Display for StringLiteralError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StringLiteralError::Unterminated => {
+                write!(f, "string literal is missing a closing '\"'")
+            }
+            StringLiteralError::UnterminatedInterpolation => {
+                write!(f, "'${{' in string literal is missing a closing '}}'")
+            }
+            StringLiteralError::InvalidVariableName(name) => {
+                write!(f, "'{}' is not a valid environment variable name", name)
+            }
+            StringLiteralError::UnknownEscape(character) => {
+                write!(f, "unknown escape sequence '\\{}'", character)
+            }
+            StringLiteralError::MalformedUnicodeEscape => {
+                write!(
+                    f,
+                    "'\\u' must be followed by a hex code point in braces, like '\\u{{1F600}}'"
+                )
+            }
+            StringLiteralError::InvalidCodePoint(code_point) => {
+                write!(
+                    f,
+                    "'\\u{{{:X}}}' is not a valid unicode character",
+                    code_point
+                )
+            }
+        }
+    }
+}
+
+impl SyntaxTokenType {
+    /// Name of the token type without its value
+    pub fn name(&self) -> &'static str {
+        match self {
+            SyntaxTokenType::WhiteSpace => "WhiteSpace",
+            SyntaxTokenType::Comment => "Comment",
+            SyntaxTokenType::NewLine => "NewLine",
+            SyntaxTokenType::Indent => "Indent",
+            SyntaxTokenType::Dedent => "Dedent",
+            SyntaxTokenType::Number(_) => "Number",
+            SyntaxTokenType::Float(_) => "Float",
+            SyntaxTokenType::String(_) => "String",
+            SyntaxTokenType::Identifier => "Identifier",
+            SyntaxTokenType::LetKeyword => "LetKeyword",
+            SyntaxTokenType::FnKeyword => "FnKeyword",
+            SyntaxTokenType::ImportKeyword => "ImportKeyword",
+            SyntaxTokenType::Plus => "Plus",
+            SyntaxTokenType::Minus => "Minus",
+            SyntaxTokenType::Star => "Star",
+            SyntaxTokenType::Slash => "Slash",
+            SyntaxTokenType::Percent => "Percent",
+            SyntaxTokenType::Ampersand => "Ampersand",
+            SyntaxTokenType::AmpersandAmpersand => "AmpersandAmpersand",
+            SyntaxTokenType::Pipe => "Pipe",
+            SyntaxTokenType::PipePipe => "PipePipe",
+            SyntaxTokenType::Caret => "Caret",
+            SyntaxTokenType::LessLess => "LessLess",
+            SyntaxTokenType::GreaterGreater => "GreaterGreater",
+            SyntaxTokenType::Less => "Less",
+            SyntaxTokenType::LessEquals => "LessEquals",
+            SyntaxTokenType::Greater => "Greater",
+            SyntaxTokenType::GreaterEquals => "GreaterEquals",
+            SyntaxTokenType::EqualsEquals => "EqualsEquals",
+            SyntaxTokenType::BangEquals => "BangEquals",
+            SyntaxTokenType::EqualsLess => "EqualsLess",
+            SyntaxTokenType::EqualsBang => "EqualsBang",
+            SyntaxTokenType::Question => "Question",
+            SyntaxTokenType::Colon => "Colon",
+            SyntaxTokenType::Equals => "Equals",
+            SyntaxTokenType::Semicolon => "Semicolon",
+            SyntaxTokenType::Comma => "Comma",
+            SyntaxTokenType::Dot => "Dot",
+            SyntaxTokenType::OpenParenthesis => "OpenParenthesis",
+            SyntaxTokenType::CloseParenthesis => "CloseParenthesis",
+            SyntaxTokenType::OpenBrace => "OpenBrace",
+            SyntaxTokenType::CloseBrace => "CloseBrace",
+            SyntaxTokenType::OpenBracket => "OpenBracket",
+            SyntaxTokenType::CloseBracket => "CloseBracket",
+            SyntaxTokenType::BadToken => "BadToken",
+            SyntaxTokenType::EndOfFile => "EndOfFile",
+        }
+    }
+}
+
+impl SyntaxTokenType {
+    /// How an error message refers to a token of this type, like `'{'` or `a number`
+    pub fn description(&self) -> &'static str {
+        match self {
+            SyntaxTokenType::WhiteSpace => "white space",
+            SyntaxTokenType::Comment => "a comment",
+            SyntaxTokenType::NewLine => "a new line",
+            SyntaxTokenType::Indent => "an indented line",
+            SyntaxTokenType::Dedent => "the end of the indented block",
+            SyntaxTokenType::Number(_) => "a number",
+            SyntaxTokenType::Float(_) => "a float",
+            SyntaxTokenType::String(_) => "a string",
+            SyntaxTokenType::Identifier => "a name",
+            SyntaxTokenType::LetKeyword => "'let'",
+            SyntaxTokenType::FnKeyword => "'fn'",
+            SyntaxTokenType::ImportKeyword => "'import'",
+            SyntaxTokenType::Plus => "'+'",
+            SyntaxTokenType::Minus => "'-'",
+            SyntaxTokenType::Star => "'*'",
+            SyntaxTokenType::Slash => "'/'",
+            SyntaxTokenType::Percent => "'%'",
+            SyntaxTokenType::Ampersand => "'&'",
+            SyntaxTokenType::AmpersandAmpersand => "'&&'",
+            SyntaxTokenType::Pipe => "'|'",
+            SyntaxTokenType::PipePipe => "'||'",
+            SyntaxTokenType::Caret => "'^'",
+            SyntaxTokenType::LessLess => "'<<'",
+            SyntaxTokenType::GreaterGreater => "'>>'",
+            SyntaxTokenType::Less => "'<'",
+            SyntaxTokenType::LessEquals => "'<='",
+            SyntaxTokenType::Greater => "'>'",
+            SyntaxTokenType::GreaterEquals => "'>='",
+            SyntaxTokenType::EqualsEquals => "'=='",
+            SyntaxTokenType::BangEquals => "'!='",
+            SyntaxTokenType::EqualsLess => "'=<'",
+            SyntaxTokenType::EqualsBang => "'=!'",
+            SyntaxTokenType::Question => "'?'",
+            SyntaxTokenType::Colon => "':'",
+            SyntaxTokenType::Equals => "'='",
+            SyntaxTokenType::Semicolon => "';'",
+            SyntaxTokenType::Comma => "','",
+            SyntaxTokenType::Dot => "'.'",
+            SyntaxTokenType::OpenParenthesis => "'('",
+            SyntaxTokenType::CloseParenthesis => "')'",
+            SyntaxTokenType::OpenBrace => "'{'",
+            SyntaxTokenType::CloseBrace => "'}'",
+            SyntaxTokenType::OpenBracket => "'['",
+            SyntaxTokenType::CloseBracket => "']'",
+            SyntaxTokenType::BadToken => "an unknown character",
+            SyntaxTokenType::EndOfFile => "the end of the input",
+        }
+    }
+}
+
+impl SyntaxToken {
+    /// Source range covered by the token
+    pub fn span(&self) -> Span {
+        Span::new(self.position, self.position + self.text.len())
+    }
+}
+
+impl Lexer {
+    pub fn new(text: String) -> Lexer {
+        Lexer {
+            text,
+            position: 0,
+            syntax_token: SyntaxToken {
+                text: "".to_string(),
+                token_type: SyntaxTokenType::BadToken,
+                position: 0,
+            },
+            layout: None,
+        }
+    }
+
+    /// Lexer where indentation delimits blocks, every line indented deeper than the one
+    /// before starts with an `Indent`, and every block it goes back out of ends with a `Dedent`
+    ///
+    /// Indentation inside brackets and braces doesn't count, so braces still work.
+    pub fn with_layout(text: String) -> Lexer {
+        Lexer {
+            layout: Some(Layout {
+                indents: vec![0],
+                dedents: 0,
+                misaligned: None,
+                line_start: true,
+                brackets: 0,
+            }),
+            ..Lexer::new(text)
+        }
+    }
+
+    /// Source text being lexed
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Start lexing again from the beginning of the text, in the same mode
+    pub fn reset(&mut self) {
+        let text: String = std::mem::take(&mut self.text);
+        *self = match self.layout {
+            Some(_) => Lexer::with_layout(text),
+            None => Lexer::new(text),
+        };
+    }
+
+    /// Get the current character in the text
+    fn current(&self) -> char {
+        self.peek(0)
+    }
+
+    /// Get the character `offset` characters after the current one
+    fn peek(&self, offset: usize) -> char {
+        self.text[self.position..]
+            .chars()
+            .nth(offset)
+            .unwrap_or('\0')
+    }
+
+    /// Move past the current character
+    fn advance(&mut self) {
+        self.position += self.current().len_utf8();
+    }
+
+    /// Store the token spanning from `start` to the current position
+    fn finish(&mut self, start: usize, token_type: SyntaxTokenType) {
+        self.syntax_token = SyntaxToken {
+            text: self.text[start..self.position].to_string(),
+            token_type,
+            position: start,
+        };
+    }
+
+    /// Consume `length` ASCII characters as a single token
+    fn punctuation(&mut self, length: usize, token_type: SyntaxTokenType) {
+        let start: usize = self.position;
+        self.position += length;
+        self.finish(start, token_type);
+    }
+
+    /// Get the next token in the text
+    pub fn next_token(&mut self) {
+        match self.layout.take() {
+            Some(mut layout) => {
+                self.next_layout_token(&mut layout);
+                self.layout = Some(layout);
+            }
+            None => self.next_plain_token(),
+        }
+    }
+
+    /// Get the next token, or the `Indent` or `Dedent` the indentation of a line calls for
+    fn next_layout_token(&mut self, layout: &mut Layout) {
+        if layout.dedents > 0 {
+            layout.dedents -= 1;
+            self.finish(self.position, SyntaxTokenType::Dedent);
+            return;
+        }
+        if let Some(start) = layout.misaligned.take() {
+            self.finish(start, SyntaxTokenType::Indent);
+            return;
+        }
+
+        if layout.line_start {
+            layout.line_start = false;
+
+            let start: usize = self.position;
+            let width: usize = self.text[start..]
+                .chars()
+                .take_while(|character: &char| *character == ' ' || *character == '\t')
+                .count();
+            let indent: usize = *layout
+                .indents
+                .last()
+                .expect("The top level is never closed");
+
+            // Blank lines and lines with only a comment don't change the indentation
+            let blank: bool = matches!(self.peek(width), '\n' | '\r' | '\0')
+                || self.text[start + width..].starts_with("//");
+            if !blank && width > indent {
+                layout.indents.push(width);
+                self.position += width;
+                self.finish(start, SyntaxTokenType::Indent);
+                return;
+            }
+            if !blank && width < indent {
+                while layout
+                    .indents
+                    .last()
+                    .is_some_and(|indent: &usize| *indent > width)
+                {
+                    layout.indents.pop();
+                    layout.dedents += 1;
+                }
+                // Deeper than the block it returns to, so it opens a new one
+                if layout.indents.last() != Some(&width) {
+                    layout.indents.push(width);
+                    layout.misaligned = Some(start);
+                }
+
+                self.position += width;
+                layout.dedents -= 1;
+                self.finish(self.position, SyntaxTokenType::Dedent);
+                return;
+            }
+        }
+
+        self.next_plain_token();
+
+        match self.syntax_token.token_type {
+            SyntaxTokenType::NewLine if layout.brackets == 0 => layout.line_start = true,
+            SyntaxTokenType::OpenParenthesis
+            | SyntaxTokenType::OpenBracket
+            | SyntaxTokenType::OpenBrace => layout.brackets += 1,
+            SyntaxTokenType::CloseParenthesis
+            | SyntaxTokenType::CloseBracket
+            | SyntaxTokenType::CloseBrace => layout.brackets = layout.brackets.saturating_sub(1),
+            // Every block still open ends before the end of the file
+            SyntaxTokenType::EndOfFile if layout.indents.len() > 1 => {
+                layout.dedents = layout.indents.len() - 2;
+                layout.indents.truncate(1);
+                self.finish(self.position, SyntaxTokenType::Dedent);
+            }
+            _ => {}
+        }
+    }
+
+    /// Get the next token in the text, ignoring layout
+    fn next_plain_token(&mut self) {
+        let start: usize = self.position;
+
+        // End of file
+        if self.position >= self.text.len() {
+            self.finish(start, SyntaxTokenType::EndOfFile);
+            return;
+        // New line
+        } else if self.current() == '\n' {
+            self.advance();
+            self.finish(start, SyntaxTokenType::NewLine);
+            return;
+        // Whitespace
+        } else if self.current().is_whitespace() {
+            while self.current().is_whitespace() && self.current() != '\n' {
+                self.advance();
+            }
+
+            self.finish(start, SyntaxTokenType::WhiteSpace);
+            return;
+        // Comment, the new line after it is a token of its own
+        } else if self.current() == '/' && self.peek(1) == '/' {
+            while self.position < self.text.len() && self.current() != '\n' {
+                self.advance();
+            }
+            // A `\r\n` line ending isn't part of the comment
+            if self.text[..self.position].ends_with('\r') {
+                self.position -= 1;
+            }
+
+            self.finish(start, SyntaxTokenType::Comment);
+            return;
+        // Line continuation, a `\` at the end of a line joins it with the next one
+        } else if self.current() == '\\'
+            && (self.peek(1) == '\n' || (self.peek(1) == '\r' && self.peek(2) == '\n'))
+        {
+            while self.current() != '\n' {
+                self.advance();
+            }
+            self.advance();
+
+            self.finish(start, SyntaxTokenType::WhiteSpace);
+            return;
+        // Hexadecimal number
+        } else if self.current() == '0' && (self.peek(1) == 'x' || self.peek(1) == 'X') {
+            self.hexadecimal();
+            return;
+        // Number
+        } else if self.current().is_ascii_digit() {
+            self.decimal();
+            return;
+        // String
+        } else if self.current() == '"' {
+            let segments: std::result::Result<Vec<StringSegment>, StringLiteralError> =
+                self.string();
+
+            self.finish(start, SyntaxTokenType::String(segments));
+            return;
+        // Identifier or keyword
+        } else if self.current().is_alphabetic() || self.current() == '_' {
+            while self.current().is_alphanumeric() || self.current() == '_' {
+                self.advance();
+            }
+
+            let token_type: SyntaxTokenType = match &self.text[start..self.position] {
+                "let" => SyntaxTokenType::LetKeyword,
+                "fn" => SyntaxTokenType::FnKeyword,
+                "import" => SyntaxTokenType::ImportKeyword,
+                _ => SyntaxTokenType::Identifier,
+            };
+
+            self.finish(start, token_type);
+            return;
+        }
+
+        // Operators
+        match self.current() {
+            '+' => self.punctuation(1, SyntaxTokenType::Plus),
+            '-' => self.punctuation(1, SyntaxTokenType::Minus),
+            '*' => self.punctuation(1, SyntaxTokenType::Star),
+            '/' => self.punctuation(1, SyntaxTokenType::Slash),
+            '%' => self.punctuation(1, SyntaxTokenType::Percent),
+            '&' if self.peek(1) == '&' => self.punctuation(2, SyntaxTokenType::AmpersandAmpersand),
+            '&' => self.punctuation(1, SyntaxTokenType::Ampersand),
+            '|' if self.peek(1) == '|' => self.punctuation(2, SyntaxTokenType::PipePipe),
+            '|' => self.punctuation(1, SyntaxTokenType::Pipe),
+            '^' => self.punctuation(1, SyntaxTokenType::Caret),
+            '<' if self.peek(1) == '<' => self.punctuation(2, SyntaxTokenType::LessLess),
+            '<' if self.peek(1) == '=' => self.punctuation(2, SyntaxTokenType::LessEquals),
+            '<' => self.punctuation(1, SyntaxTokenType::Less),
+            '>' if self.peek(1) == '>' => self.punctuation(2, SyntaxTokenType::GreaterGreater),
+            '>' if self.peek(1) == '=' => self.punctuation(2, SyntaxTokenType::GreaterEquals),
+            '>' => self.punctuation(1, SyntaxTokenType::Greater),
+            '=' if self.peek(1) == '=' => self.punctuation(2, SyntaxTokenType::EqualsEquals),
+            '=' if self.peek(1) == '<' => self.punctuation(2, SyntaxTokenType::EqualsLess),
+            '=' if self.peek(1) == '!' => self.punctuation(2, SyntaxTokenType::EqualsBang),
+            '=' => self.punctuation(1, SyntaxTokenType::Equals),
+            '!' if self.peek(1) == '=' => self.punctuation(2, SyntaxTokenType::BangEquals),
+            '?' => self.punctuation(1, SyntaxTokenType::Question),
+            ':' => self.punctuation(1, SyntaxTokenType::Colon),
+            ';' => self.punctuation(1, SyntaxTokenType::Semicolon),
+            ',' => self.punctuation(1, SyntaxTokenType::Comma),
+            '.' => self.punctuation(1, SyntaxTokenType::Dot),
+            '(' => self.punctuation(1, SyntaxTokenType::OpenParenthesis),
+            ')' => self.punctuation(1, SyntaxTokenType::CloseParenthesis),
+            '{' => self.punctuation(1, SyntaxTokenType::OpenBrace),
+            '}' => self.punctuation(1, SyntaxTokenType::CloseBrace),
+            '[' => self.punctuation(1, SyntaxTokenType::OpenBracket),
+            ']' => self.punctuation(1, SyntaxTokenType::CloseBracket),
+            _ => {
+                self.advance();
+                self.finish(start, SyntaxTokenType::BadToken);
+            }
+        }
+    }
+
+    /// Lex a string literal, splitting out `${NAME}` interpolations
+    ///
+    /// On error the rest of the line is consumed so lexing can continue after the literal
+    fn string(&mut self) -> std::result::Result<Vec<StringSegment>, StringLiteralError> {
+        let mut segments: Vec<StringSegment> = Vec::new();
+        let mut text: String = String::new();
+
+        // Skip opening `"`
+        self.advance();
+
+        loop {
+            if self.position >= self.text.len() || self.current() == '\n' {
+                return Err(StringLiteralError::Unterminated);
+            }
+
+            match self.current() {
+                '"' => {
+                    self.advance();
+                    break;
+                }
+                '\\' => {
+                    self.advance();
+                    text.push(match self.current() {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        '0' => '\0',
+                        '\\' => '\\',
+                        '"' => '"',
+                        '$' => '$',
+                        'u' => match self.unicode_escape() {
+                            Ok(character) => character,
+                            Err(error) => {
+                                self.skip_line();
+                                return Err(error);
+                            }
+                        },
+                        character => {
+                            self.skip_line();
+                            return Err(StringLiteralError::UnknownEscape(character));
+                        }
+                    });
+                    self.advance();
+                }
+                '$' if self.peek(1) == '{' => {
+                    let position: usize = self.position;
+                    self.position += 2;
+
+                    let name_start: usize = self.position;
+                    while self.current() != '}' {
+                        if self.current() == '"' || self.current() == '\n' || self.current() == '\0'
+                        {
+                            self.skip_line();
+                            return Err(StringLiteralError::UnterminatedInterpolation);
+                        }
+                        self.advance();
+                    }
+                    let name: String = self.text[name_start..self.position].to_string();
+                    self.advance();
+
+                    if name.is_empty()
+                        || !name
+                            .chars()
+                            .all(|character: char| character.is_alphanumeric() || character == '_')
+                    {
+                        self.skip_line();
+                        return Err(StringLiteralError::InvalidVariableName(name));
+                    }
+
+                    if !text.is_empty() {
+                        segments.push(StringSegment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(StringSegment::EnvironmentVariable { name, position });
+                }
+                character => {
+                    text.push(character);
+                    self.advance();
+                }
+            }
+        }
+
+        if !text.is_empty() || segments.is_empty() {
+            segments.push(StringSegment::Text(text));
+        }
+
+        Ok(segments)
+    }
+
+    /// Read the `{1F600}` of a `\u{1F600}` escape starting at the `u`, stopping on the `}`
+    fn unicode_escape(&mut self) -> std::result::Result<char, StringLiteralError> {
+        self.advance();
+        if self.current() != '{' {
+            return Err(StringLiteralError::MalformedUnicodeEscape);
+        }
+        self.advance();
+
+        let start: usize = self.position;
+        while self.current().is_ascii_hexdigit() {
+            self.advance();
+        }
+        let digits: &str = &self.text[start..self.position];
+        if self.current() != '}' || digits.is_empty() || digits.len() > 6 {
+            return Err(StringLiteralError::MalformedUnicodeEscape);
+        }
+
+        let code_point: u32 =
+            u32::from_str_radix(digits, 16).expect("At most six hex digits fit in a u32");
+        char::from_u32(code_point).ok_or(StringLiteralError::InvalidCodePoint(code_point))
+    }
+
+    /// Move to the end of the current line
+    fn skip_line(&mut self) {
+        while self.position < self.text.len() && self.current() != '\n' {
+            self.advance();
+        }
+    }
+
+    /// Lex a decimal literal, either an integer (`12`) or a float (`1.5`, `1e3`, `2.5e-3`)
+    ///
+    /// A `.` or `e` only belongs to the number when a digit follows it, so `5.sqrt()` is
+    /// `5` followed by a method call while `5.0.sqrt()` is `5.0` followed by one
+    fn decimal(&mut self) {
+        let start: usize = self.position;
+        let mut is_float: bool = false;
+
+        while self.current().is_ascii_digit() {
+            self.advance();
+        }
+
+        // Fraction
+        if self.current() == '.' && self.peek(1).is_ascii_digit() {
+            is_float = true;
+            self.advance();
+            while self.current().is_ascii_digit() {
+                self.advance();
+            }
+        }
+
+        // Exponent
+        if self.current() == 'e' || self.current() == 'E' {
+            let sign_length: usize = if self.peek(1) == '+' || self.peek(1) == '-' {
+                1
+            } else {
+                0
+            };
+
+            if self.peek(1 + sign_length).is_ascii_digit() {
+                is_float = true;
+                self.position += 1 + sign_length;
+                while self.current().is_ascii_digit() {
+                    self.advance();
+                }
+            }
+        }
+
+        let text: &str = &self.text[start..self.position];
+        let token_type: SyntaxTokenType = if is_float {
+            // Only digits, `.`, `e`, and a sign were consumed, so this always parses
+            SyntaxTokenType::Float(Ok(text.parse::<f64>().expect("Failed to parse float")))
+        } else {
+            let digits: String = text.to_string();
+            SyntaxTokenType::Number(self.integer_suffix(&digits, 10))
+        };
+
+        self.finish(start, token_type);
+    }
+
+    /// Lex a `0x` prefixed literal, either an integer (`0x1F`) or a float (`0x1.8p3`)
+    fn hexadecimal(&mut self) {
+        let start: usize = self.position;
+
+        // Skip `0x`
+        self.position += 2;
+
+        let mantissa_start: usize = self.position;
+        while self.current().is_ascii_hexdigit() {
+            self.position += 1;
+        }
+        let integer_digits: String = self.text[mantissa_start..self.position].to_string();
+
+        // Fraction
+        let mut fraction_digits: String = String::new();
+        let mut is_float: bool = false;
+        if self.current() == '.' {
+            is_float = true;
+            self.position += 1;
+
+            let fraction_start: usize = self.position;
+            while self.current().is_ascii_hexdigit() {
+                self.position += 1;
+            }
+            fraction_digits = self.text[fraction_start..self.position].to_string();
+        }
+
+        // Exponent
+        let mut exponent: Option<std::result::Result<i32, HexFloatError>> = None;
+        if self.current() == 'p' || self.current() == 'P' {
+            is_float = true;
+            self.position += 1;
+
+            let exponent_start: usize = self.position;
+            if self.current() == '+' || self.current() == '-' {
+                self.position += 1;
+            }
+            while self.current().is_ascii_digit() {
+                self.position += 1;
+            }
+
+            let exponent_text: &str = &self.text[exponent_start..self.position];
+            exponent = Some(if exponent_text.trim_start_matches(['+', '-']).is_empty() {
+                Err(HexFloatError::MissingExponentDigits)
+            } else {
+                exponent_text
+                    .parse::<i32>()
+                    .map_err(|_| HexFloatError::ExponentOutOfRange)
+            });
+        }
+
+        let token_type: SyntaxTokenType = if is_float {
+            SyntaxTokenType::Float(hex_float_value(&integer_digits, &fraction_digits, exponent))
+        } else {
+            SyntaxTokenType::Number(self.integer_suffix(&integer_digits, 16))
+        };
+
+        self.finish(start, token_type);
+    }
+
+    /// Lex the suffix right after the `digits` of an integer literal, like the `u8` of `255u8`,
+    /// and check that the value fits in the type it names
+    fn integer_suffix(
+        &mut self,
+        digits: &str,
+        radix: u32,
+    ) -> std::result::Result<IntegerLiteral, IntegerLiteralError> {
+        let start: usize = self.position;
+        while self.current().is_alphanumeric() || self.current() == '_' {
+            self.advance();
+        }
+        let suffix: &str = &self.text[start..self.position];
+
+        let integer_type: Option<IntegerType> = match suffix {
+            "" => None,
+            suffix => Some(
+                IntegerType::from_name(suffix)
+                    .ok_or_else(|| IntegerLiteralError::UnknownSuffix(suffix.to_string()))?,
+            ),
+        };
+
+        // The minus of a negative literal is a token of its own, so the smallest value of a
+        // signed type is too large until the parser sees the minus
+        let min: i128 = integer_type.map_or(isize::MIN as i128, |integer_type: IntegerType| {
+            integer_type.range().0
+        });
+        if min < 0 && i128::from_str_radix(digits, radix) == Ok(-min) {
+            return Err(IntegerLiteralError::Minimum { integer_type });
+        }
+
+        let value: isize =
+            isize::from_str_radix(digits, radix).map_err(IntegerLiteralError::Invalid)?;
+
+        if let Some(integer_type) = integer_type {
+            let (min, max): (i128, i128) = integer_type.range();
+            if !(min..=max).contains(&(value as i128)) {
+                return Err(IntegerLiteralError::OutOfRange {
+                    value,
+                    integer_type,
+                });
+            }
+        }
+
+        Ok(IntegerLiteral {
+            value,
+            integer_type,
+        })
+    }
+}
+
+/// Tokens up to the end of the text, skipping whitespace, comments and bad tokens
+impl Iterator for Lexer {
+    type Item = SyntaxToken;
+
+    fn next(&mut self) -> Option<SyntaxToken> {
+        loop {
+            self.next_token();
+
+            match self.syntax_token.token_type {
+                SyntaxTokenType::WhiteSpace
+                | SyntaxTokenType::Comment
+                | SyntaxTokenType::BadToken => continue,
+                SyntaxTokenType::EndOfFile => return None,
+                _ => return Some(self.syntax_token.clone()),
+            }
+        }
+    }
+}
+
+/// Compute the value of a hexadecimal float from its already lexed parts
+///
+/// The value is rounded to the nearest float, ties to even, like a decimal literal. It is
+/// infinite when too large and zero when too small, again like a decimal literal.
+fn hex_float_value(
+    integer_digits: &str,
+    fraction_digits: &str,
+    exponent: Option<std::result::Result<i32, HexFloatError>>,
+) -> std::result::Result<f64, HexFloatError> {
+    if integer_digits.is_empty() && fraction_digits.is_empty() {
+        return Err(HexFloatError::MissingDigits);
+    }
+
+    let exponent: i32 = match exponent {
+        Some(exponent) => exponent?,
+        None => return Err(HexFloatError::MissingExponent),
+    };
+
+    // The first 15 significant digits, 60 bits, are kept exactly, the rest only matter for
+    // rounding and for the power of two. An `i64` exponent can't overflow with an `i32`
+    // exponent and any number of digits that fits in memory.
+    let mut mantissa: u64 = 0;
+    let mut sticky: bool = false;
+    let mut binary_exponent: i64 = exponent as i64 - 4 * fraction_digits.len() as i64;
+    for digit in integer_digits
+        .chars()
+        .chain(fraction_digits.chars())
+        .map(|digit: char| digit.to_digit(16).unwrap() as u64)
+    {
+        if mantissa >> 56 == 0 {
+            mantissa = mantissa << 4 | digit;
+        } else {
+            sticky |= digit != 0;
+            binary_exponent += 4;
+        }
+    }
+
+    if mantissa == 0 {
+        return Ok(0.0);
+    }
+
+    // The value is `mantissa * 2^binary_exponent`, keep the bits from `lowest` up, which is
+    // 53 bits for a normal float and fewer for a subnormal one
+    let highest: i64 = binary_exponent + 63 - mantissa.leading_zeros() as i64;
+    let lowest: i64 = (highest - 52).max(-1074);
+    let dropped: i64 = lowest - binary_exponent;
+
+    let mut kept: u64 = match dropped {
+        ..=0 => mantissa << -dropped,
+        1..=63 => {
+            let rest: u64 = mantissa & ((1 << dropped) - 1);
+            let half: u64 = 1 << (dropped - 1);
+            let kept: u64 = mantissa >> dropped;
+            let round_up: bool = match rest.cmp(&half) {
+                std::cmp::Ordering::Greater => true,
+                std::cmp::Ordering::Equal => sticky || kept & 1 == 1,
+                std::cmp::Ordering::Less => false,
+            };
+            kept + round_up as u64
+        }
+        // Every bit is below the smallest subnormal, at most half of it
+        _ => 0,
+    };
+    let mut lowest: i64 = lowest;
+    if kept == 1 << 53 {
+        kept >>= 1;
+        lowest += 1;
+    }
+
+    // A subnormal has no implicit bit and a biased exponent of zero, so its bits are the
+    // mantissa, which also gives the smallest normal float when rounding carried into it
+    if kept < 1 << 52 {
+        return Ok(f64::from_bits(kept));
+    }
+
+    let biased: i64 = lowest + 1075;
+    if biased >= 0x7FF {
+        return Ok(f64::INFINITY);
+    }
+
+    Ok(f64::from_bits((biased as u64) << 52 | (kept - (1 << 52))))
+}
@@ -0,0 +1,42 @@
+pub mod args;
+pub mod asm;
+pub mod ast;
+pub mod build;
+pub mod builtins;
+pub mod bytecode;
+pub mod c_backend;
+pub mod check;
+pub mod color;
+pub mod dead_code;
+pub mod diagnostic;
+pub mod diff;
+pub mod disasm;
+pub mod dot;
+pub mod eval;
+pub mod fmt;
+pub mod fold;
+pub mod import;
+pub mod interner;
+pub mod ir;
+pub mod json;
+pub mod kinds;
+pub mod lexer;
+pub mod line_editor;
+pub mod link;
+pub mod llvm;
+pub mod log;
+pub mod optimize;
+pub mod parser;
+pub mod passes;
+pub mod phc;
+pub mod project;
+pub mod random;
+pub mod repl;
+pub mod run;
+pub mod source;
+pub mod target;
+pub mod tokens;
+pub mod toml;
+pub mod vm;
+pub mod wasm;
+pub mod watch;
@@ -0,0 +1,322 @@
+use std::io::{Read, Write};
+
+/// Source of lines for the REPL
+pub trait ReadLine {
+    /// Show `prompt` and read one line without its line ending, `None` at the end of input
+    ///
+    /// Fails with `std::io::ErrorKind::Interrupted` when the line is cancelled with Ctrl-C.
+    fn read_line(&mut self, prompt: &str) -> std::io::Result<Option<String>>;
+
+    /// Remember an entered line so it can be recalled later
+    fn add_history(&mut self, _line: &str) {}
+}
+
+/// Reads lines from stdin without editing, used when stdin isn't a terminal
+///
+/// Stdin is only locked while a line is read, so `input()` in the program can read from it too
+pub struct StdinReader;
+
+impl ReadLine for StdinReader {
+    fn read_line(&mut self, prompt: &str) -> std::io::Result<Option<String>> {
+        print!("{}", prompt);
+        std::io::stdout().flush()?;
+
+        let mut line: String = String::new();
+        if std::io::stdin().read_line(&mut line)? == 0 {
+            // End of input, end the prompt line
+            println!();
+            return Ok(None);
+        }
+
+        Ok(Some(line.trim_end_matches(['\n', '\r']).to_string()))
+    }
+}
+
+/// Terminal line editor with cursor movement and history
+///
+/// Keys: Left/Right, Home/End (or Ctrl-A/Ctrl-E), Backspace, Delete,
+/// Up/Down to walk the history, Ctrl-C to cancel the line, Ctrl-D on an empty line to quit
+pub struct LineEditor {
+    history: Vec<String>,
+    history_path: Option<std::path::PathBuf>,
+}
+
+/// Puts the terminal into raw mode and restores the previous mode when dropped
+struct RawMode {
+    original: libc::termios,
+}
+
+impl RawMode {
+    fn enable() -> std::io::Result<RawMode> {
+        // SAFETY: `termios` is plain data and is fully written by `tcgetattr` before being read
+        unsafe {
+            let mut original: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            // Ctrl-C and Ctrl-Z arrive as bytes instead of signals, so Ctrl-C only cancels
+            // the line
+            let mut raw: libc::termios = original;
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::IEXTEN | libc::ISIG);
+            raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+            raw.c_cc[libc::VMIN] = 1;
+            raw.c_cc[libc::VTIME] = 0;
+
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &raw) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            Ok(RawMode { original })
+        }
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        // SAFETY: restores the settings read in `enable`
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &self.original);
+        }
+    }
+}
+
+impl LineEditor {
+    /// Create an editor, loading history from `history_path` if it exists
+    pub fn new(history_path: Option<std::path::PathBuf>) -> LineEditor {
+        let history: Vec<String> = history_path
+            .as_ref()
+            .and_then(|path: &std::path::PathBuf| std::fs::read_to_string(path).ok())
+            .map(|text: String| text.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+
+        LineEditor {
+            history,
+            history_path,
+        }
+    }
+
+    /// Default history file, `$XDG_DATA_HOME/phoenix-script/history` or `~/.local/share/...`
+    pub fn default_history_path() -> Option<std::path::PathBuf> {
+        let data_directory: std::path::PathBuf = match std::env::var_os("XDG_DATA_HOME") {
+            Some(directory) if !directory.is_empty() => std::path::PathBuf::from(directory),
+            _ => std::path::PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+        };
+
+        Some(data_directory.join("phoenix-script").join("history"))
+    }
+
+    /// Write the history back to its file, failures only lose history so they are ignored
+    pub fn save_history(&self) {
+        let Some(path) = &self.history_path else {
+            return;
+        };
+
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+
+        let mut text: String = self.history.join("\n");
+        text.push('\n');
+        let _ = std::fs::write(path, text);
+    }
+
+    /// Entered lines, oldest first
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Redraw the prompt and line, leaving the terminal cursor at `cursor`
+    fn refresh(
+        &self,
+        output: &mut dyn Write,
+        prompt: &str,
+        line: &[char],
+        cursor: usize,
+    ) -> std::io::Result<()> {
+        let text: String = line.iter().collect();
+
+        write!(output, "\r{}{}\x1b[K\r", prompt, text)?;
+
+        let column: usize = prompt.chars().count() + cursor;
+        if column > 0 {
+            write!(output, "\x1b[{}C", column)?;
+        }
+
+        output.flush()
+    }
+}
+
+/// Read a single byte, `None` at the end of input
+fn read_byte(input: &mut dyn Read) -> std::io::Result<Option<u8>> {
+    let mut byte: [u8; 1] = [0];
+
+    match input.read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
+
+impl LineEditor {
+    /// Edit one line from the keys in `input`, drawing it on `output`
+    ///
+    /// `suspend` runs on Ctrl-Z, the terminal suspends the process there.
+    pub fn edit_line(
+        &mut self,
+        prompt: &str,
+        input: &mut dyn Read,
+        output: &mut dyn Write,
+        suspend: &mut dyn FnMut() -> std::io::Result<()>,
+    ) -> std::io::Result<Option<String>> {
+        let mut line: Vec<char> = Vec::new();
+        let mut cursor: usize = 0;
+        // Index into the history while browsing it, the line being typed is kept aside
+        let mut history_index: usize = self.history.len();
+        let mut draft: Vec<char> = Vec::new();
+
+        self.refresh(output, prompt, &line, cursor)?;
+
+        loop {
+            let Some(byte) = read_byte(input)? else {
+                write!(output, "\r\n")?;
+                output.flush()?;
+                return Ok(None);
+            };
+
+            match byte {
+                // Enter
+                b'\r' | b'\n' => {
+                    write!(output, "\r\n")?;
+                    output.flush()?;
+                    return Ok(Some(line.iter().collect()));
+                }
+                // Ctrl-C, drops what was typed
+                3 => {
+                    write!(output, "^C\r\n")?;
+                    output.flush()?;
+                    return Err(std::io::Error::from(std::io::ErrorKind::Interrupted));
+                }
+                // Ctrl-Z, suspends with the terminal back in its normal mode
+                26 => suspend()?,
+                // Ctrl-D, quits on an empty line and deletes otherwise
+                4 => {
+                    if line.is_empty() {
+                        write!(output, "\r\n")?;
+                        output.flush()?;
+                        return Ok(None);
+                    }
+                    if cursor < line.len() {
+                        line.remove(cursor);
+                    }
+                }
+                // Ctrl-A
+                1 => cursor = 0,
+                // Ctrl-E
+                5 => cursor = line.len(),
+                // Backspace
+                8 | 127 if cursor > 0 => {
+                    cursor -= 1;
+                    line.remove(cursor);
+                }
+                // Backspace at the start of the line, must not be inserted as a character
+                8 | 127 => {}
+                // Escape sequence
+                27 => {
+                    if read_byte(input)? != Some(b'[') {
+                        continue;
+                    }
+
+                    match read_byte(input)? {
+                        // Up
+                        Some(b'A') if history_index > 0 => {
+                            if history_index == self.history.len() {
+                                draft = line.clone();
+                            }
+                            history_index -= 1;
+                            line = self.history[history_index].chars().collect();
+                            cursor = line.len();
+                        }
+                        // Down
+                        Some(b'B') if history_index < self.history.len() => {
+                            history_index += 1;
+                            line = match self.history.get(history_index) {
+                                Some(entry) => entry.chars().collect(),
+                                None => draft.clone(),
+                            };
+                            cursor = line.len();
+                        }
+                        // Right
+                        Some(b'C') => cursor = (cursor + 1).min(line.len()),
+                        // Left
+                        Some(b'D') => cursor = cursor.saturating_sub(1),
+                        // Home
+                        Some(b'H') => cursor = 0,
+                        // End
+                        Some(b'F') => cursor = line.len(),
+                        // Delete is `ESC [ 3 ~`
+                        Some(b'3') if read_byte(input)? == Some(b'~') && cursor < line.len() => {
+                            line.remove(cursor);
+                        }
+                        _ => {}
+                    }
+                }
+                // Printable character, possibly the first byte of a multi-byte UTF-8 sequence
+                byte if byte >= 32 => {
+                    let length: usize = match byte {
+                        0xF0..=0xFF => 4,
+                        0xE0..=0xEF => 3,
+                        0xC0..=0xDF => 2,
+                        _ => 1,
+                    };
+
+                    let mut bytes: Vec<u8> = vec![byte];
+                    for _ in 1..length {
+                        if let Some(byte) = read_byte(input)? {
+                            bytes.push(byte);
+                        }
+                    }
+
+                    if let Ok(text) = std::str::from_utf8(&bytes) {
+                        for character in text.chars() {
+                            line.insert(cursor, character);
+                            cursor += 1;
+                        }
+                    }
+                }
+                _ => {}
+            }
+
+            self.refresh(output, prompt, &line, cursor)?;
+        }
+    }
+}
+
+impl ReadLine for LineEditor {
+    fn read_line(&mut self, prompt: &str) -> std::io::Result<Option<String>> {
+        let mut raw_mode: Option<RawMode> = Some(RawMode::enable()?);
+
+        self.edit_line(
+            prompt,
+            &mut std::io::stdin().lock(),
+            &mut std::io::stdout(),
+            &mut || -> std::io::Result<()> {
+                drop(raw_mode.take());
+                // SAFETY: only stops the process until the shell continues it
+                unsafe {
+                    libc::raise(libc::SIGTSTP);
+                }
+                raw_mode = Some(RawMode::enable()?);
+                Ok(())
+            },
+        )
+    }
+
+    /// Empty lines and repeats of the previous entry aren't recorded
+    fn add_history(&mut self, line: &str) {
+        if line.trim().is_empty() || self.history.last().map(String::as_str) == Some(line) {
+            return;
+        }
+
+        self.history.push(line.to_string());
+    }
+}
@@ -0,0 +1,57 @@
+/// C compiler `--link` drives, `$CC` or `cc`, if it can be found
+pub fn find_compiler() -> Option<std::path::PathBuf> {
+    let compiler: String = std::env::var("CC").unwrap_or("cc".to_string());
+
+    // A path is used as is, a bare name is looked up like the shell would
+    if compiler.contains(std::path::MAIN_SEPARATOR) {
+        let path: std::path::PathBuf = std::path::PathBuf::from(compiler);
+        return path.is_file().then_some(path);
+    }
+
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|directory: std::path::PathBuf| directory.join(&compiler))
+        .find(|path: &std::path::PathBuf| path.is_file())
+}
+
+/// Compile generated C or assembly into an executable at `executable`
+///
+/// On failure the error holds what the compiler printed, so it can be shown as is.
+pub fn link(
+    compiler: &std::path::Path,
+    source: &std::path::Path,
+    executable: &std::path::Path,
+) -> Result<(), String> {
+    let mut command: std::process::Command = std::process::Command::new(compiler);
+    command.arg(source).arg("-o").arg(executable);
+    // The C runtime uses libm for float builtins
+    if source.extension() == Some(std::ffi::OsStr::new("c")) {
+        command.arg("-lm");
+    }
+
+    let output: std::process::Output = command.output().map_err(|error: std::io::Error| {
+        format!("failed to run '{}': {}", compiler.display(), error)
+    })?;
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let status: String = match output.status.code() {
+        Some(code) => format!("exited with status {}", code),
+        None => "was killed by a signal".to_string(),
+    };
+    let stderr: String = String::from_utf8_lossy(&output.stderr)
+        .trim_end()
+        .to_string();
+    let mut message: String = format!(
+        "'{}' {} while linking '{}'",
+        compiler.display(),
+        status,
+        executable.display()
+    );
+    if !stderr.is_empty() {
+        message.push('\n');
+        message.push_str(&stderr);
+    }
+
+    Err(message)
+}
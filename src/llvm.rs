@@ -0,0 +1,486 @@
+use crate::c_backend::{function_name, global_name};
+use crate::diagnostic::Diagnostic;
+use crate::eval::Value;
+use crate::ir::{Callee, Function, Module, Operation, Target, Temporary, Terminator, Variable};
+use crate::kinds::{Kind, Kinds};
+use crate::parser::OperatorToken;
+use crate::target::TargetSpec;
+
+/// Printing through `printf`, `ph_print_int(value, end)` and `ph_print_bool(value, end)`
+/// write the value followed by the character `end`, the format of `@.ph_int` depends on the
+/// size of a C `long` so it is added by `generate`
+const RUNTIME: &str = r#"@.ph_bool = private unnamed_addr constant [5 x i8] c"%s%c\00"
+@.ph_true = private unnamed_addr constant [5 x i8] c"true\00"
+@.ph_false = private unnamed_addr constant [6 x i8] c"false\00"
+
+declare i32 @printf(ptr, ...)
+declare i32 @putchar(i32)
+declare void @llvm.trap()
+declare { i64, i1 } @llvm.sadd.with.overflow.i64(i64, i64)
+declare { i64, i1 } @llvm.ssub.with.overflow.i64(i64, i64)
+declare { i64, i1 } @llvm.smul.with.overflow.i64(i64, i64)
+declare i32 @fflush(ptr)
+
+define internal void @ph_print_int(i64 %value, i32 %end) {
+  %written = call i32 (ptr, ...) @printf(ptr @.ph_int, i64 %value, i32 %end)
+  ret void
+}
+
+define internal void @ph_print_bool(i1 %value, i32 %end) {
+  %text = select i1 %value, ptr @.ph_true, ptr @.ph_false
+  %written = call i32 (ptr, ...) @printf(ptr @.ph_bool, ptr %text, i32 %end)
+  ret void
+}
+
+define internal void @ph_print_newline() {
+  %written = call i32 @putchar(i32 10)
+  ret void
+}
+"#;
+
+/// Translate the integer subset of a program to LLVM IR text, for `ph build --emit llvm-ir`
+///
+/// Supports the same programs as `crate::asm`. Temporaries become SSA values, with `phi`
+/// nodes where the values of a conditional meet, and variables live in `alloca`s that
+/// `opt -passes=mem2reg` turns into registers. Run it with `lli script.ll` or build it with
+/// `clang script.ll -o script`, LLVM itself isn't needed to generate it. Pointers are opaque
+/// `ptr`s, so it needs LLVM 15 or newer, or `-opaque-pointers` with LLVM 14.
+///
+/// Arithmetic that overflows, dividing by zero and `/ -1` overflowing flush what was printed
+/// and call `llvm.trap`, where `ph run` fails with an error and plain LLVM instructions would
+/// wrap around or be undefined. Shifts use the low 6 bits of the amount like on x86-64.
+///
+/// `target` gives the target triple, and the width of the `long` `printf` is given.
+pub fn generate(
+    module: &Module,
+    file_name: &str,
+    target: &TargetSpec,
+) -> Result<String, Diagnostic> {
+    let kinds: Kinds = crate::kinds::check(module, "llvm-ir")?;
+
+    let mut output: String = format!(
+        "; Generated by `ph build --emit llvm-ir --target {}` from {}\nsource_filename = \"{}\"\ntarget triple = \"{}\"\n\n",
+        target.name,
+        file_name.replace('\n', " "),
+        escape(file_name),
+        target.triple
+    );
+
+    for global in &kinds.globals {
+        output.push_str(&format!(
+            "@{} = internal global i64 0\n",
+            global_name(global)
+        ));
+    }
+    if !kinds.globals.is_empty() {
+        output.push('\n');
+    }
+
+    for (function, function_kinds) in module.functions.iter().zip(&kinds.functions) {
+        let mut emitter: Emitter = Emitter::new(module, function, function_kinds);
+        emitter.function(None);
+        output.push_str(&emitter.output);
+        output.push('\n');
+    }
+
+    let mut emitter: Emitter = Emitter::new(module, &module.main, &kinds.main);
+    emitter.function(Some(module.has_result));
+    output.push_str(&emitter.output);
+    output.push('\n');
+
+    // Ints are 64 bits, which is a `long` only when pointers are
+    let format: &str = if target.pointer_width == 64 {
+        "%ld%c"
+    } else {
+        "%lld%c"
+    };
+    output.push_str(&format!(
+        "@.ph_int = private unnamed_addr constant [{} x i8] c\"{}\\00\"\n",
+        format.len() + 1,
+        format
+    ));
+    output.push_str(RUNTIME);
+
+    Ok(output)
+}
+
+/// LLVM type of a kind, nil values are never used so they have none
+fn type_name(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Bool => "i1",
+        _ => "i64",
+    }
+}
+
+/// Contents of an LLVM string literal, anything but printable ASCII is a `\XX` escape
+fn escape(text: &str) -> String {
+    text.bytes()
+        .map(|byte: u8| match byte {
+            b' '..=b'~' if byte != b'"' && byte != b'\\' => (byte as char).to_string(),
+            _ => format!("\\{:02X}", byte),
+        })
+        .collect()
+}
+
+struct Emitter<'a> {
+    module: &'a Module,
+    function: &'a Function,
+    kinds: &'a [Kind],
+    /// Value of every temporary, constants are used directly
+    operands: Vec<String>,
+    /// Predecessors of each block with the values they pass, blocks come after all of theirs
+    incoming: Vec<Vec<(String, Vec<String>)>>,
+    /// LLVM block being emitted, a division splits a block of the IR in two
+    label: String,
+    /// Some arithmetic checks for overflow or division by zero, so the function needs a block
+    /// to trap in
+    traps: bool,
+    output: String,
+}
+
+impl<'a> Emitter<'a> {
+    fn new(module: &'a Module, function: &'a Function, kinds: &'a [Kind]) -> Emitter<'a> {
+        Emitter {
+            module,
+            function,
+            kinds,
+            operands: (0..kinds.len())
+                .map(|temporary: Temporary| format!("%t{}", temporary))
+                .collect(),
+            incoming: vec![Vec::new(); function.blocks.len()],
+            label: "b0".to_string(),
+            traps: false,
+            output: String::new(),
+        }
+    }
+
+    fn line(&mut self, text: &str) {
+        self.output.push_str("  ");
+        self.output.push_str(text);
+        self.output.push('\n');
+    }
+
+    /// Start a new LLVM block
+    fn block(&mut self, label: String) {
+        self.output.push_str(&format!("{}:\n", label));
+        self.label = label;
+    }
+
+    /// Typed operand, like `i64 %t3`
+    fn typed(&self, temporary: Temporary) -> String {
+        format!(
+            "{} {}",
+            type_name(self.kinds[temporary]),
+            self.operands[temporary]
+        )
+    }
+
+    /// Emit the function, `main` gives whether the top level code has a result to print
+    fn function(&mut self, main: Option<bool>) {
+        match main {
+            Some(_) => self.output.push_str("define i32 @main() {\n"),
+            None => {
+                let parameters: Vec<String> = (0..self.function.parameters.len())
+                    .map(|slot: usize| format!("i64 %p{}", slot))
+                    .collect();
+                self.output.push_str(&format!(
+                    "define internal i64 @{}({}) {{\n",
+                    function_name(&self.function.name),
+                    parameters.join(", ")
+                ));
+            }
+        }
+
+        self.block("b0".to_string());
+        for slot in 0..self.function.locals.len() {
+            self.line(&format!("%l{} = alloca i64", slot));
+        }
+        for slot in 0..self.function.parameters.len() {
+            self.line(&format!("store i64 %p{}, ptr %l{}", slot, slot));
+        }
+
+        for (id, block) in self.function.blocks.iter().enumerate() {
+            if id > 0 {
+                self.block(format!("b{}", id));
+            }
+
+            // Each parameter takes the value its predecessor passed
+            for (index, parameter) in block.parameters.iter().enumerate() {
+                if self.kinds[*parameter] == Kind::Nil {
+                    continue;
+                }
+                let sources: Vec<String> = self.incoming[id]
+                    .iter()
+                    .map(|(label, arguments): &(String, Vec<String>)| {
+                        format!("[ {}, %{} ]", arguments[index], label)
+                    })
+                    .collect();
+                self.line(&format!(
+                    "%t{} = phi {} {}",
+                    parameter,
+                    type_name(self.kinds[*parameter]),
+                    sources.join(", ")
+                ));
+            }
+
+            for operation in &block.operations {
+                self.operation(operation);
+            }
+
+            match &block.terminator {
+                Terminator::Jump(target) => {
+                    self.jump(target);
+                    self.line(&format!("br label %b{}", target.block));
+                }
+                Terminator::Branch {
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    self.jump(then);
+                    self.jump(otherwise);
+                    self.line(&format!(
+                        "br {}, label %b{}, label %b{}",
+                        self.typed(*condition),
+                        then.block,
+                        otherwise.block
+                    ));
+                }
+                Terminator::Return(value) => match main {
+                    Some(has_result) => {
+                        // Like `ph run`, a nil result isn't printed
+                        if has_result && self.kinds[*value] != Kind::Nil {
+                            self.print(&[*value]);
+                        }
+                        self.line("ret i32 0");
+                    }
+                    None => self.line(&format!("ret {}", self.typed(*value))),
+                },
+            }
+        }
+
+        if self.traps {
+            self.block("trap".to_string());
+            self.line("%flushed = call i32 @fflush(ptr null)");
+            self.line("call void @llvm.trap()");
+            self.line("unreachable");
+        }
+        self.output.push_str("}\n");
+    }
+
+    /// Record the values the current block passes to `target`
+    fn jump(&mut self, target: &Target) {
+        let arguments: Vec<String> = target
+            .arguments
+            .iter()
+            .map(|argument: &Temporary| self.operands[*argument].clone())
+            .collect();
+        self.incoming[target.block].push((self.label.clone(), arguments));
+    }
+
+    fn operation(&mut self, operation: &Operation) {
+        match operation {
+            Operation::Constant(destination, value) => {
+                self.operands[*destination] = match value {
+                    Value::Int(value) => value.to_string(),
+                    Value::Bool(value) => value.to_string(),
+                    _ => "undef".to_string(),
+                };
+            }
+            Operation::Negate(destination, operand) => {
+                let operand: String = self.operands[*operand].clone();
+                self.checked("ssub", *destination, "0", &operand);
+            }
+            Operation::Binary {
+                destination,
+                operator_token,
+                left,
+                right,
+            }
+            | Operation::Compare {
+                destination,
+                operator_token,
+                left,
+                right,
+            } => self.binary(*destination, *operator_token, *left, *right),
+            Operation::Load(destination, variable) => {
+                let address: String = self.variable(variable);
+                self.line(&format!("%t{} = load i64, ptr {}", destination, address));
+            }
+            Operation::Store(variable, value) | Operation::Define(variable, value) => {
+                let address: String = self.variable(variable);
+                self.line(&format!("store {}, ptr {}", self.typed(*value), address));
+            }
+            Operation::Call {
+                destination,
+                callee: Callee::Function(index),
+                arguments,
+                ..
+            } => {
+                let arguments: Vec<String> = arguments
+                    .iter()
+                    .map(|argument: &Temporary| self.typed(*argument))
+                    .collect();
+                self.line(&format!(
+                    "%t{} = call i64 @{}({})",
+                    destination,
+                    function_name(&self.module.functions[*index].name),
+                    arguments.join(", ")
+                ));
+            }
+            // `print` is the only builtin that passes the check
+            Operation::Call {
+                callee: Callee::Builtin,
+                arguments,
+                ..
+            } => self.print(arguments),
+            _ => {}
+        }
+    }
+
+    fn binary(
+        &mut self,
+        destination: Temporary,
+        operator_token: OperatorToken,
+        left: Temporary,
+        right: Temporary,
+    ) {
+        let (left_operand, right_operand): (String, String) =
+            (self.operands[left].clone(), self.operands[right].clone());
+
+        let instruction: &str = match operator_token {
+            OperatorToken::Plus => "add",
+            OperatorToken::Minus => "sub",
+            OperatorToken::Star => "mul",
+            OperatorToken::Slash => "sdiv",
+            OperatorToken::Percent => "srem",
+            OperatorToken::Ampersand => "and",
+            OperatorToken::Pipe => "or",
+            OperatorToken::Caret => "xor",
+            OperatorToken::LessLess => "shl",
+            OperatorToken::GreaterGreater => "ashr",
+            OperatorToken::Less => "icmp slt",
+            OperatorToken::LessEquals => "icmp sle",
+            OperatorToken::Greater => "icmp sgt",
+            OperatorToken::GreaterEquals => "icmp sge",
+            OperatorToken::EqualsEquals => "icmp eq",
+            OperatorToken::BangEquals => "icmp ne",
+        };
+
+        let intrinsic: Option<&str> = match operator_token {
+            OperatorToken::Plus => Some("sadd"),
+            OperatorToken::Minus => Some("ssub"),
+            OperatorToken::Star => Some("smul"),
+            _ => None,
+        };
+        if let Some(intrinsic) = intrinsic {
+            self.checked(intrinsic, destination, &left_operand, &right_operand);
+            return;
+        }
+
+        let right_operand: String = match operator_token {
+            // Dividing by zero and `isize::MIN / -1` are undefined in LLVM, so they trap
+            OperatorToken::Slash | OperatorToken::Percent => {
+                let name: String = format!("%t{}", destination);
+                self.line(&format!("{}.zero = icmp eq i64 {}, 0", name, right_operand));
+                self.line(&format!(
+                    "{}.min = icmp eq i64 {}, {}",
+                    name,
+                    left_operand,
+                    i64::MIN
+                ));
+                self.line(&format!(
+                    "{}.negative = icmp eq i64 {}, -1",
+                    name, right_operand
+                ));
+                self.line(&format!(
+                    "{}.overflow = and i1 {}.min, {}.negative",
+                    name, name, name
+                ));
+                self.line(&format!(
+                    "{}.trap = or i1 {}.zero, {}.overflow",
+                    name, name, name
+                ));
+                self.line(&format!(
+                    "br i1 {}.trap, label %trap, label %t{}.ok",
+                    name, destination
+                ));
+                self.block(format!("t{}.ok", destination));
+                self.traps = true;
+                right_operand
+            }
+            // Shifting by 64 or more is poison, the amount wraps like on x86-64
+            OperatorToken::LessLess | OperatorToken::GreaterGreater => {
+                self.line(&format!(
+                    "%t{}.amount = and i64 {}, 63",
+                    destination, right_operand
+                ));
+                format!("%t{}.amount", destination)
+            }
+            _ => right_operand,
+        };
+
+        self.line(&format!(
+            "%t{} = {} {} {}, {}",
+            destination,
+            instruction,
+            type_name(self.kinds[left]),
+            left_operand,
+            right_operand
+        ));
+    }
+
+    /// Call the `llvm.<intrinsic>.with.overflow` intrinsic on two ints into `destination`,
+    /// trapping when it overflows
+    fn checked(&mut self, intrinsic: &str, destination: Temporary, left: &str, right: &str) {
+        let name: String = format!("%t{}", destination);
+        self.line(&format!(
+            "{}.checked = call {{ i64, i1 }} @llvm.{}.with.overflow.i64(i64 {}, i64 {})",
+            name, intrinsic, left, right
+        ));
+        self.line(&format!(
+            "{}.overflow = extractvalue {{ i64, i1 }} {}.checked, 1",
+            name, name
+        ));
+        self.line(&format!(
+            "br i1 {}.overflow, label %trap, label %t{}.ok",
+            name, destination
+        ));
+        self.block(format!("t{}.ok", destination));
+        self.traps = true;
+        self.line(&format!(
+            "{} = extractvalue {{ i64, i1 }} {}.checked, 0",
+            name, name
+        ));
+    }
+
+    /// Pointer to a variable
+    fn variable(&self, variable: &Variable) -> String {
+        match variable {
+            Variable::Local(slot) => format!("%l{}", slot),
+            Variable::Global(name) => format!("@{}", global_name(name)),
+        }
+    }
+
+    /// Print values separated by spaces on one line, like `print`
+    fn print(&mut self, values: &[Temporary]) {
+        if values.is_empty() {
+            self.line("call void @ph_print_newline()");
+        }
+
+        for (index, value) in values.iter().enumerate() {
+            let function: &str = match self.kinds[*value] {
+                Kind::Bool => "ph_print_bool",
+                _ => "ph_print_int",
+            };
+            let end: char = if index + 1 == values.len() { '\n' } else { ' ' };
+
+            self.line(&format!(
+                "call void @{}({}, i32 {})",
+                function,
+                self.typed(*value),
+                end as u32
+            ));
+        }
+    }
+}
@@ -0,0 +1,78 @@
+/// How much `ph` says about what it is doing, besides diagnostics and results
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// `-q`, nothing but diagnostics and results
+    Quiet,
+    /// Short confirmations like "Created project"
+    Normal,
+    /// `-v`, the phases of a build and how long each took
+    Verbose,
+    /// `-vv`, also the structures the phases produce
+    Debug,
+}
+
+impl Verbosity {
+    /// Verbosity from the global `-v` and `-q` options
+    pub fn from_arguments(args: &clap::ArgMatches) -> Verbosity {
+        if args.get_flag("quiet") {
+            return Verbosity::Quiet;
+        }
+
+        match args.get_count("verbose") {
+            0 => Verbosity::Normal,
+            1 => Verbosity::Verbose,
+            _ => Verbosity::Debug,
+        }
+    }
+}
+
+/// Set once by `main` before the subcommand runs
+static VERBOSITY: std::sync::atomic::AtomicU8 =
+    std::sync::atomic::AtomicU8::new(Verbosity::Normal as u8);
+
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, std::sync::atomic::Ordering::Relaxed);
+}
+
+pub fn verbosity() -> Verbosity {
+    match VERBOSITY.load(std::sync::atomic::Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        2 => Verbosity::Verbose,
+        _ => Verbosity::Debug,
+    }
+}
+
+/// Whether messages at `verbosity` are shown
+pub fn enabled(verbosity: Verbosity) -> bool {
+    self::verbosity() >= verbosity
+}
+
+/// Print a confirmation to stdout, unless `-q` is given
+pub fn info(message: &str) {
+    if enabled(Verbosity::Normal) {
+        println!("{}", message);
+    }
+}
+
+/// Print progress to stderr with `-v`, stdout is left to the result so `ph build` works in
+/// command substitution
+pub fn verbose(message: &str) {
+    if enabled(Verbosity::Verbose) {
+        eprintln!("{}", message);
+    }
+}
+
+/// Print that `phase` is done with how long it took since `start`, with `-v`
+pub fn phase(phase: &str, start: std::time::Instant) {
+    if enabled(Verbosity::Verbose) {
+        eprintln!("{} in {:.2?}", phase, start.elapsed());
+    }
+}
+
+/// Print a structure in full to stderr with `-vv`
+pub fn debug(name: &str, value: &impl std::fmt::Debug) {
+    if enabled(Verbosity::Debug) {
+        eprintln!("{} = {:#?}", name, value);
+    }
+}
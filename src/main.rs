@@ -1,14 +1,70 @@
-mod args;
-mod build;
+use phoenix_script::{args, ast, build, check, disasm, fmt, log, project, repl, run, tokens};
+
+/// The tree-walker and the parser recurse with the script, the default stack runs out well
+/// before `MAX_CALL_DEPTH` calls, it is only reserved and used as needed
+const STACK_SIZE: usize = 1 << 30;
 
 fn main() {
+    let thread: std::thread::JoinHandle<()> = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run_command)
+        .expect("Failed to start the main thread");
+
+    // The panic message is already printed
+    if thread.join().is_err() {
+        std::process::exit(101);
+    }
+}
+
+fn run_command() {
     let args: clap::ArgMatches = args::get_arguments();
+    log::set_verbosity(log::Verbosity::from_arguments(&args));
 
     match args.subcommand() {
         Some(("build", _)) => {
             build::build(args);
         }
 
+        Some(("check", _)) => {
+            check::check(args);
+        }
+
+        Some(("fmt", _)) => {
+            fmt::fmt(args);
+        }
+
+        Some(("new", _)) => {
+            project::new(args);
+        }
+
+        Some(("init", _)) => {
+            project::init(args);
+        }
+
+        Some(("run", _)) => {
+            run::run(args);
+        }
+
+        Some(("eval", _)) => {
+            run::eval(args);
+        }
+
+        Some(("tokens", _)) => {
+            tokens::tokens(args);
+        }
+
+        Some(("ast", _)) => {
+            ast::ast(args);
+        }
+
+        Some(("disasm", _)) => {
+            disasm::disasm(args);
+        }
+
+        Some(("repl", _)) => {
+            repl::repl();
+        }
+
         _ => {
             unreachable!("Subcommand is required")
         }
@@ -0,0 +1,289 @@
+use crate::bytecode::{ConstantKey, FunctionInfo, Instruction, Program};
+use crate::diagnostic::Span;
+use crate::eval::{binary_operation, unary_operation, Overflow, Value};
+use crate::parser::OperatorToken;
+
+/// Rewrite a compiled program into a shorter one that gives the same results
+///
+/// - Operators on constants are replaced with their result, unless they would fail
+/// - A branch on a constant condition becomes a jump, or nothing
+/// - A jump to a jump goes straight to the final target
+/// - Instructions that can't be reached are removed
+///
+/// Instructions are only combined when no jump lands between them, so every path through the
+/// program still runs the same operations.
+pub fn optimize(program: &mut Program) {
+    // Each pass can make room for another, like a folded condition making a branch constant
+    while fold_constants(program)
+        | fold_branches(program)
+        | thread_jumps(program)
+        | remove_unreachable(program)
+    {}
+
+    compact_constants(program);
+}
+
+/// Addresses a jump or a call can land on, instructions here can't be merged with the previous
+/// ones
+fn targets(program: &Program) -> std::collections::HashSet<usize> {
+    let mut targets: std::collections::HashSet<usize> = program
+        .code
+        .iter()
+        .filter_map(|instruction: &Instruction| match instruction {
+            Instruction::Jump(target) | Instruction::JumpIfFalse(target) => Some(*target),
+            _ => None,
+        })
+        .collect();
+    targets.extend(
+        program
+            .functions
+            .iter()
+            .map(|function: &FunctionInfo| function.address),
+    );
+
+    targets
+}
+
+/// Operator applied by a binary instruction
+fn binary_operator(instruction: Instruction) -> Option<OperatorToken> {
+    match instruction {
+        Instruction::Add => Some(OperatorToken::Plus),
+        Instruction::Sub => Some(OperatorToken::Minus),
+        Instruction::Mul => Some(OperatorToken::Star),
+        Instruction::Div => Some(OperatorToken::Slash),
+        Instruction::Operator(operator_token) => Some(operator_token),
+        _ => None,
+    }
+}
+
+/// Constant pushed by the instruction at `index`
+fn pushed(program: &Program, index: usize) -> Option<&Value> {
+    match program.code.get(index) {
+        Some(Instruction::Push(constant)) => program.constants.get(*constant),
+        _ => None,
+    }
+}
+
+/// Replace `Push a; Push b; <operator>` and `Push a; Negate` with a push of the result
+///
+/// Only results that can't fail are folded, an overflow or a division by zero is left to
+/// happen at runtime so it is reported, or wraps, like without optimizing.
+fn fold_constants(program: &mut Program) -> bool {
+    let targets: std::collections::HashSet<usize> = targets(program);
+    let mut removed: Vec<bool> = vec![false; program.code.len()];
+    let mut changed: bool = false;
+
+    let mut index: usize = 0;
+    while index < program.code.len() {
+        let folded: Option<(Value, usize)> = match program.code.get(index + 1) {
+            Some(Instruction::Negate) if !targets.contains(&(index + 1)) => pushed(program, index)
+                .and_then(|operand: &Value| {
+                    unary_operation(
+                        OperatorToken::Minus,
+                        operand.clone(),
+                        Span::new(0, 0),
+                        Overflow::Checked,
+                    )
+                    .ok()
+                })
+                .map(|value: Value| (value, 2)),
+            Some(Instruction::Push(_)) => program
+                .code
+                .get(index + 2)
+                .and_then(|instruction: &Instruction| binary_operator(*instruction))
+                .filter(|_: &OperatorToken| {
+                    !targets.contains(&(index + 1)) && !targets.contains(&(index + 2))
+                })
+                .and_then(|operator_token: OperatorToken| {
+                    let left: &Value = pushed(program, index)?;
+                    let right: &Value = pushed(program, index + 1)?;
+                    binary_operation(
+                        operator_token,
+                        left.clone(),
+                        right.clone(),
+                        Span::new(0, 0),
+                        Overflow::Checked,
+                    )
+                    .ok()
+                })
+                .map(|value: Value| (value, 3)),
+            _ => None,
+        };
+
+        match folded {
+            Some((value, length)) => {
+                program.constants.push(value);
+                program.code[index] = Instruction::Push(program.constants.len() - 1);
+                removed[index + 1..index + length].fill(true);
+                changed = true;
+                index += length;
+            }
+            None => index += 1,
+        }
+    }
+
+    compact(program, &removed);
+    changed
+}
+
+/// Replace `Push true; JumpIfFalse` with nothing and `Push false; JumpIfFalse` with a jump
+fn fold_branches(program: &mut Program) -> bool {
+    let targets: std::collections::HashSet<usize> = targets(program);
+    let mut removed: Vec<bool> = vec![false; program.code.len()];
+    let mut changed: bool = false;
+
+    for index in 0..program.code.len().saturating_sub(1) {
+        let Instruction::JumpIfFalse(target) = program.code[index + 1] else {
+            continue;
+        };
+        if removed[index] || targets.contains(&(index + 1)) {
+            continue;
+        }
+
+        match pushed(program, index) {
+            Some(Value::Bool(true)) => removed[index] = true,
+            Some(Value::Bool(false)) => program.code[index] = Instruction::Jump(target),
+            _ => continue,
+        }
+        removed[index + 1] = true;
+        changed = true;
+    }
+
+    compact(program, &removed);
+    changed
+}
+
+/// Point jumps that land on an unconditional jump at where that one goes, and drop jumps to
+/// the next instruction
+fn thread_jumps(program: &mut Program) -> bool {
+    let mut removed: Vec<bool> = vec![false; program.code.len()];
+    let mut changed: bool = false;
+
+    for (index, is_removed) in removed.iter_mut().enumerate() {
+        let target: usize = match program.code[index] {
+            Instruction::Jump(target) | Instruction::JumpIfFalse(target) => target,
+            _ => continue,
+        };
+
+        // Bounded so a cycle of jumps can't hang the optimizer
+        let mut final_target: usize = target;
+        for _ in 0..program.code.len() {
+            match program.code.get(final_target) {
+                Some(Instruction::Jump(next)) if *next != final_target => final_target = *next,
+                _ => break,
+            }
+        }
+
+        if target != final_target {
+            if let Instruction::Jump(target) | Instruction::JumpIfFalse(target) =
+                &mut program.code[index]
+            {
+                *target = final_target;
+            }
+            changed = true;
+        }
+
+        if program.code[index] == Instruction::Jump(index + 1) {
+            *is_removed = true;
+            changed = true;
+        }
+    }
+
+    compact(program, &removed);
+    changed
+}
+
+/// Remove instructions after a jump, a return or a failure, up to the next one that can be
+/// jumped to
+fn remove_unreachable(program: &mut Program) -> bool {
+    let targets: std::collections::HashSet<usize> = targets(program);
+    let mut removed: Vec<bool> = vec![false; program.code.len()];
+    let mut reachable: bool = true;
+
+    for (index, instruction) in program.code.iter().enumerate() {
+        if targets.contains(&index) {
+            reachable = true;
+        }
+        removed[index] = !reachable;
+
+        if matches!(
+            instruction,
+            Instruction::Jump(_) | Instruction::Return | Instruction::Fail(_)
+        ) {
+            reachable = false;
+        }
+    }
+
+    let changed: bool = removed.contains(&true);
+    compact(program, &removed);
+    changed
+}
+
+/// Drop the `removed` instructions and move jump targets and function addresses to match, a
+/// target that was removed moves to the next instruction that is kept
+fn compact(program: &mut Program, removed: &[bool]) {
+    if !removed.contains(&true) {
+        return;
+    }
+
+    let mut addresses: Vec<usize> = Vec::with_capacity(removed.len() + 1);
+    let mut next: usize = 0;
+    for is_removed in removed {
+        addresses.push(next);
+        if !is_removed {
+            next += 1;
+        }
+    }
+    addresses.push(next);
+
+    program.code = program
+        .code
+        .iter()
+        .zip(removed)
+        .filter_map(|(instruction, is_removed): (&Instruction, &bool)| {
+            (!is_removed).then_some(*instruction)
+        })
+        .collect();
+    program.spans = program
+        .spans
+        .iter()
+        .zip(removed)
+        .filter_map(|(span, is_removed): (&Span, &bool)| (!is_removed).then_some(*span))
+        .collect();
+
+    for instruction in &mut program.code {
+        if let Instruction::Jump(target) | Instruction::JumpIfFalse(target) = instruction {
+            *target = addresses[*target];
+        }
+    }
+    for function in &mut program.functions {
+        function.address = addresses[function.address];
+    }
+}
+
+/// Remove constants no instruction pushes anymore and merge equal ones
+fn compact_constants(program: &mut Program) {
+    let mut constants: Vec<Value> = Vec::new();
+    let mut indices: std::collections::HashMap<ConstantKey, usize> =
+        std::collections::HashMap::new();
+
+    for instruction in &mut program.code {
+        let Instruction::Push(constant) = instruction else {
+            continue;
+        };
+
+        let value: &Value = &program.constants[*constant];
+        *constant = match ConstantKey::of(value) {
+            Some(key) => *indices.entry(key).or_insert_with(|| {
+                constants.push(value.clone());
+                constants.len() - 1
+            }),
+            None => {
+                constants.push(value.clone());
+                constants.len() - 1
+            }
+        };
+    }
+
+    program.constants = constants;
+}
@@ -0,0 +1,1388 @@
+use crate::diagnostic::{Diagnostic, Span};
+use crate::lexer::{
+    IntegerLiteralError, IntegerType, Lexer, StringSegment, SyntaxToken, SyntaxTokenType,
+};
+
+/// Parser for the language
+///
+/// Tokens are pulled from the lexer as they are needed and at most two are held at a
+/// time, so memory use grows with the source text and the syntax tree but not with the
+/// number of tokens.
+#[derive(Debug)]
+pub struct Parser {
+    lexer: Lexer,
+    /// Tokens lexed but not consumed yet, the parser never looks more than one token ahead
+    /// apart from new lines skipped inside parentheses
+    lookahead: std::collections::VecDeque<SyntaxToken>,
+    /// Parentheses open around the current token, new lines inside them are skipped
+    parentheses: usize,
+    /// Byte offset just past the last consumed token, where the expression parsed last ends
+    previous_end: usize,
+    /// The statement parsed last ended with an indented block, whose end also ends it
+    closed_block: bool,
+    /// Read characters that start no token as new lines instead of failing on them
+    lenient: bool,
+    /// Comments lexed but not attached to a statement yet, in source order
+    pending_comments: Vec<SyntaxToken>,
+    comments: Comments,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseErrorKind {
+    /// A token that can't appear here, `expected` describes what could
+    UnexpectedToken { expected: String, found: String },
+    /// The input ended while `expected` was still required
+    UnexpectedEndOfFile { expected: String },
+    /// A number literal whose value couldn't be computed
+    InvalidNumber(String),
+    /// An integer literal bigger than the largest int
+    NumberTooLarge,
+    /// A malformed string literal
+    InvalidString(String),
+    /// An operator written the wrong way around, like `=<` for `<=`
+    MistypedOperator { found: String, suggestion: String },
+    /// A character that starts no token, only an error when the parser isn't lenient
+    UnknownCharacter(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// Byte range of the offending token, `span.start` is its byte offset in the source, an
+    /// error at the end of the input has an empty span at the end
+    pub span: Span,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ParseErrorKind::UnexpectedToken { expected, found } if found == "\n" => {
+                write!(f, "expected {} but found a new line", expected)
+            }
+            // Layout tokens are the indentation of a line, or nothing at all
+            ParseErrorKind::UnexpectedToken { expected, found } if found.is_empty() => {
+                write!(
+                    f,
+                    "expected {} but found the end of the indented block",
+                    expected
+                )
+            }
+            ParseErrorKind::UnexpectedToken { expected, found } if found.trim().is_empty() => {
+                write!(f, "expected {} but found an indented line", expected)
+            }
+            ParseErrorKind::UnexpectedToken { expected, found } => {
+                write!(f, "expected {} but found '{}'", expected, found)
+            }
+            ParseErrorKind::UnexpectedEndOfFile { expected } => {
+                write!(f, "expected {} but reached the end of the input", expected)
+            }
+            ParseErrorKind::InvalidNumber(message) => write!(f, "invalid number: {}", message),
+            ParseErrorKind::NumberTooLarge => write!(
+                f,
+                "number is too large for an int, the largest is {}, add '.0' to make it a float",
+                isize::MAX
+            ),
+            ParseErrorKind::InvalidString(message) => write!(f, "invalid string: {}", message),
+            ParseErrorKind::MistypedOperator { found, suggestion } => write!(
+                f,
+                "'{}' is not an operator, did you mean `{}`?",
+                found, suggestion
+            ),
+            ParseErrorKind::UnknownCharacter(found) => write!(f, "unknown character '{}'", found),
+        }
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(error: ParseError) -> Diagnostic {
+        Diagnostic::error(error.to_string(), error.span)
+    }
+}
+
+/// A single statement, terminated by `;` or a new line
+#[derive(Clone, Debug)]
+pub enum StatementSyntax {
+    Let(LetSyntax),
+    Assign(AssignSyntax),
+    Function(FunctionSyntax),
+    Import(ImportSyntax),
+    Expression(ExpressionSyntaxEnum),
+}
+
+/// `let name = value`, `position` is the byte offset of the name
+#[derive(Clone, Debug)]
+pub struct LetSyntax {
+    pub position: usize,
+    pub name: String,
+    pub value: ExpressionSyntaxEnum,
+}
+
+/// `name = value`, `position` is the byte offset of the name
+#[derive(Clone, Debug)]
+pub struct AssignSyntax {
+    pub position: usize,
+    pub name: String,
+    pub value: ExpressionSyntaxEnum,
+}
+
+/// `fn name(parameters) { body }`, only allowed at the top level
+///
+/// Calling the function evaluates to the value of the body's trailing expression statement,
+/// `position` is the byte offset of the name
+#[derive(Clone, Debug)]
+pub struct FunctionSyntax {
+    pub position: usize,
+    pub name: String,
+    pub parameters: Vec<String>,
+    pub body: Vec<StatementSyntax>,
+}
+
+/// `import "utils.ph"` or `import utils`, only allowed at the top level
+///
+/// `crate::import::load` replaces it with the file it names before the program is checked or
+/// run, so only the tools that print the tree see it.
+#[derive(Clone, Debug)]
+pub struct ImportSyntax {
+    /// Covers `import` through the path
+    pub span: Span,
+    /// Path or module name as written, without the quotes
+    pub path: String,
+    /// Whether the path is a string rather than a module name
+    pub quoted: bool,
+}
+
+/// Comments around one statement, see `Parser::comments`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StatementComments {
+    /// Comments on the lines before the statement, and any inside it, which go before it
+    pub leading: Vec<String>,
+    /// Comment after the statement on the line it ends on
+    pub trailing: Option<String>,
+    /// Comments after the last statement of a function body, before the body ends
+    pub closing: Vec<String>,
+}
+
+/// Every comment of a source, kept beside the syntax tree like white space
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Comments {
+    /// Comments of every statement in the order the statements start, so a function comes
+    /// before the statements of its body
+    pub statements: Vec<StatementComments>,
+    /// Comments after the last statement
+    pub end: Vec<String>,
+}
+
+/// `name(arguments)`, `span` covers the name through the closing parenthesis
+///
+/// A method call `receiver.name(arguments)` is the same as `name(receiver, arguments)`,
+/// its `span` starts at the receiver
+#[derive(Clone, Debug)]
+pub struct CallSyntax {
+    pub span: Span,
+    /// Source text covered by `span`, shown when an `assert` fails
+    pub text: String,
+    pub name: String,
+    pub arguments: Vec<ExpressionSyntaxEnum>,
+}
+
+/// Binary expression
+#[derive(Clone, Debug)]
+pub struct ExpressionSyntax {
+    /// Source range of the whole expression, from the start of `left` to the end of `right`
+    pub span: Span,
+    /// Source range of the operator
+    pub operator_span: Span,
+    pub left: ExpressionSyntaxEnum,
+    pub operator_token: OperatorToken,
+    pub right: ExpressionSyntaxEnum,
+}
+
+/// Prefix expression like `-x`, `position` is the byte offset of the operator
+#[derive(Clone, Debug)]
+pub struct UnaryExpressionSyntax {
+    pub position: usize,
+    pub operator_token: OperatorToken,
+    pub operand: ExpressionSyntaxEnum,
+}
+
+/// `target[index]`, `position` is the byte offset of the `[`
+#[derive(Clone, Debug)]
+pub struct IndexSyntax {
+    pub position: usize,
+    pub target: ExpressionSyntaxEnum,
+    pub index: ExpressionSyntaxEnum,
+}
+
+/// `condition ? then : otherwise`, `position` is the byte offset of the `?`
+#[derive(Clone, Debug)]
+pub struct ConditionalSyntax {
+    pub position: usize,
+    pub condition: ExpressionSyntaxEnum,
+    pub then: ExpressionSyntaxEnum,
+    pub otherwise: ExpressionSyntaxEnum,
+}
+
+/// `a < b <= c`, true when every comparison is, like `a < b` and `b <= c`
+///
+/// Operands are evaluated once, left to right, and evaluation stops at the first comparison
+/// that is false, so `b` runs once and `c` only runs when `a < b`.
+#[derive(Clone, Debug)]
+pub struct ChainSyntax {
+    pub first: ExpressionSyntaxEnum,
+    /// Two or more, each compares the operand before it with its own
+    pub links: Vec<ChainLink>,
+}
+
+/// `< operand` in a chain, `position` is the byte offset of the operator
+#[derive(Clone, Debug)]
+pub struct ChainLink {
+    pub position: usize,
+    pub operator_token: OperatorToken,
+    pub operand: ExpressionSyntaxEnum,
+}
+
+#[derive(Clone, Debug)]
+pub struct VariableSyntax {
+    pub position: usize,
+    pub name: String,
+}
+
+#[derive(Clone, Debug)]
+pub enum ExpressionSyntaxEnum {
+    ExpressionSyntax(Box<ExpressionSyntax>),
+    Unary(Box<UnaryExpressionSyntax>),
+    Conditional(Box<ConditionalSyntax>),
+    Chain(Box<ChainSyntax>),
+    Index(Box<IndexSyntax>),
+    Number(isize),
+    Float(f64),
+    String(Vec<StringSegment>),
+    /// `[elements]`
+    Array(Vec<ExpressionSyntaxEnum>),
+    Variable(VariableSyntax),
+    Call(CallSyntax),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OperatorToken {
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Ampersand,
+    Pipe,
+    Caret,
+    LessLess,
+    GreaterGreater,
+    Less,
+    LessEquals,
+    Greater,
+    GreaterEquals,
+    EqualsEquals,
+    BangEquals,
+}
+
+impl OperatorToken {
+    /// Operator for a token, if it is one
+    fn from_token_type(token_type: &SyntaxTokenType) -> Option<OperatorToken> {
+        match token_type {
+            SyntaxTokenType::Plus => Some(OperatorToken::Plus),
+            SyntaxTokenType::Minus => Some(OperatorToken::Minus),
+            SyntaxTokenType::Star => Some(OperatorToken::Star),
+            SyntaxTokenType::Slash => Some(OperatorToken::Slash),
+            SyntaxTokenType::Percent => Some(OperatorToken::Percent),
+            SyntaxTokenType::Ampersand => Some(OperatorToken::Ampersand),
+            SyntaxTokenType::Pipe => Some(OperatorToken::Pipe),
+            SyntaxTokenType::Caret => Some(OperatorToken::Caret),
+            SyntaxTokenType::LessLess => Some(OperatorToken::LessLess),
+            SyntaxTokenType::GreaterGreater => Some(OperatorToken::GreaterGreater),
+            SyntaxTokenType::Less => Some(OperatorToken::Less),
+            SyntaxTokenType::LessEquals => Some(OperatorToken::LessEquals),
+            SyntaxTokenType::Greater => Some(OperatorToken::Greater),
+            SyntaxTokenType::GreaterEquals => Some(OperatorToken::GreaterEquals),
+            SyntaxTokenType::EqualsEquals => Some(OperatorToken::EqualsEquals),
+            SyntaxTokenType::BangEquals => Some(OperatorToken::BangEquals),
+            _ => None,
+        }
+    }
+
+    /// Binding strength when used as a binary operator, higher binds tighter
+    ///
+    /// Follows C, so comparisons bind tighter than the bitwise operators
+    pub fn precedence(&self) -> u8 {
+        match self {
+            OperatorToken::Pipe => 1,
+            OperatorToken::Caret => 2,
+            OperatorToken::Ampersand => 3,
+            OperatorToken::EqualsEquals | OperatorToken::BangEquals => 4,
+            OperatorToken::Less
+            | OperatorToken::LessEquals
+            | OperatorToken::Greater
+            | OperatorToken::GreaterEquals => 5,
+            OperatorToken::LessLess | OperatorToken::GreaterGreater => 6,
+            OperatorToken::Plus | OperatorToken::Minus => 7,
+            OperatorToken::Star | OperatorToken::Slash | OperatorToken::Percent => 8,
+        }
+    }
+
+    /// Whether the operator only works on integers
+    pub fn is_bitwise(&self) -> bool {
+        matches!(
+            self,
+            OperatorToken::Ampersand
+                | OperatorToken::Pipe
+                | OperatorToken::Caret
+                | OperatorToken::LessLess
+                | OperatorToken::GreaterGreater
+        )
+    }
+
+    /// Whether a second one after it makes a chain, like `a < b <= c`
+    ///
+    /// Only `<`, `<=`, `>` and `>=` chain, in any mix. `==` and `!=` bind looser, so
+    /// `a < b == c` still compares the bool `a < b` with `c`.
+    pub fn chains(&self) -> bool {
+        matches!(
+            self,
+            OperatorToken::Less
+                | OperatorToken::LessEquals
+                | OperatorToken::Greater
+                | OperatorToken::GreaterEquals
+        )
+    }
+
+    /// Whether the operator compares its operands and produces a bool
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            OperatorToken::Less
+                | OperatorToken::LessEquals
+                | OperatorToken::Greater
+                | OperatorToken::GreaterEquals
+                | OperatorToken::EqualsEquals
+                | OperatorToken::BangEquals
+        )
+    }
+
+    pub fn text(&self) -> &'static str {
+        match self {
+            OperatorToken::Plus => "+",
+            OperatorToken::Minus => "-",
+            OperatorToken::Star => "*",
+            OperatorToken::Slash => "/",
+            OperatorToken::Percent => "%",
+            OperatorToken::Ampersand => "&",
+            OperatorToken::Pipe => "|",
+            OperatorToken::Caret => "^",
+            OperatorToken::LessLess => "<<",
+            OperatorToken::GreaterGreater => ">>",
+            OperatorToken::Less => "<",
+            OperatorToken::LessEquals => "<=",
+            OperatorToken::Greater => ">",
+            OperatorToken::GreaterEquals => ">=",
+            OperatorToken::EqualsEquals => "==",
+            OperatorToken::BangEquals => "!=",
+        }
+    }
+
+    /// Source range of the operator when it starts at `position`
+    pub fn span(&self, position: usize) -> Span {
+        Span::new(position, position + self.text().len())
+    }
+}
+
+/// Text of every token of `tokens`
+fn texts(tokens: Vec<SyntaxToken>) -> Vec<String> {
+    tokens
+        .into_iter()
+        .map(|token: SyntaxToken| token.text)
+        .collect()
+}
+
+/// Width of the spaces and tabs the line with the byte offset `position` starts with
+fn line_indentation(text: &str, position: usize) -> usize {
+    let line_start: usize = text[..position]
+        .rfind('\n')
+        .map_or(0, |index: usize| index + 1);
+    text[line_start..]
+        .chars()
+        .take_while(|character: &char| *character == ' ' || *character == '\t')
+        .count()
+}
+
+/// Error for an integer literal the lexer couldn't make an int of
+fn number_error(error: &IntegerLiteralError) -> ParseErrorKind {
+    match error {
+        IntegerLiteralError::Invalid(error)
+            if *error.kind() == std::num::IntErrorKind::PosOverflow =>
+        {
+            ParseErrorKind::NumberTooLarge
+        }
+        IntegerLiteralError::Minimum { integer_type: None } => ParseErrorKind::NumberTooLarge,
+        error => ParseErrorKind::InvalidNumber(error.to_string()),
+    }
+}
+
+/// Whether a token of `token_type` can start an operand, unary `+` and `-` included
+fn starts_operand(token_type: &SyntaxTokenType) -> bool {
+    matches!(
+        token_type,
+        SyntaxTokenType::Number(_)
+            | SyntaxTokenType::Float(_)
+            | SyntaxTokenType::String(_)
+            | SyntaxTokenType::Identifier
+            | SyntaxTokenType::OpenParenthesis
+            | SyntaxTokenType::OpenBracket
+            | SyntaxTokenType::Plus
+            | SyntaxTokenType::Minus
+    )
+}
+
+/// Every token the parser would see in `text`, without whitespace and bad tokens
+pub fn collect_tokens(text: String) -> Vec<SyntaxToken> {
+    Lexer::new(text).collect()
+}
+
+/// Every token `lexer` produces, including whitespace, bad tokens and the final `EndOfFile`
+pub fn collect_raw_tokens(mut lexer: Lexer) -> Vec<SyntaxToken> {
+    let mut tokens: Vec<SyntaxToken> = Vec::new();
+
+    loop {
+        lexer.next_token();
+        tokens.push(lexer.syntax_token.clone());
+
+        if lexer.syntax_token.token_type == SyntaxTokenType::EndOfFile {
+            return tokens;
+        }
+    }
+}
+
+impl Parser {
+    pub fn new(text: String) -> Parser {
+        Parser {
+            lexer: Lexer::new(text),
+            lookahead: std::collections::VecDeque::new(),
+            parentheses: 0,
+            previous_end: 0,
+            closed_block: false,
+            lenient: false,
+            pending_comments: Vec::new(),
+            comments: Comments::default(),
+        }
+    }
+
+    /// Read characters that start no token, like `@`, as new lines instead of failing on them,
+    /// so `1 @ 2` is two statements and `1 + @ 2` continues after the operator
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    /// Parser where an indented block after a function's parameters is its body, see
+    /// `Lexer::with_layout`
+    pub fn with_layout(text: String) -> Parser {
+        Parser {
+            lexer: Lexer::with_layout(text),
+            ..Parser::new(String::new())
+        }
+    }
+
+    /// Look `offset` tokens ahead, lexing only as far as needed
+    ///
+    /// Whitespace is skipped, and new lines inside parentheses, which bad tokens are when
+    /// lenient.
+    /// Past the end every token is `EndOfFile`.
+    fn peek(&mut self, offset: usize) -> &SyntaxToken {
+        let index: usize = self.lookahead_index(offset);
+        &self.lookahead[index]
+    }
+
+    /// Index in `lookahead` of the token `offset` tokens ahead, lexing it if needed
+    ///
+    /// Skipped new lines stay in `lookahead`, so they still end the statement once the
+    /// parentheses are closed.
+    fn lookahead_index(&mut self, offset: usize) -> usize {
+        let mut index: usize = 0;
+        let mut remaining: usize = offset;
+
+        loop {
+            if self.lookahead.len() <= index {
+                let token: SyntaxToken = match self.lex() {
+                    Some(token) => token,
+                    None => SyntaxToken {
+                        text: "".to_string(),
+                        token_type: SyntaxTokenType::EndOfFile,
+                        position: self.lexer.text().len(),
+                    },
+                };
+                self.lookahead.push_back(token);
+            }
+
+            let skipped: bool = self.parentheses > 0
+                && self.lookahead[index].token_type == SyntaxTokenType::NewLine;
+            if !skipped {
+                if remaining == 0 {
+                    return index;
+                }
+                remaining -= 1;
+            }
+            index += 1;
+        }
+    }
+
+    /// Next token from the lexer that isn't skipped, `None` at the end
+    fn lex(&mut self) -> Option<SyntaxToken> {
+        loop {
+            self.lexer.next_token();
+
+            match self.lexer.syntax_token.token_type {
+                SyntaxTokenType::WhiteSpace => continue,
+                SyntaxTokenType::Comment => {
+                    self.pending_comments.push(self.lexer.syntax_token.clone());
+                    continue;
+                }
+                // A new line that keeps its text, so an error on it still shows the character
+                SyntaxTokenType::BadToken if self.lenient => {
+                    return Some(SyntaxToken {
+                        token_type: SyntaxTokenType::NewLine,
+                        ..self.lexer.syntax_token.clone()
+                    })
+                }
+                SyntaxTokenType::EndOfFile => return None,
+                _ => return Some(self.lexer.syntax_token.clone()),
+            }
+        }
+    }
+
+    fn current(&mut self) -> &SyntaxToken {
+        self.peek(0)
+    }
+
+    /// Move past the current token
+    fn advance(&mut self) {
+        let index: usize = self.lookahead_index(0);
+        self.previous_end = self.lookahead[index].span().end;
+        self.lookahead.drain(..=index);
+    }
+
+    /// Move past a `(`, new lines are skipped until the matching `)`
+    fn open_parenthesis(&mut self) -> Result<(), ParseError> {
+        self.expect(SyntaxTokenType::OpenParenthesis)?;
+        self.parentheses += 1;
+        Ok(())
+    }
+
+    /// Move past a `)`, the new lines in front of it are skipped but not the ones after it
+    fn close_parenthesis(&mut self) -> Result<SyntaxToken, ParseError> {
+        let close: SyntaxToken = self.expect(SyntaxTokenType::CloseParenthesis)?;
+        self.parentheses -= 1;
+        Ok(close)
+    }
+
+    /// Return the current token and move past it
+    fn next_token(&mut self) -> SyntaxToken {
+        let index: usize = self.lookahead_index(0);
+        self.lookahead.drain(..index);
+        let token: SyntaxToken = self
+            .lookahead
+            .pop_front()
+            .expect("The current token was just lexed");
+        self.previous_end = token.span().end;
+        token
+    }
+
+    /// Return the current token and move past it if it is `expected`, or fail naming it
+    fn expect(&mut self, expected: SyntaxTokenType) -> Result<SyntaxToken, ParseError> {
+        if self.current().token_type != expected {
+            return Err(self.unexpected(expected.description()));
+        }
+
+        Ok(self.next_token())
+    }
+
+    /// Error for the current token, which isn't `expected`
+    fn unexpected(&mut self, expected: &str) -> ParseError {
+        let token: &SyntaxToken = self.current();
+
+        let kind: ParseErrorKind = match token.token_type {
+            SyntaxTokenType::EndOfFile => ParseErrorKind::UnexpectedEndOfFile {
+                expected: expected.to_string(),
+            },
+            SyntaxTokenType::EqualsLess => ParseErrorKind::MistypedOperator {
+                found: token.text.clone(),
+                suggestion: "<=".to_string(),
+            },
+            SyntaxTokenType::EqualsBang => ParseErrorKind::MistypedOperator {
+                found: token.text.clone(),
+                suggestion: "!=".to_string(),
+            },
+            SyntaxTokenType::BadToken => ParseErrorKind::UnknownCharacter(token.text.clone()),
+            _ => ParseErrorKind::UnexpectedToken {
+                expected: expected.to_string(),
+                found: token.text.clone(),
+            },
+        };
+
+        ParseError {
+            kind,
+            span: token.span(),
+        }
+    }
+
+    /// Parse all statements until the end of the input, starting over from the beginning if
+    /// anything was parsed before
+    pub fn parse_statements(&mut self) -> Result<Vec<StatementSyntax>, ParseError> {
+        self.restart();
+        let statements: Vec<StatementSyntax> = self.parse_statement_list(true)?;
+
+        if self.current().token_type != SyntaxTokenType::EndOfFile {
+            return Err(self.unexpected("a statement"));
+        }
+        self.comments.end = texts(self.take_comments(usize::MAX));
+
+        Ok(statements)
+    }
+
+    /// Go back to the beginning of the text and forget what was parsed
+    fn restart(&mut self) {
+        self.lexer.reset();
+        self.lookahead.clear();
+        self.parentheses = 0;
+        self.previous_end = 0;
+        self.closed_block = false;
+        self.pending_comments.clear();
+        self.comments = Comments::default();
+    }
+
+    /// Every token of the text as the parser reads it, without whitespace and comments
+    ///
+    /// The tokens are lexed again, the parser only keeps the few it has looked ahead at, so
+    /// this doesn't move it.
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut lexer: Lexer = self.lexer.clone();
+        lexer.reset();
+        let mut parser: Parser = Parser {
+            lexer,
+            lenient: self.lenient,
+            ..Parser::new(String::new())
+        };
+
+        std::iter::from_fn(|| parser.lex()).collect()
+    }
+
+    /// Comments of the statements parsed so far, attached to them by where they are
+    pub fn comments(&self) -> &Comments {
+        &self.comments
+    }
+
+    /// The pending comments that start before `end`
+    fn take_comments(&mut self, end: usize) -> Vec<SyntaxToken> {
+        let count: usize = self
+            .pending_comments
+            .iter()
+            .take_while(|comment: &&SyntaxToken| comment.position < end)
+            .count();
+        self.pending_comments.drain(..count).collect()
+    }
+
+    /// Parse a statement and attach the comments before it, in it and after it on its line
+    fn parse_statement_with_comments(
+        &mut self,
+        top_level: bool,
+    ) -> Result<StatementSyntax, ParseError> {
+        // Every comment before the first token of the statement is lexed by now
+        let start: usize = self.current().position;
+        let index: usize = self.comments.statements.len();
+        let leading: Vec<String> = texts(self.take_comments(start));
+        self.comments.statements.push(StatementComments {
+            leading,
+            ..StatementComments::default()
+        });
+
+        let statement: StatementSyntax = self.parse_statement(top_level)?;
+        let end: usize = self.previous_end;
+
+        // Lexing the token after the statement lexes the comment before it too
+        self.current();
+        let mut inside: Vec<SyntaxToken> = self.take_comments(end);
+
+        // An indented block only ends at the next line that isn't blank or a comment, so the
+        // comments before that line that aren't indented past the function come after it
+        if self.closed_block {
+            let indentation: usize = line_indentation(self.lexer.text(), start);
+            let text: &str = self.lexer.text();
+            let body: usize = inside
+                .iter()
+                .take_while(|comment: &&SyntaxToken| {
+                    line_indentation(text, comment.position) > indentation
+                })
+                .count();
+            let after: Vec<SyntaxToken> = inside.split_off(body);
+            self.pending_comments.splice(0..0, after);
+        }
+
+        let trailing: Option<String> = match self.pending_comments.first() {
+            Some(comment)
+                if comment.position >= end
+                    && self.lexer.text()[end..comment.position]
+                        .chars()
+                        .all(|character: char| character == ' ' || character == '\t') =>
+            {
+                Some(self.pending_comments.remove(0).text)
+            }
+            _ => None,
+        };
+
+        let comments: &mut StatementComments = &mut self.comments.statements[index];
+        match statement {
+            StatementSyntax::Function(_) => comments.closing = texts(inside),
+            _ => comments.leading.extend(texts(inside)),
+        }
+        comments.trailing = trailing;
+
+        Ok(statement)
+    }
+
+    /// Parse statements until the end of the input or a `}`, which is left unconsumed
+    fn parse_statement_list(
+        &mut self,
+        top_level: bool,
+    ) -> Result<Vec<StatementSyntax>, ParseError> {
+        let mut statements: Vec<StatementSyntax> = Vec::new();
+
+        loop {
+            // Skip empty statements
+            while matches!(
+                self.current().token_type,
+                SyntaxTokenType::NewLine | SyntaxTokenType::Semicolon
+            ) {
+                self.advance();
+            }
+
+            if matches!(
+                self.current().token_type,
+                SyntaxTokenType::EndOfFile | SyntaxTokenType::CloseBrace | SyntaxTokenType::Dedent
+            ) {
+                return Ok(statements);
+            }
+
+            self.closed_block = false;
+            statements.push(self.parse_statement_with_comments(top_level)?);
+
+            let closed_block: bool = self.closed_block;
+            match self.current().token_type {
+                SyntaxTokenType::NewLine
+                | SyntaxTokenType::Semicolon
+                | SyntaxTokenType::EndOfFile
+                | SyntaxTokenType::CloseBrace
+                | SyntaxTokenType::Dedent => {}
+                _ if closed_block => {}
+                _ => return Err(self.unexpected("';' or a new line after the statement")),
+            }
+        }
+    }
+
+    /// Parse `{ statements }`, or in layout mode the indented lines after a new line
+    fn parse_block(&mut self) -> Result<Vec<StatementSyntax>, ParseError> {
+        // Blank lines and lines with only a comment can come before the first indented one
+        let mut offset: usize = 0;
+        while self.peek(offset).token_type == SyntaxTokenType::NewLine {
+            offset += 1;
+        }
+        if offset > 0 && self.peek(offset).token_type == SyntaxTokenType::Indent {
+            for _ in 0..=offset {
+                self.advance();
+            }
+
+            let statements: Vec<StatementSyntax> = self.parse_statement_list(false)?;
+
+            self.expect(SyntaxTokenType::Dedent)?;
+            self.closed_block = true;
+
+            return Ok(statements);
+        }
+
+        self.expect(SyntaxTokenType::OpenBrace)?;
+        let statements: Vec<StatementSyntax> = self.parse_statement_list(false)?;
+        self.expect(SyntaxTokenType::CloseBrace)?;
+
+        Ok(statements)
+    }
+
+    /// Parse `fn name(parameters) { body }`
+    fn parse_function(&mut self) -> Result<FunctionSyntax, ParseError> {
+        // Skip `fn`
+        self.advance();
+
+        // Name
+        if self.current().token_type != SyntaxTokenType::Identifier {
+            return Err(self.unexpected("a function name"));
+        }
+        let name: SyntaxToken = self.next_token();
+
+        // Parameters
+        self.open_parenthesis()?;
+
+        let mut parameters: Vec<String> = Vec::new();
+        while self.current().token_type != SyntaxTokenType::CloseParenthesis {
+            if self.current().token_type != SyntaxTokenType::Identifier {
+                return Err(self.unexpected("a parameter name"));
+            }
+            parameters.push(self.next_token().text);
+
+            match self.current().token_type {
+                SyntaxTokenType::Comma => self.advance(),
+                SyntaxTokenType::CloseParenthesis => {}
+                _ => return Err(self.unexpected("',' or ')'")),
+            }
+        }
+        self.close_parenthesis()?;
+
+        Ok(FunctionSyntax {
+            position: name.position,
+            name: name.text,
+            parameters,
+            body: self.parse_block()?,
+        })
+    }
+
+    /// Parse `import "path"` or `import name`
+    fn parse_import(&mut self) -> Result<ImportSyntax, ParseError> {
+        let start: usize = self.next_token().position;
+
+        let (path, quoted): (String, bool) = match &self.current().token_type {
+            SyntaxTokenType::Identifier => (self.current().text.clone(), false),
+            SyntaxTokenType::String(Ok(segments)) => match segments.as_slice() {
+                [StringSegment::Text(path)] if !path.is_empty() => (path.clone(), true),
+                _ => {
+                    return Err(self.unexpected(
+                        "a path without interpolations, or a module name, after 'import'",
+                    ))
+                }
+            },
+            _ => return Err(self.unexpected("a path in quotes or a module name after 'import'")),
+        };
+        let end: usize = self.next_token().span().end;
+
+        Ok(ImportSyntax {
+            span: Span::new(start, end),
+            path,
+            quoted,
+        })
+    }
+
+    fn parse_statement(&mut self, top_level: bool) -> Result<StatementSyntax, ParseError> {
+        // Function
+        if self.current().token_type == SyntaxTokenType::FnKeyword {
+            if !top_level {
+                return Err(
+                    self.unexpected("a statement (functions can only be defined at the top level)")
+                );
+            }
+
+            return Ok(StatementSyntax::Function(self.parse_function()?));
+        }
+
+        // Import
+        if self.current().token_type == SyntaxTokenType::ImportKeyword {
+            if !top_level {
+                return Err(self.unexpected("a statement (imports can only be at the top level)"));
+            }
+
+            return Ok(StatementSyntax::Import(self.parse_import()?));
+        }
+
+        // Assignment
+        if self.current().token_type == SyntaxTokenType::Identifier
+            && self.peek(1).token_type == SyntaxTokenType::Equals
+        {
+            let name: SyntaxToken = self.next_token();
+            self.advance();
+
+            return Ok(StatementSyntax::Assign(AssignSyntax {
+                position: name.position,
+                name: name.text,
+                value: self.parse_expression()?,
+            }));
+        }
+
+        if self.current().token_type != SyntaxTokenType::LetKeyword {
+            return Ok(StatementSyntax::Expression(self.parse_expression()?));
+        }
+
+        self.advance();
+
+        // Name
+        if self.current().token_type != SyntaxTokenType::Identifier {
+            return Err(self.unexpected("a variable name"));
+        }
+        let name: SyntaxToken = self.next_token();
+
+        // `=`
+        self.expect(SyntaxTokenType::Equals)?;
+
+        Ok(StatementSyntax::Let(LetSyntax {
+            position: name.position,
+            name: name.text,
+            value: self.parse_expression()?,
+        }))
+    }
+
+    pub fn parse_expression(&mut self) -> Result<ExpressionSyntaxEnum, ParseError> {
+        self.parse_conditional()
+    }
+
+    /// Parse `condition ? then : otherwise`, binding looser than every binary operator
+    ///
+    /// Right associative, so `a ? b : c ? d : e` is `a ? b : (c ? d : e)`
+    fn parse_conditional(&mut self) -> Result<ExpressionSyntaxEnum, ParseError> {
+        let condition: ExpressionSyntaxEnum = self.parse_binary(0)?;
+
+        if self.current().token_type != SyntaxTokenType::Question {
+            return Ok(condition);
+        }
+        let position: usize = self.next_token().position;
+        self.skip_new_lines();
+
+        let then: ExpressionSyntaxEnum = self.parse_conditional()?;
+
+        self.expect(SyntaxTokenType::Colon)?;
+        self.skip_new_lines();
+
+        let otherwise: ExpressionSyntaxEnum = self.parse_conditional()?;
+
+        Ok(ExpressionSyntaxEnum::Conditional(Box::new(
+            ConditionalSyntax {
+                position,
+                condition,
+                then,
+                otherwise,
+            },
+        )))
+    }
+
+    /// Skip new lines after a token that can't end an expression, so it continues on the next line
+    fn skip_new_lines(&mut self) {
+        while self.current().token_type == SyntaxTokenType::NewLine {
+            self.advance();
+        }
+    }
+
+    /// Parse operators binding tighter than `minimum_precedence`, left associatively
+    fn parse_binary(&mut self, minimum_precedence: u8) -> Result<ExpressionSyntaxEnum, ParseError> {
+        let start: usize = self.current().position;
+        let mut left: ExpressionSyntaxEnum = self.parse_unary()?;
+
+        while let Some(operator_token) = OperatorToken::from_token_type(&self.current().token_type)
+        {
+            if operator_token.precedence() <= minimum_precedence {
+                break;
+            }
+
+            let position: usize = self.next_token().position;
+
+            // An operator at the end of a line continues the expression on the next line
+            self.skip_new_lines();
+
+            let right: ExpressionSyntaxEnum = self.parse_binary(operator_token.precedence())?;
+
+            if operator_token.chains() && self.current_chains() {
+                left = self.parse_chain(left, position, operator_token, right)?;
+                continue;
+            }
+
+            left = ExpressionSyntaxEnum::ExpressionSyntax(Box::new(ExpressionSyntax {
+                span: Span::new(start, self.previous_end),
+                operator_span: operator_token.span(position),
+                left,
+                operator_token,
+                right,
+            }));
+        }
+
+        Ok(left)
+    }
+
+    /// Whether the current token is an operator that continues a chain
+    fn current_chains(&mut self) -> bool {
+        OperatorToken::from_token_type(&self.current().token_type)
+            .is_some_and(|operator_token: OperatorToken| operator_token.chains())
+    }
+
+    /// Parse the rest of `first < second < ...` once a second chaining operator is found
+    fn parse_chain(
+        &mut self,
+        first: ExpressionSyntaxEnum,
+        position: usize,
+        operator_token: OperatorToken,
+        second: ExpressionSyntaxEnum,
+    ) -> Result<ExpressionSyntaxEnum, ParseError> {
+        let mut links: Vec<ChainLink> = vec![ChainLink {
+            position,
+            operator_token,
+            operand: second,
+        }];
+
+        while let Some(operator_token) = OperatorToken::from_token_type(&self.current().token_type)
+            .filter(|operator_token: &OperatorToken| operator_token.chains())
+        {
+            let position: usize = self.next_token().position;
+            self.skip_new_lines();
+
+            links.push(ChainLink {
+                position,
+                operator_token,
+                operand: self.parse_binary(operator_token.precedence())?,
+            });
+        }
+
+        Ok(ExpressionSyntaxEnum::Chain(Box::new(ChainSyntax {
+            first,
+            links,
+        })))
+    }
+
+    fn parse_unary(&mut self) -> Result<ExpressionSyntaxEnum, ParseError> {
+        let operator_token: OperatorToken = match self.current().token_type {
+            SyntaxTokenType::Plus => OperatorToken::Plus,
+            SyntaxTokenType::Minus => OperatorToken::Minus,
+            _ => return self.parse_postfix(),
+        };
+
+        let position: usize = self.next_token().position;
+
+        // The smallest value of a signed type, like `-128i8`, is written as a minus and a
+        // literal too large on its own, which only the minus makes fit
+        if let (
+            OperatorToken::Minus,
+            SyntaxTokenType::Number(Err(IntegerLiteralError::Minimum { integer_type })),
+        ) = (operator_token, &self.current().token_type)
+        {
+            let value: isize = integer_type.map_or(isize::MIN, |integer_type: IntegerType| {
+                integer_type.range().0 as isize
+            });
+            let token: SyntaxToken = self.next_token();
+
+            // A postfix or percent sign applies to the literal before the minus
+            if matches!(
+                self.current().token_type,
+                SyntaxTokenType::Dot | SyntaxTokenType::OpenBracket
+            ) || self.is_percent_sign()
+            {
+                let SyntaxTokenType::Number(Err(error)) = &token.token_type else {
+                    unreachable!("Only a number too large reaches this");
+                };
+                return Err(ParseError {
+                    kind: number_error(error),
+                    span: token.span(),
+                });
+            }
+
+            return Ok(ExpressionSyntaxEnum::Number(value));
+        }
+
+        Ok(ExpressionSyntaxEnum::Unary(Box::new(
+            UnaryExpressionSyntax {
+                position,
+                operator_token,
+                operand: self.parse_unary()?,
+            },
+        )))
+    }
+
+    /// Parse a primary expression followed by any number of `[index]` and `.name(arguments)`
+    fn parse_postfix(&mut self) -> Result<ExpressionSyntaxEnum, ParseError> {
+        let start: usize = self.current().position;
+        let mut expression: ExpressionSyntaxEnum = self.parse_primary()?;
+
+        loop {
+            if self.current().token_type == SyntaxTokenType::Dot {
+                self.advance();
+
+                if self.current().token_type != SyntaxTokenType::Identifier {
+                    return Err(self.unexpected("a method name"));
+                }
+                if self.peek(1).token_type != SyntaxTokenType::OpenParenthesis {
+                    self.advance();
+                    return Err(self.unexpected("'(' after the method name"));
+                }
+
+                // The call covers the receiver too
+                let mut call: CallSyntax = self.parse_call()?;
+                call.span.start = start;
+                call.text = self.lexer.text()[call.span.start..call.span.end].to_string();
+                call.arguments.insert(0, expression);
+                expression = ExpressionSyntaxEnum::Call(call);
+                continue;
+            }
+
+            if self.current().token_type != SyntaxTokenType::OpenBracket {
+                break;
+            }
+
+            let position: usize = self.next_token().position;
+            let index: ExpressionSyntaxEnum = self.parse_expression()?;
+
+            self.expect(SyntaxTokenType::CloseBracket)?;
+
+            expression = ExpressionSyntaxEnum::Index(Box::new(IndexSyntax {
+                position,
+                target: expression,
+                index,
+            }));
+        }
+
+        Ok(expression)
+    }
+
+    fn parse_primary(&mut self) -> Result<ExpressionSyntaxEnum, ParseError> {
+        match self.current().token_type {
+            SyntaxTokenType::Number(_) | SyntaxTokenType::Float(_) => {
+                let token: SyntaxToken = self.next_token();
+                let span: Span = token.span();
+
+                let expression: ExpressionSyntaxEnum = match token.token_type {
+                    // Every integer type is an int for now, the lexer checked the range
+                    SyntaxTokenType::Number(Ok(literal)) => {
+                        ExpressionSyntaxEnum::Number(literal.value)
+                    }
+                    SyntaxTokenType::Float(Ok(value)) => ExpressionSyntaxEnum::Float(value),
+                    SyntaxTokenType::Number(Err(error)) => {
+                        return Err(ParseError {
+                            kind: number_error(&error),
+                            span,
+                        });
+                    }
+                    SyntaxTokenType::Float(Err(error)) => {
+                        return Err(ParseError {
+                            kind: ParseErrorKind::InvalidNumber(error.to_string()),
+                            span,
+                        })
+                    }
+                    _ => unreachable!("Only numbers reach this arm"),
+                };
+
+                if self.is_percent_sign() {
+                    return self.parse_percent(expression, span.start);
+                }
+
+                Ok(expression)
+            }
+            SyntaxTokenType::String(_) => {
+                let token: SyntaxToken = self.next_token();
+                let span: Span = token.span();
+
+                match token.token_type {
+                    SyntaxTokenType::String(Ok(segments)) => {
+                        Ok(ExpressionSyntaxEnum::String(segments))
+                    }
+                    SyntaxTokenType::String(Err(error)) => Err(ParseError {
+                        kind: ParseErrorKind::InvalidString(error.to_string()),
+                        span,
+                    }),
+                    _ => unreachable!("Only strings reach this arm"),
+                }
+            }
+            SyntaxTokenType::Identifier => {
+                if self.peek(1).token_type == SyntaxTokenType::OpenParenthesis {
+                    return Ok(ExpressionSyntaxEnum::Call(self.parse_call()?));
+                }
+
+                let token: SyntaxToken = self.next_token();
+                Ok(ExpressionSyntaxEnum::Variable(VariableSyntax {
+                    position: token.position,
+                    name: token.text,
+                }))
+            }
+            SyntaxTokenType::OpenBracket => self.parse_array(),
+            SyntaxTokenType::OpenParenthesis => {
+                self.open_parenthesis()?;
+                let expression: ExpressionSyntaxEnum = self.parse_expression()?;
+                self.close_parenthesis()?;
+
+                Ok(expression)
+            }
+            // An operator where an operand should be, like the `*` in `1 + * 2`
+            ref token_type if OperatorToken::from_token_type(token_type).is_some() => {
+                Err(self.unexpected("a number"))
+            }
+            _ => Err(self.unexpected("an expression")),
+        }
+    }
+
+    /// Whether the current token is a `%` meaning percent after a number literal, not modulo
+    ///
+    /// Only the tokens decide, never the spaces between them. The `%` is modulo when an operand
+    /// follows it, like in `10 % 3`, and a percent sign otherwise, like in `10% * 200`. A `+`
+    /// or `-` after it adds to or subtracts from the percentage, so `50%+1` is 1.5 like
+    /// `50% + 1`, and modulo by a negative number is written `10 % (-3)`. `of` followed by an
+    /// operand takes a percentage of it, like in `50% of 200`, a variable named `of` is still
+    /// modulo anywhere else, like in `10 % of`.
+    fn is_percent_sign(&mut self) -> bool {
+        if self.current().token_type != SyntaxTokenType::Percent {
+            return false;
+        }
+
+        let next: &SyntaxToken = self.peek(1);
+        match &next.token_type {
+            SyntaxTokenType::Identifier if next.text == "of" => {
+                starts_operand(&self.peek(2).token_type)
+            }
+            SyntaxTokenType::Plus | SyntaxTokenType::Minus => true,
+            token_type => !starts_operand(token_type),
+        }
+    }
+
+    /// Parse the `%` after a number as dividing it by 100, and `of operand` as multiplying by it
+    fn parse_percent(
+        &mut self,
+        number: ExpressionSyntaxEnum,
+        start: usize,
+    ) -> Result<ExpressionSyntaxEnum, ParseError> {
+        // Skip `%`
+        self.advance();
+
+        let percentage: ExpressionSyntaxEnum = match number {
+            ExpressionSyntaxEnum::Number(value) => {
+                ExpressionSyntaxEnum::Float(value as f64 / 100.0)
+            }
+            ExpressionSyntaxEnum::Float(value) => ExpressionSyntaxEnum::Float(value / 100.0),
+            _ => unreachable!("Only number literals can be percentages"),
+        };
+
+        let current: &SyntaxToken = self.current();
+        if current.token_type != SyntaxTokenType::Identifier || current.text != "of" {
+            return Ok(percentage);
+        }
+        let operator_span: Span = self.next_token().span();
+        let right: ExpressionSyntaxEnum = self.parse_unary()?;
+
+        Ok(ExpressionSyntaxEnum::ExpressionSyntax(Box::new(
+            ExpressionSyntax {
+                span: Span::new(start, self.previous_end),
+                operator_span,
+                left: percentage,
+                operator_token: OperatorToken::Star,
+                right,
+            },
+        )))
+    }
+
+    /// Parse `name(arguments)`
+    fn parse_call(&mut self) -> Result<CallSyntax, ParseError> {
+        let name: SyntaxToken = self.next_token();
+
+        self.open_parenthesis()?;
+
+        let mut arguments: Vec<ExpressionSyntaxEnum> = Vec::new();
+        while self.current().token_type != SyntaxTokenType::CloseParenthesis {
+            arguments.push(self.parse_expression()?);
+
+            match self.current().token_type {
+                SyntaxTokenType::Comma => self.advance(),
+                SyntaxTokenType::CloseParenthesis => {}
+                _ => return Err(self.unexpected("',' or ')'")),
+            }
+        }
+        let close: SyntaxToken = self.close_parenthesis()?;
+
+        let span: Span = Span::new(name.position, close.span().end);
+
+        Ok(CallSyntax {
+            span,
+            text: self.lexer.text()[span.start..span.end].to_string(),
+            name: name.text,
+            arguments,
+        })
+    }
+
+    /// Parse `[elements]`, new lines are allowed between elements
+    fn parse_array(&mut self) -> Result<ExpressionSyntaxEnum, ParseError> {
+        // Skip `[`
+        self.advance();
+        self.skip_new_lines();
+
+        let mut elements: Vec<ExpressionSyntaxEnum> = Vec::new();
+        while self.current().token_type != SyntaxTokenType::CloseBracket {
+            elements.push(self.parse_expression()?);
+            self.skip_new_lines();
+
+            match self.current().token_type {
+                SyntaxTokenType::Comma => {
+                    self.advance();
+                    self.skip_new_lines();
+                }
+                SyntaxTokenType::CloseBracket => {}
+                _ => return Err(self.unexpected("',' or ']'")),
+            }
+        }
+        self.advance();
+
+        Ok(ExpressionSyntaxEnum::Array(elements))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing_lexes_only_what_it_needs() {
+        // Several megabytes of statements after the first one
+        let source: String = format!("1 + 2\n{}", "3 * 4\n".repeat(1_000_000));
+        let mut parser: Parser = Parser::new(source);
+
+        assert!(parser.parse_expression().is_ok());
+        // The lexer stopped at the new line after the expression, with nothing buffered past it
+        assert_eq!(
+            parser.lexer.syntax_token.token_type,
+            SyntaxTokenType::NewLine
+        );
+        assert_eq!(parser.lexer.syntax_token.position, 5);
+        assert_eq!(parser.lookahead.len(), 1);
+    }
+
+    #[test]
+    fn expect_consumes_a_matching_token() {
+        let mut parser: Parser = Parser::new("( x".to_string());
+
+        let token: SyntaxToken = parser
+            .expect(SyntaxTokenType::OpenParenthesis)
+            .expect("The first token is '('");
+        assert_eq!(token.text, "(");
+        assert_eq!(token.position, 0);
+        assert_eq!(parser.current().text, "x");
+    }
+
+    #[test]
+    fn expect_leaves_a_mismatched_token() {
+        let mut parser: Parser = Parser::new("x )".to_string());
+
+        assert_eq!(
+            parser.expect(SyntaxTokenType::OpenBrace).err(),
+            Some(ParseError {
+                kind: ParseErrorKind::UnexpectedToken {
+                    expected: "'{'".to_string(),
+                    found: "x".to_string(),
+                },
+                span: Span::new(0, 1),
+            })
+        );
+        assert_eq!(parser.current().text, "x");
+    }
+
+    #[test]
+    fn expect_at_the_end_of_the_input() {
+        let mut parser: Parser = Parser::new("".to_string());
+
+        assert_eq!(
+            parser.expect(SyntaxTokenType::CloseParenthesis).err(),
+            Some(ParseError {
+                kind: ParseErrorKind::UnexpectedEndOfFile {
+                    expected: "')'".to_string(),
+                },
+                span: Span::new(0, 0),
+            })
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use crate::bytecode::Program;
+use crate::diagnostic::Diagnostic;
+use crate::parser::StatementSyntax;
+
+/// Highest `--opt-level`, `-O` is short for it
+pub const MAX_OPT_LEVEL: u8 = 2;
+
+/// What a pass rewrites
+#[derive(Clone, Copy, Debug)]
+pub enum PassKind {
+    /// Rewrites the syntax tree, returning warnings, or the error that stops the build
+    Ast(fn(&mut [StatementSyntax]) -> Result<Vec<Diagnostic>, Diagnostic>),
+    /// Rewrites compiled bytecode
+    Bytecode(fn(&mut Program)),
+}
+
+/// Step of the pipeline between parsing and running
+#[derive(Clone, Copy, Debug)]
+pub struct Pass {
+    /// Name for `--emit ast-after=name`
+    pub name: &'static str,
+    /// Lowest `--opt-level` that runs the pass
+    pub level: u8,
+    pub kind: PassKind,
+}
+
+/// Every pass in the order they run, the syntax tree passes run before compiling
+///
+/// Level 0 runs none of them. Dividing a literal by zero is still reported at every level,
+/// by `crate::check::check_division`.
+pub const PASSES: [Pass; 3] = [
+    Pass {
+        name: "const_fold",
+        level: 1,
+        kind: PassKind::Ast(const_fold),
+    },
+    Pass {
+        name: "dead_code",
+        level: 2,
+        kind: PassKind::Ast(dead_code),
+    },
+    Pass {
+        name: "peephole",
+        level: 1,
+        kind: PassKind::Bytecode(crate::optimize::optimize),
+    },
+];
+
+fn const_fold(statements: &mut [StatementSyntax]) -> Result<Vec<Diagnostic>, Diagnostic> {
+    crate::fold::fold_statements(statements).map(|()| Vec::new())
+}
+
+fn dead_code(statements: &mut [StatementSyntax]) -> Result<Vec<Diagnostic>, Diagnostic> {
+    Ok(crate::dead_code::eliminate_dead_code(statements))
+}
+
+/// Pass called `name`
+pub fn find_pass(name: &str) -> Option<&'static Pass> {
+    PASSES.iter().find(|pass: &&Pass| pass.name == name)
+}
+
+/// Run the syntax tree passes of `level` in order, stopping after the one called `last`
+///
+/// Returns the warnings of every pass that ran, or the first error.
+pub fn run_ast_passes(
+    statements: &mut [StatementSyntax],
+    level: u8,
+    last: Option<&str>,
+) -> Result<Vec<Diagnostic>, Diagnostic> {
+    let mut warnings: Vec<Diagnostic> = Vec::new();
+
+    for pass in PASSES.iter().filter(|pass: &&Pass| pass.level <= level) {
+        if let PassKind::Ast(run) = pass.kind {
+            warnings.extend(run(statements)?);
+        }
+        if Some(pass.name) == last {
+            break;
+        }
+    }
+
+    Ok(warnings)
+}
+
+/// Run the bytecode passes of `level` in order, stopping after the one called `last`
+pub fn run_bytecode_passes(program: &mut Program, level: u8, last: Option<&str>) {
+    for pass in PASSES.iter().filter(|pass: &&Pass| pass.level <= level) {
+        if let PassKind::Bytecode(run) = pass.kind {
+            run(program);
+        }
+        if Some(pass.name) == last {
+            break;
+        }
+    }
+}
+
+/// Read the `-O` and `--opt-level` options
+pub fn get_opt_level(arg_match: &clap::ArgMatches) -> u8 {
+    given_opt_level(arg_match).unwrap_or(0)
+}
+
+/// Level `-O` or `--opt-level` asks for, `None` without either
+pub fn given_opt_level(arg_match: &clap::ArgMatches) -> Option<u8> {
+    if arg_match.get_flag("optimize") {
+        return Some(MAX_OPT_LEVEL);
+    }
+
+    arg_match.get_one::<u8>("opt-level").copied()
+}
@@ -0,0 +1,768 @@
+use crate::builtins::builtin;
+use crate::bytecode::{CallSite, FunctionInfo, Instruction, Program};
+use crate::diagnostic::{SourceFile, Span};
+use crate::eval::{RuntimeError, Value};
+use crate::interner::Symbol;
+use crate::lexer::StringSegment;
+use crate::parser::OperatorToken;
+
+/// First bytes of every compiled file
+pub const MAGIC: &[u8; 4] = b"PHC\0";
+
+/// Layout version, files with a different version are refused instead of being misread
+pub const VERSION: u16 = 4;
+
+/// Operators in the order of their encoding
+const OPERATORS: [OperatorToken; 16] = [
+    OperatorToken::Plus,
+    OperatorToken::Minus,
+    OperatorToken::Star,
+    OperatorToken::Slash,
+    OperatorToken::Percent,
+    OperatorToken::Ampersand,
+    OperatorToken::Pipe,
+    OperatorToken::Caret,
+    OperatorToken::LessLess,
+    OperatorToken::GreaterGreater,
+    OperatorToken::Less,
+    OperatorToken::LessEquals,
+    OperatorToken::Greater,
+    OperatorToken::GreaterEquals,
+    OperatorToken::EqualsEquals,
+    OperatorToken::BangEquals,
+];
+
+/// Encoding of an operator
+fn operator_index(operator_token: OperatorToken) -> usize {
+    OPERATORS
+        .iter()
+        .position(|known: &OperatorToken| *known == operator_token)
+        .expect("Every operator is in the table")
+}
+
+/// Why a compiled file can't be run
+#[derive(Clone, Debug, PartialEq)]
+pub enum PhcError {
+    /// Written by a ph with a different layout version
+    Version(u16),
+    /// The checksum doesn't match, the file was changed after it was written
+    Checksum,
+    /// The file ends in the middle of the program
+    Truncated,
+    /// The contents don't make a valid program, like a jump past the end
+    Invalid(String),
+}
+
+impl std::fmt::Display for PhcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PhcError::Version(version) => write!(
+                f,
+                "compiled with bytecode version {} but this ph runs version {}, build it again",
+                version, VERSION
+            ),
+            PhcError::Checksum => write!(f, "checksum mismatch, the file is corrupted"),
+            PhcError::Truncated => write!(f, "the file is truncated"),
+            PhcError::Invalid(message) => write!(f, "invalid bytecode: {}", message),
+        }
+    }
+}
+
+/// Whether `bytes` start like a compiled file rather than source text
+pub fn is_phc(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// 64-bit FNV-1a hash of `bytes`
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash: u64, byte: &u8| {
+            (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// Encode a program and the source it was compiled from, the source is kept so runtime errors
+/// can point into it, with the name of the file given and the files it imported so they are
+/// named
+///
+/// The layout is the magic bytes, the version, the length and checksum of the rest, then the
+/// program.
+/// Numbers are little endian, indices and lengths take 4 bytes. The spans of the instructions
+/// follow the code as a line table, see `Writer::line_table`.
+pub fn encode(program: &Program, name: &str, source: &str, imports: &[SourceFile]) -> Vec<u8> {
+    let mut payload: Writer = Writer::default();
+
+    payload.string(name);
+    payload.string(source);
+    payload.length(imports.len());
+    for file in imports {
+        payload.string(&file.name);
+        payload.span(Span::new(file.start, file.end));
+    }
+    payload.bytes.push(program.has_result as u8);
+
+    payload.length(program.constants.len());
+    for constant in &program.constants {
+        payload.value(constant);
+    }
+
+    payload.length(program.strings.len());
+    for segments in &program.strings {
+        payload.length(segments.len());
+        for segment in segments {
+            match segment {
+                StringSegment::Text(text) => {
+                    payload.bytes.push(0);
+                    payload.string(text);
+                }
+                StringSegment::EnvironmentVariable { name, position } => {
+                    payload.bytes.push(1);
+                    payload.string(name);
+                    payload.length(*position);
+                }
+            }
+        }
+    }
+
+    payload.length(program.names.len());
+    for name in &program.names {
+        payload.string(name);
+    }
+
+    payload.length(program.functions.len());
+    for function in &program.functions {
+        payload.string(&function.name);
+        payload.length(function.address);
+        payload.length(function.parameters);
+        payload.length(function.locals);
+    }
+
+    payload.length(program.call_sites.len());
+    for site in &program.call_sites {
+        payload.string(&site.name);
+        payload.span(site.span);
+        payload.string(&site.text);
+        payload.length(site.arguments);
+    }
+
+    payload.length(program.errors.len());
+    for error in &program.errors {
+        payload.string(&error.message);
+        payload.span(error.span);
+    }
+
+    payload.length(program.code.len());
+    for instruction in &program.code {
+        payload.instruction(*instruction);
+    }
+    payload.line_table(&program.spans);
+
+    let mut bytes: Vec<u8> = MAGIC.to_vec();
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(
+        &u32::try_from(payload.bytes.len())
+            .expect("Programs are smaller than 4 GiB")
+            .to_le_bytes(),
+    );
+    bytes.extend_from_slice(&checksum(&payload.bytes).to_le_bytes());
+    bytes.extend_from_slice(&payload.bytes);
+    bytes
+}
+
+/// Size of the magic bytes, version, payload length and checksum
+const HEADER: usize = MAGIC.len() + 2 + 4 + 8;
+
+/// Decode a compiled file into its program, the name of the file it was compiled from, its
+/// source and imported files, checking everything the machine relies on so a damaged file is
+/// refused before anything runs
+pub fn decode(bytes: &[u8]) -> Result<(Program, String, String, Vec<SourceFile>), PhcError> {
+    if !is_phc(bytes) {
+        return Err(PhcError::Invalid("missing magic bytes".to_string()));
+    }
+    if let Some(error) = check_header(bytes).into_iter().next() {
+        return Err(error);
+    }
+
+    let mut reader: Reader = Reader {
+        bytes: &bytes[HEADER..],
+        position: 0,
+    };
+    let mut program: Program = Program::default();
+    let mut name: String = String::new();
+    let mut source: String = String::new();
+    let mut imports: Vec<SourceFile> = Vec::new();
+
+    read_payload(
+        &mut reader,
+        &mut program,
+        &mut name,
+        &mut source,
+        &mut imports,
+        None,
+    )?;
+
+    if reader.position != reader.bytes.len() {
+        return Err(PhcError::Invalid(
+            "unexpected bytes after the program".to_string(),
+        ));
+    }
+
+    validate(&program, &source, &imports)?;
+
+    Ok((program, name, source, imports))
+}
+
+/// As much of a compiled file as could be read, for the disassembler
+#[derive(Debug, Default)]
+pub struct Salvaged {
+    pub program: Program,
+    pub name: String,
+    pub source: String,
+    pub imports: Vec<SourceFile>,
+    /// Everything `decode` would have refused the file for
+    pub problems: Vec<PhcError>,
+    /// Bytes in the code that don't start an instruction, with the index of the instruction
+    /// they come before
+    pub bad_bytes: Vec<(usize, u8)>,
+}
+
+/// Read a compiled file like `decode` but keep going past problems, an instruction that doesn't
+/// decode is skipped one byte at a time
+pub fn salvage(bytes: &[u8]) -> Salvaged {
+    let mut salvaged: Salvaged = Salvaged::default();
+    if !is_phc(bytes) {
+        salvaged
+            .problems
+            .push(PhcError::Invalid("missing magic bytes".to_string()));
+        return salvaged;
+    }
+
+    salvaged.problems = check_header(bytes);
+    let mut reader: Reader = Reader {
+        bytes: bytes.get(HEADER..).unwrap_or_default(),
+        position: 0,
+    };
+
+    match read_payload(
+        &mut reader,
+        &mut salvaged.program,
+        &mut salvaged.name,
+        &mut salvaged.source,
+        &mut salvaged.imports,
+        Some(&mut salvaged.bad_bytes),
+    ) {
+        Ok(()) if reader.position != reader.bytes.len() => salvaged.problems.push(
+            PhcError::Invalid("unexpected bytes after the program".to_string()),
+        ),
+        Ok(()) => {}
+        Err(error) if salvaged.problems.contains(&error) => {}
+        Err(error) => salvaged.problems.push(error),
+    }
+
+    if salvaged.problems.is_empty() && salvaged.bad_bytes.is_empty() {
+        if let Err(error) = validate(&salvaged.program, &salvaged.source, &salvaged.imports) {
+            salvaged.problems.push(error);
+        }
+    }
+
+    salvaged
+}
+
+/// Problems with the version, length and checksum of a file that starts with the magic bytes
+fn check_header(bytes: &[u8]) -> Vec<PhcError> {
+    if bytes.len() < HEADER {
+        return vec![PhcError::Truncated];
+    }
+
+    let mut problems: Vec<PhcError> = Vec::new();
+
+    let version: u16 = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version != VERSION {
+        problems.push(PhcError::Version(version));
+    }
+
+    let length: usize =
+        u32::from_le_bytes(bytes[6..10].try_into().expect("Header is 18 bytes")) as usize;
+    if bytes.len() - HEADER < length {
+        problems.push(PhcError::Truncated);
+    } else if bytes.len() - HEADER > length {
+        problems.push(PhcError::Invalid(
+            "unexpected bytes after the program".to_string(),
+        ));
+    }
+
+    let expected: u64 = u64::from_le_bytes(bytes[10..18].try_into().expect("Header is 18 bytes"));
+    if checksum(&bytes[HEADER..]) != expected {
+        problems.push(PhcError::Checksum);
+    }
+
+    problems
+}
+
+/// Read the payload into `program`, `name`, `source` and `imports`, which keep whatever was
+/// read before an error
+///
+/// With `bad_bytes`, a byte that doesn't start an instruction is recorded there and skipped
+/// instead of failing.
+fn read_payload(
+    reader: &mut Reader,
+    program: &mut Program,
+    name: &mut String,
+    source: &mut String,
+    imports: &mut Vec<SourceFile>,
+    mut bad_bytes: Option<&mut Vec<(usize, u8)>>,
+) -> Result<(), PhcError> {
+    *name = reader.string()?;
+    *source = reader.string()?;
+    for _ in 0..reader.length()? {
+        let name: String = reader.string()?;
+        let span: Span = reader.span()?;
+        imports.push(SourceFile {
+            name,
+            start: span.start,
+            end: span.end,
+        });
+    }
+    program.has_result = match reader.byte()? {
+        0 => false,
+        1 => true,
+        byte => return Err(PhcError::Invalid(format!("bad result flag {}", byte))),
+    };
+
+    for _ in 0..reader.length()? {
+        program.constants.push(reader.value()?);
+    }
+
+    for _ in 0..reader.length()? {
+        let mut segments: Vec<StringSegment> = Vec::new();
+        for _ in 0..reader.length()? {
+            segments.push(match reader.byte()? {
+                0 => StringSegment::Text(reader.string()?),
+                1 => StringSegment::EnvironmentVariable {
+                    name: reader.string()?,
+                    position: reader.length()?,
+                },
+                tag => return Err(PhcError::Invalid(format!("bad string segment {}", tag))),
+            });
+        }
+        program.strings.push(segments);
+    }
+
+    for _ in 0..reader.length()? {
+        program.names.push(reader.string()?);
+    }
+
+    for _ in 0..reader.length()? {
+        program.functions.push(FunctionInfo {
+            name: reader.string()?,
+            address: reader.length()?,
+            parameters: reader.length()?,
+            locals: reader.length()?,
+        });
+    }
+
+    for _ in 0..reader.length()? {
+        program.call_sites.push(CallSite {
+            name: reader.string()?,
+            span: reader.span()?,
+            text: reader.string()?,
+            arguments: reader.length()?,
+        });
+    }
+
+    for _ in 0..reader.length()? {
+        let message: String = reader.string()?;
+        program
+            .errors
+            .push(RuntimeError::new(message, reader.span()?));
+    }
+
+    let count: usize = reader.length()?;
+    while program.code.len() < count {
+        let start: usize = reader.position;
+
+        match (reader.instruction(), bad_bytes.as_deref_mut()) {
+            (Ok(instruction), _) => program.code.push(instruction),
+            (Err(PhcError::Invalid(_)), Some(bad_bytes)) => {
+                reader.position = start;
+                bad_bytes.push((program.code.len(), reader.byte()?));
+            }
+            (Err(error), _) => return Err(error),
+        }
+    }
+
+    program.spans = reader.line_table(program.code.len())?;
+    if program.spans.len() != program.code.len() {
+        return Err(PhcError::Invalid(
+            "the line table covers fewer instructions than there are".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Check that every operand points at something that exists, every span is in `source` and
+/// the imported files follow each other from its start
+fn validate(program: &Program, source: &str, imports: &[SourceFile]) -> Result<(), PhcError> {
+    let invalid = |index: usize, message: &str| -> PhcError {
+        PhcError::Invalid(format!("instruction {}: {}", index, message))
+    };
+    let in_source = |span: &Span| -> bool {
+        span.start <= span.end
+            && source.is_char_boundary(span.start)
+            && source.is_char_boundary(span.end)
+    };
+
+    if !program.spans.iter().all(in_source)
+        || !program
+            .call_sites
+            .iter()
+            .all(|site: &CallSite| in_source(&site.span))
+        || !program
+            .errors
+            .iter()
+            .all(|error: &RuntimeError| in_source(&error.span))
+        || !program
+            .strings
+            .iter()
+            .flatten()
+            .all(|segment: &StringSegment| {
+                match segment {
+                    StringSegment::Text(_) => true,
+                    // Errors about the variable cover `${NAME}`
+                    StringSegment::EnvironmentVariable { name, position } => in_source(&Span::new(
+                        *position,
+                        position.saturating_add(name.len() + 3),
+                    )),
+                }
+            })
+    {
+        return Err(PhcError::Invalid("span outside of the source".to_string()));
+    }
+
+    let mut previous: usize = 0;
+    for file in imports {
+        if file.start != previous || !in_source(&Span::new(file.start, file.end)) {
+            return Err(PhcError::Invalid(format!(
+                "imported file '{}' is not where the one before it ends",
+                file.name
+            )));
+        }
+        previous = file.end;
+    }
+
+    if program.code.last() != Some(&Instruction::Return) {
+        return Err(PhcError::Invalid(
+            "the program doesn't end with a return".to_string(),
+        ));
+    }
+
+    for function in &program.functions {
+        if function.address >= program.code.len() || function.locals < function.parameters {
+            return Err(PhcError::Invalid(format!(
+                "function '{}' is malformed",
+                function.name
+            )));
+        }
+    }
+
+    for (index, instruction) in program.code.iter().enumerate() {
+        let in_range: bool = match *instruction {
+            Instruction::Push(constant) => constant < program.constants.len(),
+            Instruction::Interpolate(string) => string < program.strings.len(),
+            Instruction::LoadGlobal(name)
+            | Instruction::StoreGlobal(name)
+            | Instruction::DefineGlobal(name) => (name as usize) < program.names.len(),
+            Instruction::Jump(target) | Instruction::JumpIfFalse(target) => {
+                target < program.code.len()
+            }
+            Instruction::Call { function, site } => {
+                function < program.functions.len() && site < program.call_sites.len()
+            }
+            Instruction::CallBuiltin(site) => program
+                .call_sites
+                .get(site)
+                .is_some_and(|site: &CallSite| builtin(&site.name).is_some()),
+            Instruction::Fail(error) => error < program.errors.len(),
+            _ => true,
+        };
+
+        if !in_range {
+            return Err(invalid(index, "operand out of range"));
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn length(&mut self, length: usize) {
+        let length: u32 = u32::try_from(length).expect("Programs are smaller than 4 GiB");
+        self.bytes.extend_from_slice(&length.to_le_bytes());
+    }
+
+    fn string(&mut self, text: &str) {
+        self.length(text.len());
+        self.bytes.extend_from_slice(text.as_bytes());
+    }
+
+    fn span(&mut self, span: Span) {
+        self.length(span.start);
+        self.length(span.end);
+    }
+
+    /// Spans of the instructions, as runs of instructions that share one
+    ///
+    /// Each run is the number of instructions, how far its start moved from the start of the
+    /// previous run and its length, as LEB128 numbers. Consecutive instructions usually come
+    /// from the same expression, so this takes a few bytes per statement instead of 8 per
+    /// instruction.
+    fn line_table(&mut self, spans: &[Span]) {
+        let mut runs: Vec<(usize, Span)> = Vec::new();
+        for span in spans {
+            match runs.last_mut() {
+                Some((count, last)) if last == span => *count += 1,
+                _ => runs.push((1, *span)),
+            }
+        }
+
+        self.length(runs.len());
+        let mut previous: usize = 0;
+        for (count, span) in runs {
+            self.bytes.extend(crate::wasm::unsigned(count as u64));
+            self.bytes
+                .extend(crate::wasm::signed(span.start as i64 - previous as i64));
+            self.bytes
+                .extend(crate::wasm::unsigned((span.end - span.start) as u64));
+            previous = span.start;
+        }
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Int(value) => {
+                self.bytes.push(0);
+                self.bytes.extend_from_slice(&(*value as i64).to_le_bytes());
+            }
+            Value::Float(value) => {
+                self.bytes.push(1);
+                self.bytes.extend_from_slice(&value.to_bits().to_le_bytes());
+            }
+            Value::String(text) => {
+                self.bytes.push(2);
+                self.string(text);
+            }
+            Value::Bool(value) => {
+                self.bytes.push(3);
+                self.bytes.push(*value as u8);
+            }
+            Value::Array(elements) => {
+                self.bytes.push(4);
+                self.length(elements.len());
+                for element in elements {
+                    self.value(element);
+                }
+            }
+            Value::Nil => self.bytes.push(5),
+        }
+    }
+
+    fn instruction(&mut self, instruction: Instruction) {
+        let (opcode, operands): (u8, &[usize]) = match instruction {
+            Instruction::Push(constant) => (0, &[constant]),
+            Instruction::Interpolate(string) => (1, &[string]),
+            Instruction::Array(count) => (2, &[count]),
+            Instruction::Index => (3, &[]),
+            Instruction::Negate => (4, &[]),
+            Instruction::Add => (5, &[]),
+            Instruction::Sub => (6, &[]),
+            Instruction::Mul => (7, &[]),
+            Instruction::Div => (8, &[]),
+            Instruction::Operator(operator_token) => (9, &[operator_index(operator_token)]),
+            Instruction::Load(slot) => (10, &[slot]),
+            Instruction::Store(slot) => (11, &[slot]),
+            Instruction::LoadGlobal(name) => (12, &[name as usize]),
+            Instruction::StoreGlobal(name) => (13, &[name as usize]),
+            Instruction::DefineGlobal(name) => (14, &[name as usize]),
+            Instruction::Pop => (15, &[]),
+            Instruction::Jump(target) => (16, &[target]),
+            Instruction::JumpIfFalse(target) => (17, &[target]),
+            Instruction::Call { function, site } => (18, &[function, site]),
+            Instruction::CallBuiltin(site) => (19, &[site]),
+            Instruction::Yield => (20, &[]),
+            Instruction::Return => (21, &[]),
+            Instruction::Fail(error) => (22, &[error]),
+            Instruction::Compare(operator_token) => (23, &[operator_index(operator_token)]),
+        };
+
+        self.bytes.push(opcode);
+        for operand in operands {
+            self.length(*operand);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, count: usize) -> Result<&[u8], PhcError> {
+        let bytes: &[u8] = self
+            .bytes
+            .get(self.position..self.position.saturating_add(count))
+            .ok_or(PhcError::Truncated)?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, PhcError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u64(&mut self) -> Result<u64, PhcError> {
+        Ok(u64::from_le_bytes(
+            self.take(8)?.try_into().expect("Took 8 bytes"),
+        ))
+    }
+
+    fn length(&mut self) -> Result<usize, PhcError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().expect("Took 4 bytes")) as usize)
+    }
+
+    fn symbol(&mut self) -> Result<Symbol, PhcError> {
+        Ok(u32::from_le_bytes(
+            self.take(4)?.try_into().expect("Took 4 bytes"),
+        ))
+    }
+
+    fn string(&mut self) -> Result<String, PhcError> {
+        let length: usize = self.length()?;
+        String::from_utf8(self.take(length)?.to_vec())
+            .map_err(|_| PhcError::Invalid("string is not valid UTF-8".to_string()))
+    }
+
+    fn span(&mut self) -> Result<Span, PhcError> {
+        Ok(Span::new(self.length()?, self.length()?))
+    }
+
+    /// LEB128 number of at most 64 bits, sign extended when `signed`
+    fn leb128(&mut self, signed: bool) -> Result<i128, PhcError> {
+        let mut value: i128 = 0;
+        let mut shift: u32 = 0;
+        loop {
+            let byte: u8 = self.byte()?;
+            value |= ((byte & 0x7F) as i128) << shift;
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                if signed && shift < 128 && byte & 0x40 != 0 {
+                    value |= -1 << shift;
+                }
+                return Ok(value);
+            }
+            if shift >= 70 {
+                return Err(PhcError::Invalid(
+                    "line table number is too long".to_string(),
+                ));
+            }
+        }
+    }
+
+    /// Spans of the `instructions` instructions from the runs `Writer::line_table` writes
+    fn line_table(&mut self, instructions: usize) -> Result<Vec<Span>, PhcError> {
+        let invalid = || PhcError::Invalid("line table points outside of the source".to_string());
+
+        let mut spans: Vec<Span> = Vec::new();
+        let mut previous: i128 = 0;
+        for _ in 0..self.length()? {
+            let count: i128 = self.leb128(false)?;
+            let start: i128 = previous + self.leb128(true)?;
+            let length: i128 = self.leb128(false)?;
+            previous = start;
+
+            let start: usize = usize::try_from(start).map_err(|_| invalid())?;
+            let end: usize = usize::try_from(length)
+                .ok()
+                .and_then(|length: usize| start.checked_add(length))
+                .ok_or_else(invalid)?;
+            let count: usize = usize::try_from(count)
+                .ok()
+                .filter(|count: &usize| *count <= instructions - spans.len())
+                .ok_or_else(|| {
+                    PhcError::Invalid(
+                        "the line table covers more instructions than there are".to_string(),
+                    )
+                })?;
+            spans.extend(std::iter::repeat_n(Span::new(start, end), count));
+        }
+
+        Ok(spans)
+    }
+
+    fn value(&mut self) -> Result<Value, PhcError> {
+        match self.byte()? {
+            0 => Ok(Value::Int(self.u64()? as i64 as isize)),
+            1 => Ok(Value::Float(f64::from_bits(self.u64()?))),
+            2 => Ok(Value::String(self.string()?)),
+            3 => Ok(Value::Bool(self.byte()? != 0)),
+            4 => {
+                let mut elements: Vec<Value> = Vec::new();
+                for _ in 0..self.length()? {
+                    elements.push(self.value()?);
+                }
+                Ok(Value::Array(elements))
+            }
+            5 => Ok(Value::Nil),
+            tag => Err(PhcError::Invalid(format!("bad constant tag {}", tag))),
+        }
+    }
+
+    fn operator(&mut self) -> Result<OperatorToken, PhcError> {
+        OPERATORS
+            .get(self.length()?)
+            .copied()
+            .ok_or_else(|| PhcError::Invalid("bad operator".to_string()))
+    }
+
+    fn instruction(&mut self) -> Result<Instruction, PhcError> {
+        Ok(match self.byte()? {
+            0 => Instruction::Push(self.length()?),
+            1 => Instruction::Interpolate(self.length()?),
+            2 => Instruction::Array(self.length()?),
+            3 => Instruction::Index,
+            4 => Instruction::Negate,
+            5 => Instruction::Add,
+            6 => Instruction::Sub,
+            7 => Instruction::Mul,
+            8 => Instruction::Div,
+            9 => Instruction::Operator(self.operator()?),
+            10 => Instruction::Load(self.length()?),
+            11 => Instruction::Store(self.length()?),
+            12 => Instruction::LoadGlobal(self.symbol()?),
+            13 => Instruction::StoreGlobal(self.symbol()?),
+            14 => Instruction::DefineGlobal(self.symbol()?),
+            15 => Instruction::Pop,
+            16 => Instruction::Jump(self.length()?),
+            17 => Instruction::JumpIfFalse(self.length()?),
+            18 => Instruction::Call {
+                function: self.length()?,
+                site: self.length()?,
+            },
+            19 => Instruction::CallBuiltin(self.length()?),
+            20 => Instruction::Yield,
+            21 => Instruction::Return,
+            22 => Instruction::Fail(self.length()?),
+            23 => Instruction::Compare(self.operator()?),
+            opcode => return Err(PhcError::Invalid(format!("bad opcode {}", opcode))),
+        })
+    }
+}
@@ -0,0 +1,314 @@
+/// Name of the project manifest, looked for in the current directory and every directory above
+pub const MANIFEST: &str = "Phoenix.toml";
+
+/// Entry point of a new project, relative to the manifest
+pub const DEFAULT_ENTRY: &str = "src/main.ph";
+
+/// What `Phoenix.toml` says about a project
+#[derive(Clone, Debug, PartialEq)]
+pub struct Manifest {
+    pub name: String,
+    pub version: String,
+    /// Source file `ph build` and `ph run` use without a file, relative to the manifest
+    pub entry: String,
+    /// Default for `--opt-level` when `ph build` builds, or `ph run` runs, the entry point
+    pub opt_level: Option<u8>,
+}
+
+impl Manifest {
+    pub fn new(name: &str) -> Manifest {
+        Manifest {
+            name: name.to_string(),
+            version: "0.1.0".to_string(),
+            entry: DEFAULT_ENTRY.to_string(),
+            opt_level: None,
+        }
+    }
+
+    /// The manifest as `Phoenix.toml` holds it
+    pub fn to_toml(&self) -> String {
+        let mut toml: String = format!(
+            "[package]\nname = {:?}\nversion = {:?}\nentry = {:?}\n",
+            self.name, self.version, self.entry
+        );
+        if let Some(opt_level) = self.opt_level {
+            toml.push_str(&format!("\n[build]\nopt-level = {}\n", opt_level));
+        }
+
+        toml
+    }
+}
+
+/// Parse a `Phoenix.toml`
+///
+/// `package.name` and `package.version` are required, `package.entry` defaults to
+/// `src/main.ph` and `build.opt-level` to nothing. Other keys and tables are skipped for
+/// manifests written by a newer `ph`. Errors start with the line they are about.
+pub fn parse_manifest(text: &str) -> Result<Manifest, String> {
+    let document: crate::toml::Document =
+        crate::toml::parse(text).map_err(|error: crate::toml::TomlError| error.to_string())?;
+
+    // A missing key is reported at the header of its table, or the top of the file
+    let string = |table: &str, key: &str| -> Result<Option<String>, String> {
+        match document.get(table, key) {
+            Some(entry) => match &entry.value {
+                crate::toml::Value::String(string) => Ok(Some(string.clone())),
+                value => Err(format!(
+                    "{}: '{}.{}' must be a string, found {}",
+                    entry.line,
+                    table,
+                    key,
+                    value.type_name()
+                )),
+            },
+            None => Ok(None),
+        }
+    };
+    let required = |table: &str, key: &str| -> Result<String, String> {
+        string(table, key)?.ok_or_else(|| {
+            format!(
+                "{}: missing '{}.{}'",
+                document.table_line(table).unwrap_or(1),
+                table,
+                key
+            )
+        })
+    };
+
+    let opt_level: Option<u8> = match document.get("build", "opt-level") {
+        Some(entry) => match entry.value {
+            crate::toml::Value::Integer(level)
+                if (0..=crate::passes::MAX_OPT_LEVEL as i64).contains(&level) =>
+            {
+                Some(level as u8)
+            }
+            _ => {
+                return Err(format!(
+                    "{}: 'build.opt-level' must be an integer from 0 to {}",
+                    entry.line,
+                    crate::passes::MAX_OPT_LEVEL
+                ))
+            }
+        },
+        None => None,
+    };
+
+    Ok(Manifest {
+        name: required("package", "name")?,
+        version: required("package", "version")?,
+        entry: string("package", "entry")?.unwrap_or_else(|| DEFAULT_ENTRY.to_string()),
+        opt_level,
+    })
+}
+
+/// Check that `name` can name a project and its directory
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("the project name is empty".to_string());
+    }
+    // Quotes would have to be escaped in the manifest
+    if name.contains(['/', '\\', '"']) {
+        return Err(format!(
+            "'{}' can't be a project name, it must not contain path separators or quotes",
+            name
+        ));
+    }
+    if name == "." || name == ".." {
+        return Err(format!("'{}' can't be a project name", name));
+    }
+
+    Ok(())
+}
+
+/// Write a manifest and a hello world entry point into `directory`, which must exist
+///
+/// Nothing is written if either file is already there.
+pub fn create(directory: &std::path::Path, name: &str) -> Result<(), String> {
+    let manifest: Manifest = Manifest::new(name);
+    let files: [(std::path::PathBuf, String); 2] = [
+        (directory.join(MANIFEST), manifest.to_toml()),
+        (
+            directory.join(&manifest.entry),
+            "print(\"Hello, world!\")\n".to_string(),
+        ),
+    ];
+
+    for (path, _) in &files {
+        if path.exists() {
+            return Err(format!(
+                "'{}' already exists, it was left as is",
+                path.display()
+            ));
+        }
+    }
+
+    for (path, contents) in &files {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|error: std::io::Error| {
+                format!("failed to create '{}': {}", parent.display(), error)
+            })?;
+        }
+        // `create_new` so a file that appeared since the check above is still never replaced
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .and_then(|mut file: std::fs::File| {
+                std::io::Write::write_all(&mut file, contents.as_bytes())
+            })
+            .map_err(|error: std::io::Error| {
+                format!("failed to write '{}': {}", path.display(), error)
+            })?;
+    }
+
+    Ok(())
+}
+
+pub fn new(args: clap::ArgMatches) {
+    // Get project name
+    let name: &String;
+    if let Some(arg_match) = args.subcommand_matches("new") {
+        name = arg_match
+            .get_one::<String>("name")
+            .expect("Failed to get name");
+    } else {
+        unreachable!("Subcommand is required");
+    }
+
+    if let Err(error) = validate_name(name) {
+        eprintln!("error: {}", error);
+        std::process::exit(2);
+    }
+
+    let directory: &std::path::Path = std::path::Path::new(name);
+    if directory.exists() {
+        eprintln!(
+            "error: '{}' already exists, use `ph init` inside it instead",
+            name
+        );
+        std::process::exit(1);
+    }
+
+    if let Err(error) = std::fs::create_dir(directory)
+        .map_err(|error: std::io::Error| format!("failed to create '{}': {}", name, error))
+        .and_then(|_| create(directory, name))
+    {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    }
+
+    crate::log::info(&format!("Created project '{}'", name));
+}
+
+pub fn init(args: clap::ArgMatches) {
+    // Get project name, the current directory's by default
+    let name: String;
+    if let Some(arg_match) = args.subcommand_matches("init") {
+        name = match arg_match.get_one::<String>("name") {
+            Some(name) => name.clone(),
+            None => std::env::current_dir()
+                .ok()
+                .and_then(|directory: std::path::PathBuf| {
+                    directory
+                        .file_name()
+                        .map(|name: &std::ffi::OsStr| name.to_string_lossy().into_owned())
+                })
+                .unwrap_or_default(),
+        };
+    } else {
+        unreachable!("Subcommand is required");
+    }
+
+    if let Err(error) = validate_name(&name) {
+        eprintln!("error: {}, give one with --name", error);
+        std::process::exit(2);
+    }
+
+    if let Err(error) = create(std::path::Path::new("."), &name) {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    }
+
+    crate::log::info(&format!("Created project '{}'", name));
+}
+
+/// Project found around the current directory
+#[derive(Clone, Debug, PartialEq)]
+pub struct Project {
+    pub manifest: Manifest,
+    /// Path of the entry point relative to the current directory, so messages point at it like
+    /// at a file given on the command line
+    pub entry: String,
+}
+
+/// Find the manifest in the current directory or the closest one above it, for `ph build` and
+/// `ph run` without a file, or print why there is none and exit
+pub fn find_project() -> Project {
+    let current: std::path::PathBuf = match std::env::current_dir() {
+        Ok(current) => current,
+        Err(error) => {
+            eprintln!("error: failed to get the current directory: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    let Some(depth) = current
+        .ancestors()
+        .position(|directory: &std::path::Path| directory.join(MANIFEST).is_file())
+    else {
+        eprintln!(
+            "error: no file given and no {} in this directory or any above it, give a file or \
+             create a project with `ph init`",
+            MANIFEST
+        );
+        std::process::exit(2);
+    };
+
+    let directory: std::path::PathBuf = std::iter::repeat_n("..", depth).collect();
+    let path: std::path::PathBuf = directory.join(MANIFEST);
+    let text: String = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(error) => {
+            eprintln!("error: failed to read '{}': {}", path.display(), error);
+            std::process::exit(1);
+        }
+    };
+    let manifest: Manifest = match parse_manifest(&text) {
+        Ok(manifest) => manifest,
+        Err(error) => {
+            eprintln!("error: {}:{}", path.display(), error);
+            std::process::exit(1);
+        }
+    };
+
+    Project {
+        entry: directory
+            .join(&manifest.entry)
+            .to_string_lossy()
+            .into_owned(),
+        manifest,
+    }
+}
+
+/// Directory of the entry point of the project `directory` is in, where `import name` looks
+/// for modules that aren't next to the importing file
+///
+/// `None` outside of a project or when its manifest can't be read.
+pub fn source_directory(directory: &std::path::Path) -> Option<std::path::PathBuf> {
+    let directory: &std::path::Path = match directory.as_os_str().is_empty() {
+        true => std::path::Path::new("."),
+        false => directory,
+    };
+    let depth: usize = std::fs::canonicalize(directory)
+        .ok()?
+        .ancestors()
+        .position(|directory: &std::path::Path| directory.join(MANIFEST).is_file())?;
+
+    // Relative like `directory`, so the files found are named like the importing one
+    let root: std::path::PathBuf =
+        directory.join(std::iter::repeat_n("..", depth).collect::<std::path::PathBuf>());
+    let manifest: Manifest =
+        parse_manifest(&std::fs::read_to_string(root.join(MANIFEST)).ok()?).ok()?;
+
+    Some(root.join(&manifest.entry).parent()?.to_path_buf())
+}
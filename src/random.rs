@@ -0,0 +1,72 @@
+/// Pseudo-random number generator, xoshiro256** seeded through splitmix64
+///
+/// Not suitable for anything security related, only for reproducible sequences
+#[derive(Clone, Debug)]
+pub struct Random {
+    state: [u64; 4],
+}
+
+impl Random {
+    /// Generator whose sequence depends only on `seed`
+    pub fn from_seed(seed: u64) -> Random {
+        let mut splitmix: u64 = seed;
+        let mut next = || -> u64 {
+            splitmix = splitmix.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z: u64 = splitmix;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        };
+
+        Random {
+            state: [next(), next(), next(), next()],
+        }
+    }
+
+    /// Generator seeded from the operating system's randomness
+    pub fn from_entropy() -> Random {
+        // The standard library seeds every `RandomState` from the operating system
+        let seed: u64 =
+            std::hash::BuildHasher::hash_one(&std::collections::hash_map::RandomState::new(), 0);
+
+        Random::from_seed(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let result: u64 = self.state[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t: u64 = self.state[1] << 17;
+
+        self.state[2] ^= self.state[0];
+        self.state[3] ^= self.state[1];
+        self.state[1] ^= self.state[2];
+        self.state[0] ^= self.state[3];
+        self.state[2] ^= t;
+        self.state[3] = self.state[3].rotate_left(45);
+
+        result
+    }
+
+    /// Float in `[0, 1)`
+    pub fn next_float(&mut self) -> f64 {
+        // The top 53 bits fill a float's mantissa exactly
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Integer in `low..=high`, every value equally likely, `low` must not be above `high`
+    pub fn next_in_range(&mut self, low: isize, high: isize) -> isize {
+        let span: u64 = high.abs_diff(low) as u64;
+        if span == u64::MAX {
+            return self.next_u64() as isize;
+        }
+
+        // Reject the values that would make the lower results more likely
+        let count: u64 = span + 1;
+        let limit: u64 = u64::MAX - (u64::MAX % count);
+        loop {
+            let value: u64 = self.next_u64();
+            if value < limit {
+                return low.wrapping_add((value % count) as isize);
+            }
+        }
+    }
+}
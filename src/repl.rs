@@ -0,0 +1,202 @@
+use crate::diagnostic::Diagnostic;
+use crate::eval::{Interpreter, Value};
+use crate::lexer::{SyntaxToken, SyntaxTokenType};
+use crate::line_editor::{LineEditor, ReadLine, StdinReader};
+use crate::parser::{collect_tokens, ParseError, ParseErrorKind, Parser, StatementSyntax};
+
+/// Commands starting with `:`, handled by the REPL instead of the language
+const META_COMMANDS: [(&str, &str); 5] = [
+    (":tokens <code>", "print the tokens of <code>"),
+    (":ast <code>", "print the syntax tree of <code>"),
+    (":env", "list variables and their values"),
+    (":clear", "remove all variables"),
+    (":quit", "exit the REPL"),
+];
+
+/// Read-eval-print loop, variables persist between lines
+#[derive(Debug, Default)]
+pub struct Repl {
+    interpreter: Interpreter,
+}
+
+impl Repl {
+    pub fn new() -> Repl {
+        Repl::default()
+    }
+
+    /// Read lines from `reader` until end of input or `exit`, writing values and diagnostics to `output`
+    ///
+    /// Input that ends too early, like `(1 +` or an unclosed `{`, continues on the next line.
+    /// Each line goes into the history on its own. A line cancelled with Ctrl-C drops the
+    /// unfinished input too and starts over at the first prompt.
+    pub fn run(
+        &mut self,
+        reader: &mut dyn ReadLine,
+        output: &mut dyn std::io::Write,
+    ) -> std::io::Result<()> {
+        let mut buffer: String = String::new();
+
+        loop {
+            let prompt: &str = if buffer.is_empty() { ">> " } else { "..> " };
+
+            let line: String = match reader.read_line(prompt) {
+                Ok(Some(line)) => line,
+                Ok(None) => return Ok(()),
+                Err(error) if error.kind() == std::io::ErrorKind::Interrupted => {
+                    buffer.clear();
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
+
+            if buffer.is_empty() {
+                if line.trim() == "exit" {
+                    return Ok(());
+                }
+
+                if line.trim_start().starts_with(':') {
+                    reader.add_history(&line);
+                    if !self.meta_command(line.trim(), output)? {
+                        return Ok(());
+                    }
+                    continue;
+                }
+            } else {
+                buffer.push('\n');
+            }
+
+            reader.add_history(&line);
+            buffer.push_str(&line);
+
+            if is_incomplete(&buffer) {
+                continue;
+            }
+
+            let source: String = std::mem::take(&mut buffer);
+
+            match self.evaluate(&source) {
+                Ok(Some(Value::Nil)) | Ok(None) => {}
+                Ok(Some(value)) => writeln!(output, "{}", value)?,
+                Err(diagnostic) => write!(output, "{}", diagnostic.render("<repl>", &source))?,
+            }
+        }
+    }
+
+    /// Run a `:command`, returns false when the REPL should exit
+    fn meta_command(
+        &mut self,
+        line: &str,
+        output: &mut dyn std::io::Write,
+    ) -> std::io::Result<bool> {
+        let (command, code): (&str, &str) = line.split_once(' ').unwrap_or((line, ""));
+
+        match command {
+            ":tokens" => {
+                let tokens: Vec<SyntaxToken> = collect_tokens(code.to_string());
+
+                write!(
+                    output,
+                    "{}",
+                    crate::tokens::format_tokens(&tokens, code, false)
+                )?;
+            }
+            ":ast" => {
+                let mut parser: Parser = Parser::new(code.to_string());
+                match parser.parse_statements() {
+                    Ok(statements) => {
+                        write!(output, "{}", crate::ast::format_statements(&statements))?
+                    }
+                    Err(error) => {
+                        write!(output, "{}", Diagnostic::from(error).render("<repl>", code))?
+                    }
+                }
+            }
+            ":env" => {
+                let mut variables: Vec<(&String, &Value)> = self.interpreter.variables().collect();
+                variables.sort_by(|a: &(&String, &Value), b: &(&String, &Value)| a.0.cmp(b.0));
+
+                for (name, value) in variables {
+                    writeln!(output, "{} = {}", name, value)?;
+                }
+            }
+            ":clear" => self.interpreter = Interpreter::new(),
+            ":quit" => return Ok(false),
+            _ => {
+                writeln!(output, "unknown command '{}', available commands:", command)?;
+                for (usage, description) in META_COMMANDS {
+                    writeln!(output, "  {:<16} {}", usage, description)?;
+                }
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Evaluate one line, returning the value if it ended with an expression
+    fn evaluate(&mut self, line: &str) -> Result<Option<Value>, Diagnostic> {
+        let mut parser: Parser = Parser::new(line.to_string());
+        let mut statements: Vec<StatementSyntax> = parser.parse_statements()?;
+        crate::fold::fold_statements(&mut statements)?;
+
+        Ok(self.interpreter.execute(&statements)?)
+    }
+}
+
+/// Whether `source` stops in the middle of a statement and should continue on the next line
+///
+/// That is when a bracket is still open, or it ends too early to parse, like after an
+/// operator. Input pasted with a mistake in its first lines then runs, and is reported, once
+/// it is complete instead of line by line. A bracket closed by the wrong kind completes the
+/// input, so the parser reports it.
+pub fn is_incomplete(source: &str) -> bool {
+    let mut closing: Vec<SyntaxTokenType> = Vec::new();
+    for token in collect_tokens(source.to_string()) {
+        match token.token_type {
+            SyntaxTokenType::OpenParenthesis => closing.push(SyntaxTokenType::CloseParenthesis),
+            SyntaxTokenType::OpenBracket => closing.push(SyntaxTokenType::CloseBracket),
+            SyntaxTokenType::OpenBrace => closing.push(SyntaxTokenType::CloseBrace),
+            SyntaxTokenType::CloseParenthesis
+            | SyntaxTokenType::CloseBracket
+            | SyntaxTokenType::CloseBrace => {
+                let open: Option<SyntaxTokenType> = closing.pop();
+                if open != Some(token.token_type) {
+                    return false;
+                }
+            }
+            _ => {}
+        }
+    }
+    if !closing.is_empty() {
+        return true;
+    }
+
+    let mut parser: Parser = Parser::new(source.to_string());
+    matches!(
+        parser.parse_statements(),
+        Err(ParseError {
+            kind: ParseErrorKind::UnexpectedEndOfFile { .. },
+            ..
+        })
+    )
+}
+
+pub fn repl() {
+    println!(
+        "Phoenix Script {}, type `exit` or press Ctrl-D to quit",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    let result: std::io::Result<()> = if std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+        let mut editor: LineEditor = LineEditor::new(LineEditor::default_history_path());
+        let result: std::io::Result<()> = Repl::new().run(&mut editor, &mut std::io::stdout());
+        editor.save_history();
+        result
+    } else {
+        Repl::new().run(&mut StdinReader, &mut std::io::stdout())
+    };
+
+    if let Err(error) = result {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    }
+}
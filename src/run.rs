@@ -0,0 +1,518 @@
+use crate::bytecode::Program;
+use crate::diagnostic::{Diagnostic, SourceFile};
+use crate::eval::{Interpreter, Overflow, Value};
+use crate::import::Loaded;
+use crate::parser::{Parser, StatementSyntax};
+use crate::vm::Vm;
+
+/// How a float result is printed, only the final result is affected
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FloatFormat {
+    /// Digits after the decimal point, `None` for the shortest text that reads back the same
+    pub precision: Option<usize>,
+    /// Exponential notation, like `3.3e-1`
+    pub scientific: bool,
+}
+
+impl FloatFormat {
+    pub fn format(&self, value: f64) -> String {
+        match (self.precision, self.scientific) {
+            (Some(precision), true) => format!("{:.*e}", precision, value),
+            (None, true) => format!("{:e}", value),
+            (Some(precision), false) => format!("{:.*}", precision, value),
+            (None, false) => Value::Float(value).to_string(),
+        }
+    }
+}
+
+/// What runs a program, both give the same results
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Backend {
+    /// Evaluate the syntax tree directly, kept to compare the VM against
+    Tree,
+    /// Compile to bytecode and run it on `Vm`
+    #[default]
+    Vm,
+}
+
+/// How a program is run, set from the command line
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RunOptions {
+    pub backend: Backend,
+    pub overflow: Overflow,
+    /// Fixes the random sequence, like calling `seed` first
+    pub seed: Option<u64>,
+    /// What `args()` returns
+    pub arguments: Vec<String>,
+    /// Globals defined before the program runs, from `--seed-vars`
+    pub variables: Vec<(String, Value)>,
+    /// Print the value of every top level expression statement instead of only the last
+    pub print_each: bool,
+    /// Print the result, or the error, as JSON on stdout
+    pub json_result: bool,
+    /// Print every instruction the VM runs to stderr
+    pub trace: bool,
+    /// Most instructions to trace, `None` for no limit
+    pub trace_limit: Option<usize>,
+    /// Which passes of `crate::passes` run, 0 only runs the ones every program needs
+    pub opt_level: u8,
+    /// Leave out the builtins and constants
+    pub no_std: bool,
+    /// Indentation delimits blocks, see `Lexer::with_layout`
+    pub layout: bool,
+    /// Characters that start no token are new lines instead of errors
+    pub lenient: bool,
+}
+
+/// Lex, parse, and evaluate a whole program
+///
+/// `each` is called with the value of every top level expression statement, the last one is
+/// also returned. `warn` is called with every warning, before anything runs.
+pub fn run_source(
+    source: &str,
+    options: &RunOptions,
+    each: &mut dyn FnMut(&Value),
+    warn: &mut dyn FnMut(&Diagnostic),
+) -> Result<Option<Value>, Diagnostic> {
+    let mut parser: Parser = if options.layout {
+        Parser::with_layout(source.to_string())
+    } else {
+        Parser::new(source.to_string())
+    };
+    parser.set_lenient(options.lenient);
+    let start: std::time::Instant = std::time::Instant::now();
+    let mut statements: Vec<StatementSyntax> = parser.parse_statements()?;
+    crate::log::phase("Parsed", start);
+
+    let start: std::time::Instant = std::time::Instant::now();
+    // Dead code elimination removes branches that never run, so the mistakes in them are
+    // found first, like `ph build` does
+    if crate::passes::find_pass("dead_code")
+        .is_some_and(|pass: &crate::passes::Pass| pass.level <= options.opt_level)
+    {
+        let diagnostics: Vec<Diagnostic> = match crate::check::check_statements(&statements) {
+            Ok(warnings) => warnings,
+            Err(mut diagnostics) => return Err(diagnostics.remove(0)),
+        };
+        diagnostics.iter().for_each(&mut *warn);
+    } else {
+        crate::check::check_division(&statements)?;
+    }
+    crate::log::phase("Checked", start);
+    let start: std::time::Instant = std::time::Instant::now();
+    for warning in crate::passes::run_ast_passes(&mut statements, options.opt_level, None)? {
+        warn(&warning);
+    }
+    crate::log::phase("Ran the syntax tree passes", start);
+    crate::log::debug("statements", &statements);
+
+    match options.backend {
+        Backend::Tree => {
+            let start: std::time::Instant = std::time::Instant::now();
+            let value: Option<Value> = interpreter(options).execute_each(&statements, each)?;
+            crate::log::phase("Ran", start);
+            Ok(value)
+        }
+        Backend::Vm => {
+            let start: std::time::Instant = std::time::Instant::now();
+            let mut program: Program = crate::bytecode::compile(&statements)?;
+            crate::passes::run_bytecode_passes(&mut program, options.opt_level, None);
+            crate::log::phase("Compiled to bytecode", start);
+            crate::log::debug("program", &program);
+            run_program(&program, options, each)
+        }
+    }
+}
+
+/// Run each top level statement of `source` on its own, giving the result of every one
+///
+/// Statements run in order like lines entered into the REPL, so one that fails doesn't stop
+/// the rest and later ones see the variables and functions of earlier ones. A `let` or `fn`
+/// gives `Ok(None)`. Nothing is printed or exits besides what the statements themselves do.
+/// A syntax error leaves no statements to run and is the only result.
+pub fn eval_all(source: &str) -> Vec<Result<Option<Value>, Diagnostic>> {
+    let mut parser: Parser = Parser::new(source.to_string());
+    let mut statements: Vec<StatementSyntax> = match parser.parse_statements() {
+        Ok(statements) => statements,
+        Err(error) => return vec![Err(error.into())],
+    };
+
+    let mut interpreter: Interpreter = Interpreter::new();
+    statements
+        .iter_mut()
+        .map(
+            |statement: &mut StatementSyntax| -> Result<Option<Value>, Diagnostic> {
+                crate::fold::fold_statements(std::slice::from_mut(statement))?;
+                Ok(interpreter.execute(std::slice::from_ref(statement))?)
+            },
+        )
+        .collect()
+}
+
+/// Run an already compiled program on the VM, whatever `options.backend` says
+pub fn run_program(
+    program: &Program,
+    options: &RunOptions,
+    each: &mut dyn FnMut(&Value),
+) -> Result<Option<Value>, Diagnostic> {
+    let mut vm: Vm = Vm::new(interpreter(options));
+    if options.trace {
+        vm.set_trace(options.trace_limit);
+    }
+
+    let start: std::time::Instant = std::time::Instant::now();
+    let value: Option<Value> = vm.run_each(program, each)?;
+    crate::log::phase("Ran", start);
+    Ok(value)
+}
+
+/// Interpreter set up with the options that don't depend on the backend
+fn interpreter(options: &RunOptions) -> Interpreter {
+    let mut interpreter: Interpreter = Interpreter::with_overflow(options.overflow);
+    if options.no_std {
+        interpreter.set_no_std();
+    }
+    if let Some(seed) = options.seed {
+        interpreter.set_seed(seed);
+    }
+    interpreter.set_arguments(options.arguments.clone());
+    for (name, value) in &options.variables {
+        interpreter.define_variable(name, value.clone());
+    }
+
+    interpreter
+}
+
+pub fn run(args: clap::ArgMatches) {
+    // Get file or inline source to run, the project's entry point without either
+    let project: crate::project::Project;
+    let file: &str;
+    let expression: Option<&String>;
+    let options: RunOptions;
+    let float_format: FloatFormat;
+    let dump_config: bool;
+    if let Some(arg_match) = args.subcommand_matches("run") {
+        // With `--expr` every value is an argument for the script
+        let mut values: std::iter::Flatten<std::option::IntoIter<clap::parser::ValuesRef<String>>> =
+            arg_match.get_many::<String>("file").into_iter().flatten();
+        expression = arg_match.get_one::<String>("expr");
+        // The manifest's opt-level only applies to its entry point, like for `ph build`
+        let mut manifest_opt_level: Option<u8> = None;
+        file = match expression {
+            Some(_) => "<expr>",
+            None => match values.next() {
+                Some(file) => file,
+                None => {
+                    project = crate::project::find_project();
+                    manifest_opt_level = project.manifest.opt_level;
+                    &project.entry
+                }
+            },
+        };
+        // The command line overrides the manifest
+        let opt_level: u8 = crate::passes::given_opt_level(arg_match)
+            .or(manifest_opt_level)
+            .unwrap_or(0);
+
+        // A `--` right after the file only separates it from the script's arguments
+        let mut arguments: Vec<String> = values.cloned().collect();
+        if arguments.first().map(String::as_str) == Some("--") {
+            arguments.remove(0);
+        }
+
+        options = RunOptions {
+            backend: get_backend(arg_match, opt_level),
+            overflow: get_overflow(arg_match),
+            seed: get_seed(arg_match),
+            arguments,
+            variables: get_variables(arg_match),
+            print_each: arg_match.get_flag("print-each"),
+            json_result: arg_match.get_flag("json-result"),
+            trace: arg_match.get_flag("trace"),
+            trace_limit: arg_match.get_one::<usize>("trace-limit").copied(),
+            opt_level,
+            no_std: arg_match.get_flag("no-std"),
+            layout: arg_match.get_flag("layout"),
+            lenient: arg_match.get_flag("lenient"),
+        };
+        float_format = get_float_format(arg_match);
+        dump_config = arg_match.get_flag("dump-config");
+    } else {
+        unreachable!("Subcommand is required");
+    }
+
+    if dump_config {
+        print!("{}", format_config(&options, float_format));
+        return;
+    }
+
+    crate::log::verbose(&format!("Running {}", file));
+
+    // Inline source takes the same path as the contents of a file
+    if let Some(expression) = expression {
+        let loaded: Loaded = load(file, expression, &options, float_format);
+        print_result(file, &loaded, &options, float_format, None);
+        return;
+    }
+
+    // Get file contents, a compiled file runs without parsing
+    let bytes: Vec<u8> = crate::source::read_bytes(file);
+    if crate::phc::is_phc(&bytes) {
+        let (mut program, name, source, imports): (Program, String, String, Vec<SourceFile>) =
+            match crate::phc::decode(&bytes) {
+                Ok(decoded) => decoded,
+                Err(error) => {
+                    eprintln!("error: cannot run '{}': {}", file, error);
+                    std::process::exit(1);
+                }
+            };
+
+        crate::passes::run_bytecode_passes(&mut program, options.opt_level, None);
+
+        // Errors point into the file the program was compiled from, not the compiled file
+        let loaded: Loaded = Loaded { source, imports };
+        print_result(&name, &loaded, &options, float_format, Some(&program));
+        return;
+    }
+
+    let file_contents: String = crate::source::source_text(file, bytes);
+    let loaded: Loaded = load(file, &file_contents, &options, float_format);
+
+    print_result(file, &loaded, &options, float_format, None);
+}
+
+pub fn eval(args: clap::ArgMatches) {
+    // Get expression to evaluate, shell words are joined back together with spaces
+    let expression: String;
+    let options: RunOptions;
+    let float_format: FloatFormat;
+    let dump_config: bool;
+    if let Some(arg_match) = args.subcommand_matches("eval") {
+        expression = arg_match
+            .get_many::<String>("expression")
+            .expect("Failed to get expression")
+            .map(String::as_str)
+            .collect::<Vec<&str>>()
+            .join(" ");
+        options = RunOptions {
+            backend: get_backend(arg_match, crate::passes::get_opt_level(arg_match)),
+            overflow: get_overflow(arg_match),
+            seed: get_seed(arg_match),
+            arguments: Vec::new(),
+            variables: get_variables(arg_match),
+            print_each: arg_match.get_flag("print-each"),
+            json_result: arg_match.get_flag("json-result"),
+            trace: arg_match.get_flag("trace"),
+            trace_limit: arg_match.get_one::<usize>("trace-limit").copied(),
+            opt_level: crate::passes::get_opt_level(arg_match),
+            no_std: arg_match.get_flag("no-std"),
+            layout: arg_match.get_flag("layout"),
+            lenient: arg_match.get_flag("lenient"),
+        };
+        float_format = get_float_format(arg_match);
+        dump_config = arg_match.get_flag("dump-config");
+    } else {
+        unreachable!("Subcommand is required");
+    }
+
+    if dump_config {
+        print!("{}", format_config(&options, float_format));
+        return;
+    }
+
+    let loaded: Loaded = load("<eval>", &expression, &options, float_format);
+    print_result("<eval>", &loaded, &options, float_format, None);
+}
+
+/// Put `source` together with the files it imports, or exit with the diagnostic like
+/// `print_result` does
+fn load(file_name: &str, source: &str, options: &RunOptions, float_format: FloatFormat) -> Loaded {
+    match crate::import::load(file_name, source, options.layout, options.lenient) {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            if options.json_result {
+                println!(
+                    "{}",
+                    crate::json::format_result(
+                        &Err(error.diagnostic.clone()),
+                        float_format,
+                        &error.file_name,
+                        &[],
+                        &error.source
+                    )
+                );
+            } else {
+                eprint!("{}", error.render());
+            }
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Settings a program runs with after defaults and implied options are applied, one
+/// `key = value` line each in TOML syntax, unset options are commented out
+pub fn format_config(options: &RunOptions, float_format: FloatFormat) -> String {
+    let backend: &str = match options.backend {
+        Backend::Tree => "tree",
+        Backend::Vm => "vm",
+    };
+    let overflow: &str = match options.overflow {
+        Overflow::Checked => "checked",
+        Overflow::Wrap => "wrap",
+    };
+    let arguments: Vec<String> = options
+        .arguments
+        .iter()
+        .map(|argument: &String| format!("{:?}", argument))
+        .collect();
+    let variables: Vec<String> = options
+        .variables
+        .iter()
+        .map(|(name, value): &(String, Value)| format!("{} = {}", name, value))
+        .collect();
+    let optional = |name: &str, value: Option<String>| -> String {
+        match value {
+            Some(value) => format!("{} = {}\n", name, value),
+            None => format!("# {} is not set\n", name),
+        }
+    };
+
+    let mut config: String = String::new();
+    config.push_str(&format!("backend = {:?}\n", backend));
+    config.push_str(&format!("overflow = {:?}\n", overflow));
+    config.push_str(&format!("opt_level = {}\n", options.opt_level));
+    config.push_str(&format!("no_std = {}\n", options.no_std));
+    config.push_str(&format!("layout = {}\n", options.layout));
+    config.push_str(&format!("lenient = {}\n", options.lenient));
+    config.push_str(&format!("print_each = {}\n", options.print_each));
+    config.push_str(&format!("json_result = {}\n", options.json_result));
+    config.push_str(&optional(
+        "precision",
+        float_format
+            .precision
+            .map(|precision: usize| precision.to_string()),
+    ));
+    config.push_str(&format!("scientific = {}\n", float_format.scientific));
+    config.push_str(&optional(
+        "seed",
+        // Shown like it was given, TOML integers are signed
+        options.seed.map(|seed: u64| (seed as i64).to_string()),
+    ));
+    config.push_str(&format!("trace = {}\n", options.trace));
+    config.push_str(&optional(
+        "trace_limit",
+        options.trace_limit.map(|limit: usize| limit.to_string()),
+    ));
+    config.push_str(&format!("arguments = [{}]\n", arguments.join(", ")));
+    config.push_str(&format!("variables = {{ {} }}\n", variables.join(", ")));
+
+    config
+}
+
+/// Read the `--backend` option, `--trace` and optimizing at `opt_level` always run on the VM
+fn get_backend(arg_match: &clap::ArgMatches, opt_level: u8) -> Backend {
+    match arg_match.get_one::<String>("backend").map(String::as_str) {
+        Some("tree") if !arg_match.get_flag("trace") && opt_level == 0 => Backend::Tree,
+        _ => Backend::Vm,
+    }
+}
+
+/// Read the `--overflow` option
+fn get_overflow(arg_match: &clap::ArgMatches) -> Overflow {
+    match arg_match.get_one::<String>("overflow").map(String::as_str) {
+        Some("wrap") => Overflow::Wrap,
+        _ => Overflow::Checked,
+    }
+}
+
+/// Read the `--precision` and `--scientific` options
+fn get_float_format(arg_match: &clap::ArgMatches) -> FloatFormat {
+    FloatFormat {
+        precision: arg_match.get_one::<usize>("precision").copied(),
+        scientific: arg_match.get_flag("scientific"),
+    }
+}
+
+/// Read the `--seed-vars` options, in the order they were given
+fn get_variables(arg_match: &clap::ArgMatches) -> Vec<(String, Value)> {
+    arg_match
+        .get_many::<(String, Value)>("seed-vars")
+        .map(|variables: clap::parser::ValuesRef<(String, Value)>| variables.cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Read the `--seed` option, a negative seed is used as its two's complement bits
+fn get_seed(arg_match: &clap::ArgMatches) -> Option<u64> {
+    arg_match
+        .get_one::<i64>("seed")
+        .map(|seed: &i64| *seed as u64)
+}
+
+/// Run `loaded`, or `compiled` from it, print the value of its trailing expression, or of
+/// every expression with `--print-each`, or exit with the diagnostic
+///
+/// With `--json-result` the result and the diagnostic are both printed as JSON to stdout.
+fn print_result(
+    file_name: &str,
+    loaded: &Loaded,
+    options: &RunOptions,
+    float_format: FloatFormat,
+    compiled: Option<&Program>,
+) {
+    let print = |value: &Value| match value {
+        Value::Nil => {}
+        Value::Float(value) => println!("{}", float_format.format(*value)),
+        value => println!("{}", value),
+    };
+    let run = |each: &mut dyn FnMut(&Value)| -> Result<Option<Value>, Diagnostic> {
+        match compiled {
+            Some(program) => run_program(program, options, each),
+            None => run_source(
+                &loaded.source,
+                options,
+                each,
+                &mut |warning: &Diagnostic| {
+                    eprint!(
+                        "{}",
+                        warning.render_imported(file_name, &loaded.imports, &loaded.source)
+                    )
+                },
+            ),
+        }
+    };
+
+    let result: Result<Option<Value>, Diagnostic> = if options.print_each {
+        run(&mut |value: &Value| print(value)).map(|_| None)
+    } else {
+        run(&mut |_: &Value| {})
+    };
+
+    if options.json_result {
+        println!(
+            "{}",
+            crate::json::format_result(
+                &result,
+                float_format,
+                file_name,
+                &loaded.imports,
+                &loaded.source
+            )
+        );
+        if result.is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    match result {
+        Ok(Some(value)) => print(&value),
+        Ok(None) => {}
+        Err(diagnostic) => {
+            eprint!(
+                "{}",
+                diagnostic.render_imported(file_name, &loaded.imports, &loaded.source)
+            );
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+/// File argument that reads the source from stdin, for `ph build -` and `ph check -`
+pub const STDIN: &str = "-";
+
+/// Name diagnostics give source read from stdin
+pub const STDIN_NAME: &str = "<stdin>";
+
+/// Read a file's bytes, or print why it can't be read and exit
+pub fn read_bytes(file: &str) -> Vec<u8> {
+    if std::path::Path::new(file).is_dir() {
+        eprintln!("error: '{}' is a directory, expected a file", file);
+        std::process::exit(1);
+    }
+
+    match std::fs::read(file) {
+        Ok(bytes) => bytes,
+        Err(error) => {
+            eprintln!("error: failed to read '{}': {}", file, error);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Read a source file, or print why it can't be read and exit
+pub fn read_source(file: &str) -> String {
+    source_text(file, read_bytes(file))
+}
+
+/// Read the source a command was given, from stdin when it is `-`, or print why it can't be
+/// read and exit
+///
+/// Returns the name to give the source in messages with it.
+pub fn read_input(file: &str) -> (&str, String) {
+    if file != STDIN {
+        return (file, read_source(file));
+    }
+
+    let mut bytes: Vec<u8> = Vec::new();
+    if let Err(error) = std::io::Read::read_to_end(&mut std::io::stdin(), &mut bytes) {
+        eprintln!("error: failed to read stdin: {}", error);
+        std::process::exit(1);
+    }
+
+    (STDIN_NAME, source_text(STDIN_NAME, bytes))
+}
+
+/// Bytes read from `file` as source text, or print that they aren't text and exit
+///
+/// A byte order mark some editors put at the start of UTF-8 files is dropped, it isn't part
+/// of the program. Only one at the very start is, anywhere else it is a bad token.
+pub fn source_text(file: &str, bytes: Vec<u8>) -> String {
+    match String::from_utf8(bytes) {
+        Ok(file_contents) => match file_contents.strip_prefix('\u{FEFF}') {
+            Some(rest) => rest.to_string(),
+            None => file_contents,
+        },
+        Err(_) => {
+            eprintln!(
+                "error: failed to read '{}': stream did not contain valid UTF-8",
+                file
+            );
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+/// Object file format of a target, which decides the assembler directives
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ObjectFormat {
+    Elf,
+    MachO,
+    Wasm,
+}
+
+/// Platform the native backends generate code for, chosen with `ph build --target`
+///
+/// Backends read everything platform specific from here, so a new target only needs a new
+/// entry in `TARGETS`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TargetSpec {
+    pub name: &'static str,
+    /// Bits in a pointer, and in a C `long`
+    pub pointer_width: u32,
+    /// LLVM target triple
+    pub triple: &'static str,
+    pub object_format: ObjectFormat,
+    /// Prepended to C symbols like `main` and `printf`
+    pub symbol_prefix: &'static str,
+    /// Prefix of assembler labels that stay out of the symbol table
+    pub local_label_prefix: &'static str,
+    /// Backends that can generate code for the target
+    pub backends: &'static [&'static str],
+}
+
+pub const TARGETS: [TargetSpec; 3] = [
+    TargetSpec {
+        name: "x86_64-linux",
+        pointer_width: 64,
+        triple: "x86_64-pc-linux-gnu",
+        object_format: ObjectFormat::Elf,
+        symbol_prefix: "",
+        local_label_prefix: ".L",
+        backends: &["c", "asm", "llvm-ir"],
+    },
+    TargetSpec {
+        name: "x86_64-macos",
+        pointer_width: 64,
+        triple: "x86_64-apple-macosx10.15.0",
+        object_format: ObjectFormat::MachO,
+        symbol_prefix: "_",
+        local_label_prefix: "L",
+        backends: &["c", "asm", "llvm-ir"],
+    },
+    TargetSpec {
+        name: "wasm32",
+        pointer_width: 32,
+        triple: "wasm32-unknown-wasi",
+        object_format: ObjectFormat::Wasm,
+        symbol_prefix: "",
+        local_label_prefix: ".L",
+        backends: &["wasm", "llvm-ir"],
+    },
+];
+
+impl TargetSpec {
+    /// Target a backend generates code for without `--target`, the host unless only
+    /// WebAssembly makes sense
+    pub fn default_for(backend: &str) -> TargetSpec {
+        if backend == "wasm" {
+            return TARGETS[2];
+        }
+
+        if cfg!(target_os = "macos") {
+            TARGETS[1]
+        } else {
+            TARGETS[0]
+        }
+    }
+
+    /// Whether `backend` can generate code for the target
+    pub fn supports(&self, backend: &str) -> bool {
+        self.backends.contains(&backend)
+    }
+}
+
+pub fn find_target(name: &str) -> Option<TargetSpec> {
+    TARGETS
+        .into_iter()
+        .find(|target: &TargetSpec| target.name == name)
+}
+
+/// Names of every target, for errors
+pub fn target_names() -> String {
+    let names: Vec<&str> = TARGETS
+        .iter()
+        .map(|target: &TargetSpec| target.name)
+        .collect();
+
+    names.join(", ")
+}
@@ -0,0 +1,123 @@
+use crate::color::Color;
+use crate::diagnostic::line_column;
+use crate::lexer::{Lexer, SyntaxToken, SyntaxTokenType};
+use crate::parser::collect_raw_tokens;
+
+/// Color of a token type in the token dump, `None` for plain text
+fn token_color(token_type: &SyntaxTokenType) -> Option<Color> {
+    match token_type {
+        SyntaxTokenType::Number(_) | SyntaxTokenType::Float(_) => Some(Color::Green),
+        SyntaxTokenType::String(_) => Some(Color::Magenta),
+        SyntaxTokenType::LetKeyword
+        | SyntaxTokenType::FnKeyword
+        | SyntaxTokenType::ImportKeyword => Some(Color::Blue),
+        SyntaxTokenType::OpenParenthesis
+        | SyntaxTokenType::CloseParenthesis
+        | SyntaxTokenType::OpenBrace
+        | SyntaxTokenType::CloseBrace
+        | SyntaxTokenType::OpenBracket
+        | SyntaxTokenType::CloseBracket => Some(Color::Yellow),
+        SyntaxTokenType::BadToken | SyntaxTokenType::EqualsLess | SyntaxTokenType::EqualsBang => {
+            Some(Color::Red)
+        }
+        SyntaxTokenType::WhiteSpace
+        | SyntaxTokenType::Comment
+        | SyntaxTokenType::NewLine
+        | SyntaxTokenType::Identifier
+        | SyntaxTokenType::Semicolon
+        | SyntaxTokenType::Comma
+        | SyntaxTokenType::EndOfFile => None,
+        _ => Some(Color::Cyan),
+    }
+}
+
+/// Format tokens one per line as `line:column Type "text"`, with the type colored if `color`
+pub fn format_tokens(tokens: &[SyntaxToken], source: &str, color: bool) -> String {
+    let mut output: String = String::new();
+
+    for token in tokens {
+        let (line, column): (usize, usize) = line_column(source, token.position);
+        let location: String = format!("{}:{}", line, column);
+
+        // Pad before coloring, escape sequences would count towards the width
+        let name: String = format!("{:<16}", token.token_type.name());
+        let name: String = match token_color(&token.token_type) {
+            Some(token_color) => token_color.paint(&name, color),
+            None => name,
+        };
+
+        output.push_str(&format!("{:<8} {} {:?}\n", location, name, token.text));
+    }
+
+    output
+}
+
+/// Count tokens per type, most common first, ties in name order
+pub fn count_tokens(tokens: &[SyntaxToken]) -> Vec<(&'static str, usize)> {
+    let mut counts: std::collections::HashMap<&'static str, usize> =
+        std::collections::HashMap::new();
+
+    for token in tokens {
+        *counts.entry(token.token_type.name()).or_insert(0) += 1;
+    }
+
+    let mut counts: Vec<(&'static str, usize)> = counts.into_iter().collect();
+    counts.sort_by(|left: &(&str, usize), right: &(&str, usize)| {
+        right.1.cmp(&left.1).then(left.0.cmp(right.0))
+    });
+
+    counts
+}
+
+/// Format token counts one per line as `Type count`, followed by the total
+pub fn format_token_counts(tokens: &[SyntaxToken]) -> String {
+    let mut output: String = String::new();
+
+    for (name, count) in count_tokens(tokens) {
+        output.push_str(&format!("{:<16} {}\n", name, count));
+    }
+    output.push_str(&format!("{:<16} {}\n", "Total", tokens.len()));
+
+    output
+}
+
+pub fn tokens(args: clap::ArgMatches) {
+    // Get file to tokenize
+    let file: &String;
+    let no_color: bool;
+    let raw: bool;
+    let layout: bool;
+    if let Some(arg_match) = args.subcommand_matches("tokens") {
+        file = arg_match
+            .get_one::<String>("file")
+            .expect("Failed to get file");
+        no_color = arg_match.get_flag("no-color");
+        raw = arg_match.get_flag("raw");
+        layout = arg_match.get_flag("layout");
+    } else {
+        unreachable!("Subcommand is required");
+    }
+
+    // Get file contents
+    let file_contents: String = crate::source::read_source(file);
+
+    let lexer: Lexer = if layout {
+        Lexer::with_layout(file_contents.clone())
+    } else {
+        Lexer::new(file_contents.clone())
+    };
+    let tokens: Vec<SyntaxToken> = if raw {
+        collect_raw_tokens(lexer)
+    } else {
+        lexer.collect()
+    };
+
+    print!(
+        "{}",
+        format_tokens(
+            &tokens,
+            &file_contents,
+            crate::color::stdout_color(no_color)
+        )
+    );
+}
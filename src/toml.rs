@@ -0,0 +1,217 @@
+/// Value of a key, in the subset of TOML that `Phoenix.toml` and `.phfmt.toml` use
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+}
+
+impl Value {
+    /// How an error message refers to the type of the value
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "a string",
+            Value::Integer(_) => "an integer",
+            Value::Boolean(_) => "a boolean",
+        }
+    }
+}
+
+/// A `key = value` line
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    /// Table the key is in, empty before the first `[table]` header
+    pub table: String,
+    pub key: String,
+    pub value: Value,
+    /// Line number, from 1
+    pub line: usize,
+}
+
+/// Tables and keys of a document, in the order they are written
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Document {
+    /// Every `[table]` header with its line number
+    pub tables: Vec<(String, usize)>,
+    pub entries: Vec<Entry>,
+}
+
+impl Document {
+    pub fn get(&self, table: &str, key: &str) -> Option<&Entry> {
+        self.entries
+            .iter()
+            .find(|entry: &&Entry| entry.table == table && entry.key == key)
+    }
+
+    /// Line of the header of `table`, where a key missing from it is reported
+    pub fn table_line(&self, table: &str) -> Option<usize> {
+        self.tables
+            .iter()
+            .find(|(name, _): &&(String, usize)| name == table)
+            .map(|(_, line): &(String, usize)| *line)
+    }
+}
+
+/// Problem on a line of a document
+#[derive(Clone, Debug, PartialEq)]
+pub struct TomlError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for TomlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.line, self.message)
+    }
+}
+
+/// Parse `[table]` headers and `key = value` lines with string, integer and boolean values
+///
+/// That is all the configuration files need, arrays, inline tables, dotted keys and multi-line
+/// strings are errors. A key or table defined twice is an error like in TOML.
+pub fn parse(text: &str) -> Result<Document, TomlError> {
+    let mut document: Document = Document::default();
+    let mut table: String = String::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let number: usize = index + 1;
+        let error = |message: String| -> TomlError {
+            TomlError {
+                line: number,
+                message,
+            }
+        };
+
+        let line: &str = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[') {
+            let name: &str = header
+                .strip_suffix(']')
+                .map(str::trim)
+                .filter(|name: &&str| is_bare_key(name))
+                .ok_or_else(|| {
+                    error(format!(
+                        "expected a table header like [name], found '{}'",
+                        line
+                    ))
+                })?;
+            if let Some(first) = document.table_line(name) {
+                return Err(error(format!(
+                    "table [{}] is defined twice, first on line {}",
+                    name, first
+                )));
+            }
+
+            table = name.to_string();
+            document.tables.push((table.clone(), number));
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(error(format!("expected `key = value`, found '{}'", line)));
+        };
+        let (key, value): (&str, &str) = (key.trim(), value.trim());
+        if !is_bare_key(key) {
+            return Err(error(format!(
+                "'{}' is not a key, keys are letters, digits, '_' and '-'",
+                key
+            )));
+        }
+        if let Some(first) = document.get(&table, key) {
+            return Err(error(format!(
+                "'{}' is defined twice, first on line {}",
+                key, first.line
+            )));
+        }
+
+        let value: Value = parse_value(value).ok_or_else(|| {
+            error(format!(
+                "expected a string, an integer, true or false for '{}', found '{}'",
+                key, value
+            ))
+        })?;
+        document.entries.push(Entry {
+            table: table.clone(),
+            key: key.to_string(),
+            value,
+            line: number,
+        });
+    }
+
+    Ok(document)
+}
+
+/// `line` up to a `#` outside of a string
+fn strip_comment(line: &str) -> &str {
+    let mut quoted: bool = false;
+    let mut escaped: bool = false;
+    for (index, character) in line.char_indices() {
+        match character {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+
+    line
+}
+
+fn is_bare_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.chars().all(|character: char| {
+            character.is_ascii_alphanumeric() || character == '_' || character == '-'
+        })
+}
+
+/// A basic string, a decimal integer or a boolean, `None` for anything else
+fn parse_value(value: &str) -> Option<Value> {
+    match value {
+        "true" => return Some(Value::Boolean(true)),
+        "false" => return Some(Value::Boolean(false)),
+        _ => {}
+    }
+
+    if let Some(quoted) = value.strip_prefix('"') {
+        let mut string: String = String::new();
+        let mut characters: std::str::Chars = quoted.chars();
+        loop {
+            match characters.next()? {
+                '"' => break,
+                '\\' => string.push(match characters.next()? {
+                    '"' => '"',
+                    '\\' => '\\',
+                    'n' => '\n',
+                    't' => '\t',
+                    _ => return None,
+                }),
+                character => string.push(character),
+            }
+        }
+
+        // Nothing may follow the closing quote
+        return characters
+            .as_str()
+            .is_empty()
+            .then_some(Value::String(string));
+    }
+
+    // Underscores may separate digits, like `1_000`
+    let digits: &str = value.strip_prefix(['+', '-']).unwrap_or(value);
+    if digits.starts_with(|character: char| character.is_ascii_digit())
+        && !digits.ends_with('_')
+        && !digits.contains("__")
+    {
+        return value
+            .replace('_', "")
+            .parse::<i64>()
+            .ok()
+            .map(Value::Integer);
+    }
+
+    None
+}
@@ -0,0 +1,390 @@
+use crate::builtins::builtin;
+use crate::bytecode::{CallSite, FunctionInfo, Instruction, Program};
+use crate::diagnostic::Span;
+use crate::disasm::{constant_text, format_operands, mnemonic};
+use crate::eval::{
+    binary_operation, call_depth_error, index_value, interpolate, unary_operation, CallFrame,
+    Interpreter, RuntimeError, Value, MAX_CALL_DEPTH,
+};
+use crate::parser::OperatorToken;
+
+/// Function call in progress
+#[derive(Clone, Copy, Debug)]
+struct Frame {
+    /// Instruction to continue at after returning
+    return_address: usize,
+    /// Stack index of the first parameter, locals follow the parameters
+    base: usize,
+    /// Call site the function was called from, for the trace
+    site: usize,
+}
+
+/// Printing of instructions as they run, for `--trace`
+#[derive(Clone, Copy, Debug)]
+struct Trace {
+    /// Most instructions to print, `None` for no limit
+    limit: Option<usize>,
+    /// Instructions printed so far
+    count: usize,
+}
+
+/// Stack slots shown in each line of a trace, counted from the top
+const TRACE_STACK_SLOTS: usize = 3;
+
+/// Longest value shown in a trace, in characters, longer values are cut
+const TRACE_VALUE_LENGTH: usize = 32;
+
+/// Stack machine running a compiled `Program`, gives the same results as the tree-walker
+#[derive(Debug)]
+pub struct Vm {
+    /// Runs the builtins, it keeps their state like the random generator and the input
+    interpreter: Interpreter,
+    /// Value of every global by its index in `Program::names`, `None` until it is defined
+    globals: Vec<Option<Value>>,
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    trace: Option<Trace>,
+}
+
+/// Stop on a bug in the compiler, like popping an empty stack, `index` is the instruction
+fn internal_error(index: usize, message: &str) -> ! {
+    panic!("internal error at instruction {}: {}", index, message)
+}
+
+impl Vm {
+    /// Machine whose builtins run on `interpreter`, which also decides what overflow does
+    pub fn new(interpreter: Interpreter) -> Vm {
+        Vm {
+            interpreter,
+            globals: Vec::new(),
+            stack: Vec::new(),
+            frames: Vec::new(),
+            trace: None,
+        }
+    }
+
+    /// Print every instruction to stderr before it runs, with the top of the stack, stopping
+    /// after `limit` instructions
+    pub fn set_trace(&mut self, limit: Option<usize>) {
+        self.trace = Some(Trace { limit, count: 0 });
+    }
+
+    /// Run a program from its first instruction, returning the value of a trailing expression
+    /// statement
+    ///
+    /// Globals are indexed by the program's names, so each run starts without the globals of
+    /// an earlier one.
+    pub fn run(&mut self, program: &Program) -> Result<Option<Value>, RuntimeError> {
+        self.run_each(program, &mut |_: &Value| {})
+    }
+
+    /// Like `run`, also calling `each` with the value of every top level expression statement
+    pub fn run_each(
+        &mut self,
+        program: &Program,
+        each: &mut dyn FnMut(&Value),
+    ) -> Result<Option<Value>, RuntimeError> {
+        // Only the names the program uses get a slot, the globals of the interpreter like `PI`
+        // and the ones from `--seed-vars` start defined
+        self.globals = program
+            .names
+            .iter()
+            .map(|name: &String| self.interpreter.variable(name).cloned())
+            .collect();
+
+        // Chosen once so running without a trace doesn't check for one every instruction
+        let result: Result<Value, RuntimeError> = if self.trace.is_some() {
+            self.execute::<true>(program, each)
+        } else {
+            self.execute::<false>(program, each)
+        };
+
+        match result {
+            Ok(value) => Ok(program.has_result.then_some(value)),
+            Err(mut error) => {
+                // Innermost call first, like the tree-walker builds it while unwinding
+                for frame in self.frames.iter().rev() {
+                    let site: &CallSite = &program.call_sites[frame.site];
+                    error.trace.push(CallFrame {
+                        name: site.name.clone(),
+                        call_span: site.span,
+                    });
+                }
+
+                self.stack.clear();
+                self.frames.clear();
+                Err(error)
+            }
+        }
+    }
+
+    fn pop(&mut self, index: usize) -> Value {
+        self.stack
+            .pop()
+            .unwrap_or_else(|| internal_error(index, "stack underflow"))
+    }
+
+    /// Remove the top `count` values, oldest first
+    fn pop_many(&mut self, index: usize, count: usize) -> Vec<Value> {
+        if count > self.stack.len() {
+            internal_error(index, "stack underflow");
+        }
+
+        self.stack.split_off(self.stack.len() - count)
+    }
+
+    /// Stack index of local `slot` of the running function
+    fn slot(&self, index: usize, slot: usize) -> usize {
+        let Some(frame) = self.frames.last() else {
+            internal_error(index, "local variable used outside of a function");
+        };
+
+        if frame.base + slot >= self.stack.len() {
+            internal_error(index, "local variable slot is past the top of the stack");
+        }
+
+        frame.base + slot
+    }
+
+    fn jump(program: &Program, index: usize, target: usize) -> usize {
+        if target >= program.code.len() {
+            internal_error(index, &format!("bad jump target {}", target));
+        }
+
+        target
+    }
+
+    fn execute<const TRACE: bool>(
+        &mut self,
+        program: &Program,
+        each: &mut dyn FnMut(&Value),
+    ) -> Result<Value, RuntimeError> {
+        let mut address: usize = 0;
+
+        loop {
+            let index: usize = address;
+            let Some(instruction) = program.code.get(index) else {
+                internal_error(index, "ran past the end of the program");
+            };
+            let span: Span = program.spans[index];
+            address += 1;
+
+            if TRACE {
+                self.trace_instruction(program, index);
+            }
+
+            match *instruction {
+                Instruction::Push(constant) => self.stack.push(program.constants[constant].clone()),
+                Instruction::Interpolate(string) => {
+                    self.stack
+                        .push(Value::String(interpolate(&program.strings[string])?));
+                }
+                Instruction::Array(count) => {
+                    let elements: Vec<Value> = self.pop_many(index, count);
+                    self.stack.push(Value::Array(elements));
+                }
+                Instruction::Index => {
+                    let position: Value = self.pop(index);
+                    let target: Value = self.pop(index);
+                    self.stack.push(index_value(target, position, span)?);
+                }
+                Instruction::Negate => {
+                    let operand: Value = self.pop(index);
+                    self.stack.push(unary_operation(
+                        OperatorToken::Minus,
+                        operand,
+                        span,
+                        self.interpreter.overflow(),
+                    )?);
+                }
+                Instruction::Add => self.binary(index, OperatorToken::Plus, span)?,
+                Instruction::Sub => self.binary(index, OperatorToken::Minus, span)?,
+                Instruction::Mul => self.binary(index, OperatorToken::Star, span)?,
+                Instruction::Div => self.binary(index, OperatorToken::Slash, span)?,
+                Instruction::Operator(operator_token) => {
+                    self.binary(index, operator_token, span)?
+                }
+                Instruction::Compare(operator_token) => {
+                    let right: Value = self.pop(index);
+                    let left: Value = self.pop(index);
+                    self.stack.push(right.clone());
+                    self.stack.push(binary_operation(
+                        operator_token,
+                        left,
+                        right,
+                        span,
+                        self.interpreter.overflow(),
+                    )?);
+                }
+                Instruction::Load(slot) => {
+                    let slot: usize = self.slot(index, slot);
+                    self.stack.push(self.stack[slot].clone());
+                }
+                Instruction::Store(slot) => {
+                    let value: Value = self.pop(index);
+                    let slot: usize = self.slot(index, slot);
+                    self.stack[slot] = value;
+                }
+                Instruction::LoadGlobal(name) => {
+                    let value: Value = self.globals[name as usize].clone().ok_or_else(|| {
+                        RuntimeError::new(
+                            format!("undefined variable '{}'", program.names[name as usize]),
+                            span,
+                        )
+                    })?;
+                    self.stack.push(value);
+                }
+                Instruction::StoreGlobal(name) => {
+                    let value: Value = self.pop(index);
+                    match &mut self.globals[name as usize] {
+                        Some(global) => *global = value,
+                        None => {
+                            return Err(RuntimeError::new(
+                                format!(
+                                "cannot assign to undefined variable '{}', use 'let' to define it",
+                                program.names[name as usize]
+                            ),
+                                span,
+                            ))
+                        }
+                    }
+                }
+                Instruction::DefineGlobal(name) => {
+                    let value: Value = self.pop(index);
+                    self.globals[name as usize] = Some(value);
+                }
+                Instruction::Pop => {
+                    self.pop(index);
+                }
+                Instruction::Jump(target) => address = Vm::jump(program, index, target),
+                Instruction::JumpIfFalse(target) => match self.pop(index) {
+                    Value::Bool(true) => {}
+                    Value::Bool(false) => address = Vm::jump(program, index, target),
+                    value => {
+                        return Err(RuntimeError::new(
+                            format!("condition must be a bool but found {}", value.type_name()),
+                            span,
+                        ))
+                    }
+                },
+                Instruction::Call { function, site } => {
+                    let function: &FunctionInfo = &program.functions[function];
+                    if self.frames.len() >= MAX_CALL_DEPTH {
+                        return Err(call_depth_error(&function.name, span));
+                    }
+                    if function.parameters > self.stack.len() {
+                        internal_error(index, "stack underflow");
+                    }
+
+                    let base: usize = self.stack.len() - function.parameters;
+                    self.stack.resize(base + function.locals, Value::Nil);
+                    self.frames.push(Frame {
+                        return_address: address,
+                        base,
+                        site,
+                    });
+                    address = Vm::jump(program, index, function.address);
+                }
+                Instruction::CallBuiltin(site) => {
+                    let site: &CallSite = &program.call_sites[site];
+                    let Some((group, _)) = builtin(&site.name) else {
+                        internal_error(index, &format!("'{}' is not a builtin", site.name));
+                    };
+                    // Compiled without knowing about `--no-std`, so it is only an error here
+                    if self.interpreter.builtin(&site.name).is_none() {
+                        return Err(RuntimeError::new(
+                            format!("undefined function '{}'", site.name),
+                            site.span,
+                        ));
+                    }
+
+                    let arguments: Vec<Value> = self.pop_many(index, site.arguments);
+                    let result: Value = self
+                        .interpreter
+                        .call_builtin(group, &site.name, &arguments, site.span, &site.text)?;
+                    self.stack.push(result);
+                }
+                Instruction::Yield => match self.stack.last() {
+                    Some(value) => each(value),
+                    None => internal_error(index, "stack underflow"),
+                },
+                Instruction::Return => {
+                    let value: Value = self.pop(index);
+
+                    let Some(frame) = self.frames.pop() else {
+                        return Ok(value);
+                    };
+                    self.stack.truncate(frame.base);
+                    self.stack.push(value);
+                    address = frame.return_address;
+                }
+                Instruction::Fail(error) => return Err(program.errors[error].clone()),
+            }
+        }
+    }
+
+    /// Print the instruction at `index` and the top of the stack to stderr, unless the trace
+    /// limit was reached
+    fn trace_instruction(&mut self, program: &Program, index: usize) {
+        let Some(trace) = &mut self.trace else {
+            return;
+        };
+
+        if trace.limit.is_some_and(|limit: usize| trace.count >= limit) {
+            if trace.limit == Some(trace.count) {
+                eprintln!("trace limit of {} instructions reached", trace.count);
+                trace.count += 1;
+            }
+            return;
+        }
+        trace.count += 1;
+
+        let top: usize = self.stack.len().saturating_sub(TRACE_STACK_SLOTS);
+        let mut slots: Vec<String> = self.stack[top..]
+            .iter()
+            .map(|value: &Value| {
+                let text: String = constant_text(value);
+                match text.char_indices().nth(TRACE_VALUE_LENGTH) {
+                    Some((end, _)) => format!("{}...", &text[..end]),
+                    None => text,
+                }
+            })
+            .collect();
+        if top > 0 {
+            slots.insert(0, "..".to_string());
+        }
+
+        let instruction: &Instruction = &program.code[index];
+        let operands: String = format_operands(program, instruction, &|target: usize| {
+            format!("@{:04}", target)
+        });
+        eprintln!(
+            "{:04}  {:<14} {:<20} [{}]",
+            index,
+            mnemonic(instruction),
+            operands,
+            slots.join(", ")
+        );
+    }
+
+    /// Pop two operands and push the result of `operator_token`
+    fn binary(
+        &mut self,
+        index: usize,
+        operator_token: OperatorToken,
+        span: Span,
+    ) -> Result<(), RuntimeError> {
+        let right: Value = self.pop(index);
+        let left: Value = self.pop(index);
+
+        self.stack.push(binary_operation(
+            operator_token,
+            left,
+            right,
+            span,
+            self.interpreter.overflow(),
+        )?);
+
+        Ok(())
+    }
+}
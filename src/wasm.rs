@@ -0,0 +1,495 @@
+use crate::diagnostic::Diagnostic;
+use crate::eval::Value;
+use crate::ir::{Callee, Function, Module, Operation, Target, Temporary, Terminator, Variable};
+use crate::kinds::{Kind, Kinds};
+use crate::parser::OperatorToken;
+
+/// Module and name of the imported function that prints a value
+///
+/// It is called as `print(value: i64, kind: i32, end: i32)` and writes `value` as an int for
+/// `kind` 0, as `true` or `false` for 1, or nothing for 2, followed by the character `end`.
+pub const PRINT_IMPORT: (&str, &str) = ("env", "print");
+
+const I32: u8 = 0x7F;
+const I64: u8 = 0x7E;
+
+/// Translate the integer subset of a program to a WebAssembly module in the binary format,
+/// for `ph build --emit wasm`
+///
+/// Supports the same programs as `crate::asm`. The module exports `main`, which runs the
+/// top level code, and imports `env.print` to print, see `PRINT_IMPORT`.
+/// `examples/run-wasm.mjs` runs it with Node.js.
+///
+/// Arithmetic that overflows is checked after it wraps and runs `unreachable`, so it traps
+/// like dividing by zero or `/ -1` overflowing does, where `ph run` fails with an error.
+/// Shifts use the low 6 bits of the amount, which is what the wasm instructions do.
+pub fn generate(module: &Module) -> Result<Vec<u8>, Diagnostic> {
+    let kinds: Kinds = crate::kinds::check(module, "wasm")?;
+    // The import comes first in the function index space, `main` last
+    let main_index: usize = 1 + module.functions.len();
+
+    // Type 0 is `print`, 1 is `main` and the functions follow in order
+    let mut types: Vec<Vec<u8>> = vec![
+        function_type(&[I64, I32, I32], &[]),
+        function_type(&[], &[]),
+    ];
+    for function in &module.functions {
+        types.push(function_type(&vec![I64; function.parameters.len()], &[I64]));
+    }
+
+    let mut import: Vec<u8> = Vec::new();
+    name(&mut import, PRINT_IMPORT.0);
+    name(&mut import, PRINT_IMPORT.1);
+    // A function of type 0
+    import.extend([0x00, 0x00]);
+
+    let function_types: Vec<Vec<u8>> = (0..=module.functions.len())
+        .map(|index: usize| {
+            let type_index: usize = if index == module.functions.len() {
+                1
+            } else {
+                2 + index
+            };
+            unsigned(type_index as u64)
+        })
+        .collect();
+
+    let globals: Vec<Vec<u8>> = kinds
+        .globals
+        .iter()
+        .map(|_: &String| {
+            // Mutable i64 starting at 0
+            let mut global: Vec<u8> = vec![I64, 0x01, 0x42];
+            global.extend(signed(0));
+            global.push(0x0B);
+            global
+        })
+        .collect();
+
+    let mut export: Vec<u8> = Vec::new();
+    name(&mut export, "main");
+    export.push(0x00);
+    export.extend(unsigned(main_index as u64));
+
+    let mut bodies: Vec<Vec<u8>> = Vec::new();
+    for (function, function_kinds) in module.functions.iter().zip(&kinds.functions) {
+        bodies.push(Emitter::new(function, function_kinds, &kinds.globals).function(None));
+    }
+    bodies.push(
+        Emitter::new(&module.main, &kinds.main, &kinds.globals).function(Some(module.has_result)),
+    );
+
+    // Magic number and version 1
+    let mut output: Vec<u8> = b"\0asm".to_vec();
+    output.extend([0x01, 0x00, 0x00, 0x00]);
+    section(&mut output, 1, &types);
+    section(&mut output, 2, &[import]);
+    section(&mut output, 3, &function_types);
+    if !globals.is_empty() {
+        section(&mut output, 6, &globals);
+    }
+    section(&mut output, 7, &[export]);
+    let bodies: Vec<Vec<u8>> = bodies
+        .into_iter()
+        .map(|body: Vec<u8>| {
+            let mut sized: Vec<u8> = unsigned(body.len() as u64);
+            sized.extend(body);
+            sized
+        })
+        .collect();
+    section(&mut output, 10, &bodies);
+
+    Ok(output)
+}
+
+/// Unsigned LEB128
+pub fn unsigned(mut value: u64) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    loop {
+        let byte: u8 = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return bytes;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+/// Signed LEB128, ends once the rest of the value is the sign bit of the last byte
+pub fn signed(mut value: i64) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    loop {
+        let byte: u8 = (value & 0x7F) as u8;
+        value >>= 7;
+        let sign: bool = byte & 0x40 != 0;
+        if (value == 0 && !sign) || (value == -1 && sign) {
+            bytes.push(byte);
+            return bytes;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+/// Section with its id, size and a vector of entries
+fn section(output: &mut Vec<u8>, id: u8, entries: &[Vec<u8>]) {
+    let mut contents: Vec<u8> = unsigned(entries.len() as u64);
+    for entry in entries {
+        contents.extend(entry);
+    }
+
+    output.push(id);
+    output.extend(unsigned(contents.len() as u64));
+    output.extend(contents);
+}
+
+fn function_type(parameters: &[u8], results: &[u8]) -> Vec<u8> {
+    let mut function_type: Vec<u8> = vec![0x60];
+    function_type.extend(unsigned(parameters.len() as u64));
+    function_type.extend(parameters);
+    function_type.extend(unsigned(results.len() as u64));
+    function_type.extend(results);
+    function_type
+}
+
+fn name(output: &mut Vec<u8>, text: &str) {
+    output.extend(unsigned(text.len() as u64));
+    output.extend(text.as_bytes());
+}
+
+/// Value type of a kind, nil values are never used but still get a local
+fn value_type(kind: Kind) -> u8 {
+    match kind {
+        Kind::Int => I64,
+        _ => I32,
+    }
+}
+
+struct Emitter<'a> {
+    function: &'a Function,
+    kinds: &'a [Kind],
+    globals: &'a [String],
+    code: Vec<u8>,
+}
+
+impl<'a> Emitter<'a> {
+    fn new(function: &'a Function, kinds: &'a [Kind], globals: &'a [String]) -> Emitter<'a> {
+        Emitter {
+            function,
+            kinds,
+            globals,
+            code: Vec::new(),
+        }
+    }
+
+    /// Locals come first, starting with the parameters, then temporaries
+    fn temporary(&self, temporary: Temporary) -> Vec<u8> {
+        unsigned((self.function.locals.len() + temporary) as u64)
+    }
+
+    fn get(&mut self, temporary: Temporary) {
+        self.code.push(0x20);
+        let index: Vec<u8> = self.temporary(temporary);
+        self.code.extend(index);
+    }
+
+    fn set(&mut self, temporary: Temporary) {
+        self.code.push(0x21);
+        let index: Vec<u8> = self.temporary(temporary);
+        self.code.extend(index);
+    }
+
+    /// Body of the function with its locals, `main` gives whether the top level code has a
+    /// result to print
+    ///
+    /// Blocks only jump forward, so every block but the first follows the `end` of a wasm
+    /// `block` that the ones before it can `br` out of to get there.
+    fn function(mut self, main: Option<bool>) -> Vec<u8> {
+        let count: usize = self.function.blocks.len();
+        for _ in 1..count {
+            self.code.extend([0x02, 0x40]);
+        }
+
+        for (id, block) in self.function.blocks.iter().enumerate() {
+            if id > 0 {
+                self.code.push(0x0B);
+            }
+
+            for operation in &block.operations {
+                self.operation(operation);
+            }
+
+            match &block.terminator {
+                Terminator::Jump(target) => self.jump(id, target, 0),
+                Terminator::Branch {
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    self.get(*condition);
+                    // Inside the `if` there is one more label to break out of
+                    self.code.extend([0x04, 0x40]);
+                    self.jump(id, then, 1);
+                    self.code.push(0x0B);
+                    self.jump(id, otherwise, 0);
+                }
+                Terminator::Return(value) => {
+                    match main {
+                        // Like `ph run`, a nil result isn't printed
+                        Some(has_result) => {
+                            if has_result && self.kinds[*value] != Kind::Nil {
+                                self.print(&[*value]);
+                            }
+                        }
+                        None => self.get(*value),
+                    }
+                    self.code.push(0x0F);
+                }
+            }
+        }
+        self.code.push(0x0B);
+
+        // Parameters are declared by the type, the rest in runs of one type
+        let mut types: Vec<u8> =
+            vec![I64; self.function.locals.len() - self.function.parameters.len()];
+        types.extend(self.kinds.iter().map(|kind: &Kind| value_type(*kind)));
+        let mut runs: Vec<(usize, u8)> = Vec::new();
+        for local_type in types {
+            match runs.last_mut() {
+                Some((run, run_type)) if *run_type == local_type => *run += 1,
+                _ => runs.push((1, local_type)),
+            }
+        }
+
+        let mut body: Vec<u8> = unsigned(runs.len() as u64);
+        for (run, local_type) in runs {
+            body.extend(unsigned(run as u64));
+            body.push(local_type);
+        }
+        body.extend(self.code);
+        body
+    }
+
+    /// Move the arguments into the parameters of `target` and go there, `depth` is the number
+    /// of labels opened in the current block
+    fn jump(&mut self, id: usize, target: &Target, depth: usize) {
+        let parameters: &[Temporary] = &self.function.blocks[target.block].parameters;
+        for (parameter, argument) in parameters.iter().zip(&target.arguments) {
+            if self.kinds[*parameter] != Kind::Nil {
+                self.get(*argument);
+                self.set(*parameter);
+            }
+        }
+
+        // The innermost wasm block ends right before the next block, so that one falls through
+        if depth > 0 || target.block != id + 1 {
+            self.code.push(0x0C);
+            self.code
+                .extend(unsigned((target.block - id - 1 + depth) as u64));
+        }
+    }
+
+    fn operation(&mut self, operation: &Operation) {
+        match operation {
+            Operation::Constant(destination, value) => {
+                match value {
+                    Value::Int(value) => {
+                        self.code.push(0x42);
+                        self.code.extend(signed(*value as i64));
+                    }
+                    Value::Bool(value) => {
+                        self.code.push(0x41);
+                        self.code.extend(signed(i64::from(*value)));
+                    }
+                    _ => return,
+                }
+                self.set(*destination);
+            }
+            Operation::Negate(destination, operand) => {
+                self.code.push(0x42);
+                self.code.extend(signed(0));
+                self.get(*operand);
+                self.code.push(0x7D);
+                self.set(*destination);
+
+                // Only the smallest int negates to itself
+                self.get(*operand);
+                self.code.push(0x42);
+                self.code.extend(signed(i64::MIN));
+                self.code.push(0x51);
+                self.trap_if();
+            }
+            Operation::Binary {
+                destination,
+                operator_token,
+                left,
+                right,
+            }
+            | Operation::Compare {
+                destination,
+                operator_token,
+                left,
+                right,
+            } => {
+                self.get(*left);
+                self.get(*right);
+                let bool_operands: bool = self.kinds[*left] == Kind::Bool;
+                self.code.push(match operator_token {
+                    OperatorToken::EqualsEquals if bool_operands => 0x46,
+                    OperatorToken::BangEquals if bool_operands => 0x47,
+                    OperatorToken::EqualsEquals => 0x51,
+                    OperatorToken::BangEquals => 0x52,
+                    OperatorToken::Less => 0x53,
+                    OperatorToken::Greater => 0x55,
+                    OperatorToken::LessEquals => 0x57,
+                    OperatorToken::GreaterEquals => 0x59,
+                    OperatorToken::Plus => 0x7C,
+                    OperatorToken::Minus => 0x7D,
+                    OperatorToken::Star => 0x7E,
+                    OperatorToken::Slash => 0x7F,
+                    OperatorToken::Percent => 0x81,
+                    OperatorToken::Ampersand => 0x83,
+                    OperatorToken::Pipe => 0x84,
+                    OperatorToken::Caret => 0x85,
+                    OperatorToken::LessLess => 0x86,
+                    OperatorToken::GreaterGreater => 0x87,
+                });
+                self.set(*destination);
+                self.check_overflow(*operator_token, *destination, *left, *right);
+            }
+            Operation::Load(destination, variable) => {
+                let (opcode, index): (u8, usize) = self.variable(variable);
+                self.code.push(opcode);
+                self.code.extend(unsigned(index as u64));
+                self.set(*destination);
+            }
+            Operation::Store(variable, value) | Operation::Define(variable, value) => {
+                self.get(*value);
+                let (opcode, index): (u8, usize) = self.variable(variable);
+                // `local.set` and `global.set` follow their `get`
+                self.code.push(opcode + 1);
+                self.code.extend(unsigned(index as u64));
+            }
+            Operation::Call {
+                destination,
+                callee: Callee::Function(index),
+                arguments,
+                ..
+            } => {
+                for argument in arguments {
+                    self.get(*argument);
+                }
+                self.code.push(0x10);
+                self.code.extend(unsigned(1 + *index as u64));
+                self.set(*destination);
+            }
+            // `print` is the only builtin that passes the check
+            Operation::Call {
+                callee: Callee::Builtin,
+                arguments,
+                ..
+            } => self.print(arguments),
+            _ => {}
+        }
+    }
+
+    /// Trap if `result` of `left operator right` wrapped around
+    ///
+    /// A sum overflowed when its sign differs from that of both operands, a difference when
+    /// the operands' signs differ and its sign differs from the left one. A product did when
+    /// dividing it by a nonzero left operand doesn't give the right one back, `i64::MIN * -1`
+    /// makes that division trap by itself.
+    fn check_overflow(
+        &mut self,
+        operator_token: OperatorToken,
+        result: Temporary,
+        left: Temporary,
+        right: Temporary,
+    ) {
+        match operator_token {
+            OperatorToken::Plus | OperatorToken::Minus => {
+                // (left ^ result) & (right ^ result) < 0 for a sum, or with left ^ right for
+                // a difference
+                self.get(left);
+                self.get(result);
+                self.code.push(0x85);
+                if operator_token == OperatorToken::Plus {
+                    self.get(right);
+                    self.get(result);
+                } else {
+                    self.get(left);
+                    self.get(right);
+                }
+                self.code.push(0x85);
+                self.code.push(0x83);
+                self.code.push(0x42);
+                self.code.extend(signed(0));
+                self.code.push(0x53);
+                self.trap_if();
+            }
+            OperatorToken::Star => {
+                // if left != 0 { if result / left != right { unreachable } }
+                self.get(left);
+                self.code.extend([0x50, 0x45, 0x04, 0x40]);
+                self.get(result);
+                self.get(left);
+                self.code.push(0x7F);
+                self.get(right);
+                self.code.push(0x52);
+                self.trap_if();
+                self.code.push(0x0B);
+            }
+            _ => {}
+        }
+    }
+
+    /// Trap if the i32 on the stack isn't zero
+    fn trap_if(&mut self) {
+        self.code.extend([0x04, 0x40, 0x00, 0x0B]);
+    }
+
+    /// `local.get` or `global.get` and the index of a variable
+    fn variable(&self, variable: &Variable) -> (u8, usize) {
+        match variable {
+            Variable::Local(slot) => (0x20, *slot),
+            Variable::Global(name) => (
+                0x23,
+                self.globals
+                    .iter()
+                    .position(|global: &String| global == name)
+                    .expect("The check found every global"),
+            ),
+        }
+    }
+
+    /// Print values separated by spaces on one line, like `print`
+    fn print(&mut self, values: &[Temporary]) {
+        if values.is_empty() {
+            self.code.push(0x42);
+            self.code.extend(signed(0));
+            self.print_call(2, '\n');
+        }
+
+        for (index, value) in values.iter().enumerate() {
+            self.get(*value);
+            let kind: i64 = match self.kinds[*value] {
+                Kind::Bool => {
+                    // i64.extend_i32_u
+                    self.code.push(0xAD);
+                    1
+                }
+                _ => 0,
+            };
+            let end: char = if index + 1 == values.len() { '\n' } else { ' ' };
+            self.print_call(kind, end);
+        }
+    }
+
+    fn print_call(&mut self, kind: i64, end: char) {
+        self.code.push(0x41);
+        self.code.extend(signed(kind));
+        self.code.push(0x41);
+        self.code.extend(signed(end as i64));
+        self.code.extend([0x10, 0x00]);
+    }
+}
@@ -0,0 +1,234 @@
+/// How often the watched files are looked at for changes
+pub const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// How long the files must stay the same after a change before the rebuild, so an editor that
+/// writes a temporary file and renames it over the old one causes one rebuild, not two
+pub const DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Set by the Ctrl-C handler, the loop stops at the next poll
+static INTERRUPTED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+extern "C" fn interrupt(_: libc::c_int) {
+    INTERRUPTED.store(true, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// What a watched file looked like, `None` while it is missing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Stamp {
+    modified: std::time::SystemTime,
+    /// Catches a write within the same mtime tick on file systems with coarse times
+    len: u64,
+}
+
+/// Every watched file with how it last looked
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    files: Vec<(std::path::PathBuf, Option<Stamp>)>,
+}
+
+impl Snapshot {
+    pub fn take(files: &[std::path::PathBuf]) -> Snapshot {
+        Snapshot {
+            files: files
+                .iter()
+                .map(|file: &std::path::PathBuf| {
+                    let stamp: Option<Stamp> =
+                        std::fs::metadata(file)
+                            .ok()
+                            .and_then(|metadata: std::fs::Metadata| {
+                                Some(Stamp {
+                                    modified: metadata.modified().ok()?,
+                                    len: metadata.len(),
+                                })
+                            });
+                    (file.clone(), stamp)
+                })
+                .collect(),
+        }
+    }
+
+    /// Files that were created, changed or removed since `self` was taken
+    pub fn changes(&self, now: &Snapshot) -> Vec<std::path::PathBuf> {
+        now.files
+            .iter()
+            .filter(|(file, stamp): &&(std::path::PathBuf, Option<Stamp>)| {
+                !self.files.contains(&(file.clone(), *stamp))
+            })
+            .map(|(file, _): &(std::path::PathBuf, Option<Stamp>)| file.clone())
+            .collect()
+    }
+}
+
+/// What the watch loop does after looking at the files
+#[derive(Debug, PartialEq, Eq)]
+pub enum Decision {
+    /// Nothing changed, look again after `POLL_INTERVAL`
+    Wait,
+    /// The files are still changing, look again after `DEBOUNCE`
+    Settle,
+    /// The files settled after changing, rebuild for the changed files
+    Rebuild(Vec<std::path::PathBuf>),
+}
+
+/// Decides from snapshots of the watched files when to rebuild
+#[derive(Debug)]
+pub struct Watcher {
+    /// The files when the last build started
+    built: Snapshot,
+    /// The files when they were last looked at, while they are changing
+    changing: Option<Snapshot>,
+}
+
+impl Watcher {
+    pub fn new(built: Snapshot) -> Watcher {
+        Watcher {
+            built,
+            changing: None,
+        }
+    }
+
+    /// Decide what to do now that the files look like `now`
+    ///
+    /// A change is only rebuilt for once two looks in a row find the files the same, and not at
+    /// all when they settle back to how they were built.
+    pub fn poll(&mut self, now: Snapshot) -> Decision {
+        let settled: bool = match self.changing.take() {
+            Some(previous) => previous == now,
+            None if self.built.changes(&now).is_empty() => return Decision::Wait,
+            None => false,
+        };
+        if !settled {
+            self.changing = Some(now);
+            return Decision::Settle;
+        }
+
+        match self.built.changes(&now) {
+            changes if changes.is_empty() => Decision::Wait,
+            changes => Decision::Rebuild(changes),
+        }
+    }
+}
+
+/// The file given and every file it imports, which are the files a change to rebuilds for
+///
+/// When the imports can't be resolved, the file with the broken import is watched so fixing
+/// it rebuilds.
+pub fn watched_files(file: &str, layout: bool, lenient: bool) -> Vec<std::path::PathBuf> {
+    let mut files: Vec<std::path::PathBuf> = vec![std::path::PathBuf::from(file)];
+    let Ok(source) = std::fs::read_to_string(file) else {
+        return files;
+    };
+
+    let names: Vec<String> = match crate::import::load(file, &source, layout, lenient) {
+        Ok(loaded) => loaded
+            .imports
+            .into_iter()
+            .map(|import: crate::diagnostic::SourceFile| import.name)
+            .collect(),
+        Err(error) => vec![error.file_name],
+    };
+    for name in names {
+        let path: std::path::PathBuf = std::path::PathBuf::from(name);
+        if !files.contains(&path) {
+            files.push(path);
+        }
+    }
+
+    files
+}
+
+/// Print a separator with the time and the files in `changes`, none for the first build, and
+/// run `command` to completion
+///
+/// A build that fails is reported by `command` itself.
+pub fn rebuild(command: &mut std::process::Command, changes: &[std::path::PathBuf]) {
+    if crate::log::enabled(crate::log::Verbosity::Normal) {
+        let reason: String = match changes {
+            [] => "building".to_string(),
+            [file] => format!("{} changed, rebuilding", file.display()),
+            [file, rest @ ..] => format!(
+                "{} and {} more changed, rebuilding",
+                file.display(),
+                rest.len()
+            ),
+        };
+        eprintln!("---------- [{}] {}", timestamp(), reason);
+    }
+
+    if let Err(error) = command.status() {
+        eprintln!("error: failed to run the build: {}", error);
+        std::process::exit(1);
+    }
+}
+
+/// Exit with a usage error if `file` is stdin, which can't change once it is read
+pub fn reject_stdin(file: &str) {
+    if file == crate::source::STDIN {
+        eprintln!("error: --watch needs a file, stdin can't be watched");
+        std::process::exit(2);
+    }
+}
+
+/// Run the command `ph` was started with, without `--watch`, then again each time `file` or a
+/// file it imports changes, until Ctrl-C
+///
+/// Each build runs in a child process, so one that fails and exits doesn't stop the watching.
+pub fn watch(file: &str, layout: bool, lenient: bool) {
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        );
+    }
+
+    let executable: std::path::PathBuf = match std::env::current_exe() {
+        Ok(executable) => executable,
+        Err(error) => {
+            eprintln!("error: failed to find the ph executable: {}", error);
+            std::process::exit(1);
+        }
+    };
+    let mut command: std::process::Command = std::process::Command::new(executable);
+    command.args(
+        std::env::args_os()
+            .skip(1)
+            .filter(|argument: &std::ffi::OsString| argument != "--watch"),
+    );
+
+    let mut files: Vec<std::path::PathBuf> = watched_files(file, layout, lenient);
+    let mut watcher: Watcher = Watcher::new(Snapshot::take(&files));
+    rebuild(&mut command, &[]);
+
+    let mut wait: std::time::Duration = POLL_INTERVAL;
+    loop {
+        std::thread::sleep(wait);
+        if INTERRUPTED.load(std::sync::atomic::Ordering::Relaxed) {
+            break;
+        }
+
+        wait = match watcher.poll(Snapshot::take(&files)) {
+            Decision::Wait => POLL_INTERVAL,
+            Decision::Settle => DEBOUNCE,
+            Decision::Rebuild(changes) => {
+                // The change may have added or removed imports, and files are looked at before
+                // the build so a change while it runs causes another
+                files = watched_files(file, layout, lenient);
+                watcher = Watcher::new(Snapshot::take(&files));
+                rebuild(&mut command, &changes);
+                POLL_INTERVAL
+            }
+        };
+    }
+}
+
+/// Local time of day as `hh:mm:ss`
+fn timestamp() -> String {
+    // SAFETY: `tm` is plain data and is fully written by `localtime_r` before being read
+    unsafe {
+        let now: libc::time_t = libc::time(std::ptr::null_mut());
+        let mut time: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&now, &mut time);
+        format!("{:02}:{:02}:{:02}", time.tm_hour, time.tm_min, time.tm_sec)
+    }
+}
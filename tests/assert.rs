@@ -0,0 +1,38 @@
+mod common;
+
+#[test]
+fn passing_assert_lets_the_program_go_on() {
+    assert_eq!(
+        common::ph_stdout(
+            &["eval", "assert(1 + 1 == 2)\nassert_eq(2 * 3, 6)\nprint(1)"],
+            ""
+        ),
+        "1\n"
+    );
+}
+
+#[test]
+fn failing_assert_stops_the_program_with_its_source() {
+    let output: std::process::Output = common::output(
+        common::ph().args(["eval", "print(1)\nassert(1 + 1 == 3)\nprint(2)"]),
+        "",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "runtime error: assertion failed: assert(1 + 1 == 3)\n --> <eval>:2:1\n  |\n2 | assert(1 + 1 == 3)\n  | ^^^^^^^^^^^^^^^^^^\n"
+    );
+}
+
+#[test]
+fn failing_assert_eq_shows_both_values() {
+    assert_eq!(
+        common::error("assert_eq(\"1\", 1)"),
+        "assertion failed: left = \"1\", right = 1"
+    );
+    assert_eq!(
+        common::error("assert_eq(1, 1.0)"),
+        "assertion failed: left = 1, right = 1.0"
+    );
+}
@@ -0,0 +1,573 @@
+use phoenix_script::link::find_compiler;
+
+mod common;
+
+/// Emit `artifact` for `source` into `output` with `ph build`
+fn emit(source: &std::path::Path, artifact: &str, output: &std::path::Path) {
+    common::ph_stdout(
+        &[
+            "build",
+            &source.display().to_string(),
+            "--emit",
+            artifact,
+            "-o",
+            &output.display().to_string(),
+        ],
+        "",
+    );
+}
+
+/// Run `command` and fail the test with what it printed unless it succeeds without warnings
+fn compile(command: &mut std::process::Command) {
+    let output: std::process::Output = common::output(command, "");
+    let stderr: String = String::from_utf8_lossy(&output.stderr).to_string();
+    assert!(
+        output.status.success() && stderr.is_empty(),
+        "{:?} failed with {}:\n{}",
+        command,
+        output.status,
+        stderr
+    );
+}
+
+#[test]
+fn c_output_compiles_cleanly_and_matches_ph_run() {
+    let Some(compiler) = find_compiler() else {
+        eprintln!("skipped: no C compiler");
+        return;
+    };
+    let directory: std::path::PathBuf = common::scratch_directory("backend_c");
+
+    for (index, example) in common::examples().into_iter().enumerate() {
+        let c: std::path::PathBuf = directory.join(format!("{}.c", index));
+        let executable: std::path::PathBuf = directory.join(index.to_string());
+        emit(&example.path, "c", &c);
+        compile(
+            std::process::Command::new(&compiler)
+                .args(["-std=c99", "-Wall"])
+                .arg(&c)
+                .arg("-o")
+                .arg(&executable)
+                .arg("-lm"),
+        );
+
+        assert_eq!(
+            common::run_native(&executable, &example),
+            common::run_example(&example, &[]),
+            "{}",
+            example.name()
+        );
+    }
+}
+
+/// Integer programs the assembly backend takes, besides the examples it takes
+const INTEGER_PROGRAM: &str = "\
+fn fact(n) {
+    n < 2 ? 1 : n * fact(n - 1)
+}
+fn gcd(a, b) {
+    b == 0 ? a : gcd(b, a % b)
+}
+print(fact(10))
+print(gcd(1071, 462))
+print(-7 / 2, -7 % 2, 7 / -2)
+print(1 < 2, 3 >= 4, 2 == 2)
+print((1 << 10) | 5, 12 & 10, 12 ^ 10, -16 >> 2)
+let x = 5
+x > 3 ? x * 100 : 0
+";
+
+/// Examples in the integer subset the assembly backend takes
+const INTEGER_EXAMPLES: [&str; 5] = [
+    "continuation",
+    "functions",
+    "math",
+    "operators",
+    "variables",
+];
+
+#[test]
+fn asm_output_assembles_and_matches_ph_run() {
+    let Some(compiler) = find_compiler() else {
+        eprintln!("skipped: no C compiler to assemble with");
+        return;
+    };
+    let directory: std::path::PathBuf = common::scratch_directory("backend_asm");
+
+    let examples: Vec<common::Example> = integer_programs(&directory);
+    for (index, example) in examples.iter().enumerate() {
+        let assembly: std::path::PathBuf = directory.join(format!("{}.s", index));
+        let executable: std::path::PathBuf = directory.join(index.to_string());
+        emit(&example.path, "asm", &assembly);
+        compile(
+            std::process::Command::new(&compiler)
+                .arg(&assembly)
+                .arg("-o")
+                .arg(&executable),
+        );
+
+        assert_eq!(
+            common::run_native(&executable, example),
+            common::run_example(example, &[]),
+            "{}",
+            example.name()
+        );
+    }
+}
+
+#[test]
+fn asm_division_by_zero_is_the_hardware_trap() {
+    let Some(compiler) = find_compiler() else {
+        eprintln!("skipped: no C compiler to assemble with");
+        return;
+    };
+    let directory: std::path::PathBuf = common::scratch_directory("backend_asm_trap");
+    let source: std::path::PathBuf = directory.join("divide.ph");
+    let assembly: std::path::PathBuf = directory.join("divide.s");
+    let executable: std::path::PathBuf = directory.join("divide");
+    std::fs::write(&source, "let zero = 0\nprint(1 / zero)\n").unwrap();
+
+    emit(&source, "asm", &assembly);
+    compile(
+        std::process::Command::new(&compiler)
+            .arg(&assembly)
+            .arg("-o")
+            .arg(&executable),
+    );
+    let status: std::process::ExitStatus =
+        common::output(&mut std::process::Command::new(&executable), "").status;
+
+    assert_eq!(
+        std::os::unix::process::ExitStatusExt::signal(&status),
+        Some(libc::SIGFPE)
+    );
+}
+
+/// The integer examples the native backends take and `INTEGER_PROGRAM`, written into
+/// `directory`
+fn integer_programs(directory: &std::path::Path) -> Vec<common::Example> {
+    let mut examples: Vec<common::Example> = common::examples()
+        .into_iter()
+        .filter(|example: &common::Example| {
+            example
+                .path
+                .file_stem()
+                .is_some_and(|stem: &std::ffi::OsStr| {
+                    INTEGER_EXAMPLES.contains(&stem.to_string_lossy().as_ref())
+                })
+        })
+        .collect();
+    assert_eq!(examples.len(), INTEGER_EXAMPLES.len());
+
+    std::fs::write(directory.join("integers.ph"), INTEGER_PROGRAM).unwrap();
+    examples.push(common::Example {
+        path: directory.join("integers.ph"),
+        stdin: String::new(),
+        arguments: Vec::new(),
+        expected: String::new(),
+    });
+    examples
+}
+
+/// An if/else whose value is used after it, so the branches must merge with a phi
+const PHI_PROGRAM: &str = "fn pick(c) {\n    c > 0 ? 10 : 20\n}\nprint(pick(1) + pick(0))\n";
+
+const PHI_FUNCTION: &str = "\
+define internal i64 @f_pick(i64 %p0) {
+b0:
+  %l0 = alloca i64
+  store i64 %p0, ptr %l0
+  %t0 = load i64, ptr %l0
+  %t2 = icmp sgt i64 %t0, 0
+  br i1 %t2, label %b1, label %b2
+b1:
+  br label %b3
+b2:
+  br label %b3
+b3:
+  %t5 = phi i64 [ 10, %b1 ], [ 20, %b2 ]
+  ret i64 %t5
+}
+";
+
+#[test]
+fn llvm_ir_merges_branches_with_a_phi() {
+    let directory: std::path::PathBuf = common::scratch_directory("backend_llvm_phi");
+    std::fs::write(directory.join("phi.ph"), PHI_PROGRAM).unwrap();
+
+    let output: std::process::Output = common::output(
+        common::ph().current_dir(&directory).args([
+            "build",
+            "phi.ph",
+            "--emit",
+            "llvm-ir",
+            "--target",
+            "x86_64-linux",
+        ]),
+        "",
+    );
+    let ir: String = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert!(ir.starts_with(
+        "; Generated by `ph build --emit llvm-ir --target x86_64-linux` from phi.ph\n"
+    ));
+    assert!(ir.contains(PHI_FUNCTION), "{}", ir);
+}
+
+/// Flags `tool` needs to read the opaque pointers of the output, which LLVM 14 only reads when
+/// asked
+fn llvm_flags(tool: &std::path::Path) -> Vec<&'static str> {
+    let output: std::process::Output =
+        common::output(std::process::Command::new(tool).arg("--version"), "");
+    match String::from_utf8_lossy(&output.stdout).contains("LLVM version 14.") {
+        true => vec!["-opaque-pointers"],
+        false => Vec::new(),
+    }
+}
+
+#[test]
+fn llvm_ir_passes_llvm_as_and_matches_ph_run() {
+    let Some(llvm_as) = common::find_tool("llvm-as") else {
+        return;
+    };
+    let lli: Option<std::path::PathBuf> = common::find_tool("lli");
+    let directory: std::path::PathBuf = common::scratch_directory("backend_llvm");
+    std::fs::write(directory.join("phi.ph"), PHI_PROGRAM).unwrap();
+
+    let mut programs: Vec<common::Example> = integer_programs(&directory);
+    programs.push(common::Example {
+        path: directory.join("phi.ph"),
+        stdin: String::new(),
+        arguments: Vec::new(),
+        expected: String::new(),
+    });
+    for (index, program) in programs.iter().enumerate() {
+        let ir: std::path::PathBuf = directory.join(format!("{}.ll", index));
+        let bitcode: std::path::PathBuf = directory.join(format!("{}.bc", index));
+        emit(&program.path, "llvm-ir", &ir);
+        compile(
+            std::process::Command::new(&llvm_as)
+                .args(llvm_flags(&llvm_as))
+                .arg(&ir)
+                .arg("-o")
+                .arg(&bitcode),
+        );
+
+        if let Some(lli) = &lli {
+            let output: std::process::Output = common::output(
+                std::process::Command::new(lli)
+                    .args(llvm_flags(lli))
+                    .arg(&bitcode),
+                "",
+            );
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout),
+                common::run_example(program, &[]),
+                "{}",
+                program.name()
+            );
+        }
+    }
+}
+
+/// Read an unsigned LEB128 number at `position` in `bytes`, moving past it
+fn leb128(bytes: &[u8], position: &mut usize) -> u64 {
+    let mut value: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let byte: u8 = *bytes.get(*position).expect("LEB128 number past the end");
+        *position += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+    }
+    panic!("LEB128 number longer than 64 bits");
+}
+
+/// Fail unless `module` follows the structure of the binary format: the header, sections in
+/// order that each end where their size says, a body for every function and a `main` export
+fn validate_wasm(module: &[u8]) {
+    assert_eq!(&module[..8], b"\0asm\x01\0\0\0", "missing header");
+
+    let mut position: usize = 8;
+    let mut last_id: u8 = 0;
+    let mut functions: Option<u64> = None;
+    let mut bodies: Option<u64> = None;
+    let mut exports_main: bool = false;
+    while position < module.len() {
+        let id: u8 = module[position];
+        position += 1;
+        let size: usize = leb128(module, &mut position) as usize;
+        let end: usize = position + size;
+        assert!(end <= module.len(), "section {} ends past the module", id);
+
+        // Custom sections may go anywhere, the others once each in order of their id
+        if id != 0 {
+            assert!(id <= 12 && id > last_id, "section {} after {}", id, last_id);
+            last_id = id;
+        }
+
+        let mut cursor: usize = position;
+        match id {
+            3 => functions = Some(leb128(module, &mut cursor)),
+            7 => {
+                for _ in 0..leb128(module, &mut cursor) {
+                    let length: usize = leb128(module, &mut cursor) as usize;
+                    let name: &[u8] = &module[cursor..cursor + length];
+                    cursor += length;
+                    let kind: u8 = module[cursor];
+                    cursor += 1;
+                    leb128(module, &mut cursor);
+                    exports_main |= name == b"main" && kind == 0;
+                }
+                assert_eq!(cursor, end, "export section size");
+            }
+            10 => {
+                let count: u64 = leb128(module, &mut cursor);
+                for _ in 0..count {
+                    let length: usize = leb128(module, &mut cursor) as usize;
+                    assert_eq!(module[cursor + length - 1], 0x0b, "body without end");
+                    cursor += length;
+                }
+                assert_eq!(cursor, end, "code section size");
+                bodies = Some(count);
+            }
+            _ => {}
+        }
+        position = end;
+    }
+
+    assert_eq!(functions, bodies, "functions without a body");
+    assert!(exports_main, "no main export");
+}
+
+#[test]
+fn wasm_output_is_a_valid_module_that_matches_ph_run() {
+    let node: Option<std::path::PathBuf> = common::find_tool("node");
+    let directory: std::path::PathBuf = common::scratch_directory("backend_wasm");
+
+    for (index, program) in integer_programs(&directory).iter().enumerate() {
+        let module: std::path::PathBuf = directory.join(format!("{}.wasm", index));
+        emit(&program.path, "wasm", &module);
+        validate_wasm(&std::fs::read(&module).unwrap());
+
+        if let Some(node) = &node {
+            let harness: std::path::PathBuf = common::examples_directory().join("run-wasm.mjs");
+            let output: std::process::Output = common::output(
+                std::process::Command::new(node).arg(harness).arg(&module),
+                "",
+            );
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout),
+                common::run_example(program, &[]),
+                "{}",
+                program.name()
+            );
+        }
+    }
+}
+
+#[test]
+fn link_builds_an_executable_of_hello_arithmetic() {
+    if find_compiler().is_none() {
+        eprintln!("skipped: no C compiler");
+        return;
+    }
+    let directory: std::path::PathBuf = common::scratch_directory("backend_link");
+    std::fs::write(
+        directory.join("hello.ph"),
+        "print(1 + 2 * 3)\nprint(10 - 4)\n",
+    )
+    .unwrap();
+
+    for (artifact, intermediate) in [("c", "hello-c.c"), ("asm", "hello-asm.s")] {
+        let executable: String = format!("hello-{}", artifact);
+        let build: std::process::Output = common::output(
+            common::ph().current_dir(&directory).args([
+                "build",
+                "hello.ph",
+                "--emit",
+                artifact,
+                "--link",
+                "-o",
+                &executable,
+            ]),
+            "",
+        );
+        assert!(
+            build.status.success(),
+            "{}",
+            String::from_utf8_lossy(&build.stderr)
+        );
+        assert!(!directory.join(intermediate).exists());
+
+        let output: std::process::Output = common::output(
+            &mut std::process::Command::new(directory.join(&executable)),
+            "",
+        );
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "7\n6\n");
+    }
+
+    let build: std::process::Output = common::output(
+        common::ph().current_dir(&directory).args([
+            "build",
+            "hello.ph",
+            "--emit",
+            "c",
+            "--link",
+            "--keep-intermediates",
+            "-o",
+            "kept",
+        ]),
+        "",
+    );
+    assert!(build.status.success());
+    assert!(directory.join("kept.c").is_file());
+}
+
+#[test]
+fn link_without_a_compiler_says_so() {
+    let directory: std::path::PathBuf = common::scratch_directory("backend_link_missing");
+    std::fs::write(directory.join("hello.ph"), "print(1)\n").unwrap();
+
+    let output: std::process::Output = common::output(
+        common::ph()
+            .current_dir(&directory)
+            .env("CC", directory.join("missing-cc"))
+            .args(["build", "hello.ph", "--emit", "c", "--link"]),
+        "",
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--link needs a C compiler"));
+}
+
+/// Bodies of `f(a, b)`, what it is called with, and whether that overflows
+const OVERFLOW_CASES: [(&str, &str, bool); 8] = [
+    ("a + b", "9223372036854775807, 1", true),
+    ("a - b", "-9223372036854775808, 1", true),
+    ("a * b", "-9223372036854775808, -1", true),
+    ("a * b", "4294967296, 4294967296", true),
+    ("-a", "-9223372036854775808, 0", true),
+    ("a - b", "-1, 9223372036854775807", false),
+    ("a * b", "-4294967296, 2147483648", false),
+    ("-a", "9223372036854775807, 0", false),
+];
+
+/// Check that the program `run` builds from a source file in a directory prints what `ph run`
+/// does for every case of `OVERFLOW_CASES`, and fails where it does
+///
+/// The operation is in a function so it isn't computed while building, and `1` is printed
+/// before it to check that what was printed comes out before the program stops.
+fn assert_overflow_traps(
+    name: &str,
+    run: &dyn Fn(&std::path::Path, &std::path::Path) -> std::process::Output,
+) {
+    let directory: std::path::PathBuf = common::scratch_directory(name);
+
+    for (index, (body, arguments, overflows)) in OVERFLOW_CASES.iter().enumerate() {
+        let source: std::path::PathBuf = directory.join(format!("{}.ph", index));
+        std::fs::write(
+            &source,
+            format!(
+                "fn f(a, b) {{\n    {}\n}}\nprint(1)\nprint(f({}))\n",
+                body, arguments
+            ),
+        )
+        .unwrap();
+
+        let expected: std::process::Output =
+            common::output(common::ph().arg("run").arg(&source), "");
+        assert_eq!(expected.status.success(), !overflows, "{}", body);
+        if *overflows {
+            assert!(String::from_utf8_lossy(&expected.stderr).contains("with overflow"));
+        }
+
+        let output: std::process::Output = run(&source, &directory);
+        assert_eq!(
+            output.status.success(),
+            !overflows,
+            "{}: {}({})",
+            name,
+            body,
+            arguments
+        );
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&expected.stdout),
+            "{}: {}({})",
+            name,
+            body,
+            arguments
+        );
+    }
+}
+
+#[test]
+fn native_arithmetic_overflow_traps_like_ph_run() {
+    if let Some(compiler) = find_compiler() {
+        assert_overflow_traps(
+            "backend_asm_overflow",
+            &|source: &std::path::Path, directory: &std::path::Path| {
+                let executable: std::path::PathBuf = directory.join("program");
+                emit(source, "asm", &directory.join("program.s"));
+                compile(
+                    std::process::Command::new(&compiler)
+                        .arg(directory.join("program.s"))
+                        .arg("-o")
+                        .arg(&executable),
+                );
+                let output: std::process::Output =
+                    common::output(&mut std::process::Command::new(&executable), "");
+                if !output.status.success() {
+                    assert_eq!(
+                        std::os::unix::process::ExitStatusExt::signal(&output.status),
+                        Some(libc::SIGILL)
+                    );
+                }
+                output
+            },
+        );
+    }
+
+    if let (Some(llvm_as), Some(lli)) = (common::find_tool("llvm-as"), common::find_tool("lli")) {
+        assert_overflow_traps(
+            "backend_llvm_overflow",
+            &|source: &std::path::Path, directory: &std::path::Path| {
+                let bitcode: std::path::PathBuf = directory.join("program.bc");
+                emit(source, "llvm-ir", &directory.join("program.ll"));
+                compile(
+                    std::process::Command::new(&llvm_as)
+                        .args(llvm_flags(&llvm_as))
+                        .arg(directory.join("program.ll"))
+                        .arg("-o")
+                        .arg(&bitcode),
+                );
+                common::output(
+                    std::process::Command::new(&lli)
+                        .args(llvm_flags(&lli))
+                        .arg(&bitcode),
+                    "",
+                )
+            },
+        );
+    }
+
+    if let Some(node) = common::find_tool("node") {
+        assert_overflow_traps(
+            "backend_wasm_overflow",
+            &|source: &std::path::Path, directory: &std::path::Path| {
+                let module: std::path::PathBuf = directory.join("program.wasm");
+                emit(source, "wasm", &module);
+                validate_wasm(&std::fs::read(&module).unwrap());
+                common::output(
+                    std::process::Command::new(&node)
+                        .arg(common::examples_directory().join("run-wasm.mjs"))
+                        .arg(&module),
+                    "",
+                )
+            },
+        );
+    }
+}
@@ -0,0 +1,40 @@
+use phoenix_script::eval::Value;
+
+mod common;
+
+#[test]
+fn bitwise_operators_work_on_ints() {
+    assert_eq!(common::value("6 & 3"), Value::Int(2));
+    assert_eq!(common::value("1 << 4"), Value::Int(16));
+    assert_eq!(common::value("5 ^ 1"), Value::Int(4));
+    assert_eq!(common::value("6 | 3"), Value::Int(7));
+    assert_eq!(common::value("16 >> 2"), Value::Int(4));
+    // Shifting right keeps the sign
+    assert_eq!(common::value("-8 >> 1"), Value::Int(-4));
+
+    assert_eq!(common::ph_stdout(&["eval", "6 & 3"], ""), "2\n");
+}
+
+#[test]
+fn precedence_is_like_c() {
+    // `&` before `^` before `|`
+    assert_eq!(common::value("1 | 2 ^ 3 & 4"), Value::Int(3));
+    // Arithmetic before shifts and bitwise operators
+    assert_eq!(common::value("1 << 2 + 1"), Value::Int(8));
+    assert_eq!(common::value("1 + 2 & 3"), Value::Int(3));
+    // Comparisons before `&`, so this is `(1 < 2) & 3`
+    assert_eq!(
+        common::error("let x = 1\nx < 2 & 3"),
+        "cannot apply '&' to bool and int"
+    );
+}
+
+#[test]
+fn shift_out_of_range_is_an_error() {
+    for amount in ["-1", "64"] {
+        assert_eq!(
+            common::error(&format!("let n = {}\n1 << n", amount)),
+            format!("shift amount {} is out of range, expected 0 to 63", amount)
+        );
+    }
+}
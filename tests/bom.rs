@@ -0,0 +1,57 @@
+mod common;
+
+/// `ph` with `arguments` in a new directory named `name`, where `b.ph` holds `bytes`
+fn ph_on(
+    name: &str,
+    bytes: &[u8],
+    arguments: &[&str],
+) -> (std::path::PathBuf, std::process::Output) {
+    let directory: std::path::PathBuf = common::scratch_directory(name);
+    std::fs::write(directory.join("b.ph"), bytes).expect("Failed to write the program");
+
+    let output: std::process::Output =
+        common::output(common::ph().current_dir(&directory).args(arguments), "");
+    (directory, output)
+}
+
+#[test]
+fn leading_bom_is_skipped() {
+    let (directory, build): (std::path::PathBuf, std::process::Output) =
+        ph_on("bom_build", b"\xef\xbb\xbf1 + 2", &["build", "b.ph"]);
+    assert!(
+        build.status.success(),
+        "{}",
+        String::from_utf8_lossy(&build.stderr)
+    );
+
+    let run: std::process::Output = common::output(
+        common::ph().current_dir(&directory).args(["run", "b.phc"]),
+        "",
+    );
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "3\n");
+
+    // From stdin too
+    let output: std::process::Output =
+        common::output(common::ph().args(["check", "-"]), "\u{feff}1 + 2");
+    assert!(output.status.success());
+}
+
+#[test]
+fn columns_after_a_bom_start_at_the_first_character() {
+    let (_, output): (std::path::PathBuf, std::process::Output) =
+        ph_on("bom_columns", b"\xef\xbb\xbf1 + * 2", &["run", "b.ph"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "error: expected a number but found '*'\n --> b.ph:1:5\n  |\n1 | 1 + * 2\n  |     ^\n"
+    );
+}
+
+#[test]
+fn bom_anywhere_else_is_an_unknown_character() {
+    let (_, output): (std::path::PathBuf, std::process::Output) =
+        ph_on("bom_middle", b"1 + \xef\xbb\xbf2", &["run", "b.ph"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .starts_with("error: unknown character '\u{feff}'\n --> b.ph:1:5\n"));
+}
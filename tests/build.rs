@@ -0,0 +1,109 @@
+mod common;
+
+#[test]
+fn build_expr_writes_bytecode_and_runs_nothing() {
+    let directory: std::path::PathBuf = common::scratch_directory("build_expr");
+
+    let build: std::process::Output = common::output(
+        common::ph()
+            .current_dir(&directory)
+            .args(["build", "--expr", "print(1 + 2)\n4"]),
+        "",
+    );
+    assert!(build.status.success());
+    assert!(build.stdout.is_empty());
+
+    let run: std::process::Output = common::output(
+        common::ph()
+            .current_dir(&directory)
+            .args(["run", "expr.phc"]),
+        "",
+    );
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "3\n4\n");
+
+    let build: std::process::Output = common::output(
+        common::ph()
+            .current_dir(&directory)
+            .args(["build", "--expr", "5", "-o", "five.phc"]),
+        "",
+    );
+    assert!(build.status.success());
+    assert!(directory.join("five.phc").is_file());
+}
+
+#[test]
+fn directory_input_is_an_error() {
+    let directory: std::path::PathBuf = common::scratch_directory("build_directory_input");
+    std::fs::create_dir(directory.join("src")).expect("Failed to create the directory");
+
+    for command in ["build", "run", "check", "ast", "tokens", "fmt"] {
+        let output: std::process::Output = common::output(
+            common::ph().current_dir(&directory).args([command, "src"]),
+            "",
+        );
+        assert_eq!(output.status.code(), Some(1), "{}", command);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr),
+            "error: 'src' is a directory, expected a file\n",
+            "{}",
+            command
+        );
+    }
+}
+
+#[test]
+fn build_takes_a_file_or_an_expression() {
+    let directory: std::path::PathBuf = common::scratch_directory("build_file_or_expr");
+    std::fs::write(directory.join("f.ph"), "print(1)\n").expect("Failed to write the program");
+    let build = |arguments: &[&str]| -> std::process::Output {
+        common::output(
+            common::ph()
+                .current_dir(&directory)
+                .arg("build")
+                .args(arguments),
+            "",
+        )
+    };
+
+    assert!(build(&["f.ph"]).status.success());
+    assert!(directory.join("f.phc").is_file());
+    assert!(build(&["--expr", "2"]).status.success());
+    assert!(directory.join("expr.phc").is_file());
+
+    let both: std::process::Output = build(&["f.ph", "--expr", "2"]);
+    assert_eq!(both.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&both.stderr)
+        .starts_with("error: the argument '[file]' cannot be used with '--expr <expr>'\n"));
+
+    // Without either, and no project to build
+    let neither: std::process::Output = build(&[]);
+    assert_eq!(neither.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&neither.stderr).starts_with("error: no file given"));
+}
+
+#[test]
+fn stdout_has_nothing_but_the_artifact() {
+    let directory: std::path::PathBuf = common::scratch_directory("build_stdout");
+    std::fs::write(directory.join("f.ph"), "1 + 2\n").expect("Failed to write the program");
+    let build = |arguments: &[&str]| -> std::process::Output {
+        let output: std::process::Output =
+            common::output(common::ph().current_dir(&directory).args(arguments), "");
+        assert!(output.status.success());
+        output
+    };
+
+    let plain: std::process::Output = build(&["build", "f.ph"]);
+    assert!(plain.stdout.is_empty());
+    assert!(plain.stderr.is_empty());
+
+    // Progress goes to stderr
+    let verbose: std::process::Output = build(&["-v", "build", "f.ph"]);
+    assert!(verbose.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&verbose.stderr).starts_with("Building f.ph\n"));
+
+    let emitted: std::process::Output = build(&["-v", "build", "--emit", "ast", "f.ph"]);
+    assert_eq!(
+        String::from_utf8_lossy(&emitted.stdout),
+        "Binary +\n  Number 1\n  Number 2\n"
+    );
+}
@@ -0,0 +1,210 @@
+use phoenix_script::diagnostic::Diagnostic;
+use phoenix_script::eval::Value;
+use phoenix_script::run::eval_all;
+
+mod common;
+
+#[test]
+fn abs() {
+    assert_eq!(common::value("abs(-3)"), Value::Int(3));
+    assert_eq!(common::value("abs(-2.5)"), Value::Float(2.5));
+    assert_eq!(
+        common::error("abs(-9223372036854775807 - 1)"),
+        "attempt to take 'abs' of -9223372036854775808 with overflow"
+    );
+    assert_eq!(
+        common::error("abs(\"a\")"),
+        "'abs' expects numbers but got string"
+    );
+    assert_eq!(
+        common::error("abs()"),
+        "function 'abs' expects 1 argument but got 0"
+    );
+}
+
+#[test]
+fn min_and_max() {
+    assert_eq!(common::value("min(3, 1)"), Value::Int(1));
+    assert_eq!(common::value("max(3, 1)"), Value::Int(3));
+    assert_eq!(common::value("min(1, 2.5)"), Value::Float(1.0));
+    assert_eq!(
+        common::error("max(1, \"a\")"),
+        "'max' expects numbers but got string"
+    );
+    assert_eq!(
+        common::error("min(1)"),
+        "function 'min' expects 2 arguments but got 1"
+    );
+}
+
+#[test]
+fn pow() {
+    assert_eq!(common::value("pow(2, 10)"), Value::Int(1024));
+    assert_eq!(common::value("pow(0, 0)"), Value::Int(1));
+    assert_eq!(common::value("pow(2.0, 0.5)"), Value::Float(2f64.sqrt()));
+    assert_eq!(
+        common::error("pow(9223372036854775807, 2)"),
+        "attempt to raise 9223372036854775807 to the power 2 with overflow"
+    );
+    assert_eq!(
+        common::error("pow(2, -1)"),
+        "'pow' can't raise an int to the negative power -1, use a float base"
+    );
+    assert_eq!(
+        common::error("pow(2)"),
+        "function 'pow' expects 2 arguments but got 1"
+    );
+}
+
+#[test]
+fn pow_of_zero_and_one_with_huge_exponents() {
+    assert_eq!(common::value("pow(1, 9223372036854775807)"), Value::Int(1));
+    assert_eq!(common::value("pow(0, 9223372036854775807)"), Value::Int(0));
+    assert_eq!(
+        common::value("pow(-1, 9223372036854775807)"),
+        Value::Int(-1)
+    );
+    assert_eq!(common::value("pow(-1, 9223372036854775806)"), Value::Int(1));
+    assert_eq!(
+        common::error("pow(2, 4294967296)"),
+        "attempt to raise 2 to the power 4294967296 with overflow"
+    );
+}
+
+#[test]
+fn sqrt() {
+    assert_eq!(common::value("sqrt(16)"), Value::Float(4.0));
+    assert!(matches!(common::value("sqrt(-1)"), Value::Float(value) if value.is_nan()));
+    assert_eq!(
+        common::error("sqrt(\"x\")"),
+        "'sqrt' expects numbers but got string"
+    );
+}
+
+#[test]
+fn floor_ceil_and_round() {
+    assert_eq!(common::value("floor(2.7)"), Value::Int(2));
+    assert_eq!(common::value("floor(-2.5)"), Value::Int(-3));
+    assert_eq!(common::value("ceil(2.1)"), Value::Int(3));
+    assert_eq!(common::value("ceil(-2.5)"), Value::Int(-2));
+    assert_eq!(common::value("round(2.4)"), Value::Int(2));
+    // Halves go away from zero
+    assert_eq!(common::value("round(2.5)"), Value::Int(3));
+    assert_eq!(common::value("round(-2.5)"), Value::Int(-3));
+    // An int is already whole
+    assert_eq!(common::value("floor(3)"), Value::Int(3));
+    assert_eq!(
+        common::error("floor(1e300)"),
+        "'floor' of 1e300 doesn't fit in an int"
+    );
+}
+
+#[test]
+fn errors_point_at_the_call() {
+    let results: Vec<Result<Option<Value>, Diagnostic>> = eval_all("1 + abs(\"a\")");
+    let diagnostic: &Diagnostic = results[0].as_ref().expect_err("abs takes numbers");
+    assert_eq!(diagnostic.span.start, 4);
+}
+
+#[test]
+fn time_builtins() {
+    let now: isize = match common::value("now_ms()") {
+        Value::Int(now) => now,
+        value => panic!("expected an int, found {:?}", value),
+    };
+    // Some time after 2023
+    assert!(now > 1_700_000_000_000, "{}", now);
+
+    assert_eq!(
+        common::value("let start = now_ms()\nsleep_ms(30)\n30 <= elapsed_ms(start) < 10000"),
+        Value::Bool(true)
+    );
+    assert_eq!(common::value("sleep_ms(0)"), Value::Nil);
+
+    assert_eq!(
+        common::error("sleep_ms(-1)"),
+        "'sleep_ms' can't sleep for a negative time, got -1"
+    );
+    assert_eq!(
+        common::error("elapsed_ms(\"a\")"),
+        "'elapsed_ms' expects an int but got string"
+    );
+}
+
+#[test]
+fn env_builtins() {
+    // In `ph` so the variables of the tests don't mix
+    let eval_env = |source: &str| -> std::process::Output {
+        common::output(
+            common::ph()
+                .args(["eval", source])
+                .env("PH_SET", "abc")
+                .env_remove("PH_UNSET"),
+            "",
+        )
+    };
+
+    assert_eq!(eval_env("env(\"PH_SET\")").stdout, b"abc\n");
+    // Nil, which prints nothing
+    assert_eq!(eval_env("env(\"PH_UNSET\")").stdout, b"");
+    let unset: std::process::Output = common::output(
+        common::ph()
+            .args(["eval", "--json-result", "env(\"PH_UNSET\")"])
+            .env_remove("PH_UNSET"),
+        "",
+    );
+    assert_eq!(unset.stdout, b"{\"ok\": true, \"value\": null}\n");
+    assert_eq!(
+        eval_env("set_env(\"PH_UNSET\", \"new\")\nenv(\"PH_UNSET\")").stdout,
+        b"new\n"
+    );
+
+    let invalid: std::process::Output = eval_env("set_env(\"A=B\", \"1\")");
+    assert_eq!(invalid.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&invalid.stderr)
+        .starts_with("runtime error: invalid environment variable name \"A=B\"\n"));
+
+    assert_eq!(common::error("env(1)"), "'env' expects strings but got int");
+}
+
+#[test]
+fn file_builtins() {
+    let directory: std::path::PathBuf = common::scratch_directory("builtins_files");
+    let eval_in = |source: &str| -> std::process::Output {
+        common::output(
+            common::ph().current_dir(&directory).args(["eval", source]),
+            "",
+        )
+    };
+
+    let round_trip: std::process::Output = eval_in(
+        "write_file(\"a.txt\", \"one\\n\")\nappend_file(\"a.txt\", \"two\\n\")\nprint(file_exists(\"a.txt\"))\nprint(file_exists(\"missing.txt\"))\nread_file(\"a.txt\")",
+    );
+    assert!(round_trip.status.success());
+    assert_eq!(round_trip.stdout, b"true\nfalse\none\ntwo\n\n");
+    assert_eq!(
+        std::fs::read_to_string(directory.join("a.txt")).expect("The file wasn't written"),
+        "one\ntwo\n"
+    );
+    // Writing replaces what was there
+    assert_eq!(
+        eval_in("write_file(\"a.txt\", \"three\")\nread_file(\"a.txt\")").stdout,
+        b"three\n"
+    );
+
+    for (source, message) in [
+        (
+            "read_file(\"missing.txt\")",
+            "failed to read 'missing.txt': No such file or directory (os error 2)",
+        ),
+        (
+            "write_file(\"missing/a.txt\", \"a\")",
+            "failed to write 'missing/a.txt': No such file or directory (os error 2)",
+        ),
+    ] {
+        let output: std::process::Output = eval_in(source);
+        assert_eq!(output.status.code(), Some(1));
+        assert!(String::from_utf8_lossy(&output.stderr)
+            .starts_with(&format!("runtime error: {}\n", message)));
+    }
+}
@@ -0,0 +1,74 @@
+use phoenix_script::bytecode::{compile, Instruction, Program};
+use phoenix_script::eval::Value;
+use phoenix_script::interner::Interner;
+use phoenix_script::parser::Parser;
+
+/// `source` compiled to bytecode
+fn program(source: &str) -> Program {
+    compile(&Parser::new(source.to_string()).parse_statements().unwrap()).unwrap()
+}
+
+#[test]
+fn a_repeated_string_is_one_pool_entry() {
+    let program: Program = program(&"print(\"hello\")\n".repeat(50));
+    let strings: Vec<&Value> = program
+        .constants
+        .iter()
+        .filter(|constant: &&Value| matches!(constant, Value::String(_)))
+        .collect();
+
+    assert_eq!(strings, [&Value::String("hello".to_string())]);
+    assert_eq!(
+        program
+            .code
+            .iter()
+            .filter(|instruction: &&Instruction| **instruction == Instruction::Push(0))
+            .count(),
+        50
+    );
+}
+
+#[test]
+fn variables_are_accessed_through_indexed_slots() {
+    let program: Program =
+        program("let x = 1\nfn f(a) {\n    let b = a + x\n    b\n}\nx = f(2)\nx\n");
+
+    // Globals by the symbol of their name, locals by their slot in the frame
+    assert_eq!(program.names, ["x"]);
+    assert_eq!(
+        program.code,
+        [
+            Instruction::Push(0),
+            Instruction::DefineGlobal(0),
+            Instruction::Push(1),
+            Instruction::Call {
+                function: 0,
+                site: 0
+            },
+            Instruction::StoreGlobal(0),
+            Instruction::LoadGlobal(0),
+            Instruction::Yield,
+            Instruction::Return,
+            Instruction::Load(0),
+            Instruction::LoadGlobal(0),
+            Instruction::Add,
+            Instruction::Store(1),
+            Instruction::Load(1),
+            Instruction::Return,
+        ]
+    );
+}
+
+#[test]
+fn interned_names_get_one_id_each() {
+    let mut interner: Interner = Interner::new();
+    let x: u32 = interner.intern("x");
+    let y: u32 = interner.intern("y");
+
+    assert_eq!((x, y), (0, 1));
+    assert_eq!(interner.intern("x"), x);
+    assert_eq!(interner.get("y"), Some(y));
+    assert_eq!(interner.get("z"), None);
+    assert_eq!(interner.resolve(y), "y");
+    assert_eq!(interner.len(), 2);
+}
@@ -0,0 +1,60 @@
+use phoenix_script::eval::Value;
+
+mod common;
+
+/// Prints its argument before giving it back, to show when and how often it runs
+const NOISY: &str = "fn f(x) {\n    print(x)\n    x\n}\n";
+
+#[test]
+fn chain_is_true_when_every_comparison_is() {
+    assert_eq!(common::value("let x = 5\n1 < x < 10"), Value::Bool(true));
+    assert_eq!(
+        common::value("let x = 5\n1 < x <= 5 >= 2"),
+        Value::Bool(true)
+    );
+}
+
+#[test]
+fn chain_is_false_when_any_comparison_is() {
+    assert_eq!(common::value("let x = 15\n1 < x < 10"), Value::Bool(false));
+    assert_eq!(common::value("let x = -5\n1 < x < 10"), Value::Bool(false));
+    assert_eq!(
+        common::value("let x = 5\n1 < x <= 5 > 5"),
+        Value::Bool(false)
+    );
+}
+
+#[test]
+fn operands_run_once_and_stop_at_the_first_false_comparison() {
+    for level in ["0", "1", "2"] {
+        let satisfied: String = format!("{}print(f(1) < f(3) < f(5))", NOISY);
+        assert_eq!(
+            common::ph_stdout(&["eval", "--opt-level", level, &satisfied], ""),
+            "1\n3\n5\ntrue\n",
+            "level {}",
+            level
+        );
+
+        let unsatisfied: String = format!("{}print(f(9) < f(5) < f(3))", NOISY);
+        assert_eq!(
+            common::ph_stdout(&["eval", "--opt-level", level, &unsatisfied], ""),
+            "9\n5\nfalse\n",
+            "level {}",
+            level
+        );
+    }
+}
+
+#[test]
+fn equality_does_not_chain() {
+    // `(1 < 2) == (0 < 1)`, not `1 < 2 && 2 == 0 && 0 < 1`
+    assert_eq!(common::value("1 < 2 == 0 < 1"), Value::Bool(true));
+
+    let directory: std::path::PathBuf = common::scratch_directory("chain_equality");
+    let file: std::path::PathBuf = directory.join("chain.ph");
+    std::fs::write(&file, "a < b == c\na < b <= c").expect("Failed to write the program");
+    assert_eq!(
+        common::ph_stdout(&["ast", &file.display().to_string()], ""),
+        "Binary ==\n  Binary <\n    Variable a\n    Variable b\n  Variable c\nChain < <=\n  Variable a\n  Variable b\n  Variable c\n"
+    );
+}
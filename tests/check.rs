@@ -0,0 +1,148 @@
+mod common;
+
+/// `ph check` with `arguments` on `source` read from stdin
+fn check(arguments: &[&str], source: &str) -> std::process::Output {
+    common::output(common::ph().arg("check").args(arguments).arg("-"), source)
+}
+
+#[test]
+fn builtin_arguments_of_the_wrong_type_fail_the_check() {
+    for (source, message) in [
+        ("sqrt(\"a\")", "'sqrt' expects numbers but got string"),
+        ("max(1, \"a\")", "'max' expects numbers but got string"),
+        ("len(1)", "'len' expects a string or an array but got int"),
+        (
+            "substr(\"a\", 0, \"b\")",
+            "'substr' expects a string and two ints but got string and int and string",
+        ),
+        ("assert(1)", "'assert' expects a bool but got int"),
+        ("sleep_ms(1.5)", "'sleep_ms' expects an int but got float"),
+        (
+            "write_file(\"a\", 2)",
+            "'write_file' expects strings but got string and int",
+        ),
+    ] {
+        let checked: std::process::Output = check(&[], source);
+        assert_eq!(checked.status.code(), Some(1), "{}", source);
+        assert!(
+            String::from_utf8_lossy(&checked.stderr).contains(&format!("error: {}", message)),
+            "{}",
+            source
+        );
+
+        // Same words as when it runs
+        assert!(common::error(source).contains(message), "{}", source);
+    }
+}
+
+#[test]
+fn builtin_arguments_of_the_right_or_unknown_type_pass_the_check() {
+    for source in [
+        "sqrt(2)",
+        "max(1, 2.5)",
+        "len(\"ab\")",
+        "substr(\"abc\", 1, 1)",
+        "fn f(x) { sqrt(x) }\nf(4)",
+        "let s = input()\ntrim(s)",
+    ] {
+        let checked: std::process::Output = check(&[], source);
+        assert!(
+            checked.status.success(),
+            "{}: {}",
+            source,
+            String::from_utf8_lossy(&checked.stderr)
+        );
+    }
+}
+
+#[test]
+fn syntax_check_skips_what_the_full_check_finds() {
+    for source in ["sqrt(\"a\")", "1 + \"a\"", "undefined_name"] {
+        assert_eq!(check(&[], source).status.code(), Some(1), "{}", source);
+
+        let syntax: std::process::Output = check(&["--syntax"], source);
+        assert!(syntax.status.success(), "{}", source);
+        assert!(syntax.stderr.is_empty(), "{}", source);
+    }
+
+    // Both find syntax errors
+    for arguments in [&[][..], &["--syntax"]] {
+        let checked: std::process::Output = check(arguments, "1 + * 2");
+        assert_eq!(checked.status.code(), Some(1));
+        assert!(String::from_utf8_lossy(&checked.stderr).contains("error: "));
+    }
+}
+
+#[test]
+fn check_writes_nothing_and_fails_where_build_does() {
+    let directory: std::path::PathBuf = common::scratch_directory("check_like_build");
+
+    for (index, source) in [
+        "print(1 + 2)\n",
+        "1 + * 2\n",
+        "sqrt(\"a\")\n",
+        "undefined_name\n",
+        "1 / 0\n",
+        "write_file(\"written.txt\", \"x\")\n",
+    ]
+    .iter()
+    .enumerate()
+    {
+        let file: String = format!("{}.ph", index);
+        std::fs::write(directory.join(&file), source).expect("Failed to write the program");
+
+        let checked: std::process::Output = common::output(
+            common::ph().current_dir(&directory).args(["check", &file]),
+            "",
+        );
+        // Nothing runs or is written
+        assert!(checked.stdout.is_empty(), "{}", source);
+        assert!(!directory.join(format!("{}.phc", index)).exists());
+        assert!(!directory.join("written.txt").exists());
+
+        let built: std::process::Output = common::output(
+            common::ph().current_dir(&directory).args(["build", &file]),
+            "",
+        );
+        assert_eq!(checked.status.code(), built.status.code(), "{}", source);
+        assert_eq!(checked.stderr, built.stderr, "{}", source);
+    }
+}
+
+#[test]
+fn unused_binding_is_a_warning_with_its_name_and_position() {
+    let checked: std::process::Output = check(&[], "let x = 5\nlet y = 1\nprint(y)\n");
+    // A warning alone passes
+    assert!(checked.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&checked.stderr),
+        "warning: variable 'x' is never used\n --> <stdin>:1:5\n  |\n1 | let x = 5\n  |     ^\n"
+    );
+}
+
+#[test]
+fn used_binding_has_no_warning() {
+    for source in [
+        "let x = 5\nprint(x)\n",
+        "let x = 5\nlet x = x + 1\nprint(x)\n",
+        "let x = 5\nfn f() { x }\nprint(f())\n",
+        "let _x = 5\n",
+    ] {
+        let checked: std::process::Output = check(&[], source);
+        assert!(checked.status.success(), "{}", source);
+        assert!(checked.stderr.is_empty(), "{}", source);
+    }
+}
+
+#[test]
+fn shadowed_binding_that_was_never_read_warns() {
+    let checked: std::process::Output = check(&[], "let x = 5\nlet x = 6\nprint(x)\n");
+    assert!(checked.status.success());
+    let stderr: String = String::from_utf8_lossy(&checked.stderr).to_string();
+    assert_eq!(stderr.matches("warning: ").count(), 1);
+    assert!(stderr.contains(" --> <stdin>:1:5\n"));
+
+    let inner: std::process::Output = check(&[], "fn f(a) {\n    let b = 1\n    a\n}\nf(1)\n");
+    assert!(String::from_utf8_lossy(&inner.stderr)
+        .starts_with("warning: variable 'b' is never used\n --> <stdin>:2:9\n"));
+}
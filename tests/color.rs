@@ -0,0 +1,83 @@
+use phoenix_script::tokens::format_tokens;
+
+mod common;
+
+/// Stdout of `ph tokens` with `options` on a small program in a new directory named `name`,
+/// run on a terminal through `script`, with `no_color_variable` as `NO_COLOR` when given
+fn tokens_on_a_terminal(
+    script: &std::path::Path,
+    name: &str,
+    options: &str,
+    no_color_variable: Option<&str>,
+) -> String {
+    let directory: std::path::PathBuf = common::scratch_directory(name);
+    std::fs::write(directory.join("t.ph"), "1 + (2)\n").expect("Failed to write the program");
+
+    let mut command: std::process::Command = std::process::Command::new(script);
+    command.current_dir(&directory).args([
+        "-qec",
+        &format!(
+            "{} tokens {} t.ph",
+            env!("CARGO_BIN_EXE_phoenix-script"),
+            options
+        ),
+        "/dev/null",
+    ]);
+    command.env_remove("NO_COLOR");
+    if let Some(value) = no_color_variable {
+        command.env("NO_COLOR", value);
+    }
+
+    let output: std::process::Output = common::output(&mut command, "");
+    assert!(output.status.success());
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn token_types_are_colored_only_when_asked() {
+    let tokens: Vec<phoenix_script::lexer::SyntaxToken> =
+        phoenix_script::parser::collect_tokens("1 + (2)".to_string());
+
+    let colored: String = format_tokens(&tokens, "1 + (2)", true);
+    assert!(colored.contains("\x1b[32mNumber          \x1b[0m \"1\"\n"));
+    assert!(colored.contains("\x1b[36mPlus            \x1b[0m \"+\"\n"));
+
+    let plain: String = format_tokens(&tokens, "1 + (2)", false);
+    assert!(!plain.contains('\x1b'));
+    assert!(plain.starts_with("1:1      Number           \"1\"\n"));
+}
+
+#[test]
+fn no_color_removes_the_escape_codes_on_a_terminal() {
+    let Some(script) = common::find_tool("script") else {
+        return;
+    };
+
+    assert!(tokens_on_a_terminal(&script, "color_terminal", "", None).contains("\x1b[32mNumber"));
+    // An empty NO_COLOR doesn't count
+    assert!(tokens_on_a_terminal(&script, "color_empty_variable", "", Some("")).contains('\x1b'));
+
+    for (name, options, no_color_variable) in [
+        ("color_option", "--no-color", None),
+        ("color_variable", "", Some("1")),
+    ] {
+        let output: String = tokens_on_a_terminal(&script, name, options, no_color_variable);
+        assert!(!output.contains('\x1b'), "{:?}", output);
+        assert!(output.starts_with("1:1      Number           \"1\""));
+    }
+}
+
+#[test]
+fn output_that_isnt_a_terminal_is_never_colored() {
+    let directory: std::path::PathBuf = common::scratch_directory("color_pipe");
+    std::fs::write(directory.join("t.ph"), "1 + (2)\n").expect("Failed to write the program");
+
+    let output: std::process::Output = common::output(
+        common::ph()
+            .current_dir(&directory)
+            .env_remove("NO_COLOR")
+            .args(["tokens", "t.ph"]),
+        "",
+    );
+    assert!(!output.stdout.contains(&0x1b));
+}
@@ -0,0 +1,178 @@
+// Each test crate uses a different part of this module
+#![allow(dead_code)]
+
+/// An example program with what it is given and what it must print, from the files next to
+/// it: `name.out` is the expected stdout, `name.in` the stdin and `name.args` the arguments
+#[derive(Clone, Debug)]
+pub struct Example {
+    pub path: std::path::PathBuf,
+    pub stdin: String,
+    pub arguments: Vec<String>,
+    pub expected: String,
+}
+
+impl Example {
+    pub fn name(&self) -> String {
+        self.path.display().to_string()
+    }
+}
+
+/// Directory the example programs are in
+pub fn examples_directory() -> std::path::PathBuf {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("examples")
+}
+
+/// Every `.ph` file under `examples/`, sorted so failures are reported in the same order each
+/// run
+pub fn example_sources() -> Vec<std::path::PathBuf> {
+    let mut sources: Vec<std::path::PathBuf> = Vec::new();
+    let mut directories: Vec<std::path::PathBuf> = vec![examples_directory()];
+
+    while let Some(directory) = directories.pop() {
+        for entry in std::fs::read_dir(&directory).expect("Failed to read the examples") {
+            let path: std::path::PathBuf = entry.expect("Failed to read the examples").path();
+            if path.is_dir() {
+                directories.push(path);
+            } else if path.extension() == Some(std::ffi::OsStr::new("ph")) {
+                sources.push(path);
+            }
+        }
+    }
+
+    sources.sort();
+    sources
+}
+
+/// Every `.ph` file under `examples/` with a `.out` file
+pub fn examples() -> Vec<Example> {
+    let examples: Vec<Example> = example_sources()
+        .into_iter()
+        .filter_map(|path: std::path::PathBuf| {
+            let expected: String = std::fs::read_to_string(path.with_extension("out")).ok()?;
+            Some(Example {
+                stdin: std::fs::read_to_string(path.with_extension("in")).unwrap_or_default(),
+                arguments: std::fs::read_to_string(path.with_extension("args"))
+                    .unwrap_or_default()
+                    .split_whitespace()
+                    .map(str::to_string)
+                    .collect(),
+                expected,
+                path,
+            })
+        })
+        .collect();
+
+    assert!(!examples.is_empty(), "no examples with a .out file");
+    examples
+}
+
+/// The `ph` binary built for the tests
+pub fn ph() -> std::process::Command {
+    std::process::Command::new(env!("CARGO_BIN_EXE_phoenix-script"))
+}
+
+/// Run `command` with `stdin` and wait for it
+pub fn output(command: &mut std::process::Command, stdin: &str) -> std::process::Output {
+    let mut child: std::process::Child = command
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to start ph");
+
+    let mut input: std::process::ChildStdin = child.stdin.take().expect("stdin is piped");
+    std::io::Write::write_all(&mut input, stdin.as_bytes()).expect("Failed to write stdin");
+    drop(input);
+
+    child.wait_with_output().expect("Failed to wait for ph")
+}
+
+/// Run `ph` with `arguments` and `stdin`, and return its stdout, failing the test with its
+/// stderr when it doesn't succeed
+pub fn ph_stdout(arguments: &[&str], stdin: &str) -> String {
+    let output: std::process::Output = output(ph().args(arguments), stdin);
+    assert!(
+        output.status.success(),
+        "ph {} failed with {}:\n{}",
+        arguments.join(" "),
+        output.status,
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8(output.stdout).expect("ph printed invalid UTF-8")
+}
+
+/// Stdout of `ph run` on `example` with `options` before the file
+pub fn run_example(example: &Example, options: &[&str]) -> String {
+    let path: String = example.path.display().to_string();
+    let mut arguments: Vec<&str> = vec!["run"];
+    arguments.extend_from_slice(options);
+    arguments.push(&path);
+    arguments.extend(example.arguments.iter().map(String::as_str));
+
+    ph_stdout(&arguments, &example.stdin)
+}
+
+/// A new empty directory for one test's files, removed at the start of the next run
+pub fn scratch_directory(name: &str) -> std::path::PathBuf {
+    let directory: std::path::PathBuf =
+        std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).expect("Failed to create a scratch directory");
+    directory
+}
+
+/// `name` looked up in `PATH`, tests that need a tool that isn't installed are skipped
+pub fn find_tool(name: &str) -> Option<std::path::PathBuf> {
+    let found: Option<std::path::PathBuf> = std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|directory: std::path::PathBuf| directory.join(name))
+        .find(|path: &std::path::PathBuf| path.is_file());
+    if found.is_none() {
+        eprintln!("skipped: {} is not installed", name);
+    }
+    found
+}
+
+/// Run the executable `program` on `example`'s arguments and stdin and return its stdout
+pub fn run_native(program: &std::path::Path, example: &Example) -> String {
+    let output: std::process::Output = output(
+        std::process::Command::new(program).args(&example.arguments),
+        &example.stdin,
+    );
+    assert!(
+        output.status.success(),
+        "{} failed with {}:\n{}",
+        example.name(),
+        output.status,
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8(output.stdout).expect("The program printed invalid UTF-8")
+}
+
+/// Result of the last statement of `source` run with `eval_all`, which must give a value or
+/// fail
+pub fn eval(
+    source: &str,
+) -> Result<phoenix_script::eval::Value, phoenix_script::diagnostic::Diagnostic> {
+    match phoenix_script::run::eval_all(source).pop() {
+        Some(Ok(Some(value))) => Ok(value),
+        Some(Err(diagnostic)) => Err(diagnostic),
+        result => panic!("expected a value from {:?}, found {:?}", source, result),
+    }
+}
+
+/// Value of the last statement of `source`
+pub fn value(source: &str) -> phoenix_script::eval::Value {
+    eval(source).unwrap_or_else(|diagnostic: phoenix_script::diagnostic::Diagnostic| {
+        panic!("expected a value from {:?}, found {:?}", source, diagnostic)
+    })
+}
+
+/// Message of the error the last statement of `source` fails with
+pub fn error(source: &str) -> String {
+    match eval(source) {
+        Err(diagnostic) => diagnostic.message,
+        Ok(value) => panic!("expected an error from {:?}, found {:?}", source, value),
+    }
+}
@@ -0,0 +1,73 @@
+mod common;
+
+#[test]
+fn mistakes_in_dead_code_are_found_before_it_is_removed() {
+    let source: &str = "let x = 1 > 2 ? \"a\" - 1 : 5\nx\n";
+
+    // Without optimizations the branch just never runs
+    assert_eq!(common::ph_stdout(&["run", "--expr", source], ""), "5\n");
+
+    for options in [["run", "-O"], ["build", "-O"]] {
+        let output: std::process::Output =
+            common::output(common::ph().args(options).args(["--expr", source]), "");
+        let stderr: String = String::from_utf8_lossy(&output.stderr).to_string();
+
+        assert_eq!(output.status.code(), Some(1), "{:?}", options);
+        assert!(output.stdout.is_empty(), "{:?}", options);
+        assert!(
+            stderr.starts_with("error: cannot apply '-' to string and int"),
+            "{:?}: {}",
+            options,
+            stderr
+        );
+    }
+}
+
+#[test]
+fn run_shows_the_warnings_of_dead_code_elimination() {
+    let output: std::process::Output = common::output(
+        common::ph().args(["run", "-O", "--expr", "let x = 1 > 2 ? 3 : 5\nx\n"]),
+        "",
+    );
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "5\n");
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .starts_with("warning: the condition is always false, the '?' branch never runs"));
+}
+
+/// Syntax tree `build -O --emit ast` prints for `source`
+fn optimized_ast(source: &str) -> String {
+    common::ph_stdout(&["build", "-O", "--emit", "ast", "--expr", source], "")
+}
+
+#[test]
+fn unused_literals_in_function_bodies_are_removed() {
+    assert_eq!(
+        optimized_ast("fn f() {\n1\n\"s\"\n[1, 2.5]\nprint(2)\n1 < 2 ? 3 : 4\n5\n}\nf()"),
+        "Function f()\n  Call print\n    Number 2\n  Number 5\nCall f\n"
+    );
+
+    // The last one is the return value, and anything that could fail stays
+    assert_eq!(
+        optimized_ast("fn f(x) {\nx\n\"${HOME}\"\n[x]\n1\n}"),
+        "Function f(x)\n  Variable x\n  String \"${HOME}\"\n  Array\n    Variable x\n  Number 1\n"
+    );
+}
+
+#[test]
+fn the_branch_that_runs_replaces_the_conditional() {
+    assert_eq!(
+        optimized_ast("let x = 1 < 2 ? (2 > 3 ? \"a\" : \"b\") : \"c\""),
+        "Let x\n  String \"b\"\n"
+    );
+}
+
+#[test]
+fn top_level_literals_are_kept_for_print_each() {
+    assert_eq!(optimized_ast("1\n2\n3"), "Number 1\nNumber 2\nNumber 3\n");
+    assert_eq!(
+        common::ph_stdout(&["run", "-O", "--print-each", "--expr", "1\n\"a\"\n3"], ""),
+        "1\na\n3\n"
+    );
+}
@@ -0,0 +1,58 @@
+mod common;
+
+/// Run every example with each set of `options` and report each one whose stdout differs
+/// from the stdout of the first
+fn compare(options: &[&[&str]]) {
+    let mut failures: Vec<String> = Vec::new();
+    for example in common::examples() {
+        let expected: String = common::run_example(&example, options[0]);
+        for other in &options[1..] {
+            let stdout: String = common::run_example(&example, other);
+            if stdout != expected {
+                failures.push(format!(
+                    "{}: {:?} printed\n{}\n{:?} printed\n{}",
+                    example.name(),
+                    options[0],
+                    expected,
+                    other,
+                    stdout
+                ));
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn tree_walker_and_vm_agree_on_every_example() {
+    compare(&[&["--backend", "tree"], &["--backend", "vm"]]);
+}
+
+#[test]
+fn peephole_optimized_bytecode_agrees_with_unoptimized() {
+    compare(&[&["--opt-level", "0"], &["--opt-level", "1"]]);
+}
+
+#[test]
+fn every_opt_level_agrees_with_unoptimized() {
+    compare(&[
+        &["--opt-level", "0"],
+        &["--opt-level", "1"],
+        &["--opt-level", "2"],
+        &["-O"],
+    ]);
+}
+
+#[test]
+fn an_opt_level_past_two_is_a_usage_error() {
+    let math: String = common::examples_directory()
+        .join("math.ph")
+        .display()
+        .to_string();
+    let output: std::process::Output =
+        common::output(common::ph().args(["run", "--opt-level", "3", &math]), "");
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--opt-level"));
+}
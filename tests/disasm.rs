@@ -0,0 +1,59 @@
+mod common;
+
+/// Counts down by calling itself, the language has no loop statement
+const PROGRAM: &str = "fn count(n) {\n    n > 0 ? count(n - 1) + 1 : 0\n}\nprint(count(3))\n";
+
+const LISTING: &str = "\
+; instructions: 18, constants: 3, functions: 1
+main:
+  0000  push           3
+       ; 4 | print(count(3))
+  0001  call           count/1
+  0002  call_builtin   print/1
+  0003  yield
+  0004  return
+count/1:
+       ; 2 | n > 0 ? count(n - 1) + 1 : 0
+  0005  load           0
+  0006  push           0
+  0007  operator       >
+  0008  jump_if_false  L0
+  0009  load           0
+  0010  push           1
+  0011  sub
+  0012  call           count/1
+  0013  push           1
+  0014  add
+  0015  jump           L1
+L0:
+  0016  push           0
+L1:
+  0017  return
+";
+
+#[test]
+fn disassembly_of_a_recursive_function() {
+    let directory: std::path::PathBuf = common::scratch_directory("disasm_snapshot");
+    let source: std::path::PathBuf = directory.join("count.ph");
+    let compiled: std::path::PathBuf = directory.join("count.phc");
+    std::fs::write(&source, PROGRAM).expect("Failed to write the program");
+
+    common::ph_stdout(
+        &[
+            "build",
+            &source.display().to_string(),
+            "-o",
+            &compiled.display().to_string(),
+        ],
+        "",
+    );
+
+    assert_eq!(
+        common::ph_stdout(&["disasm", &compiled.display().to_string()], ""),
+        LISTING
+    );
+    assert_eq!(
+        common::ph_stdout(&["run", &compiled.display().to_string()], ""),
+        "3\n"
+    );
+}
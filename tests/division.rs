@@ -0,0 +1,55 @@
+mod common;
+
+/// `ph eval` on `source` that must fail, with its stdout and stderr
+fn eval_failure(source: &str) -> (String, String) {
+    let output: std::process::Output = common::output(common::ph().args(["eval", source]), "");
+    assert_eq!(output.status.code(), Some(1), "{}", source);
+    (
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+#[test]
+fn literal_divisor_of_zero_is_found_before_running() {
+    let (stdout, stderr): (String, String) = eval_failure("print(1)\n1 / 0");
+    assert!(stdout.is_empty());
+    assert_eq!(
+        stderr,
+        "error: division by zero\n --> <eval>:2:3\n  |\n2 | 1 / 0\n  |   ^\n"
+    );
+}
+
+#[test]
+fn folded_divisor_of_zero_is_found_before_running() {
+    let (stdout, stderr): (String, String) = eval_failure("print(1)\n1 % (2 - 2)");
+    assert!(stdout.is_empty());
+    assert!(
+        stderr.starts_with("error: division by zero\n --> <eval>:2:3\n"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn variable_divisor_of_zero_fails_when_it_runs() {
+    for operator in ["/", "%"] {
+        let (stdout, stderr): (String, String) =
+            eval_failure(&format!("let d = 0\nprint(1)\n10 {} d", operator));
+        // Everything before the division ran
+        assert_eq!(stdout, "1\n");
+        assert_eq!(
+            stderr,
+            format!(
+                "runtime error: division by zero\n --> <eval>:3:4\n  |\n3 | 10 {} d\n  |    ^\n",
+                operator
+            )
+        );
+    }
+}
+
+#[test]
+fn float_division_by_zero_is_infinite() {
+    assert_eq!(common::ph_stdout(&["eval", "1.0 / 0"], ""), "inf\n");
+    assert_eq!(common::error("let d = 0\n10 / d"), "division by zero");
+}
@@ -0,0 +1,27 @@
+mod common;
+
+/// Graph `build --emit dot` prints for `source` with the extra `arguments`
+fn dot(source: &str, arguments: &[&str]) -> String {
+    let mut all: Vec<&str> = vec!["build", "--expr", source, "--emit", "dot"];
+    all.extend_from_slice(arguments);
+    common::ph_stdout(&all, "")
+}
+
+#[test]
+fn graph_has_a_node_per_syntax_node_at_every_level() {
+    for arguments in [&[][..], &["-O"][..], &["--opt-level", "2"][..]] {
+        let graph: String = dot("1 + 2", arguments);
+        let nodes: usize = graph
+            .lines()
+            .filter(|line: &&str| line.contains("[label="))
+            .count();
+        let edges: usize = graph
+            .lines()
+            .filter(|line: &&str| line.contains("->"))
+            .count();
+
+        assert_eq!(nodes, 3, "{:?}\n{}", arguments, graph);
+        assert_eq!(edges, 2, "{:?}\n{}", arguments, graph);
+        assert!(graph.contains("n0 [label=\"+\"]"));
+    }
+}
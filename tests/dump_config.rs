@@ -0,0 +1,125 @@
+mod common;
+
+/// Directory with a project whose manifest sets `build.opt-level = 2`
+fn project(name: &str) -> std::path::PathBuf {
+    let directory: std::path::PathBuf = common::scratch_directory(name);
+    std::fs::create_dir(directory.join("src")).expect("Failed to create the source directory");
+    std::fs::write(directory.join("src/main.ph"), "1 + 2\n").expect("Failed to write the entry");
+    std::fs::write(
+        directory.join("Phoenix.toml"),
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[build]\nopt-level = 2\n",
+    )
+    .expect("Failed to write the manifest");
+    directory
+}
+
+/// Stdout of `ph` with `arguments` run in `directory`, which must succeed
+fn ph_in(directory: &std::path::Path, arguments: &[&str]) -> String {
+    let output: std::process::Output =
+        common::output(common::ph().current_dir(directory).args(arguments), "");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).expect("Output is UTF-8")
+}
+
+#[test]
+fn run_config_without_a_manifest_has_the_defaults() {
+    let config: String = common::ph_stdout(&["run", "--dump-config", "--expr", "1"], "");
+
+    assert!(config.starts_with("backend = \"vm\"\noverflow = \"checked\"\nopt_level = 0\n"));
+    assert!(config.contains("# seed is not set\n"));
+    assert!(config.ends_with("arguments = []\nvariables = {  }\n"));
+}
+
+#[test]
+fn run_config_takes_the_opt_level_from_the_manifest() {
+    let directory: std::path::PathBuf = project("dump_config_run");
+
+    assert!(ph_in(&directory, &["run", "--dump-config"]).contains("\nopt_level = 2\n"));
+    // From a subdirectory too, and the command line overrides it
+    let source: std::path::PathBuf = directory.join("src");
+    assert!(ph_in(&source, &["run", "--dump-config"]).contains("\nopt_level = 2\n"));
+    assert!(
+        ph_in(&directory, &["run", "--dump-config", "--opt-level", "1"])
+            .contains("\nopt_level = 1\n")
+    );
+    // Only for the entry point
+    assert!(
+        ph_in(&directory, &["run", "--dump-config", "src/main.ph"]).contains("\nopt_level = 0\n")
+    );
+    // Optimizing always runs on the vm
+    assert!(
+        ph_in(&directory, &["run", "--dump-config", "--backend", "tree"])
+            .starts_with("backend = \"vm\"\n")
+    );
+}
+
+#[test]
+fn build_config_without_a_manifest_has_the_defaults() {
+    assert_eq!(
+        common::ph_stdout(&["build", "--dump-config", "--expr", "1"], ""),
+        "file = \"<expr>\"\n\
+         opt_level = 0\n\
+         layout = false\n\
+         lenient = false\n\
+         emit = []\n\
+         # output is not set\n\
+         # target is not set\n\
+         link = false\n\
+         keep_intermediates = false\n"
+    );
+
+    // The file isn't read
+    let config: String = common::ph_stdout(
+        &[
+            "build",
+            "--dump-config",
+            "missing.ph",
+            "--emit",
+            "ast,ir",
+            "--target",
+            "wasm32",
+            "-o",
+            "out",
+            "-O",
+        ],
+        "",
+    );
+    assert!(config.starts_with("file = \"missing.ph\"\nopt_level = 2\n"));
+    assert!(config.contains("emit = [\"ast\", \"ir\"]\noutput = \"out\"\ntarget = \"wasm32\"\n"));
+}
+
+#[test]
+fn build_config_takes_the_opt_level_from_the_manifest() {
+    let directory: std::path::PathBuf = project("dump_config_build");
+
+    assert!(ph_in(&directory, &["build", "--dump-config"])
+        .starts_with("file = \"src/main.ph\"\nopt_level = 2\n"));
+    assert!(ph_in(&directory.join("src"), &["build", "--dump-config"])
+        .starts_with("file = \"../src/main.ph\"\nopt_level = 2\n"));
+    assert!(
+        ph_in(&directory, &["build", "--dump-config", "--opt-level", "0"])
+            .contains("\nopt_level = 0\n")
+    );
+}
+
+#[test]
+fn config_without_a_file_or_manifest_is_an_error() {
+    let directory: std::path::PathBuf = common::scratch_directory("dump_config_no_manifest");
+
+    for command in ["run", "build"] {
+        let output: std::process::Output = common::output(
+            common::ph()
+                .current_dir(&directory)
+                .args([command, "--dump-config"]),
+            "",
+        );
+        assert_eq!(output.status.code(), Some(2), "{}", command);
+        assert!(
+            String::from_utf8_lossy(&output.stderr).contains("no file given and no Phoenix.toml")
+        );
+    }
+}
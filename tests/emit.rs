@@ -0,0 +1,97 @@
+mod common;
+
+/// `ph build` with `arguments` on `source` written to `add.ph` in a new directory named `name`
+fn build(
+    name: &str,
+    source: &str,
+    arguments: &[&str],
+) -> (std::path::PathBuf, std::process::Output) {
+    let directory: std::path::PathBuf = common::scratch_directory(name);
+    std::fs::write(directory.join("add.ph"), source).expect("Failed to write the program");
+
+    let output: std::process::Output = common::output(
+        common::ph()
+            .current_dir(&directory)
+            .args(["build", "add.ph"])
+            .args(arguments),
+        "",
+    );
+    (directory, output)
+}
+
+#[test]
+fn two_artifacts_are_written_to_their_own_files() {
+    let (directory, output): (std::path::PathBuf, std::process::Output) = build(
+        "emit_two",
+        "let x = 1 + 2\nprint(x)\n",
+        &["--emit", "ast,ir"],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(output.stdout.is_empty());
+
+    let ast: String =
+        std::fs::read_to_string(directory.join("add.ast")).expect("Failed to read the syntax tree");
+    assert!(ast.starts_with("Let x\n"), "{}", ast);
+    assert!(ast.contains("Call print\n"), "{}", ast);
+
+    let ir: String =
+        std::fs::read_to_string(directory.join("add.ir")).expect("Failed to read the IR");
+    assert!(ir.starts_with("main:\n"), "{}", ir);
+    assert!(ir.contains("call print("), "{}", ir);
+}
+
+#[test]
+fn failed_stage_is_named_and_nothing_is_written() {
+    for (name, source, stage) in [
+        ("emit_parse_failure", "1 + * 2\n", "parsing"),
+        ("emit_check_failure", "sqrt(\"a\")\n", "checking"),
+    ] {
+        let (directory, output): (std::path::PathBuf, std::process::Output) =
+            build(name, source, &["--emit", "ast,ir"]);
+        assert_eq!(output.status.code(), Some(1), "{}", source);
+
+        let stderr: String = String::from_utf8_lossy(&output.stderr).into_owned();
+        for artifact in ["ast", "ir"] {
+            assert!(
+                stderr.contains(&format!(
+                    "error: cannot emit '{}' because {} failed",
+                    artifact, stage
+                )),
+                "{}",
+                stderr
+            );
+            assert!(!directory.join("add").with_extension(artifact).exists());
+        }
+    }
+}
+
+#[test]
+fn ast_is_the_same_as_emit_ast() {
+    for level in ["0", "2"] {
+        let program: &str = "let x = 1 + 2\nx * (3 + 4)\n";
+        let (_, shorthand): (std::path::PathBuf, std::process::Output) = build(
+            "emit_ast_shorthand",
+            program,
+            &["--ast", "--opt-level", level],
+        );
+        let (_, emitted): (std::path::PathBuf, std::process::Output) = build(
+            "emit_ast_emit",
+            program,
+            &["--emit", "ast", "--opt-level", level],
+        );
+
+        assert!(shorthand.status.success());
+        assert!(!shorthand.stdout.is_empty());
+        assert_eq!(shorthand.stdout, emitted.stdout, "level {}", level);
+    }
+
+    let (_, both): (std::path::PathBuf, std::process::Output) =
+        build("emit_ast_conflict", "1\n", &["--ast", "--emit", "ir"]);
+    assert_eq!(both.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&both.stderr)
+        .starts_with("error: the argument '--ast' cannot be used with '--emit <emit>'"));
+}
@@ -0,0 +1,75 @@
+use phoenix_script::eval::{Environment, EnvironmentError, ScopeKind, Value};
+
+#[test]
+fn nested_scopes_see_outer_bindings() {
+    let mut environment: Environment = Environment::new();
+    environment.define("x", Value::Int(1));
+    environment.push_scope(ScopeKind::Block);
+    environment.define("y", Value::Int(2));
+    environment.push_scope(ScopeKind::Block);
+
+    assert_eq!(environment.get("x"), Some(&Value::Int(1)));
+    assert_eq!(environment.get("y"), Some(&Value::Int(2)));
+
+    environment.pop_scope();
+    environment.pop_scope();
+    assert_eq!(environment.get("y"), None);
+}
+
+#[test]
+fn define_shadows_in_the_innermost_scope() {
+    let mut environment: Environment = Environment::new();
+    environment.define("x", Value::Int(1));
+    environment.push_scope(ScopeKind::Block);
+    environment.define("x", Value::String("inner".to_string()));
+
+    assert_eq!(
+        environment.get("x"),
+        Some(&Value::String("inner".to_string()))
+    );
+
+    environment.pop_scope();
+    assert_eq!(environment.get("x"), Some(&Value::Int(1)));
+}
+
+#[test]
+fn assign_walks_out_through_two_levels() {
+    let mut environment: Environment = Environment::new();
+    environment.define("x", Value::Int(1));
+    environment.push_scope(ScopeKind::Block);
+    environment.push_scope(ScopeKind::Block);
+
+    assert_eq!(environment.assign("x", Value::Int(5)), Ok(()));
+    // The binding changed where it was defined, no new one was made inside
+    environment.pop_scope();
+    environment.pop_scope();
+    assert_eq!(environment.get("x"), Some(&Value::Int(5)));
+}
+
+#[test]
+fn lookup_and_assignment_of_an_undefined_name_fail() {
+    let mut environment: Environment = Environment::new();
+    environment.push_scope(ScopeKind::Block);
+
+    assert_eq!(environment.get("missing"), None);
+    assert_eq!(
+        environment.assign("missing", Value::Int(1)),
+        Err(EnvironmentError::Undefined("missing".to_string()))
+    );
+}
+
+#[test]
+fn function_scope_sees_globals_but_not_its_caller() {
+    let mut environment: Environment = Environment::new();
+    environment.define("global", Value::Int(1));
+    environment.push_scope(ScopeKind::Block);
+    environment.define("caller", Value::Int(2));
+    environment.push_scope(ScopeKind::Function);
+
+    assert_eq!(environment.get("global"), Some(&Value::Int(1)));
+    assert_eq!(environment.get("caller"), None);
+    assert_eq!(
+        environment.assign("caller", Value::Int(3)),
+        Err(EnvironmentError::Undefined("caller".to_string()))
+    );
+}
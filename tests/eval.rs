@@ -0,0 +1,32 @@
+mod common;
+
+#[test]
+fn expression_is_evaluated_and_printed() {
+    assert_eq!(common::ph_stdout(&["eval", "(1 + 2) * 4"], ""), "12\n");
+    // The arguments are joined with spaces
+    assert_eq!(
+        common::ph_stdout(&["eval", "(1", "+", "2)", "*", "4"], ""),
+        "12\n"
+    );
+    assert_eq!(common::ph_stdout(&["e", "1 + 2"], ""), "3\n");
+}
+
+#[test]
+fn errors_are_in_eval() {
+    let output: std::process::Output = common::output(common::ph().args(["eval", "1", "+"]), "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "error: expected an expression but reached the end of the input\n --> <eval>:1:4\n  |\n1 | 1 +\n  |    ^\n"
+    );
+}
+
+#[test]
+fn expression_is_required() {
+    let output: std::process::Output = common::output(common::ph().arg("eval"), "");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with(
+        "error: the following required arguments were not provided:\n  <expression>...\n"
+    ));
+}
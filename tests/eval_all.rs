@@ -0,0 +1,30 @@
+use phoenix_script::diagnostic::Diagnostic;
+use phoenix_script::eval::Value;
+use phoenix_script::run::eval_all;
+
+#[test]
+fn one_result_per_statement() {
+    let results: Vec<Result<Option<Value>, Diagnostic>> = eval_all("1 + 2\n10 / missing\n");
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].as_ref().ok(), Some(&Some(Value::Int(3))));
+    let error: &Diagnostic = results[1].as_ref().expect_err("missing is undefined");
+    assert!(error.message.contains("missing"), "{}", error.message);
+}
+
+#[test]
+fn later_statements_run_after_a_failure_and_see_earlier_ones() {
+    let results: Vec<Result<Option<Value>, Diagnostic>> = eval_all("let x = 4\n1 / 0\nx * 2\n");
+
+    assert_eq!(results[0].as_ref().ok(), Some(&None));
+    assert!(results[1].is_err());
+    assert_eq!(results[2].as_ref().ok(), Some(&Some(Value::Int(8))));
+}
+
+#[test]
+fn a_syntax_error_is_the_only_result() {
+    let results: Vec<Result<Option<Value>, Diagnostic>> = eval_all("1 + )\n2\n");
+
+    assert_eq!(results.len(), 1);
+    assert!(results[0].is_err());
+}
@@ -0,0 +1,43 @@
+mod common;
+
+#[test]
+fn examples_print_their_expected_output() {
+    let mut failures: Vec<String> = Vec::new();
+    for example in common::examples() {
+        let stdout: String = common::run_example(&example, &[]);
+        if stdout != example.expected {
+            failures.push(format!(
+                "{}: expected\n{}\nfound\n{}",
+                example.name(),
+                example.expected,
+                stdout
+            ));
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn math_example_prints_seven() {
+    let math: std::path::PathBuf = common::examples_directory().join("math.ph");
+    assert_eq!(
+        common::ph_stdout(&["run", &math.display().to_string()], ""),
+        "7\n"
+    );
+}
+
+#[test]
+fn runtime_errors_are_diagnostics_with_spans() {
+    for (source, message) in [
+        ("let x = 0\n1 + 10 / x", "division by zero"),
+        ("9223372036854775807 + 1", "overflow"),
+    ] {
+        let output: std::process::Output = common::output(common::ph().args(["eval", source]), "");
+        let stderr: String = String::from_utf8_lossy(&output.stderr).into_owned();
+
+        assert_eq!(output.status.code(), Some(1), "{}", stderr);
+        assert!(stderr.contains(message), "{}", stderr);
+        assert!(stderr.contains(" --> <eval>:"), "{}", stderr);
+    }
+}
@@ -0,0 +1,55 @@
+mod common;
+
+/// Two statements, the second reads what the first defined
+const TWO_LINES: &str = "let x = 2\nprint(x * 3)\nprint(x + 1)";
+
+#[test]
+fn two_line_expr_runs_both_statements() {
+    assert_eq!(
+        common::ph_stdout(&["run", "--expr", TWO_LINES], ""),
+        "6\n3\n"
+    );
+}
+
+#[test]
+fn expr_runs_the_same_as_a_file() {
+    let directory: std::path::PathBuf = common::scratch_directory("expr_like_file");
+    std::fs::write(directory.join("main.ph"), TWO_LINES).expect("Failed to write the program");
+
+    let file: std::process::Output = common::output(
+        common::ph()
+            .current_dir(&directory)
+            .args(["run", "main.ph"]),
+        "",
+    );
+    assert!(file.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&file.stdout),
+        common::ph_stdout(&["run", "--expr", TWO_LINES], "")
+    );
+
+    let ast: std::process::Output = common::output(
+        common::ph()
+            .current_dir(&directory)
+            .args(["ast", "main.ph"]),
+        "",
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&ast.stdout),
+        common::ph_stdout(&["build", "--expr", TWO_LINES, "--emit", "ast"], "")
+    );
+}
+
+#[test]
+fn error_on_the_second_line_of_an_expr_points_there() {
+    let output: std::process::Output = common::output(
+        common::ph().args(["run", "--expr", "let x = 2\nprint(x +)"]),
+        "",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "error: expected an expression but found ')'\n --> <expr>:2:10\n  |\n2 | print(x +)\n  |          ^\n"
+    );
+}
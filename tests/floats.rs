@@ -0,0 +1,79 @@
+use phoenix_script::eval::Value;
+
+mod common;
+
+#[test]
+fn int_and_float_arithmetic_gives_a_float() {
+    assert_eq!(common::value("1 + 2.5"), Value::Float(3.5));
+    assert_eq!(common::value("2.5 + 1"), Value::Float(3.5));
+    assert_eq!(common::value("1 - 2.5"), Value::Float(-1.5));
+    assert_eq!(common::value("2 * 1.5"), Value::Float(3.0));
+    assert_eq!(common::value("7 / 2.0"), Value::Float(3.5));
+
+    assert_eq!(common::ph_stdout(&["eval", "1 + 2.5"], ""), "3.5\n");
+    assert_eq!(common::ph_stdout(&["eval", "2.5 + 1"], ""), "3.5\n");
+    // A whole float keeps its point so it doesn't look like an int
+    assert_eq!(common::ph_stdout(&["eval", "2 * 1.5"], ""), "3.0\n");
+}
+
+#[test]
+fn float_division_by_zero_follows_ieee() {
+    assert_eq!(common::value("1.0 / 0"), Value::Float(f64::INFINITY));
+    assert_eq!(common::value("-1.0 / 0"), Value::Float(f64::NEG_INFINITY));
+    assert!(matches!(common::value("0.0 / 0"), Value::Float(value) if value.is_nan()));
+
+    assert_eq!(common::ph_stdout(&["eval", "1.0 / 0"], ""), "inf\n");
+    assert_eq!(common::ph_stdout(&["eval", "-1.0 / 0"], ""), "-inf\n");
+    assert_eq!(common::ph_stdout(&["eval", "0.0 / 0"], ""), "NaN\n");
+}
+
+#[test]
+fn nan_compares_like_ieee() {
+    assert_eq!(common::value("0.0 / 0 == 0.0 / 0"), Value::Bool(false));
+    assert_eq!(common::value("0.0 / 0 != 0.0 / 0"), Value::Bool(true));
+    assert_eq!(common::value("0.0 / 0 < 1.0"), Value::Bool(false));
+    assert_eq!(common::value("0.0 / 0 >= 1.0"), Value::Bool(false));
+}
+
+#[test]
+fn pi_and_e_are_floats_that_can_be_shadowed() {
+    match common::value("PI * 2") {
+        Value::Float(value) => assert!((value - std::f64::consts::TAU).abs() < 1e-12, "{}", value),
+        value => panic!("expected a float, found {:?}", value),
+    }
+    assert_eq!(common::value("E"), Value::Float(std::f64::consts::E));
+    assert_eq!(
+        common::ph_stdout(&["eval", "PI * 2"], ""),
+        "6.283185307179586\n"
+    );
+
+    assert_eq!(common::value("let PI = 3\nPI * 2"), Value::Int(6));
+}
+
+#[test]
+fn result_precision_and_scientific_notation() {
+    let result = |options: &[&str], source: &str| -> String {
+        let mut arguments: Vec<&str> = vec!["eval"];
+        arguments.extend_from_slice(options);
+        arguments.push(source);
+        common::ph_stdout(&arguments, "")
+    };
+
+    // Shortest round trip by default
+    assert_eq!(result(&[], "1.0 / 3.0"), "0.3333333333333333\n");
+    assert_eq!(result(&["--precision", "4"], "1.0 / 3.0"), "0.3333\n");
+    assert_eq!(
+        result(&["--scientific"], "1.0 / 3.0"),
+        "3.333333333333333e-1\n"
+    );
+    assert_eq!(
+        result(&["--precision", "2", "--scientific"], "12345.678"),
+        "1.23e4\n"
+    );
+    // Ints and what the program prints itself are left alone
+    assert_eq!(result(&["--precision", "2"], "3"), "3\n");
+    assert_eq!(
+        result(&["--precision", "2"], "print(1.0 / 3.0)\n2.0 / 3.0"),
+        "0.3333333333333333\n0.67\n"
+    );
+}
@@ -0,0 +1,187 @@
+use phoenix_script::ast::format_statements;
+use phoenix_script::fmt::{format_source, FormatOptions};
+use phoenix_script::parser::{Parser, StatementSyntax};
+
+mod common;
+
+/// Programs that exercise what the examples don't, like percentages, chains and literals
+/// whose spelling must survive
+const CORPUS: &[&str] = &[
+    "let x = 10% * 200 + 50% of (3 + 4)\nlet y = 10 % (-3) + 0xff + 5u8\n",
+    "let a = 1 < 2 < 3\nlet b = (1 < 2) == true\nlet c = a ? b ? 1 : 2 : 3\n",
+    "fn f(a, b) { a - (b - 1) }\nprint(f(1, 2), [1, [2, 3]][1][0], \"x\".len())\n",
+    "let s = \"tab\\t and ${HOME}\"\nlet n = -(-1) + +2 * (1 + 2) / 3\n",
+    "// first\nlet x = 1 // one\nfn f() { // f\n    x // x\n    // end of f\n}\n// last\n",
+];
+
+/// The syntax tree of `source` without spans, which formatting must not change
+fn tree(source: &str) -> String {
+    let statements: Vec<StatementSyntax> = Parser::new(source.to_string())
+        .parse_statements()
+        .unwrap_or_else(|error| panic!("failed to parse\n{}\n{:?}", source, error));
+    format_statements(&statements)
+}
+
+/// The examples and the corpus above
+fn sources() -> Vec<(String, String)> {
+    let mut sources: Vec<(String, String)> = common::example_sources()
+        .into_iter()
+        .map(|path: std::path::PathBuf| {
+            let source: String = std::fs::read_to_string(&path).expect("Failed to read an example");
+            (path.display().to_string(), source)
+        })
+        .collect();
+    sources.extend(
+        CORPUS
+            .iter()
+            .enumerate()
+            .map(|(index, source): (usize, &&str)| {
+                (format!("corpus {}", index), source.to_string())
+            }),
+    );
+    sources
+}
+
+#[test]
+fn formatting_keeps_the_syntax_tree() {
+    for options in [
+        FormatOptions::default(),
+        FormatOptions {
+            max_line_width: 20,
+            ..FormatOptions::default()
+        },
+    ] {
+        for (name, source) in sources() {
+            let formatted: String = format_source(&source, &options).unwrap();
+            assert_eq!(
+                tree(&formatted),
+                tree(&source),
+                "{} formatted as\n{}",
+                name,
+                formatted
+            );
+        }
+    }
+}
+
+#[test]
+fn formatting_twice_is_formatting_once() {
+    for (name, source) in sources() {
+        let once: String = format_source(&source, &FormatOptions::default()).unwrap();
+        let twice: String = format_source(&once, &FormatOptions::default()).unwrap();
+        assert_eq!(twice, once, "{}", name);
+    }
+}
+
+#[test]
+fn check_passes_a_formatted_file_silently() {
+    let directory: std::path::PathBuf = common::scratch_directory("fmt_check_formatted");
+    std::fs::write(directory.join("f.ph"), "let x = 1\nprint(x)\n").unwrap();
+
+    let output: std::process::Output = common::output(
+        common::ph()
+            .current_dir(&directory)
+            .args(["fmt", "--check", "f.ph"]),
+        "",
+    );
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn check_fails_an_unformatted_file_with_a_diff_and_writes_nothing() {
+    let directory: std::path::PathBuf = common::scratch_directory("fmt_check_unformatted");
+    std::fs::write(directory.join("f.ph"), "let x = 1\n").unwrap();
+    std::fs::write(directory.join("u.ph"), "let x=1+2\nprint( x )\n").unwrap();
+
+    let output: std::process::Output = common::output(
+        common::ph()
+            .current_dir(&directory)
+            .args(["fmt", "--check", "u.ph", "f.ph"]),
+        "",
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "--- u.ph\n+++ u.ph\n@@ -1,2 +1,2 @@\n-let x=1+2\n-print( x )\n+let x = 1 + 2\n+print(x)\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(directory.join("u.ph")).unwrap(),
+        "let x=1+2\nprint( x )\n"
+    );
+}
+
+#[test]
+fn wrapping_at_forty_columns_is_stable() {
+    let options: FormatOptions = FormatOptions {
+        max_line_width: 40,
+        ..FormatOptions::default()
+    };
+    let source: &str = "fn f(a) {\n    let total = first_value * 2 + second_value * 3 - third_value / 4 + fourth_value % 5 + max(alpha, beta, gamma, delta, [epsilon, zeta, eta, theta])\n    total\n}\n";
+
+    let once: String = format_source(source, &options).unwrap();
+    let twice: String = format_source(&once, &options).unwrap();
+
+    assert!(once.lines().count() > 4, "{}", once);
+    assert!(once.lines().all(|line: &str| line.len() <= 40), "{}", once);
+    assert_eq!(twice, once);
+    assert_eq!(tree(&once), tree(source));
+}
+
+#[test]
+fn comment_after_an_expression_stays_after_it() {
+    let formatted: String = format_source("1+2   // note  \n", &FormatOptions::default()).unwrap();
+
+    assert_eq!(formatted, "1 + 2 // note\n");
+    assert_eq!(
+        format_source(&formatted, &FormatOptions::default()).unwrap(),
+        formatted
+    );
+}
+
+#[test]
+fn comments_keep_their_place_around_statements() {
+    let source: &str = "// header\nlet x = 1+2 // note\n// about f\nfn f(a) { // on the brace\n    a*2 // double\n    // closing\n}\nfn g() {\n    // nothing yet\n}\nprint(f(x),\n    // between arguments\n    x)   // after call\n1; 2 // two\n// the end\n";
+
+    let formatted: String = format_source(source, &FormatOptions::default()).unwrap();
+    assert_eq!(
+        formatted,
+        "// header\n\
+         let x = 1 + 2 // note\n\
+         \n\
+         // about f\n\
+         fn f(a) {\n    // on the brace\n    a * 2 // double\n    // closing\n}\n\
+         \n\
+         fn g() {\n    // nothing yet\n}\n\
+         \n\
+         // between arguments\n\
+         print(f(x), x) // after call\n\
+         1\n\
+         2 // two\n\
+         // the end\n"
+    );
+    assert_eq!(
+        format_source(&formatted, &FormatOptions::default()).unwrap(),
+        formatted
+    );
+}
+
+#[test]
+fn comments_after_an_indented_body_stay_outside_it() {
+    let options: FormatOptions = FormatOptions {
+        layout: true,
+        ..FormatOptions::default()
+    };
+    let source: &str =
+        "fn add(a, b) // adds\n    // the sum\n    a + b\n    // inside\n// call it\nadd(1, 2)\n";
+
+    let formatted: String = format_source(source, &options).unwrap();
+    assert_eq!(
+        formatted,
+        "fn add(a, b)\n    // adds\n    // the sum\n    a + b\n    // inside\n\n// call it\nadd(1, 2)\n"
+    );
+    assert_eq!(format_source(&formatted, &options).unwrap(), formatted);
+}
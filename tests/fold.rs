@@ -0,0 +1,58 @@
+mod common;
+
+/// `ph ast --folded` on `source` written to a file in a new directory named `name`
+fn folded(name: &str, source: &str) -> std::process::Output {
+    let directory: std::path::PathBuf = common::scratch_directory(name);
+    std::fs::write(directory.join("fold.ph"), source).expect("Failed to write the program");
+
+    common::output(
+        common::ph()
+            .current_dir(&directory)
+            .args(["ast", "--folded", "fold.ph"]),
+        "",
+    )
+}
+
+/// Tree `ph ast --folded` prints for `source`, which must fold
+fn folded_tree(name: &str, source: &str) -> String {
+    let output: std::process::Output = folded(name, source);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn literal_operations_fold_to_a_literal() {
+    assert_eq!(folded_tree("fold_literals", "(2 + 3) * 4"), "Number 20\n");
+    assert_eq!(folded_tree("fold_negation", "-(3)"), "Number -3\n");
+}
+
+#[test]
+fn division_by_a_literal_zero_fails_when_folding() {
+    let output: std::process::Output = folded("fold_division", "1 / 0");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "error: division by zero\n --> fold.ph:1:3\n  |\n1 | 1 / 0\n  |   ^\n"
+    );
+}
+
+#[test]
+fn operation_that_overflows_is_left_for_the_runtime() {
+    assert_eq!(
+        folded_tree("fold_overflow", "9223372036854775807 + 1"),
+        "Binary +\n  Number 9223372036854775807\n  Number 1\n"
+    );
+}
+
+#[test]
+fn variables_are_left_untouched() {
+    assert_eq!(
+        folded_tree("fold_variables", "let x = 1\n(x + 2) * 4"),
+        "Let x\n  Number 1\nBinary *\n  Binary +\n    Variable x\n    Number 2\n  Number 4\n"
+    );
+}
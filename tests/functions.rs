@@ -0,0 +1,127 @@
+use phoenix_script::diagnostic::Diagnostic;
+use phoenix_script::eval::Value;
+
+mod common;
+
+/// Value of the last statement of `source` on the tree walker, after checking that the vm
+/// prints the same
+fn eval(source: &str) -> Result<Value, Diagnostic> {
+    let result: Result<Value, Diagnostic> = common::eval(source);
+
+    let output: std::process::Output = common::output(
+        common::ph().args(["run", "--backend", "vm", "--expr", source]),
+        "",
+    );
+    match &result {
+        Ok(value) => assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            format!("{}\n", value)
+        ),
+        Err(diagnostic) => assert!(String::from_utf8_lossy(&output.stderr)
+            .starts_with(&format!("runtime error: {}", diagnostic.message))),
+    }
+
+    result
+}
+
+#[test]
+fn simple_function() {
+    assert_eq!(
+        eval("fn square(x) { x * x }\nsquare(5)").ok(),
+        Some(Value::Int(25))
+    );
+    // Parameters live in a scope of their own
+    assert_eq!(
+        eval("let x = 3\nfn square(x) { x * x }\nsquare(5) + x").ok(),
+        Some(Value::Int(28))
+    );
+}
+
+#[test]
+fn recursive_factorial() {
+    assert_eq!(
+        eval("fn factorial(n) { n < 2 ? 1 : n * factorial(n - 1) }\nfactorial(20)").ok(),
+        Some(Value::Int(2432902008176640000))
+    );
+}
+
+#[test]
+fn endless_recursion_stops_at_the_depth_limit() {
+    // Deep enough to overflow the stack of a test thread, so it runs in `ph`
+    for backend in ["tree", "vm"] {
+        let output: std::process::Output = common::output(
+            common::ph().args([
+                "run",
+                "--backend",
+                backend,
+                "--expr",
+                "fn f(n) { f(n + 1) }\nf(0)",
+            ]),
+            "",
+        );
+
+        assert_eq!(output.status.code(), Some(1));
+        assert!(String::from_utf8_lossy(&output.stderr)
+            .starts_with("runtime error: calling 'f' exceeds the maximum call depth of 10000"));
+    }
+}
+
+#[test]
+fn wrong_argument_count_is_an_error() {
+    let error: Diagnostic = eval("fn square(x) { x * x }\nsquare(1, 2)").unwrap_err();
+    assert_eq!(
+        error.message,
+        "function 'square' expects 1 argument but got 2"
+    );
+    // The error points at the call
+    assert_eq!(error.span.start, 23);
+}
+
+#[test]
+fn runtime_error_shows_each_call_in_order() {
+    let source: &str = "fn a(x) { b(x) }\nfn b(x) { c(x) }\nfn c(x) { x / 0 }\na(1)";
+
+    let error: Diagnostic = eval(source).unwrap_err();
+    let callees: Vec<&str> = error
+        .trace
+        .iter()
+        .map(|(callee, _): &(String, phoenix_script::diagnostic::Span)| callee.as_str())
+        .collect();
+    assert_eq!(callees, ["c", "b", "a"]);
+
+    for backend in ["tree", "vm"] {
+        let output: std::process::Output = common::output(
+            common::ph().args(["run", "--backend", backend, "--expr", source]),
+            "",
+        );
+        assert_eq!(output.status.code(), Some(1));
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr),
+            "runtime error: division by zero\n --> <expr>:3:13\n  |\n3 | fn c(x) { x / 0 }\n  |             ^\n  = in 'c', called at <expr>:2:11\n  = in 'b', called at <expr>:1:11\n  = in 'a', called at <expr>:4:1\n",
+            "backend {}",
+            backend
+        );
+    }
+}
+
+#[test]
+fn deep_trace_is_cut_short() {
+    // 31 calls, only the innermost 10 are shown
+    let output: std::process::Output = common::output(
+        common::ph().args([
+            "run",
+            "--expr",
+            "fn f(n) { n == 0 ? n / n : f(n - 1) }\nf(30)",
+        ]),
+        "",
+    );
+    assert_eq!(output.status.code(), Some(1));
+
+    let stderr: String = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert_eq!(stderr.matches("  = in 'f', called at").count(), 10);
+    assert!(
+        stderr.ends_with("  = ... and 21 more frames\n"),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,47 @@
+use phoenix_script::diagnostic::Diagnostic;
+use phoenix_script::eval::Value;
+use phoenix_script::fmt::{format_source, FormatOptions};
+use phoenix_script::run::eval_all;
+
+mod common;
+
+#[test]
+fn smallest_signed_values_are_written_with_a_minus() {
+    assert_eq!(common::value("-128i8"), Value::Int(-128));
+    assert_eq!(common::value("-0x80i8"), Value::Int(-128));
+    assert_eq!(common::value("-32768i16"), Value::Int(-32768));
+    assert_eq!(common::value("-2147483648i32"), Value::Int(-2147483648));
+    assert_eq!(
+        common::value("-9223372036854775808i64"),
+        Value::Int(isize::MIN)
+    );
+    assert_eq!(
+        common::value("-9223372036854775808"),
+        Value::Int(isize::MIN)
+    );
+    assert_eq!(common::value("- -128i8"), Value::Int(128));
+}
+
+#[test]
+fn the_magnitude_alone_is_still_out_of_range() {
+    let message: String =
+        "invalid number: 128 doesn't fit in i8, which goes from -128 to 127".to_string();
+    assert_eq!(common::error("128i8"), message.clone());
+    // The method call and the percent sign bind to the literal before the minus does
+    assert_eq!(common::error("-128i8.abs()"), message.clone());
+    assert_eq!(common::error("-128i8%"), message);
+    assert!(common::eval("-129i8").is_err());
+    assert!(common::error("9223372036854775808").starts_with("number is too large for an int"));
+}
+
+#[test]
+fn formatting_keeps_the_minus_of_a_smallest_value() {
+    let source: &str = "let a = -128i8\nlet b = -(-128i8)\nlet c = (-0x80i8).abs()\n";
+    assert_eq!(
+        format_source(source, &FormatOptions::default()).unwrap(),
+        source
+    );
+    assert!(eval_all(source)
+        .iter()
+        .all(|result: &Result<Option<Value>, Diagnostic>| result.is_ok()));
+}
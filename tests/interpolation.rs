@@ -0,0 +1,49 @@
+mod common;
+
+/// `ph eval` on `source` with `variables` set and `PH_UNSET` removed from its environment
+fn eval_with(source: &str, variables: &[(&str, &str)]) -> std::process::Output {
+    common::output(
+        common::ph()
+            .args(["eval", source])
+            .envs(variables.iter().copied())
+            .env_remove("PH_UNSET"),
+        "",
+    )
+}
+
+#[test]
+fn variable_is_put_into_the_string() {
+    let output: std::process::Output =
+        eval_with("\"home is ${HOME}\"", &[("HOME", "/home/phoenix")]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "home is /home/phoenix\n"
+    );
+
+    let output: std::process::Output = eval_with(
+        "\"${PH_A}-${PH_B}, $PH_A\"",
+        &[("PH_A", "one"), ("PH_B", "two")],
+    );
+    // Only `${NAME}` is interpolated
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "one-two, $PH_A\n");
+}
+
+#[test]
+fn unset_variable_is_an_error() {
+    let output: std::process::Output = eval_with("print(1)\n\"x ${PH_UNSET} y\"", &[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "runtime error: environment variable 'PH_UNSET' is not set\n --> <eval>:2:4\n  |\n2 | \"x ${PH_UNSET} y\"\n  |    ^^^^^^^^^^^\n"
+    );
+}
+
+#[test]
+fn unclosed_interpolation_is_a_syntax_error() {
+    let output: std::process::Output = eval_with("\"${HOME\"", &[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .starts_with("error: invalid string: '${' in string literal is missing a closing '}'\n"));
+}
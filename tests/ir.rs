@@ -0,0 +1,95 @@
+mod common;
+
+/// Program with a recursive function whose conditional branches, and a chained comparison
+/// that stops early
+const PROGRAM: &str = "fn count(n) { n > 0 ? count(n - 1) + 1 : 0 }\n\
+                       fn between(x) { 0 < x < 10 }\n\
+                       count(3)";
+
+#[test]
+fn conditional_and_chained_comparison_lower_to_blocks() {
+    assert_eq!(
+        common::ph_stdout(&["build", "--expr", PROGRAM, "--emit", "ir"], ""),
+        "main:\n\
+         \x20 b0:\n\
+         \x20   t0 = const 3\n\
+         \x20   t1 = call count(t0)\n\
+         \x20   yield t1\n\
+         \x20   return t1\n\
+         fn count(n):\n\
+         \x20 b0:\n\
+         \x20   t0 = load local 0 (n)\n\
+         \x20   t1 = const 0\n\
+         \x20   t2 = t0 > t1\n\
+         \x20   branch t2, b1, b2\n\
+         \x20 b1:\n\
+         \x20   t3 = load local 0 (n)\n\
+         \x20   t4 = const 1\n\
+         \x20   t5 = t3 - t4\n\
+         \x20   t6 = call count(t5)\n\
+         \x20   t7 = const 1\n\
+         \x20   t8 = t6 + t7\n\
+         \x20   jump b3(t8)\n\
+         \x20 b2:\n\
+         \x20   t9 = const 0\n\
+         \x20   jump b3(t9)\n\
+         \x20 b3(t10):\n\
+         \x20   return t10\n\
+         fn between(x):\n\
+         \x20 b0:\n\
+         \x20   t0 = const 0\n\
+         \x20   t1 = load local 0 (x)\n\
+         \x20   t2 = compare t0 < t1\n\
+         \x20   branch t2, b1, b2(t1)\n\
+         \x20 b1:\n\
+         \x20   t3 = const 10\n\
+         \x20   t4 = t1 < t3\n\
+         \x20   jump b3(t4)\n\
+         \x20 b2(t5):\n\
+         \x20   drop t5\n\
+         \x20   t6 = const false\n\
+         \x20   jump b3(t6)\n\
+         \x20 b3(t7):\n\
+         \x20   return t7\n"
+    );
+}
+
+#[test]
+fn dump_ir_prints_the_operations_in_order() {
+    let ir: String = common::ph_stdout(&["build", "--dump-ir", "--expr", "1 + 2 * 3"], "");
+    assert_eq!(
+        ir,
+        "main:\n\
+         \x20 b0:\n\
+         \x20   t0 = const 1\n\
+         \x20   t1 = const 2\n\
+         \x20   t2 = const 3\n\
+         \x20   t3 = t1 * t2\n\
+         \x20   t4 = t0 + t3\n\
+         \x20   yield t4\n\
+         \x20   return t4\n"
+    );
+    assert_eq!(
+        common::ph_stdout(&["build", "--emit", "ir", "--expr", "1 + 2 * 3"], ""),
+        ir
+    );
+
+    let directory: std::path::PathBuf = common::scratch_directory("dump_ir_output");
+    let written: std::process::Output = common::output(
+        common::ph().current_dir(&directory).args([
+            "build",
+            "--dump-ir",
+            "--expr",
+            "1 + 2 * 3",
+            "-o",
+            "sum.ir",
+        ]),
+        "",
+    );
+    assert!(written.status.success());
+    assert!(written.stdout.is_empty());
+    assert_eq!(
+        std::fs::read_to_string(directory.join("sum.ir")).expect("Failed to read the IR"),
+        ir
+    );
+}
@@ -0,0 +1,49 @@
+mod common;
+
+/// Stdout of `ph eval --json-result` on `source`, with whether it succeeded
+fn json_result(source: &str) -> (String, bool) {
+    let output: std::process::Output =
+        common::output(common::ph().args(["eval", "--json-result", source]), "");
+    assert!(output.stderr.is_empty(), "{}", source);
+    (
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        output.status.success(),
+    )
+}
+
+#[test]
+fn value_is_wrapped_with_ok() {
+    for (source, json) in [
+        ("7", "{\"ok\": true, \"value\": 7}\n"),
+        ("2.5", "{\"ok\": true, \"value\": 2.5}\n"),
+        ("2.0", "{\"ok\": true, \"value\": 2.0}\n"),
+        (
+            "\"a\\\"b\\n\"",
+            "{\"ok\": true, \"value\": \"a\\\"b\\n\"}\n",
+        ),
+        ("[1, 2]", "{\"ok\": true, \"value\": [1, 2]}\n"),
+        // JSON has no infinity
+        ("1.0 / 0", "{\"ok\": true, \"value\": \"inf\"}\n"),
+        ("let x = 1", "{\"ok\": true, \"value\": null}\n"),
+    ] {
+        assert_eq!(json_result(source), (json.to_string(), true), "{}", source);
+    }
+}
+
+#[test]
+fn error_is_wrapped_with_where_it_happened() {
+    assert_eq!(
+        json_result("1 / 0"),
+        (
+            "{\"ok\": false, \"error\": {\"severity\": \"error\", \"message\": \"division by zero\", \"file\": \"<eval>\", \"line\": 1, \"column\": 3, \"start\": 2, \"end\": 3, \"trace\": []}}\n".to_string(),
+            false
+        )
+    );
+    assert_eq!(
+        json_result("fn f(x) { x / 0 }\nf(1)"),
+        (
+            "{\"ok\": false, \"error\": {\"severity\": \"runtime error\", \"message\": \"division by zero\", \"file\": \"<eval>\", \"line\": 1, \"column\": 13, \"start\": 12, \"end\": 13, \"trace\": [{\"function\": \"f\", \"file\": \"<eval>\", \"line\": 2, \"column\": 1}]}}\n".to_string(),
+            false
+        )
+    );
+}
@@ -0,0 +1,139 @@
+use phoenix_script::fmt::{format_source, FormatOptions};
+use phoenix_script::lexer::{Lexer, SyntaxToken, SyntaxTokenType};
+
+mod common;
+
+/// Program whose function body is delimited by indentation
+const PROGRAM: &str = "fn add(a, b)\n    let sum = a + b\n    sum\n\nadd(1, 2)\n";
+
+/// Names of the token types `source` lexes to in layout mode, without whitespace
+fn token_types(source: &str) -> Vec<&'static str> {
+    Lexer::with_layout(source.to_string())
+        .map(|token: SyntaxToken| token.token_type.name())
+        .collect()
+}
+
+/// `ph` with `arguments` on `PROGRAM` written to `name` in a new directory
+fn ph_on_program(name: &str, arguments: &[&str]) -> std::process::Output {
+    let directory: std::path::PathBuf = common::scratch_directory(name);
+    std::fs::write(directory.join("add.ph"), PROGRAM).expect("Failed to write the program");
+
+    common::output(
+        common::ph()
+            .current_dir(&directory)
+            .args(arguments)
+            .arg("add.ph"),
+        "",
+    )
+}
+
+#[test]
+fn indented_block_is_between_indent_and_dedent() {
+    assert_eq!(
+        token_types("fn f(a)\n    a\nf(1)\n"),
+        [
+            "FnKeyword",
+            "Identifier",
+            "OpenParenthesis",
+            "Identifier",
+            "CloseParenthesis",
+            "NewLine",
+            "Indent",
+            "Identifier",
+            "NewLine",
+            "Dedent",
+            "Identifier",
+            "OpenParenthesis",
+            "Number",
+            "CloseParenthesis",
+            "NewLine",
+        ]
+    );
+}
+
+#[test]
+fn blocks_open_at_the_end_of_the_file_are_closed() {
+    let types: Vec<&str> = token_types("a\n    b\n        c");
+    let indents: usize = types
+        .iter()
+        .filter(|name: &&&str| **name == "Indent")
+        .count();
+
+    assert_eq!(indents, 2);
+    assert_eq!(types[types.len() - 2..], ["Dedent", "Dedent"]);
+}
+
+#[test]
+fn without_layout_indentation_is_whitespace() {
+    let tokens: Vec<SyntaxToken> = phoenix_script::parser::collect_tokens(PROGRAM.to_string());
+
+    assert!(!tokens.iter().any(|token: &SyntaxToken| matches!(
+        token.token_type,
+        SyntaxTokenType::Indent | SyntaxTokenType::Dedent
+    )));
+}
+
+#[test]
+fn check_accepts_a_layout_file_with_layout() {
+    let checked: std::process::Output = ph_on_program("layout_check", &["check", "--layout"]);
+    assert!(checked.status.success());
+    assert!(checked.stderr.is_empty());
+
+    let plain: std::process::Output = ph_on_program("layout_check_plain", &["check"]);
+    assert_eq!(plain.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&plain.stderr).contains("expected '{' but found a new line"));
+}
+
+#[test]
+fn build_compiles_a_layout_file_with_layout() {
+    let directory: std::path::PathBuf = common::scratch_directory("layout_build");
+    std::fs::write(directory.join("add.ph"), PROGRAM).expect("Failed to write the program");
+
+    let build: std::process::Output = common::output(
+        common::ph()
+            .current_dir(&directory)
+            .args(["build", "--layout", "add.ph", "-o", "add.phc"]),
+        "",
+    );
+    assert!(
+        build.status.success(),
+        "{}",
+        String::from_utf8_lossy(&build.stderr)
+    );
+
+    let run: std::process::Output = common::output(
+        common::ph()
+            .current_dir(&directory)
+            .args(["run", "add.phc"]),
+        "",
+    );
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "3\n");
+}
+
+#[test]
+fn fmt_keeps_a_layout_file_in_layout() {
+    let options: FormatOptions = FormatOptions {
+        layout: true,
+        ..FormatOptions::default()
+    };
+    let source: &str = "fn add(a,b)\n    let sum=a+b\n    sum\nfn none() {}\nadd(1,2)\n";
+
+    let formatted: String = format_source(source, &options).unwrap();
+    assert_eq!(
+        formatted,
+        "fn add(a, b)\n    let sum = a + b\n    sum\n\nfn none() {}\n\nadd(1, 2)\n"
+    );
+    assert_eq!(format_source(&formatted, &options).unwrap(), formatted);
+}
+
+#[test]
+fn fmt_reads_layout_files_only_with_layout() {
+    let formatted: std::process::Output =
+        ph_on_program("layout_fmt", &["fmt", "--layout", "--check"]);
+    assert!(formatted.status.success());
+    assert!(formatted.stdout.is_empty());
+
+    let plain: std::process::Output = ph_on_program("layout_fmt_plain", &["fmt", "--check"]);
+    assert_eq!(plain.status.code(), Some(1));
+    assert!(plain.stdout.is_empty());
+}
@@ -0,0 +1,49 @@
+use phoenix_script::parser::{ParseErrorKind, Parser, StatementSyntax};
+
+mod common;
+
+/// Statements `source` parses to, or the kind of its syntax error
+fn parse(source: &str, lenient: bool) -> Result<Vec<StatementSyntax>, ParseErrorKind> {
+    let mut parser: Parser = Parser::new(source.to_string());
+    parser.set_lenient(lenient);
+    parser
+        .parse_statements()
+        .map_err(|error: phoenix_script::parser::ParseError| error.kind)
+}
+
+#[test]
+fn bad_token_is_an_error_by_default() {
+    assert_eq!(
+        parse("1 @ 2", false).err(),
+        Some(ParseErrorKind::UnknownCharacter("@".to_string()))
+    );
+
+    let output: std::process::Output = common::output(common::ph().args(["eval", "1 @ 2"]), "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown character '@'"));
+}
+
+#[test]
+fn bad_token_ends_the_statement_when_lenient() {
+    assert_eq!(
+        parse("1 @ 2", true).map(|statements: Vec<StatementSyntax>| statements.len()),
+        Ok(2)
+    );
+
+    assert_eq!(
+        common::ph_stdout(&["eval", "--lenient", "1 @ 2"], ""),
+        "2\n"
+    );
+    assert_eq!(
+        common::ph_stdout(&["eval", "--lenient", "let x = 1 @ x + 1"], ""),
+        "2\n"
+    );
+}
+
+#[test]
+fn bad_token_after_an_operator_continues_the_expression_when_lenient() {
+    assert_eq!(
+        common::ph_stdout(&["eval", "--lenient", "1 + @ 2"], ""),
+        "3\n"
+    );
+}
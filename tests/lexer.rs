@@ -0,0 +1,209 @@
+use phoenix_script::ast::format_statements;
+use phoenix_script::lexer::{
+    HexFloatError, IntegerLiteralError, IntegerType, Lexer, SyntaxToken, SyntaxTokenType,
+};
+use phoenix_script::parser::{collect_tokens, Parser};
+
+/// Value of the float literal `source` is made of
+fn float(source: &str) -> Result<f64, HexFloatError> {
+    let tokens: Vec<SyntaxToken> = collect_tokens(source.to_string());
+    match &tokens[..] {
+        [SyntaxToken {
+            token_type: SyntaxTokenType::Float(value),
+            ..
+        }] => value.clone(),
+        tokens => panic!("expected one float token, found {:?}", tokens),
+    }
+}
+
+#[test]
+fn hex_float_value() {
+    assert_eq!(float("0x1.8p3"), Ok(12.0));
+    assert_eq!(float("0x.1p4"), Ok(1.0));
+    assert_eq!(float("0xAp-1"), Ok(5.0));
+    assert_eq!(float("0x0p99"), Ok(0.0));
+}
+
+#[test]
+fn hex_float_rounds_to_nearest_even() {
+    // Halfway between 1 and the next float, with and without digits after the half
+    assert_eq!(float("0x1.00000000000008p0"), Ok(1.0));
+    assert_eq!(float("0x1.000000000000080001p0"), Ok(1.0 + f64::EPSILON));
+    assert_eq!(float("0x1.00000000000018p0"), Ok(1.0 + 2.0 * f64::EPSILON));
+    assert_eq!(float("0xffffffffffffffffffp0"), Ok(2f64.powi(72)));
+}
+
+#[test]
+fn hex_float_subnormal_and_overflow() {
+    assert_eq!(float("0x1p-1074"), Ok(5e-324));
+    assert_eq!(float("0x1.8p-1075"), Ok(5e-324));
+    assert_eq!(float("0x1p-1075"), Ok(0.0));
+    assert_eq!(float("0x1p-1022"), Ok(f64::MIN_POSITIVE));
+    assert_eq!(float("0x0.ffffffffffffffp-1022"), Ok(f64::MIN_POSITIVE));
+    assert_eq!(float("0x1.fffffffffffffp1023"), Ok(f64::MAX));
+    assert_eq!(float("0x1p1024"), Ok(f64::INFINITY));
+}
+
+#[test]
+fn hex_float_extreme_exponents_and_digits() {
+    assert_eq!(float("0x1.8p-2147483647"), Ok(0.0));
+    assert_eq!(float("0x1.8p2147483647"), Ok(f64::INFINITY));
+    assert_eq!(float(&format!("0x1.{}p0", "0".repeat(300))), Ok(1.0));
+    assert_eq!(
+        float(&format!("0x{}p-1200", "1".repeat(300))),
+        float("0x1.111111111111111p-4")
+    );
+}
+
+#[test]
+fn hex_float_errors() {
+    assert_eq!(float("0x1.8"), Err(HexFloatError::MissingExponent));
+    assert_eq!(float("0x1p"), Err(HexFloatError::MissingExponentDigits));
+    assert_eq!(float("0x.p1"), Err(HexFloatError::MissingDigits));
+    assert_eq!(
+        float("0x1p99999999999"),
+        Err(HexFloatError::ExponentOutOfRange)
+    );
+}
+
+#[test]
+fn parsing_again_starts_over() {
+    let source: &str = "let x = 1 + 2\nfn f(a) { a * x }\nf(3)\n";
+    let mut parser: Parser = Parser::new(source.to_string());
+
+    let tokens: Vec<SyntaxToken> = parser.tokens();
+    assert_eq!(tokens.len(), 23);
+
+    let first: String = format_statements(&parser.parse_statements().unwrap());
+    // Tokens and statements come out the same however often they are asked for
+    assert_eq!(parser.tokens().len(), tokens.len());
+    let second: String = format_statements(&parser.parse_statements().unwrap());
+    assert_eq!(second, first);
+    assert_eq!(parser.tokens().len(), tokens.len());
+
+    // Also after parsing only part of the text
+    let mut parser: Parser = Parser::new(source.to_string());
+    assert!(parser.parse_expression().is_err());
+    assert_eq!(
+        format_statements(&parser.parse_statements().unwrap()),
+        first
+    );
+}
+
+#[test]
+fn parser_tokens_are_read_like_the_parser_reads_them() {
+    let mut parser: Parser = Parser::with_layout("fn f()\n    1 // one\n".to_string());
+    parser.set_lenient(true);
+    let names: Vec<&str> = parser
+        .tokens()
+        .iter()
+        .map(|token: &SyntaxToken| token.token_type.name())
+        .collect();
+    assert!(names.contains(&"Indent") && names.contains(&"Dedent"));
+    assert!(!names.contains(&"Comment"));
+
+    let mut parser: Parser = Parser::new("1 @ 2".to_string());
+    parser.set_lenient(true);
+    assert_eq!(parser.tokens()[1].token_type, SyntaxTokenType::NewLine);
+    assert_eq!(parser.tokens()[1].text, "@");
+}
+
+#[test]
+fn signed_minimum_is_lexed_without_its_minus() {
+    let tokens: Vec<SyntaxToken> = collect_tokens("-128i8 9223372036854775808 128u8".to_string());
+    let types: Vec<&SyntaxTokenType> = tokens
+        .iter()
+        .map(|token: &SyntaxToken| &token.token_type)
+        .collect();
+
+    assert!(matches!(types[0], SyntaxTokenType::Minus));
+    assert_eq!(
+        types[1],
+        &SyntaxTokenType::Number(Err(IntegerLiteralError::Minimum {
+            integer_type: Some(IntegerType::I8)
+        }))
+    );
+    assert_eq!(
+        types[2],
+        &SyntaxTokenType::Number(Err(IntegerLiteralError::Minimum { integer_type: None }))
+    );
+    assert!(matches!(types[3], SyntaxTokenType::Number(Ok(_))));
+}
+
+#[test]
+fn comment_runs_to_the_end_of_the_line() {
+    let tokens: Vec<SyntaxToken> =
+        phoenix_script::parser::collect_raw_tokens(Lexer::new("1 // a / b\r\n2".to_string()));
+    let comment: &SyntaxToken = tokens
+        .iter()
+        .find(|token: &&SyntaxToken| token.token_type == SyntaxTokenType::Comment)
+        .expect("The comment is a token");
+
+    assert_eq!(comment.text, "// a / b");
+    assert_eq!(comment.position, 2);
+    // Trivia like white space, the tokens the parser sees don't include it
+    assert_eq!(collect_tokens("1 // a / b\n2".to_string()).len(), 3);
+    assert_eq!(
+        phoenix_script::run::eval_all("1 + 2 // note")
+            .pop()
+            .unwrap()
+            .ok(),
+        Some(Some(phoenix_script::eval::Value::Int(3)))
+    );
+}
+
+#[test]
+fn unicode_escape_is_the_character() {
+    let value = |source: &str| -> Option<phoenix_script::eval::Value> {
+        phoenix_script::run::eval_all(source)
+            .pop()
+            .unwrap()
+            .ok()
+            .flatten()
+    };
+
+    assert_eq!(
+        value("\"\\u{1F600}\""),
+        Some(phoenix_script::eval::Value::String("\u{1F600}".to_string()))
+    );
+    assert_eq!(
+        value("\"a\\u{41}b\""),
+        Some(phoenix_script::eval::Value::String("aAb".to_string()))
+    );
+}
+
+#[test]
+fn unicode_escape_errors() {
+    for (source, message) in [
+        (
+            "\"\\u{D800}\"",
+            "'\\u{D800}' is not a valid unicode character",
+        ),
+        (
+            "\"\\u{110000}\"",
+            "'\\u{110000}' is not a valid unicode character",
+        ),
+        (
+            "\"\\u{}\"",
+            "'\\u' must be followed by a hex code point in braces, like '\\u{1F600}'",
+        ),
+        (
+            "\"\\u41\"",
+            "'\\u' must be followed by a hex code point in braces, like '\\u{1F600}'",
+        ),
+    ] {
+        let error: phoenix_script::parser::ParseError = Parser::new(format!("1 + {}", source))
+            .parse_statements()
+            .expect_err("expected an invalid string");
+
+        assert_eq!(
+            error.kind,
+            phoenix_script::parser::ParseErrorKind::InvalidString(message.to_string()),
+            "{}",
+            source
+        );
+        // The whole literal
+        assert_eq!(error.span.start, 4);
+        assert_eq!(error.span.end, 4 + source.len());
+    }
+}
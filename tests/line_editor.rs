@@ -0,0 +1,120 @@
+use phoenix_script::line_editor::{LineEditor, ReadLine};
+
+mod common;
+
+/// Line `editor` returns after the keys in `keys`
+fn edit(editor: &mut LineEditor, keys: &str) -> std::io::Result<Option<String>> {
+    let mut output: Vec<u8> = Vec::new();
+
+    editor.edit_line(
+        ">> ",
+        &mut keys.as_bytes(),
+        &mut output,
+        &mut || -> std::io::Result<()> { Ok(()) },
+    )
+}
+
+/// Line the keys in `keys` enter in an editor without history
+fn line(keys: &str) -> Option<String> {
+    edit(&mut LineEditor::new(None), keys).expect("Editing doesn't fail")
+}
+
+#[test]
+fn backspace_deletes_before_the_cursor() {
+    assert_eq!(line("abc\x7f\r"), Some("ab".to_string()));
+    assert_eq!(line("abc\x08\x08\r"), Some("a".to_string()));
+    assert_eq!(line("abc\x1b[D\x7f\r"), Some("ac".to_string()));
+}
+
+#[test]
+fn backspace_at_the_start_of_the_line_does_nothing() {
+    assert_eq!(line("\x7f\r"), Some(String::new()));
+    assert_eq!(line("ab\x01\x7f\x08\r"), Some("ab".to_string()));
+}
+
+#[test]
+fn cursor_keys_move_where_characters_are_inserted() {
+    // Left, Home and Ctrl-A
+    assert_eq!(line("ac\x1b[Db\r"), Some("abc".to_string()));
+    assert_eq!(line("bc\x1b[Ha\r"), Some("abc".to_string()));
+    assert_eq!(line("bc\x01a\r"), Some("abc".to_string()));
+    // Right, End and Ctrl-E
+    assert_eq!(line("ab\x01\x1b[Cx\r"), Some("axb".to_string()));
+    assert_eq!(line("ab\x01\x1b[Fc\r"), Some("abc".to_string()));
+    assert_eq!(line("ab\x01\x05c\r"), Some("abc".to_string()));
+    // Right stops at the end and Left at the start
+    assert_eq!(line("a\x1b[C\x1b[Cb\r"), Some("ab".to_string()));
+    assert_eq!(line("a\x1b[D\x1b[Db\r"), Some("ba".to_string()));
+}
+
+#[test]
+fn delete_removes_under_the_cursor() {
+    assert_eq!(line("abc\x01\x1b[3~\r"), Some("bc".to_string()));
+    assert_eq!(line("abc\x1b[3~\r"), Some("abc".to_string()));
+    assert_eq!(line("abc\x01\x04\r"), Some("bc".to_string()));
+}
+
+#[test]
+fn multi_byte_characters_are_one_character() {
+    assert_eq!(line("né\x7f\r"), Some("n".to_string()));
+    assert_eq!(line("€b\x1b[Da\r"), Some("€ab".to_string()));
+}
+
+#[test]
+fn ctrl_d_on_an_empty_line_and_end_of_input_quit() {
+    assert_eq!(line("\x04"), None);
+    assert_eq!(line(""), None);
+}
+
+#[test]
+fn ctrl_c_cancels_the_line() {
+    let error: std::io::Error =
+        edit(&mut LineEditor::new(None), "abc\x03").expect_err("Ctrl-C cancels");
+    assert_eq!(error.kind(), std::io::ErrorKind::Interrupted);
+}
+
+#[test]
+fn up_and_down_walk_the_history_and_keep_the_draft() {
+    let mut editor: LineEditor = LineEditor::new(None);
+    editor.add_history("first");
+    editor.add_history("second");
+
+    assert_eq!(
+        edit(&mut editor, "\x1b[A\r").unwrap(),
+        Some("second".to_string())
+    );
+    assert_eq!(
+        edit(&mut editor, "\x1b[A\x1b[A\x1b[A\r").unwrap(),
+        Some("first".to_string())
+    );
+    assert_eq!(
+        edit(&mut editor, "draft\x1b[A\x1b[B\r").unwrap(),
+        Some("draft".to_string())
+    );
+    assert_eq!(
+        edit(&mut editor, "\x1b[A!\r").unwrap(),
+        Some("second!".to_string())
+    );
+}
+
+#[test]
+fn history_skips_empty_lines_and_repeats() {
+    let mut editor: LineEditor = LineEditor::new(None);
+    for entry in ["1 + 1", "1 + 1", "", "   ", "2", "1 + 1"] {
+        editor.add_history(entry);
+    }
+
+    assert_eq!(editor.history(), ["1 + 1", "2", "1 + 1"]);
+}
+
+#[test]
+fn history_is_saved_and_loaded() {
+    let path: std::path::PathBuf = common::scratch_directory("history").join("history");
+
+    let mut editor: LineEditor = LineEditor::new(Some(path.clone()));
+    editor.add_history("let x = 1");
+    editor.add_history("x");
+    editor.save_history();
+
+    assert_eq!(LineEditor::new(Some(path)).history(), ["let x = 1", "x"]);
+}
@@ -0,0 +1,89 @@
+mod common;
+
+/// Directory with a project whose manifest sets `build.opt-level = 2`, with an empty
+/// `src/deep` directory inside
+fn project(name: &str) -> std::path::PathBuf {
+    let directory: std::path::PathBuf = common::scratch_directory(name);
+    std::fs::create_dir_all(directory.join("src/deep"))
+        .expect("Failed to create the source directory");
+    std::fs::write(directory.join("src/main.ph"), "print(1 + 2)\n")
+        .expect("Failed to write the entry");
+    std::fs::write(
+        directory.join("Phoenix.toml"),
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[build]\nopt-level = 2\n",
+    )
+    .expect("Failed to write the manifest");
+    directory
+}
+
+/// `ph` with `arguments` run in `directory`
+fn ph_in(directory: &std::path::Path, arguments: &[&str]) -> std::process::Output {
+    common::output(common::ph().current_dir(directory).args(arguments), "")
+}
+
+#[test]
+fn build_from_a_subdirectory_builds_the_entry_point() {
+    let directory: std::path::PathBuf = project("manifest_subdirectory");
+
+    let build: std::process::Output = ph_in(&directory.join("src/deep"), &["build"]);
+    assert!(
+        build.status.success(),
+        "{}",
+        String::from_utf8_lossy(&build.stderr)
+    );
+    assert!(directory.join("src/main.phc").is_file());
+
+    let run: std::process::Output = ph_in(&directory.join("src/deep"), &["run"]);
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "3\n");
+}
+
+#[test]
+fn opt_level_comes_from_the_manifest_unless_given() {
+    let directory: std::path::PathBuf = project("manifest_opt_level");
+    let bytecode = |arguments: &[&str]| -> String {
+        let output: std::process::Output = ph_in(&directory.join("src"), arguments);
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    // Folded at the manifest's level
+    assert!(!bytecode(&["build", "--emit", "bytecode"]).contains("add"));
+    assert!(bytecode(&["build", "--emit", "bytecode", "--opt-level", "0"]).contains("add"));
+}
+
+#[test]
+fn no_file_and_no_manifest_is_an_error() {
+    let directory: std::path::PathBuf = common::scratch_directory("manifest_missing");
+
+    for command in ["build", "run"] {
+        let output: std::process::Output = ph_in(&directory, &[command]);
+        assert_eq!(output.status.code(), Some(2), "{}", command);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr),
+            "error: no file given and no Phoenix.toml in this directory or any above it, give a file or create a project with `ph init`\n"
+        );
+    }
+}
+
+#[test]
+fn bad_manifest_names_the_key_and_line() {
+    let directory: std::path::PathBuf = project("manifest_bad");
+
+    for (manifest, message) in [
+        (
+            "[package]\nversion = \"0.1.0\"\n",
+            "error: ../Phoenix.toml:1: missing 'package.name'\n",
+        ),
+        (
+            "[package]\nname = \"demo\"\nversion = 1\n",
+            "error: ../Phoenix.toml:3: 'package.version' must be a string, found an integer\n",
+        ),
+    ] {
+        std::fs::write(directory.join("Phoenix.toml"), manifest)
+            .expect("Failed to write the manifest");
+
+        let output: std::process::Output = ph_in(&directory.join("src"), &["build"]);
+        assert_eq!(output.status.code(), Some(1));
+        assert_eq!(String::from_utf8_lossy(&output.stderr), message);
+    }
+}
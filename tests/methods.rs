@@ -0,0 +1,32 @@
+use phoenix_script::eval::Value;
+
+mod common;
+
+#[test]
+fn method_call_passes_the_receiver_first() {
+    assert_eq!(common::value("(-5).abs()"), Value::Int(5));
+    assert_eq!(common::value("5.sqrt()"), Value::Float(5f64.sqrt()));
+    assert_eq!(common::value("2.5.floor()"), Value::Int(2));
+    assert_eq!(common::value("let x = 3\nx.pow(2)"), Value::Int(9));
+    assert_eq!(common::value("\"ab\".len()"), Value::Int(2));
+
+    assert_eq!(common::ph_stdout(&["eval", "(-5).abs()"], ""), "5\n");
+    assert_eq!(
+        common::ph_stdout(&["eval", "5.sqrt()"], ""),
+        "2.23606797749979\n"
+    );
+}
+
+#[test]
+fn method_call_binds_tighter_than_negation() {
+    assert_eq!(common::value("-5.abs()"), Value::Int(-5));
+}
+
+#[test]
+fn method_errors_are_those_of_the_function() {
+    assert_eq!(common::error("(-5).nope()"), "undefined function 'nope'");
+    assert_eq!(
+        common::error("5.abs(1)"),
+        "function 'abs' expects 1 argument but got 2"
+    );
+}
@@ -0,0 +1,36 @@
+mod common;
+
+/// `ph eval --no-std` on `source`
+fn eval_no_std(source: &str) -> std::process::Output {
+    common::output(common::ph().args(["eval", "--no-std", source]), "")
+}
+
+#[test]
+fn builtins_are_unknown_names() {
+    for (source, message) in [
+        ("sqrt(4)", "undefined function 'sqrt'"),
+        ("print(1)", "undefined function 'print'"),
+        ("PI", "undefined variable 'PI'"),
+        ("E * 2", "undefined variable 'E'"),
+    ] {
+        let output: std::process::Output = eval_no_std(source);
+        assert_eq!(output.status.code(), Some(1), "{}", source);
+        assert!(output.stdout.is_empty());
+        assert!(
+            String::from_utf8_lossy(&output.stderr)
+                .starts_with(&format!("runtime error: {}\n --> <eval>:1:1\n", message)),
+            "{}",
+            source
+        );
+    }
+
+    // They are there without it
+    assert_eq!(common::ph_stdout(&["eval", "sqrt(4)"], ""), "2.0\n");
+}
+
+#[test]
+fn arithmetic_still_works() {
+    let output: std::process::Output = eval_no_std("let x = 2\nx + 2 * 3");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "8\n");
+}
@@ -0,0 +1,108 @@
+mod common;
+
+/// Largest int
+const MAX: &str = "9223372036854775807";
+
+/// Smallest int, written without a literal that would be too large
+const MIN: &str = "-9223372036854775807 - 1";
+
+/// `ph eval` with `arguments` before the source, which binds `a` to `value` first so the
+/// operation isn't folded away
+fn eval_on(arguments: &[&str], value: &str, operation: &str) -> std::process::Output {
+    let source: String = format!("let a = {}\n{}", value, operation);
+    common::output(common::ph().arg("eval").args(arguments).arg(&source), "")
+}
+
+#[test]
+fn each_operator_fails_past_its_boundary() {
+    for (value, operation, message, column) in [
+        (
+            MAX,
+            "a + 1",
+            "attempt to add with overflow: 9223372036854775807 + 1",
+            3,
+        ),
+        (
+            MIN,
+            "a - 1",
+            "attempt to subtract with overflow: -9223372036854775808 - 1",
+            3,
+        ),
+        (
+            MAX,
+            "a * 2",
+            "attempt to multiply with overflow: 9223372036854775807 * 2",
+            3,
+        ),
+        (
+            MIN,
+            "-a",
+            "attempt to negate with overflow: -(-9223372036854775808)",
+            1,
+        ),
+        (
+            MIN,
+            "a / -1",
+            "attempt to divide with overflow: -9223372036854775808 / -1",
+            3,
+        ),
+        (
+            MIN,
+            "a % -1",
+            "attempt to calculate the remainder with overflow: -9223372036854775808 % -1",
+            3,
+        ),
+    ] {
+        let output: std::process::Output = eval_on(&[], value, operation);
+        assert_eq!(output.status.code(), Some(1), "{}", operation);
+        assert!(output.stdout.is_empty());
+        assert!(
+            String::from_utf8_lossy(&output.stderr).starts_with(&format!(
+                "runtime error: {}\n --> <eval>:2:{}\n",
+                message, column
+            )),
+            "{}: {}",
+            operation,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
+
+#[test]
+fn each_operator_reaches_its_boundary() {
+    for (value, operation, result) in [
+        ("9223372036854775806", "a + 1", MAX),
+        ("-9223372036854775807", "a - 1", "-9223372036854775808"),
+        ("4611686018427387903", "a * 2", "9223372036854775806"),
+        (MAX, "-a", "-9223372036854775807"),
+        (MIN, "a / 1", "-9223372036854775808"),
+        (MIN, "a % 1", "0"),
+    ] {
+        let output: std::process::Output = eval_on(&[], value, operation);
+        assert!(output.status.success(), "{}", operation);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            format!("{}\n", result),
+            "{}",
+            operation
+        );
+    }
+}
+
+#[test]
+fn wrapping_is_asked_for() {
+    for (value, operation, result) in [
+        (MAX, "a + 1", "-9223372036854775808"),
+        (MIN, "a - 1", MAX),
+        (MIN, "-a", "-9223372036854775808"),
+    ] {
+        let output: std::process::Output = eval_on(&["--overflow", "wrap"], value, operation);
+        assert!(output.status.success(), "{}", operation);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            format!("{}\n", result),
+            "{}",
+            operation
+        );
+    }
+}
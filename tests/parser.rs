@@ -0,0 +1,128 @@
+use phoenix_script::diagnostic::Span;
+use phoenix_script::parser::{
+    ExpressionSyntax, ExpressionSyntaxEnum, ParseError, ParseErrorKind, Parser, StatementSyntax,
+};
+
+mod common;
+
+/// Syntax error `source` fails to parse with
+fn parse_error(source: &str) -> ParseError {
+    Parser::new(source.to_string())
+        .parse_statements()
+        .expect_err("expected a syntax error")
+}
+
+#[test]
+fn operator_where_a_number_is_needed_is_pointed_at() {
+    let error: ParseError = parse_error("1 + * 2");
+    assert_eq!(
+        error.kind,
+        ParseErrorKind::UnexpectedToken {
+            expected: "a number".to_string(),
+            found: "*".to_string(),
+        }
+    );
+    assert_eq!(error.span, Span::new(4, 5));
+    assert_eq!(error.to_string(), "expected a number but found '*'");
+
+    let output: std::process::Output = common::output(common::ph().args(["eval", "1 + * 2"]), "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "error: expected a number but found '*'\n --> <eval>:1:5\n  |\n1 | 1 + * 2\n  |     ^\n"
+    );
+}
+
+#[test]
+fn mistyped_operator_suggests_the_right_one() {
+    for (found, suggestion) in [("=<", "<="), ("=!", "!=")] {
+        let source: String = format!("1 {} 2", found);
+        let error: ParseError = parse_error(&source);
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::MistypedOperator {
+                found: found.to_string(),
+                suggestion: suggestion.to_string(),
+            }
+        );
+        assert_eq!(error.span, Span::new(2, 4));
+
+        let output: std::process::Output = common::output(common::ph().args(["eval", &source]), "");
+        assert_eq!(output.status.code(), Some(1));
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr),
+            format!(
+                "error: '{}' is not an operator, did you mean `{}`?\n --> <eval>:1:3\n  |\n1 | {}\n  |   ^^\n",
+                found, suggestion, source
+            )
+        );
+    }
+}
+
+#[test]
+fn parenthesized_expression_continues_across_lines() {
+    let statements: Vec<phoenix_script::parser::StatementSyntax> =
+        Parser::new("(1 +\n 2 +\n 3)".to_string())
+            .parse_statements()
+            .expect("Expected it to parse");
+    assert_eq!(statements.len(), 1);
+
+    assert_eq!(common::ph_stdout(&["eval", "(1 +\n 2 +\n 3)"], ""), "6\n");
+    assert_eq!(
+        common::ph_stdout(
+            &["eval", "let x = (1\n + 2) * 3\nprint(x)\nmax(x,\n 10)"],
+            ""
+        ),
+        "9\n10\n"
+    );
+}
+
+#[test]
+fn error_offset_counts_bytes_from_the_start_of_the_source() {
+    // `é` takes two bytes, so the `)` is at byte 25 but character 24
+    let source: &str = "let s = \"é\"\nlet x = 1 + )";
+    let error: ParseError = parse_error(source);
+    assert_eq!(error.span, Span::new(25, 26));
+    assert_eq!(&source[error.span.start..error.span.end], ")");
+
+    let output: std::process::Output = common::output(common::ph().args(["eval", source]), "");
+    assert!(String::from_utf8_lossy(&output.stderr).contains(" --> <eval>:2:13\n"));
+}
+
+#[test]
+fn error_at_the_end_of_the_input_has_an_empty_span_there() {
+    let error: ParseError = parse_error("print(1 +");
+    assert_eq!(
+        error.to_string(),
+        "expected an expression but reached the end of the input"
+    );
+    assert_eq!(error.span, Span::new(9, 9));
+}
+
+#[test]
+fn binary_expression_spans_its_operands_and_its_operator() {
+    let statements: Vec<StatementSyntax> = Parser::new("1 + 2 * 3".to_string())
+        .parse_statements()
+        .expect("Expected it to parse");
+    let addition: &ExpressionSyntax = match &statements[..] {
+        [StatementSyntax::Expression(ExpressionSyntaxEnum::ExpressionSyntax(addition))] => addition,
+        _ => panic!("expected one binary expression, found {:?}", statements),
+    };
+    assert_eq!(addition.span, Span::new(0, 9));
+    assert_eq!(addition.operator_span, Span::new(2, 3));
+
+    let multiplication: &ExpressionSyntax = match &addition.right {
+        ExpressionSyntaxEnum::ExpressionSyntax(multiplication) => multiplication,
+        right => panic!("expected `2 * 3` on the right, found {:?}", right),
+    };
+    assert_eq!(multiplication.span, Span::new(4, 9));
+    assert_eq!(multiplication.operator_span, Span::new(6, 7));
+
+    // A type error points at the whole sub-expression
+    let output: std::process::Output =
+        common::output(common::ph().args(["check", "-"]), "1 + 2 * \"a\"");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "error: cannot apply '*' to int and string\n --> <stdin>:1:5\n  |\n1 | 1 + 2 * \"a\"\n  |     ^^^^^^^\n"
+    );
+}
@@ -0,0 +1,59 @@
+use phoenix_script::passes::{find_pass, Pass, PASSES};
+
+mod common;
+
+/// Bytecode listing of `source` built at `level`
+fn bytecode(source: &str, level: &str) -> String {
+    common::ph_stdout(
+        &[
+            "build",
+            "--expr",
+            source,
+            "--opt-level",
+            level,
+            "--emit",
+            "bytecode",
+        ],
+        "",
+    )
+}
+
+#[test]
+fn opt_level_zero_runs_no_pass() {
+    assert!(PASSES.iter().all(|pass: &Pass| pass.level > 0));
+    assert_eq!(
+        find_pass("const_fold").map(|pass: &Pass| pass.level),
+        Some(1)
+    );
+
+    assert!(bytecode("1 + 2", "0").contains("add"));
+    assert!(!bytecode("1 + 2", "1").contains("add"));
+}
+
+#[test]
+fn literal_division_by_zero_is_found_at_every_level() {
+    for level in ["0", "1", "2"] {
+        let run: std::process::Output = common::output(
+            common::ph().args(["eval", "--opt-level", level, "print(1)\n1 / 0"]),
+            "",
+        );
+        assert_eq!(run.status.code(), Some(1), "level {}", level);
+        // Found before anything runs
+        assert!(run.stdout.is_empty(), "level {}", level);
+        assert!(String::from_utf8_lossy(&run.stderr).contains("error: division by zero"));
+
+        let build: std::process::Output = common::output(
+            common::ph().args([
+                "build",
+                "--expr",
+                "1 / 0",
+                "--opt-level",
+                level,
+                "--emit",
+                "ast",
+            ]),
+            "",
+        );
+        assert_eq!(build.status.code(), Some(1), "level {}", level);
+    }
+}
@@ -0,0 +1,41 @@
+use phoenix_script::eval::Value;
+use phoenix_script::fmt::{format_source, FormatOptions};
+
+mod common;
+
+#[test]
+fn percent_sign_before_an_operator() {
+    assert_eq!(common::value("10% * 200"), Value::Float(20.0));
+    assert_eq!(common::value("(10%)"), Value::Float(0.1));
+    assert_eq!(common::value("10%"), Value::Float(0.1));
+}
+
+#[test]
+fn spaces_around_the_percent_sign_do_not_matter() {
+    assert_eq!(common::value("50%+1"), Value::Float(1.5));
+    assert_eq!(common::value("50% + 1"), Value::Float(1.5));
+    assert_eq!(common::value("50 % + 1"), Value::Float(1.5));
+    assert_eq!(common::value("10%3"), Value::Int(1));
+    assert_eq!(common::value("10 % 3"), Value::Int(1));
+    assert_eq!(common::value("10 % (-3)"), Value::Int(1));
+}
+
+#[test]
+fn of_is_a_variable_unless_an_operand_follows() {
+    assert_eq!(common::value("50% of 200"), Value::Float(100.0));
+    assert_eq!(common::value("let of = 3\n10%of"), Value::Int(1));
+    assert_eq!(common::value("let of = 3\n10 % of * 2"), Value::Int(2));
+}
+
+#[test]
+fn formatting_keeps_modulo_apart_from_percentages() {
+    let source: &str =
+        "let of = [3]\nlet a = 10 % (-3)\nlet b = 10 % (of[0]) + 1\nlet c = 50% - 1\n";
+    let formatted: String = format_source(source, &FormatOptions::default()).unwrap();
+
+    assert_eq!(formatted, source);
+    assert_eq!(
+        common::value(&format!("{}a + b + c", formatted)),
+        Value::Float(2.5)
+    );
+}
@@ -0,0 +1,98 @@
+use phoenix_script::phc::{decode, PhcError};
+
+mod common;
+
+#[test]
+fn every_example_runs_the_same_compiled() {
+    let directory: std::path::PathBuf = common::scratch_directory("phc_round_trip");
+    let mut failures: Vec<String> = Vec::new();
+    for (index, example) in common::examples().into_iter().enumerate() {
+        let compiled: std::path::PathBuf = directory.join(format!("{}.phc", index));
+        common::ph_stdout(
+            &[
+                "build",
+                &example.path.display().to_string(),
+                "-o",
+                &compiled.display().to_string(),
+            ],
+            "",
+        );
+
+        let stdout: String = common::run_example(
+            &common::Example {
+                path: compiled,
+                ..example.clone()
+            },
+            &[],
+        );
+        if stdout != example.expected {
+            failures.push(format!(
+                "{}: expected\n{}\nfound\n{}",
+                example.name(),
+                example.expected,
+                stdout
+            ));
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn a_flipped_byte_anywhere_is_refused() {
+    let directory: std::path::PathBuf = common::scratch_directory("phc_flipped_byte");
+    let compiled: std::path::PathBuf = directory.join("math.phc");
+    common::ph_stdout(
+        &[
+            "build",
+            &common::examples_directory()
+                .join("math.ph")
+                .display()
+                .to_string(),
+            "-o",
+            &compiled.display().to_string(),
+        ],
+        "",
+    );
+    let bytes: Vec<u8> = std::fs::read(&compiled).expect("Failed to read the compiled file");
+    assert!(decode(&bytes).is_ok());
+
+    for index in 0..bytes.len() {
+        let mut flipped: Vec<u8> = bytes.clone();
+        flipped[index] ^= 0x01;
+        assert!(decode(&flipped).is_err(), "byte {} was not noticed", index);
+    }
+
+    // Past the 18 byte header, the checksum is what notices
+    let mut flipped: Vec<u8> = bytes.clone();
+    flipped[20] ^= 0x01;
+    assert_eq!(decode(&flipped).err(), Some(PhcError::Checksum));
+
+    std::fs::write(&compiled, &flipped).expect("Failed to write the compiled file");
+    let output: std::process::Output = common::output(common::ph().arg("run").arg(&compiled), "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: cannot run"));
+}
+
+#[test]
+fn division_by_zero_in_a_function_points_at_the_source_file() {
+    let directory: std::path::PathBuf = common::scratch_directory("phc_source_name");
+    std::fs::write(directory.join("z.ph"), "fn f(x) {\n    10 / x\n}\nf(0)\n")
+        .expect("Failed to write the program");
+
+    let build: std::process::Output = common::output(
+        common::ph().current_dir(&directory).args(["build", "z.ph"]),
+        "",
+    );
+    assert!(build.status.success());
+
+    let output: std::process::Output = common::output(
+        common::ph().current_dir(&directory).args(["run", "z.phc"]),
+        "",
+    );
+    let stderr: String = String::from_utf8_lossy(&output.stderr).to_string();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.contains(" --> z.ph:2:8"), "{}", stderr);
+    assert!(stderr.contains("called at z.ph:4:1"), "{}", stderr);
+}
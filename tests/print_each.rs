@@ -0,0 +1,40 @@
+mod common;
+
+#[test]
+fn every_expression_statement_is_printed() {
+    for backend in ["tree", "vm"] {
+        assert_eq!(
+            common::ph_stdout(
+                &[
+                    "run",
+                    "--backend",
+                    backend,
+                    "--print-each",
+                    "--expr",
+                    "1\n2.5\n\"three\""
+                ],
+                ""
+            ),
+            "1\n2.5\nthree\n",
+            "backend {}",
+            backend
+        );
+    }
+
+    // Without it only the last one is
+    assert_eq!(
+        common::ph_stdout(&["run", "--expr", "1\n2.5\n\"three\""], ""),
+        "three\n"
+    );
+}
+
+#[test]
+fn bindings_and_nil_results_print_nothing() {
+    assert_eq!(
+        common::ph_stdout(
+            &["eval", "--print-each", "1 + 1\nlet x = 3\nprint(9)\nx * 2"],
+            ""
+        ),
+        "2\n9\n6\n"
+    );
+}
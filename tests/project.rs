@@ -0,0 +1,122 @@
+mod common;
+
+/// `ph` with `arguments` in `directory`
+fn ph_in(directory: &std::path::Path, arguments: &[&str]) -> std::process::Output {
+    common::output(common::ph().current_dir(directory).args(arguments), "")
+}
+
+/// Check that `directory` has the files of a new project named `name`, and that it builds and
+/// runs from there
+fn assert_project(directory: &std::path::Path, name: &str) {
+    assert_eq!(
+        std::fs::read_to_string(directory.join("Phoenix.toml")).expect("No manifest"),
+        format!(
+            "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nentry = \"src/main.ph\"\n",
+            name
+        )
+    );
+    assert_eq!(
+        std::fs::read_to_string(directory.join("src/main.ph")).expect("No entry point"),
+        "print(\"Hello, world!\")\n"
+    );
+
+    let build: std::process::Output = ph_in(directory, &["build"]);
+    assert!(
+        build.status.success(),
+        "{}",
+        String::from_utf8_lossy(&build.stderr)
+    );
+    assert!(directory.join("src/main.phc").is_file());
+
+    let run: std::process::Output = ph_in(directory, &["run"]);
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "Hello, world!\n");
+}
+
+#[test]
+fn new_creates_a_project_that_builds_and_runs() {
+    let directory: std::path::PathBuf = common::scratch_directory("project_new");
+
+    let created: std::process::Output = ph_in(&directory, &["new", "demo"]);
+    assert!(created.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&created.stdout),
+        "Created project 'demo'\n"
+    );
+    assert_project(&directory.join("demo"), "demo");
+
+    let again: std::process::Output = ph_in(&directory, &["new", "demo"]);
+    assert_eq!(again.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&again.stderr),
+        "error: 'demo' already exists, use `ph init` inside it instead\n"
+    );
+}
+
+#[test]
+fn init_creates_a_project_named_after_the_directory() {
+    let directory: std::path::PathBuf = common::scratch_directory("project_init");
+
+    let created: std::process::Output = ph_in(&directory, &["init"]);
+    assert!(created.status.success());
+    assert_project(&directory, "project_init");
+}
+
+#[test]
+fn init_leaves_existing_files_alone() {
+    for (name, existing) in [
+        ("project_init_manifest", "Phoenix.toml"),
+        ("project_init_entry", "src/main.ph"),
+    ] {
+        let directory: std::path::PathBuf = common::scratch_directory(name);
+        std::fs::create_dir(directory.join("src")).expect("Failed to create the directory");
+        std::fs::write(directory.join(existing), "mine\n").expect("Failed to write the file");
+
+        let output: std::process::Output = ph_in(&directory, &["init"]);
+        assert_eq!(output.status.code(), Some(1));
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr),
+            format!(
+                "error: './{}' already exists, it was left as is\n",
+                existing
+            )
+        );
+
+        // Nothing was written, not even the other file
+        assert_eq!(
+            std::fs::read_to_string(directory.join(existing)).expect("The file is gone"),
+            "mine\n"
+        );
+        assert_eq!(
+            std::fs::read_dir(&directory)
+                .expect("Failed to list the directory")
+                .count()
+                + std::fs::read_dir(directory.join("src"))
+                    .expect("Failed to list the source directory")
+                    .count(),
+            2
+        );
+    }
+}
+
+#[test]
+fn bad_project_names_are_rejected() {
+    let directory: std::path::PathBuf = common::scratch_directory("project_names");
+
+    for (name, message) in [
+        (
+            "a/b",
+            "error: 'a/b' can't be a project name, it must not contain path separators or quotes\n",
+        ),
+        ("", "error: the project name is empty\n"),
+    ] {
+        let output: std::process::Output = ph_in(&directory, &["new", name]);
+        assert_eq!(output.status.code(), Some(2), "{:?}", name);
+        assert_eq!(String::from_utf8_lossy(&output.stderr), message);
+    }
+    assert_eq!(
+        std::fs::read_dir(&directory)
+            .expect("Failed to list the directory")
+            .count(),
+        0
+    );
+}
@@ -0,0 +1,63 @@
+use phoenix_script::eval::Value;
+
+mod common;
+
+/// Program printing a few random numbers
+const DRAWS: &str = "print(random_int(1, 100))\nprint(random_int(1, 100))\nprint(random())";
+
+#[test]
+fn seeded_sequence_is_always_the_same() {
+    let seeded: String = format!("seed(42)\n{}", DRAWS);
+    assert_eq!(
+        common::ph_stdout(&["eval", &seeded], ""),
+        "43\n3\n0.6800434110281394\n"
+    );
+
+    // Seeding again starts the sequence over
+    assert_eq!(
+        common::value("seed(42)\nlet a = random()\nseed(42)\na == random()"),
+        Value::Bool(true)
+    );
+}
+
+#[test]
+fn seed_option_is_the_same_as_calling_seed() {
+    let seeded: String = format!("seed(42)\n{}", DRAWS);
+    assert_eq!(
+        common::ph_stdout(&["run", "--seed", "42", "--expr", DRAWS], ""),
+        common::ph_stdout(&["run", "--expr", &seeded], "")
+    );
+    assert_ne!(
+        common::ph_stdout(&["run", "--seed", "43", "--expr", DRAWS], ""),
+        common::ph_stdout(&["run", "--expr", &seeded], "")
+    );
+}
+
+#[test]
+fn random_values_stay_in_range() {
+    let inclusive: String = common::ph_stdout(
+        &[
+            "run",
+            "--seed",
+            "1",
+            "--expr",
+            "fn draw(n) { n == 0 ? 0 : draw(n - 1) + (random_int(1, 2) == 2 ? 1 : 0) }\ndraw(200)",
+        ],
+        "",
+    );
+    let twos: isize = inclusive.trim().parse().expect("Expected a count");
+    // Both ends of the range come up
+    assert!(twos > 0 && twos < 200, "{}", twos);
+
+    assert!(
+        matches!(common::value("seed(7)\nrandom()"), Value::Float(value) if (0.0..1.0).contains(&value))
+    );
+}
+
+#[test]
+fn empty_range_is_an_error() {
+    assert_eq!(
+        common::error("random_int(5, 1)"),
+        "'random_int' range is empty, 5 is above 1"
+    );
+}
@@ -0,0 +1,193 @@
+use phoenix_script::line_editor::ReadLine;
+use phoenix_script::repl::{is_incomplete, Repl};
+
+/// Reader that answers prompts from a script, recording the prompts it was shown
+struct ScriptedReader {
+    /// Lines still to read, `None` cancels the line like Ctrl-C
+    lines: std::collections::VecDeque<Option<&'static str>>,
+    prompts: Vec<String>,
+    history: Vec<String>,
+}
+
+impl ScriptedReader {
+    fn new(lines: &[Option<&'static str>]) -> ScriptedReader {
+        ScriptedReader {
+            lines: lines.iter().copied().collect(),
+            prompts: Vec::new(),
+            history: Vec::new(),
+        }
+    }
+}
+
+impl ReadLine for ScriptedReader {
+    fn read_line(&mut self, prompt: &str) -> std::io::Result<Option<String>> {
+        self.prompts.push(prompt.to_string());
+
+        match self.lines.pop_front() {
+            Some(Some(line)) => Ok(Some(line.to_string())),
+            Some(None) => Err(std::io::Error::from(std::io::ErrorKind::Interrupted)),
+            // End of the script is the end of input
+            None => Ok(None),
+        }
+    }
+
+    fn add_history(&mut self, line: &str) {
+        self.history.push(line.to_string());
+    }
+}
+
+/// Output of a REPL session over `lines`, and the reader after it
+fn session(lines: &[&'static str]) -> (String, ScriptedReader) {
+    let script: Vec<Option<&'static str>> = lines.iter().copied().map(Some).collect();
+    scripted_session(&script)
+}
+
+/// Output of a REPL session over `lines` where `None` is Ctrl-C, and the reader after it
+fn scripted_session(lines: &[Option<&'static str>]) -> (String, ScriptedReader) {
+    let mut reader: ScriptedReader = ScriptedReader::new(lines);
+    let mut output: Vec<u8> = Vec::new();
+
+    Repl::new()
+        .run(&mut reader, &mut output)
+        .expect("The session doesn't fail");
+
+    (String::from_utf8(output).expect("Output is UTF-8"), reader)
+}
+
+#[test]
+fn bindings_persist_between_lines() {
+    let (output, _): (String, ScriptedReader) = session(&["let x = 2", "let y = x * 3", "x + y"]);
+    assert_eq!(output, "8\n");
+}
+
+#[test]
+fn an_error_does_not_end_the_session() {
+    let (output, reader): (String, ScriptedReader) =
+        session(&["let x = 1", "x / 0", "undefined_name", "x + 1"]);
+
+    assert!(output.contains("error: division by zero"), "{}", output);
+    assert!(output.ends_with("2\n"), "{}", output);
+    // Every line was read, and once more for the end of input
+    assert_eq!(reader.prompts.len(), 5);
+}
+
+#[test]
+fn end_of_input_exits_cleanly() {
+    let (output, reader): (String, ScriptedReader) = session(&[]);
+    assert!(output.is_empty());
+    assert_eq!(reader.prompts, [">> "]);
+
+    let (output, reader): (String, ScriptedReader) = session(&["1", "exit", "2"]);
+    assert_eq!(output, "1\n");
+    assert_eq!(reader.lines.len(), 1);
+}
+
+#[test]
+fn entered_lines_go_into_the_history() {
+    let (_, reader): (String, ScriptedReader) = session(&["let x = 1", "x"]);
+    assert_eq!(reader.history, ["let x = 1", "x"]);
+}
+
+#[test]
+fn unfinished_input_is_incomplete() {
+    assert!(is_incomplete("(1 +"));
+    assert!(is_incomplete("1 +"));
+    assert!(is_incomplete("fn f() {"));
+    assert!(is_incomplete("[1, 2"));
+
+    assert!(!is_incomplete("(1 + 2)"));
+    assert!(!is_incomplete("1 + )"));
+    assert!(!is_incomplete("(1 + 2]"));
+}
+
+#[test]
+fn unfinished_input_continues_on_the_next_line() {
+    let (output, reader): (String, ScriptedReader) = session(&["(1 +", "2)", "3"]);
+
+    assert_eq!(output, "3\n3\n");
+    assert_eq!(reader.prompts, [">> ", "..> ", ">> ", ">> "]);
+    assert_eq!(reader.history, ["(1 +", "2)", "3"]);
+}
+
+#[test]
+fn env_lists_variables_in_order() {
+    let (output, _): (String, ScriptedReader) = session(&["let b = 2", "let a = \"x\"", ":env"]);
+    assert_eq!(
+        output,
+        "E = 2.718281828459045\nPI = 3.141592653589793\na = x\nb = 2\n"
+    );
+}
+
+#[test]
+fn clear_drops_bindings() {
+    let (output, _): (String, ScriptedReader) = session(&["let b = 2", ":clear", ":env", "b"]);
+    assert_eq!(
+        output,
+        "E = 2.718281828459045\n\
+         PI = 3.141592653589793\n\
+         runtime error: undefined variable 'b'\n \
+         --> <repl>:1:1\n  \
+         |\n\
+         1 | b\n  \
+         | ^\n"
+    );
+}
+
+#[test]
+fn tokens_and_ast_print_their_code() {
+    let (output, _): (String, ScriptedReader) = session(&[":tokens 1 + x", ":ast 1 + 2 * x"]);
+    assert_eq!(
+        output,
+        "1:1      Number           \"1\"\n\
+         1:3      Plus             \"+\"\n\
+         1:5      Identifier       \"x\"\n\
+         Binary +\n  \
+         Number 1\n  \
+         Binary *\n    \
+         Number 2\n    \
+         Variable x\n"
+    );
+
+    let (output, _): (String, ScriptedReader) = session(&[":ast 1 +"]);
+    assert!(output.starts_with("error: expected an expression but reached the end of the input\n"));
+}
+
+#[test]
+fn unknown_command_lists_the_commands() {
+    let (output, _): (String, ScriptedReader) = session(&[":bogus"]);
+    assert_eq!(
+        output,
+        "unknown command ':bogus', available commands:\n  \
+         :tokens <code>   print the tokens of <code>\n  \
+         :ast <code>      print the syntax tree of <code>\n  \
+         :env             list variables and their values\n  \
+         :clear           remove all variables\n  \
+         :quit            exit the REPL\n"
+    );
+}
+
+#[test]
+fn quit_ends_the_session() {
+    let (output, reader): (String, ScriptedReader) = session(&["1", ":quit", "2"]);
+    assert_eq!(output, "1\n");
+    assert_eq!(reader.lines.len(), 1);
+}
+
+#[test]
+fn ctrl_c_drops_unfinished_input() {
+    let (output, reader): (String, ScriptedReader) =
+        scripted_session(&[Some("(1 +"), Some("2 +"), None, Some("10"), Some(")")]);
+
+    // `10` starts fresh instead of finishing `(1 + 2 + 10`, so `)` is a syntax error
+    assert!(output.starts_with("10\nerror: "), "{}", output);
+    assert_eq!(reader.prompts, [">> ", "..> ", "..> ", ">> ", ">> ", ">> "]);
+}
+
+#[test]
+fn ctrl_c_on_an_empty_line_keeps_the_session() {
+    let (output, reader): (String, ScriptedReader) =
+        scripted_session(&[Some("let x = 1"), None, Some("x")]);
+
+    assert_eq!(output, "1\n");
+    assert_eq!(reader.prompts, [">> ", ">> ", ">> ", ">> "]);
+}
@@ -0,0 +1,79 @@
+mod common;
+
+/// `ph run` with `arguments` before `source` written to `name` in a new directory
+fn run(name: &str, source: &str, arguments: &[&str]) -> std::process::Output {
+    let directory: std::path::PathBuf = common::scratch_directory(name);
+    std::fs::write(directory.join("main.ph"), source).expect("Failed to write the program");
+
+    common::output(
+        common::ph()
+            .current_dir(&directory)
+            .arg("run")
+            .args(arguments)
+            .arg("main.ph"),
+        "",
+    )
+}
+
+#[test]
+fn seeded_variables_are_defined_before_the_program_runs() {
+    let output: std::process::Output = run(
+        "seed_vars_two",
+        "print(x + y)\nprint(x * y)\n",
+        &["--seed-vars", "x=5", "--seed-vars", "y=10"],
+    );
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "15\n50\n");
+
+    assert_eq!(
+        common::ph_stdout(&["eval", "--seed-vars", "x=1.5", "x * 2"], ""),
+        "3.0\n"
+    );
+}
+
+#[test]
+fn program_can_redefine_a_seeded_variable() {
+    let output: std::process::Output = run(
+        "seed_vars_shadowed",
+        "let x = 1\nprint(x)\n",
+        &["--seed-vars", "x=5"],
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n");
+}
+
+#[test]
+fn value_that_is_not_a_number_is_a_usage_error() {
+    for (seed, message) in [
+        ("y=abc", "'abc' is not a number"),
+        ("x", "expected name=value"),
+    ] {
+        let output: std::process::Output =
+            run("seed_vars_invalid", "print(1)\n", &["--seed-vars", seed]);
+        assert_eq!(output.status.code(), Some(2), "{}", seed);
+        assert!(output.stdout.is_empty());
+        assert!(
+            String::from_utf8_lossy(&output.stderr).contains(message),
+            "{}",
+            seed
+        );
+    }
+}
+
+#[test]
+fn after_the_file_it_is_an_argument_of_the_script() {
+    let directory: std::path::PathBuf = common::scratch_directory("seed_vars_after");
+    std::fs::write(directory.join("main.ph"), "print(args())\n")
+        .expect("Failed to write the program");
+
+    let output: std::process::Output = common::output(
+        common::ph()
+            .current_dir(&directory)
+            .args(["run", "main.ph", "--seed-vars", "x=5"]),
+        "",
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "[\"--seed-vars\", \"x=5\"]\n"
+    );
+}
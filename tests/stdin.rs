@@ -0,0 +1,79 @@
+mod common;
+
+/// `ph` with `arguments` in `directory`, with `stdin`
+fn ph_in(directory: &std::path::Path, arguments: &[&str], stdin: &str) -> std::process::Output {
+    common::output(common::ph().current_dir(directory).args(arguments), stdin)
+}
+
+#[test]
+fn build_reads_stdin_into_out_phc() {
+    let directory: std::path::PathBuf = common::scratch_directory("stdin_build");
+
+    let build: std::process::Output = ph_in(&directory, &["build", "-"], "print(1 + 2)\n");
+    assert!(
+        build.status.success(),
+        "{}",
+        String::from_utf8_lossy(&build.stderr)
+    );
+    assert!(directory.join("out.phc").is_file());
+
+    let run: std::process::Output = ph_in(&directory, &["run", "out.phc"], "");
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "3\n");
+
+    // An output given on the command line still wins
+    let build: std::process::Output = ph_in(
+        &directory,
+        &["build", "-", "-o", "sum.phc"],
+        "print(1 + 2)\n",
+    );
+    assert!(build.status.success());
+    assert!(directory.join("sum.phc").is_file());
+}
+
+#[test]
+fn errors_in_stdin_are_about_stdin() {
+    let directory: std::path::PathBuf = common::scratch_directory("stdin_errors");
+
+    for command in ["build", "check"] {
+        let output: std::process::Output = ph_in(&directory, &[command, "-"], "1 + * 2\n");
+        assert_eq!(output.status.code(), Some(1), "{}", command);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr),
+            "error: expected a number but found '*'\n --> <stdin>:1:5\n  |\n1 | 1 + * 2\n  |     ^\n"
+        );
+    }
+    assert!(!directory.join("out.phc").exists());
+
+    let checked: std::process::Output = ph_in(&directory, &["check", "-"], "print(1)\n");
+    assert!(checked.status.success());
+    assert!(checked.stderr.is_empty());
+}
+
+#[test]
+fn stdin_that_isnt_utf8_is_an_error_like_a_file() {
+    let directory: std::path::PathBuf = common::scratch_directory("stdin_not_utf8");
+    std::fs::write(directory.join("bad.ph"), b"print(\"\xff\")").expect("Failed to write the file");
+
+    let mut command: std::process::Command = common::ph();
+    command.current_dir(&directory).args(["build", "-"]);
+    let child: std::process::Child = command
+        .stdin(std::process::Stdio::from(
+            std::fs::File::open(directory.join("bad.ph")).expect("Failed to open the file"),
+        ))
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to start ph");
+    let stdin: std::process::Output = child.wait_with_output().expect("Failed to wait for ph");
+    let file: std::process::Output = ph_in(&directory, &["build", "bad.ph"], "");
+
+    for (output, name) in [(stdin, "<stdin>"), (file, "bad.ph")] {
+        assert_eq!(output.status.code(), Some(1));
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr),
+            format!(
+                "error: failed to read '{}': stream did not contain valid UTF-8\n",
+                name
+            )
+        );
+    }
+}
@@ -0,0 +1,126 @@
+mod common;
+
+/// Program every target builds
+const PROGRAM: &str = "print(1 + 2)";
+
+/// Assembly `ph build` generates for `PROGRAM` on `target`
+fn asm(target: &str) -> String {
+    common::ph_stdout(
+        &[
+            "build", "--expr", PROGRAM, "--emit", "asm", "--target", target,
+        ],
+        "",
+    )
+}
+
+#[test]
+fn linux_assembly() {
+    assert_eq!(
+        asm("x86_64-linux"),
+        "# Generated by `ph build --emit asm --target x86_64-linux` from <expr>\n\
+         \n\
+         \x20   .text\n\
+         \x20   .globl main\n\
+         \n\
+         main:\n\
+         \x20   pushq %rbp\n\
+         \x20   movq %rsp, %rbp\n\
+         \x20   subq $32, %rsp\n\
+         \x20   movq $1, -8(%rbp)\n\
+         \x20   movq $2, -16(%rbp)\n\
+         \x20   movq -8(%rbp), %rax\n\
+         \x20   movq -16(%rbp), %rcx\n\
+         \x20   addq %rcx, %rax\n\
+         \x20   jo ph_overflow\n\
+         \x20   movq %rax, -24(%rbp)\n\
+         \x20   movq -24(%rbp), %rdi\n\
+         \x20   movl $10, %esi\n\
+         \x20   call ph_print_int\n\
+         \x20   xorl %eax, %eax\n\
+         \x20   leave\n\
+         \x20   ret\n\
+         \n\
+         ph_overflow:\n\
+         \x20   xorl %edi, %edi\n\
+         \x20   call fflush@PLT\n\
+         \x20   ud2\n\
+         \n\
+         ph_print_int:\n\
+         \x20   movq %rsi, %rdx\n\
+         \x20   movq %rdi, %rsi\n\
+         \x20   leaq .Lph_int(%rip), %rdi\n\
+         \x20   xorl %eax, %eax\n\
+         \x20   jmp printf@PLT\n\
+         \n\
+         ph_print_bool:\n\
+         \x20   movq %rsi, %rdx\n\
+         \x20   leaq .Lph_true(%rip), %rsi\n\
+         \x20   leaq .Lph_false(%rip), %rax\n\
+         \x20   testq %rdi, %rdi\n\
+         \x20   cmovzq %rax, %rsi\n\
+         \x20   leaq .Lph_bool(%rip), %rdi\n\
+         \x20   xorl %eax, %eax\n\
+         \x20   jmp printf@PLT\n\
+         \n\
+         ph_print_newline:\n\
+         \x20   movl $10, %edi\n\
+         \x20   jmp putchar@PLT\n\
+         \n\
+         \x20   .section .rodata\n\
+         .Lph_int:\n\
+         \x20   .asciz \"%ld%c\"\n\
+         .Lph_bool:\n\
+         \x20   .asciz \"%s%c\"\n\
+         .Lph_true:\n\
+         \x20   .asciz \"true\"\n\
+         .Lph_false:\n\
+         \x20   .asciz \"false\"\n\
+         \n\
+         \x20   .section .note.GNU-stack,\"\",@progbits\n"
+    );
+}
+
+#[test]
+fn macos_assembly_differs_in_symbols_and_sections() {
+    // Only ELF has the note that asks for a stack that can't be executed
+    let expected: String = asm("x86_64-linux")
+        .replace("--target x86_64-linux", "--target x86_64-macos")
+        .replace("main", "_main")
+        .replace("fflush@PLT", "_fflush")
+        .replace("printf@PLT", "_printf")
+        .replace("putchar@PLT", "_putchar")
+        .replace(".Lph_", "Lph_")
+        .replace(
+            ".section .rodata",
+            ".section __TEXT,__cstring,cstring_literals",
+        )
+        .replace("\n    .section .note.GNU-stack,\"\",@progbits\n", "");
+
+    assert_eq!(asm("x86_64-macos"), expected);
+}
+
+#[test]
+fn target_the_backend_doesnt_support_is_an_error() {
+    let output: std::process::Output = common::output(
+        common::ph().args([
+            "build", "--expr", PROGRAM, "--emit", "asm", "--target", "wasm32",
+        ]),
+        "",
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "error: the asm backend can't generate code for wasm32, it supports x86_64-linux, x86_64-macos\n"
+    );
+
+    let output: std::process::Output = common::output(
+        common::ph().args([
+            "build", "--expr", PROGRAM, "--emit", "asm", "--target", "arm",
+        ]),
+        "",
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with(
+        "error: invalid value 'arm' for '--target <target>': expected one of x86_64-linux, x86_64-macos, wasm32\n"
+    ));
+}
@@ -0,0 +1,45 @@
+use phoenix_script::eval::Value;
+
+mod common;
+
+#[test]
+fn condition_picks_a_branch() {
+    assert_eq!(common::value("let x = 5\nx > 0 ? 1 : -1"), Value::Int(1));
+    assert_eq!(common::value("let x = -5\nx > 0 ? 1 : -1"), Value::Int(-1));
+}
+
+#[test]
+fn nested_conditionals_group_to_the_right() {
+    let directory: std::path::PathBuf = common::scratch_directory("ternary_nested");
+    let file: std::path::PathBuf = directory.join("nested.ph");
+    std::fs::write(&file, "a ? b : c ? d : e").expect("Failed to write the program");
+
+    let tree: String = common::ph_stdout(&["ast", &file.display().to_string()], "");
+    assert_eq!(
+        tree,
+        "Conditional\n  Variable a\n  Variable b\n  Conditional\n    Variable c\n    Variable d\n    Variable e\n"
+    );
+
+    // Grouped to the left the int in the middle would be a condition
+    for (source, value) in [
+        ("1 < 2 ? 10 : 1 < 2 ? 20 : 30", 10),
+        ("1 > 2 ? 10 : 1 < 2 ? 20 : 30", 20),
+        ("1 > 2 ? 10 : 1 > 2 ? 20 : 30", 30),
+        ("1 < 2 ? 1 > 2 ? 10 : 20 : 30", 20),
+    ] {
+        assert_eq!(common::value(source), Value::Int(value), "{}", source);
+    }
+}
+
+#[test]
+fn condition_that_isnt_a_bool_is_an_error() {
+    assert_eq!(
+        common::error("let x = 1\nx ? 2 : 3"),
+        "condition must be a bool but found int"
+    );
+
+    let output: std::process::Output = common::output(common::ph().args(["eval", "1 ? 2 : 3"]), "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .starts_with("runtime error: condition must be a bool but found int\n"));
+}
@@ -0,0 +1,111 @@
+mod common;
+
+/// `ph` with `arguments` then a file holding `source`, in a new directory named `name`
+fn ph_on(
+    name: &str,
+    source: &str,
+    arguments: &[&str],
+) -> (std::path::PathBuf, std::process::Output) {
+    let directory: std::path::PathBuf = common::scratch_directory(name);
+    std::fs::write(directory.join("c.ph"), source).expect("Failed to write the program");
+
+    let output: std::process::Output = common::output(
+        common::ph()
+            .current_dir(&directory)
+            .args(arguments)
+            .arg("c.ph"),
+        "",
+    );
+    (directory, output)
+}
+
+#[test]
+fn count_tokens_tallies_each_type() {
+    let (_, output): (std::path::PathBuf, std::process::Output) = ph_on(
+        "tokens_count",
+        "let x = (1 + 2) * 3\nprint(x)\n",
+        &["build", "--count-tokens"],
+    );
+    assert!(output.status.success());
+    // Most first, then by name
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Identifier       3\n\
+         Number           3\n\
+         CloseParenthesis 2\n\
+         NewLine          2\n\
+         OpenParenthesis  2\n\
+         Equals           1\n\
+         LetKeyword       1\n\
+         Plus             1\n\
+         Star             1\n\
+         Total            16\n"
+    );
+}
+
+#[test]
+fn count_tokens_doesnt_parse_or_write() {
+    let (directory, output): (std::path::PathBuf, std::process::Output) = ph_on(
+        "tokens_count_invalid",
+        "1 + * 2\n",
+        &["build", "--count-tokens"],
+    );
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with("Total            5\n"));
+    assert!(!directory.join("c.phc").exists());
+}
+
+/// Token types `ph tokens` with `arguments` lists for `source`, one per line
+fn token_types(name: &str, source: &str, arguments: &[&str]) -> Vec<String> {
+    let (_, output): (std::path::PathBuf, std::process::Output) = ph_on(name, source, arguments);
+    assert!(output.status.success());
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line: &str| {
+            line.split_whitespace()
+                .nth(1)
+                .unwrap_or_default()
+                .to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn raw_tokens_add_whitespace_runs_and_the_end_of_file() {
+    let source: &str = "1  +   2\n";
+    let filtered: Vec<String> = token_types("tokens_filtered", source, &["tokens"]);
+    let raw: Vec<String> = token_types("tokens_raw", source, &["tokens", "--raw"]);
+
+    assert_eq!(filtered, ["Number", "Plus", "Number", "NewLine"]);
+    assert_eq!(
+        raw,
+        [
+            "Number",
+            "WhiteSpace",
+            "Plus",
+            "WhiteSpace",
+            "Number",
+            "NewLine",
+            "EndOfFile",
+        ]
+    );
+    assert_eq!(raw.len(), filtered.len() + 3);
+
+    // Only the parser's filtering differs
+    let kept: Vec<&String> = raw
+        .iter()
+        .filter(|name: &&String| !matches!(name.as_str(), "WhiteSpace" | "EndOfFile"))
+        .collect();
+    assert_eq!(kept, filtered.iter().collect::<Vec<&String>>());
+}
+
+#[test]
+fn raw_tokens_keep_bad_tokens() {
+    assert_eq!(
+        token_types("tokens_bad", "1 @ 2", &["tokens"]),
+        ["Number", "Number"]
+    );
+    assert!(token_types("tokens_bad_raw", "1 @ 2", &["tokens", "--raw"])
+        .contains(&"BadToken".to_string()));
+}
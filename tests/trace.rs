@@ -0,0 +1,90 @@
+mod common;
+
+/// `ph run` with `arguments` on `source` written to `name` in a new directory
+fn run(name: &str, source: &str, arguments: &[&str]) -> std::process::Output {
+    let directory: std::path::PathBuf = common::scratch_directory(name);
+    std::fs::write(directory.join("main.ph"), source).expect("Failed to write the program");
+
+    let output: std::process::Output = common::output(
+        common::ph()
+            .current_dir(&directory)
+            .arg("run")
+            .args(arguments)
+            .arg("main.ph"),
+        "",
+    );
+    assert!(output.status.success());
+    output
+}
+
+/// Mnemonics of the traced instructions, in the order they ran
+fn mnemonics(stderr: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(stderr)
+        .lines()
+        .filter_map(|line: &str| line.split_whitespace().nth(1))
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn trace_lists_the_instructions_in_order() {
+    let output: std::process::Output =
+        run("trace_order", "let x = 1 + 2\nprint(x)\n", &["--trace"]);
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+    assert_eq!(
+        mnemonics(&output.stderr),
+        [
+            "push",
+            "push",
+            "add",
+            "define_global",
+            "load_global",
+            "call_builtin",
+            "yield",
+            "return",
+        ]
+    );
+    // Program counter, instruction and the stack before it
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .any(|line: &str| line.starts_with("0002  add") && line.ends_with("[1, 2]")));
+}
+
+#[test]
+fn trace_goes_through_calls() {
+    let output: std::process::Output = run(
+        "trace_calls",
+        "fn count(n) {\n    n > 0 ? count(n - 1) + 1 : 0\n}\nprint(count(1))\n",
+        &["--trace"],
+    );
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n");
+    let calls: usize = mnemonics(&output.stderr)
+        .iter()
+        .filter(|mnemonic: &&String| *mnemonic == "call")
+        .count();
+    assert_eq!(calls, 2);
+}
+
+#[test]
+fn trace_limit_stops_the_trace_but_not_the_program() {
+    let output: std::process::Output = run(
+        "trace_limit",
+        "print(1 + 2)\n",
+        &["--trace", "--trace-limit", "2"],
+    );
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+    let stderr: String = String::from_utf8_lossy(&output.stderr).to_string();
+    assert_eq!(stderr.lines().count(), 3);
+    assert!(stderr.ends_with("trace limit of 2 instructions reached\n"));
+}
+
+#[test]
+fn without_trace_nothing_is_traced() {
+    let output: std::process::Output = run("trace_off", "print(1 + 2)\n", &[]);
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+    assert!(output.stderr.is_empty());
+}
@@ -0,0 +1,86 @@
+mod common;
+
+/// Program every test builds or runs, written to `add.ph` in a new directory named `name`
+fn program(name: &str) -> std::path::PathBuf {
+    let directory: std::path::PathBuf = common::scratch_directory(name);
+    std::fs::write(directory.join("add.ph"), "let x = 1 + 2\nprint(x)\n")
+        .expect("Failed to write the program");
+    directory
+}
+
+/// `ph` with `arguments` in `directory`
+fn ph_in(directory: &std::path::Path, arguments: &[&str]) -> std::process::Output {
+    let output: std::process::Output =
+        common::output(common::ph().current_dir(directory).args(arguments), "");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+#[test]
+fn default_output_has_no_debug_dumps() {
+    let directory: std::path::PathBuf = program("verbosity_default");
+
+    for arguments in [&["build", "add.ph"][..], &["run", "add.ph"]] {
+        let output: std::process::Output = ph_in(&directory, arguments);
+        assert!(output.stderr.is_empty(), "{:?}", arguments);
+        for noise in ["Parser {", "Lexer {"] {
+            assert!(!String::from_utf8_lossy(&output.stdout).contains(noise));
+        }
+    }
+}
+
+#[test]
+fn quiet_prints_nothing_but_the_result() {
+    let directory: std::path::PathBuf = program("verbosity_quiet");
+
+    let build: std::process::Output = ph_in(&directory, &["-q", "build", "add.ph"]);
+    assert!(build.stdout.is_empty());
+    assert!(build.stderr.is_empty());
+
+    let run: std::process::Output = ph_in(&directory, &["-q", "run", "add.ph"]);
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "3\n");
+    assert!(run.stderr.is_empty());
+}
+
+#[test]
+fn verbose_prints_each_phase_with_its_time() {
+    let directory: std::path::PathBuf = program("verbosity_verbose");
+
+    let build: std::process::Output = ph_in(&directory, &["-v", "build", "add.ph"]);
+    let stderr: String = String::from_utf8_lossy(&build.stderr).into_owned();
+    for phase in ["Parsed and checked in ", "Compiled to bytecode in "] {
+        assert!(stderr.contains(phase), "{}", stderr);
+    }
+
+    let run: std::process::Output = ph_in(&directory, &["-v", "run", "add.ph"]);
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "3\n");
+    let stderr: String = String::from_utf8_lossy(&run.stderr).into_owned();
+    assert!(stderr.starts_with("Running add.ph\n"), "{}", stderr);
+    for phase in [
+        "Parsed in ",
+        "Checked in ",
+        "Ran the syntax tree passes in ",
+        "Ran in ",
+    ] {
+        assert!(stderr.contains(phase), "{}", stderr);
+    }
+    // The structures are only dumped with -vv
+    assert!(!stderr.contains("statements = "));
+}
+
+#[test]
+fn very_verbose_also_prints_the_structures() {
+    let directory: std::path::PathBuf = program("verbosity_debug");
+
+    for arguments in [&["-vv", "build", "add.ph"][..], &["-vv", "run", "add.ph"]] {
+        let output: std::process::Output = ph_in(&directory, arguments);
+        let stderr: String = String::from_utf8_lossy(&output.stderr).into_owned();
+        assert!(stderr.contains("statements = ["), "{}", stderr);
+        assert!(stderr.contains("program = "), "{}", stderr);
+        assert!(stderr.contains("Parsed"), "{}", stderr);
+    }
+}
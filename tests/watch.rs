@@ -0,0 +1,118 @@
+use phoenix_script::watch::{watched_files, Decision, Snapshot, Watcher};
+
+mod common;
+
+/// Write `text` to `name` in `directory`, returning its path
+///
+/// Each write in a test has a different length, so it is seen as a change even when the
+/// modification time doesn't move.
+fn write(directory: &std::path::Path, name: &str, text: &str) -> std::path::PathBuf {
+    let path: std::path::PathBuf = directory.join(name);
+    std::fs::write(&path, text).expect("Failed to write a watched file");
+    path
+}
+
+#[test]
+fn unchanged_files_wait() {
+    let directory: std::path::PathBuf = common::scratch_directory("watch_unchanged");
+    let files: Vec<std::path::PathBuf> = vec![write(&directory, "main.ph", "1")];
+
+    let mut watcher: Watcher = Watcher::new(Snapshot::take(&files));
+    assert_eq!(watcher.poll(Snapshot::take(&files)), Decision::Wait);
+    assert_eq!(watcher.poll(Snapshot::take(&files)), Decision::Wait);
+}
+
+#[test]
+fn a_change_rebuilds_once_the_files_settle() {
+    let directory: std::path::PathBuf = common::scratch_directory("watch_debounce");
+    let main: std::path::PathBuf = write(&directory, "main.ph", "1");
+    let files: Vec<std::path::PathBuf> = vec![main.clone()];
+
+    let mut watcher: Watcher = Watcher::new(Snapshot::take(&files));
+
+    write(&directory, "main.ph", "12");
+    assert_eq!(watcher.poll(Snapshot::take(&files)), Decision::Settle);
+    // Still being written
+    write(&directory, "main.ph", "123");
+    assert_eq!(watcher.poll(Snapshot::take(&files)), Decision::Settle);
+    assert_eq!(
+        watcher.poll(Snapshot::take(&files)),
+        Decision::Rebuild(vec![main])
+    );
+}
+
+#[test]
+fn a_file_put_back_before_it_settles_does_not_rebuild() {
+    let directory: std::path::PathBuf = common::scratch_directory("watch_put_back");
+    let main: std::path::PathBuf = write(&directory, "main.ph", "1");
+    let files: Vec<std::path::PathBuf> = vec![main.clone()];
+    let built: Snapshot = Snapshot::take(&files);
+
+    let mut watcher: Watcher = Watcher::new(built.clone());
+    std::fs::remove_file(&main).expect("Failed to remove the watched file");
+    assert_eq!(watcher.poll(Snapshot::take(&files)), Decision::Settle);
+
+    // Seen as it was when built, like after an editor renames its temporary file over it
+    assert_eq!(watcher.poll(built.clone()), Decision::Settle);
+    assert_eq!(watcher.poll(built), Decision::Wait);
+}
+
+#[test]
+fn imports_are_watched_and_refreshed() {
+    let directory: std::path::PathBuf = common::scratch_directory("watch_imports");
+    let main: std::path::PathBuf = write(&directory, "main.ph", "import a\na()\n");
+    let a: std::path::PathBuf = write(&directory, "a.ph", "fn a() { 1 }\n");
+    let b: std::path::PathBuf = write(&directory, "b.ph", "fn b() { 2 }\n");
+    let main_name: &str = main.to_str().expect("The path is UTF-8");
+
+    let files: Vec<std::path::PathBuf> = watched_files(main_name, false, false);
+    assert_eq!(files, [main.clone(), a.clone()]);
+
+    // Changing the imports rebuilds for the file with them, and the new import is watched after
+    let mut watcher: Watcher = Watcher::new(Snapshot::take(&files));
+    write(&directory, "main.ph", "import b\nb()\n");
+    watcher.poll(Snapshot::take(&files));
+    assert_eq!(
+        watcher.poll(Snapshot::take(&files)),
+        Decision::Rebuild(vec![main.clone()])
+    );
+
+    let files: Vec<std::path::PathBuf> = watched_files(main_name, false, false);
+    assert_eq!(files, [main, b.clone()]);
+
+    let mut watcher: Watcher = Watcher::new(Snapshot::take(&files));
+    write(&directory, "a.ph", "fn a() { 10 }\n");
+    assert_eq!(watcher.poll(Snapshot::take(&files)), Decision::Wait);
+
+    write(&directory, "b.ph", "fn b() { 20 }\n");
+    watcher.poll(Snapshot::take(&files));
+    assert_eq!(
+        watcher.poll(Snapshot::take(&files)),
+        Decision::Rebuild(vec![b])
+    );
+}
+
+#[test]
+fn a_broken_import_watches_the_file_with_it() {
+    let directory: std::path::PathBuf = common::scratch_directory("watch_broken_import");
+    let main: std::path::PathBuf = write(&directory, "main.ph", "import missing\n");
+
+    assert_eq!(
+        watched_files(main.to_str().expect("The path is UTF-8"), false, false),
+        [main]
+    );
+}
+
+#[test]
+fn stdin_cant_be_watched() {
+    for command in ["build", "check"] {
+        let output: std::process::Output =
+            common::output(common::ph().args([command, "--watch", "-"]), "1\n");
+
+        assert_eq!(output.status.code(), Some(2), "{}", command);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr),
+            "error: --watch needs a file, stdin can't be watched\n"
+        );
+    }
+}